@@ -20,6 +20,7 @@
             pkg-config
             systemd
             udev
+            alsa-lib
             wayland
             wayland-protocols
             libxkbcommon
@@ -39,6 +40,7 @@
               pkgs.mesa
               pkgs.libGL
               pkgs.dbus
+              pkgs.alsa-lib
             ]}:$LD_LIBRARY_PATH
           '';
         };
@@ -1,13 +1,54 @@
+use crate::{connection, theme};
+
+#[derive(Clone)]
 pub enum AppAction {
     SpawnWidget(WidgetType),
+    // Goes to the focused widget when it has a use for it, otherwise to a new one
+    ShowMessage {
+        msg_name: String,
+        new_widget: bool,
+    },
+    ShowSignal {
+        msg_id: u32,
+        msg_name: String,
+        signal_name: String,
+        new_widget: bool,
+    },
+    // Named layouts, saving under an existing name replaces it
+    SaveLayout(String),
+    LoadLayout(String),
+    DeleteLayout(String),
+    // Moves a pane of the tile tree into its own window, and back
+    DetachWidget(egui_tiles::TileId),
+    DockWidget(eframe::egui::ViewportId),
+    SwitchProfile(String),
+    // Starts as a copy of the current profile, and is switched to
+    CreateProfile(String),
+    Connect(connection::ConnectionSource),
+    Disconnect,
+    SelectDbc,
+    ToggleTheme,
+    SelectTheme(String),
+    // Shown without changing the selected theme, until `EndThemePreview`
+    PreviewTheme(theme::ThemeColors),
+    EndThemePreview,
+    // A theme file was written, themes are reloaded and it is selected
+    ThemeSaved(String),
+    // The formatter config was edited, it is read again from the profile
+    ReloadFormatter,
     ToggleSidebar,
     ToggleCommandPalette,
+    ToggleShortcutHelp,
     CloseActiveWidget,
     IncreaseScale,
     DecreaseScale,
+    TogglePauseFocused,
+    ClearFocused,
+    ToggleFreezeAll,
+    AddMarker,
 }
 
-#[derive(Clone, Debug, PartialEq)]
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq)]
 pub enum WidgetType {
     ViewerTable,
     ViewerList,
@@ -25,6 +66,14 @@ pub enum WidgetType {
     GgPlot,
     Dynamics,
     Jitter,
+    DerivedSignals,
+    DbcBrowser,
+    Sequences,
+    RestBus,
+    Fleet,
+    UdsClient,
+    ThemeEditor,
+    FormatterEditor,
 }
 
 impl AppAction {
@@ -41,6 +90,14 @@ impl AppAction {
             ("Spawn G-G Plot", WidgetType::GgPlot),
             ("Spawn Dynamics", WidgetType::Dynamics),
             ("Spawn Jitter", WidgetType::Jitter),
+            ("Spawn Derived Signals", WidgetType::DerivedSignals),
+            ("Spawn DBC Browser", WidgetType::DbcBrowser),
+            ("Spawn Sequences", WidgetType::Sequences),
+            ("Spawn Rest Bus", WidgetType::RestBus),
+            ("Spawn Fleet", WidgetType::Fleet),
+            ("Spawn UDS Client", WidgetType::UdsClient),
+            ("Spawn Theme Editor", WidgetType::ThemeEditor),
+            ("Spawn Formatter Editor", WidgetType::FormatterEditor),
         ]
     }
 }
@@ -0,0 +1,511 @@
+use crate::{config, derived, messages};
+
+pub const ALARMS_CONFIG_FILE: &str = "alarms.json";
+pub const ALARMS_LOG_FILE: &str = "alarms.log";
+
+const BEEP_INTERVAL_SECS: u64 = 2;
+const BEEP_FREQ_HZ: f32 = 880.0;
+const BEEP_DURATION_MS: u64 = 250;
+const BEEP_VOLUME: f32 = 0.2;
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, PartialEq, Debug, Default)]
+pub enum Severity {
+    #[default]
+    Warning,
+    Critical,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+pub struct AlarmRule {
+    pub name: String,
+    // Ex: "cell_telemetry.voltage < 3.0 for 500 ms" or "thermistor_telemetry.temperature > 58"
+    pub rule: String,
+    #[serde(default)]
+    pub severity: Severity,
+    // Signals that identify separate instances inside one message (ex: module_num, cell_num),
+    // so a multiplexed cell dropping out of range doesn't get reset by its neighbours
+    #[serde(default)]
+    pub group_by: Vec<String>,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct AlarmConfig {
+    #[serde(default = "default_true")]
+    pub sound_enabled: bool,
+    #[serde(default)]
+    pub rules: Vec<AlarmRule>,
+}
+
+impl Default for AlarmConfig {
+    fn default() -> Self {
+        Self {
+            sound_enabled: true,
+            rules: Vec::new(),
+        }
+    }
+}
+
+impl AlarmConfig {
+    pub fn load() -> Self {
+        config::load_json(ALARMS_CONFIG_FILE).unwrap_or_default()
+    }
+
+    pub fn save(&self) {
+        config::save_json(ALARMS_CONFIG_FILE, self);
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, serde::Serialize, serde::Deserialize)]
+pub enum Comparison {
+    #[serde(rename = "<")]
+    Lt,
+    #[serde(rename = "<=")]
+    Le,
+    #[serde(rename = ">")]
+    Gt,
+    #[serde(rename = ">=")]
+    Ge,
+    #[serde(rename = "==")]
+    Eq,
+    #[serde(rename = "!=")]
+    Ne,
+}
+
+impl Comparison {
+    pub fn eval(&self, lhs: f64, rhs: f64) -> bool {
+        match self {
+            Comparison::Lt => lhs < rhs,
+            Comparison::Le => lhs <= rhs,
+            Comparison::Gt => lhs > rhs,
+            Comparison::Ge => lhs >= rhs,
+            Comparison::Eq => lhs == rhs,
+            Comparison::Ne => lhs != rhs,
+        }
+    }
+
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            Comparison::Lt => "<",
+            Comparison::Le => "<=",
+            Comparison::Gt => ">",
+            Comparison::Ge => ">=",
+            Comparison::Eq => "==",
+            Comparison::Ne => "!=",
+        }
+    }
+}
+
+pub struct Condition {
+    msg_glob: globset::GlobMatcher,
+    signal_glob: globset::GlobMatcher,
+    comparison: Comparison,
+    threshold: f64,
+    hold: chrono::Duration,
+}
+
+impl Condition {
+    /// Parses `<msg>.<signal> <op> <value> [for <n> ms|s]`.
+    /// Message and signal names may be globs, the same as in the formatter config.
+    pub fn parse(rule: &str) -> Result<Self, String> {
+        let tokens: Vec<&str> = rule.split_whitespace().collect();
+        if tokens.len() != 3 && tokens.len() != 6 {
+            return Err("expected `<msg>.<signal> <op> <value> [for <n> ms|s]`".to_string());
+        }
+
+        let (msg_pattern, signal_pattern) = tokens[0]
+            .rsplit_once('.')
+            .ok_or_else(|| format!("`{}` is not of the form <msg>.<signal>", tokens[0]))?;
+        let msg_glob = globset::Glob::new(msg_pattern)
+            .map_err(|e| e.to_string())?
+            .compile_matcher();
+        let signal_glob = globset::Glob::new(signal_pattern)
+            .map_err(|e| e.to_string())?
+            .compile_matcher();
+
+        let comparison = match tokens[1] {
+            "<" => Comparison::Lt,
+            "<=" => Comparison::Le,
+            ">" => Comparison::Gt,
+            ">=" => Comparison::Ge,
+            "==" => Comparison::Eq,
+            "!=" => Comparison::Ne,
+            other => return Err(format!("unknown comparison `{}`", other)),
+        };
+
+        let threshold = tokens[2]
+            .parse::<f64>()
+            .map_err(|_| format!("`{}` is not a number", tokens[2]))?;
+
+        let hold = if tokens.len() == 6 {
+            if tokens[3] != "for" {
+                return Err(format!("expected `for`, found `{}`", tokens[3]));
+            }
+            let amount = tokens[4]
+                .parse::<f64>()
+                .map_err(|_| format!("`{}` is not a number", tokens[4]))?;
+            let ms = match tokens[5] {
+                "ms" => amount,
+                "s" => amount * 1000.0,
+                other => return Err(format!("unknown time unit `{}`", other)),
+            };
+            chrono::Duration::milliseconds(ms.round() as i64)
+        } else {
+            chrono::Duration::zero()
+        };
+
+        Ok(Self {
+            msg_glob,
+            signal_glob,
+            comparison,
+            threshold,
+            hold,
+        })
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum AlarmState {
+    // Condition is true and nobody has acknowledged it yet
+    Active,
+    // Condition is still true, but someone has seen it
+    Acknowledged,
+    // Condition went away before anyone acknowledged it
+    Returned,
+}
+
+pub struct AlarmEntry {
+    pub rule_name: String,
+    pub detail: String,
+    pub severity: Severity,
+    pub state: AlarmState,
+    pub fired_at: chrono::DateTime<chrono::Local>,
+    pub last_value: f64,
+    rule_idx: usize,
+    group: String,
+}
+
+#[derive(Default)]
+struct GroupState {
+    true_since: Option<chrono::DateTime<chrono::Local>>,
+    last_value: f64,
+    firing: bool,
+}
+
+struct CompiledRule {
+    condition: Result<Condition, String>,
+    groups: std::collections::HashMap<String, GroupState>,
+}
+
+pub struct AlarmEngine {
+    pub config: AlarmConfig,
+    pub entries: Vec<AlarmEntry>,
+    rules: Vec<CompiledRule>,
+    beeper: Option<Beeper>,
+    last_beep: Option<std::time::Instant>,
+}
+
+impl AlarmEngine {
+    pub fn new(config: AlarmConfig) -> Self {
+        let mut engine = Self {
+            config,
+            entries: Vec::new(),
+            rules: Vec::new(),
+            beeper: Beeper::new(),
+            last_beep: None,
+        };
+        engine.recompile();
+        engine
+    }
+
+    pub fn load() -> Self {
+        Self::new(AlarmConfig::load())
+    }
+
+    /// Rebuilds the compiled rules after `config.rules` was edited. Resets all pending and
+    /// firing state, since rule indices may no longer line up.
+    pub fn recompile(&mut self) {
+        self.rules = self
+            .config
+            .rules
+            .iter()
+            .map(|rule| CompiledRule {
+                condition: Condition::parse(&rule.rule),
+                groups: std::collections::HashMap::new(),
+            })
+            .collect();
+        self.entries.clear();
+    }
+
+    pub fn rule_error(&self, rule_idx: usize) -> Option<&str> {
+        self.rules
+            .get(rule_idx)
+            .and_then(|r| r.condition.as_ref().err())
+            .map(|e| e.as_str())
+    }
+
+    pub fn process_messages(&mut self, can_messages: &[messages::MsgFromCan]) {
+        for msg in can_messages {
+            match msg {
+                messages::MsgFromCan::ParsedMessage(parsed) => {
+                    let values: Vec<(&str, f64)> = parsed
+                        .decoded
+                        .signals
+                        .iter()
+                        .map(|(name, sig)| (name.as_str(), sig.value.physical))
+                        .collect();
+                    self.process_values(
+                        parsed.timestamp,
+                        &parsed.decoded.name,
+                        &values,
+                        |g: &str| parsed.decoded.signals.get(g).map(|s| s.value.int_rounded()),
+                    );
+                }
+                messages::MsgFromCan::DerivedSignal(derived_signal) => {
+                    self.process_values(
+                        derived_signal.timestamp,
+                        derived::DERIVED_MSG_NAME,
+                        &[(derived_signal.name.as_str(), derived_signal.value)],
+                        |_: &str| None,
+                    );
+                }
+                _ => {}
+            }
+        }
+        self.update_firing(chrono::Local::now());
+        self.maybe_beep();
+    }
+
+    fn process_values(
+        &mut self,
+        timestamp: chrono::DateTime<chrono::Local>,
+        msg_name: &str,
+        values: &[(&str, f64)],
+        group_value: impl Fn(&str) -> Option<i64>,
+    ) {
+        for (rule, compiled) in self.config.rules.iter().zip(self.rules.iter_mut()) {
+            if !rule.enabled {
+                continue;
+            }
+            let Ok(condition) = &compiled.condition else {
+                continue;
+            };
+            if !condition.msg_glob.is_match(msg_name) {
+                continue;
+            }
+
+            let group = rule
+                .group_by
+                .iter()
+                .filter_map(|g| group_value(g).map(|v| format!("{}={}", g, v)))
+                .collect::<Vec<_>>()
+                .join(" ");
+
+            for &(sig_name, value) in values {
+                if !condition.signal_glob.is_match(sig_name) {
+                    continue;
+                }
+
+                let key = if group.is_empty() {
+                    format!("{}.{}", msg_name, sig_name)
+                } else {
+                    format!("{}.{} [{}]", msg_name, sig_name, group)
+                };
+                let state = compiled.groups.entry(key).or_default();
+                state.last_value = value;
+                if condition.comparison.eval(value, condition.threshold) {
+                    state.true_since.get_or_insert(timestamp);
+                } else {
+                    state.true_since = None;
+                }
+            }
+        }
+    }
+
+    fn update_firing(&mut self, now: chrono::DateTime<chrono::Local>) {
+        for (rule_idx, (rule, compiled)) in self
+            .config
+            .rules
+            .iter()
+            .zip(self.rules.iter_mut())
+            .enumerate()
+        {
+            let Ok(condition) = &compiled.condition else {
+                continue;
+            };
+            for (group, state) in compiled.groups.iter_mut() {
+                let should_fire = rule.enabled
+                    && state
+                        .true_since
+                        .is_some_and(|since| now - since >= condition.hold);
+
+                if should_fire && !state.firing {
+                    state.firing = true;
+                    let entry = self
+                        .entries
+                        .iter_mut()
+                        .find(|e| e.rule_idx == rule_idx && e.group == *group);
+                    match entry {
+                        Some(entry) => {
+                            // Came back before it was acknowledged, re-arm it
+                            entry.state = AlarmState::Active;
+                            entry.last_value = state.last_value;
+                        }
+                        None => self.entries.push(AlarmEntry {
+                            rule_name: rule.name.clone(),
+                            detail: format!(
+                                "{} ({} {} {})",
+                                group,
+                                state.last_value,
+                                condition.comparison.symbol(),
+                                condition.threshold
+                            ),
+                            severity: rule.severity,
+                            state: AlarmState::Active,
+                            fired_at: now,
+                            last_value: state.last_value,
+                            rule_idx,
+                            group: group.clone(),
+                        }),
+                    }
+                    log_event("FIRED", &rule.name, group, state.last_value);
+                } else if !should_fire && state.firing {
+                    state.firing = false;
+                    // Acknowledged alarms that return to normal need no further attention,
+                    // unacknowledged ones stay around until someone sees them
+                    self.entries.retain(|e| {
+                        !(e.rule_idx == rule_idx
+                            && e.group == *group
+                            && e.state == AlarmState::Acknowledged)
+                    });
+                    for entry in self
+                        .entries
+                        .iter_mut()
+                        .filter(|e| e.rule_idx == rule_idx && e.group == *group)
+                    {
+                        entry.state = AlarmState::Returned;
+                    }
+                    log_event("RETURNED", &rule.name, group, state.last_value);
+                } else if state.firing {
+                    for entry in self
+                        .entries
+                        .iter_mut()
+                        .filter(|e| e.rule_idx == rule_idx && e.group == *group)
+                    {
+                        entry.last_value = state.last_value;
+                    }
+                }
+            }
+        }
+    }
+
+    fn maybe_beep(&mut self) {
+        if !self.config.sound_enabled || self.unacknowledged_count() == 0 {
+            return;
+        }
+        let due = self
+            .last_beep
+            .is_none_or(|t| t.elapsed().as_secs() >= BEEP_INTERVAL_SECS);
+        if due && let Some(beeper) = &self.beeper {
+            beeper.beep();
+            self.last_beep = Some(std::time::Instant::now());
+        }
+    }
+
+    pub fn unacknowledged_count(&self) -> usize {
+        self.entries
+            .iter()
+            .filter(|e| e.state != AlarmState::Acknowledged)
+            .count()
+    }
+
+    pub fn acknowledge(&mut self, entry_idx: usize) {
+        let Some(entry) = self.entries.get_mut(entry_idx) else {
+            return;
+        };
+        log_event("ACK", &entry.rule_name, &entry.group, entry.last_value);
+        if entry.state == AlarmState::Returned {
+            self.entries.remove(entry_idx);
+        } else {
+            entry.state = AlarmState::Acknowledged;
+        }
+    }
+
+    pub fn acknowledge_all(&mut self) {
+        for idx in (0..self.entries.len()).rev() {
+            if self.entries[idx].state != AlarmState::Acknowledged {
+                self.acknowledge(idx);
+            }
+        }
+    }
+
+    /// Removes every alarm that is no longer firing, acknowledged or not.
+    pub fn clear_returned(&mut self) {
+        self.entries.retain(|e| e.state != AlarmState::Returned);
+    }
+}
+
+fn log_event(event: &str, rule_name: &str, group: &str, value: f64) {
+    let line = format!(
+        "{} {:<8} {} | {} | value={}\n",
+        chrono::Local::now().format("%Y-%m-%d %H:%M:%S%.3f"),
+        event,
+        rule_name,
+        group,
+        value
+    );
+    log::warn!("Alarm {}", line.trim_end());
+    if let Err(e) = config::append(ALARMS_LOG_FILE, line) {
+        log::error!("Failed to write {}: {}", ALARMS_LOG_FILE, e);
+    }
+}
+
+// The audio output stream isn't Send, so it lives on its own thread and we just poke it
+struct Beeper {
+    tx: std::sync::mpsc::Sender<()>,
+}
+
+impl Beeper {
+    // None when no audio output can be opened
+    fn new() -> Option<Self> {
+        let (tx, rx) = std::sync::mpsc::channel::<()>();
+        let (ready_tx, ready_rx) = std::sync::mpsc::channel::<bool>();
+        std::thread::spawn(move || {
+            let (_stream, handle) = match rodio::OutputStream::try_default() {
+                Ok(v) => {
+                    let _ = ready_tx.send(true);
+                    v
+                }
+                Err(e) => {
+                    log::error!("No audio output available for alarms: {}", e);
+                    let _ = ready_tx.send(false);
+                    return;
+                }
+            };
+            while rx.recv().is_ok() {
+                use rodio::Source as _;
+                match rodio::Sink::try_new(&handle) {
+                    Ok(sink) => {
+                        sink.append(
+                            rodio::source::SineWave::new(BEEP_FREQ_HZ)
+                                .take_duration(std::time::Duration::from_millis(BEEP_DURATION_MS))
+                                .amplify(BEEP_VOLUME),
+                        );
+                        sink.detach();
+                    }
+                    Err(e) => log::error!("Failed to play alarm sound: {}", e),
+                }
+            }
+        });
+        ready_rx.recv().unwrap_or(false).then_some(Self { tx })
+    }
+
+    fn beep(&self) {
+        let _ = self.tx.send(());
+    }
+}
@@ -1,28 +1,50 @@
 use crate::{
-    action, connection, formatter, messages, settings, shortcuts, theme, ui, util, widgets,
-    workspace,
+    action, alarms, config, connection, dbc, formatter, layout, messages, settings, shortcuts,
+    theme, transmit_sets, ui, util, widgets, workspace,
 };
 use eframe::egui;
 
 const UI_SCALE_STEP: f32 = 0.2;
+// The workspace is compared with the last saved one this often, and written when it changed
+const LAYOUT_AUTOSAVE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(1);
 pub struct ParserInfo {
-    pub dbc_path: std::path::PathBuf,
-    pub parser: can_decode::Parser,
+    pub dbc_paths: Vec<std::path::PathBuf>,
+    pub parser: dbc::merged::MergedParser,
+    pub lint: Vec<dbc::lint::LintIssue>,
 }
 
 impl ParserInfo {
-    // Returns None if parsing fails (missing file, invalid file, etc)
-    pub fn new(dbc_path: std::path::PathBuf) -> Option<Self> {
-        let parser = can_decode::Parser::from_dbc_file(&dbc_path)
+    // Returns None if parsing any of the files fails (missing file, invalid file, etc)
+    pub fn new(dbc_paths: Vec<std::path::PathBuf>) -> Option<Self> {
+        let parser = dbc::merged::MergedParser::from_dbc_files(&dbc_paths)
             .map_err(|e| {
-                log::error!("Failed to parse DBC file at {}: {}", dbc_path.display(), e);
+                log::error!("Failed to parse DBC files: {}", e);
                 e
             })
             .ok()?;
-        Some(Self { dbc_path, parser })
+        Some(Self::from_parser(dbc_paths, parser))
     }
-    pub fn new_maybe(dbc_path: Option<std::path::PathBuf>) -> Option<Self> {
-        dbc_path.and_then(Self::new)
+    pub fn from_parser(
+        dbc_paths: Vec<std::path::PathBuf>,
+        parser: dbc::merged::MergedParser,
+    ) -> Self {
+        let lint = dbc::lint::lint(&parser);
+        // The details are in the lint view, logging each one floods the log at every reload
+        if !lint.is_empty() {
+            log::warn!("DBC check found {} issue(s)", lint.len());
+        }
+        Self {
+            dbc_paths,
+            parser,
+            lint,
+        }
+    }
+    pub fn new_maybe(dbc_paths: Vec<std::path::PathBuf>) -> Option<Self> {
+        if dbc_paths.is_empty() {
+            None
+        } else {
+            Self::new(dbc_paths)
+        }
     }
 }
 
@@ -51,47 +73,92 @@ pub struct DAQApp {
     pub next_gg_plot_num: usize,
     pub next_dynamics_num: usize,
     pub next_jitter_num: usize,
+    pub next_derived_signals_num: usize,
+    pub next_dbc_browser_num: usize,
+    pub next_sequences_num: usize,
+    pub next_rest_bus_num: usize,
+    pub next_fleet_num: usize,
+    pub next_uds_client_num: usize,
+    pub next_theme_editor_num: usize,
+    pub next_formatter_editor_num: usize,
     pub can_to_ui_rx: std::sync::mpsc::Receiver<messages::MsgFromCan>,
     pub ui_to_can_tx: std::sync::mpsc::Sender<messages::MsgFromUi>,
     pub action_queue: Vec<action::AppAction>,
     pub selected_source: Option<connection::ConnectionSource>,
     pub theme: egui::Style,
-    pub theme_selection: theme::ThemeSelection,
+    // What widgets draw with, the selected theme's or the one being edited
+    pub theme_colors: theme::ThemeColors,
+    pub theme_name: String,
+    pub themes: theme::Themes,
     pub pixels_per_point: Option<f32>,
     pub serial_ports: Vec<serialport::SerialPortInfo>,
     pub parser: Option<ParserInfo>,
     pub can_bus_speed: connection::CanBusSpeed,
     pub udp_port: u16,
     pub can_messages: Vec<messages::MsgFromCan>,
+    pub alarms: alarms::AlarmEngine,
+    pub alarm_panel: ui::alarms::AlarmPanel,
+    pub dbc_watchers: Vec<dbc::watcher::DbcWatcher>,
+    pub dbc_reload_report: Option<ui::dbc_reload::DbcReloadReport>,
+    // Why config files couldn't be loaded or were only partly loaded, shown until dismissed
+    pub config_errors: Vec<String>,
+    pub layouts: layout::LayoutsConfig,
+    pub last_layout_autosave: std::time::Instant,
+    // Name typed in the sidebar for the next "Save layout"
+    pub new_layout_name: String,
+    pub detached: Vec<workspace::DetachedWidget>,
+    // Viewport IDs are never reused, egui keeps state per ID
+    pub next_detached_num: u64,
+    // Pane clicked last, where signals picked in the command palette go
+    pub focused_tile: Option<egui_tiles::TileId>,
+    pub keymap: shortcuts::Keymap,
+    pub show_shortcut_help: bool,
+    pub next_marker_num: usize,
+    // Where settings, the formatter and layouts are read from and saved to
+    pub profile: config::Profile,
+    pub profiles: Vec<String>,
+    // Name typed in the sidebar for the next "New profile"
+    pub new_profile_name: String,
 }
 
 impl DAQApp {
     pub fn save_settings(&self) {
         let settings = settings::Settings {
-            dbc_path: self.parser.as_ref().map(|p| p.dbc_path.clone()),
+            dbc_paths: self
+                .parser
+                .as_ref()
+                .map(|p| p.dbc_paths.clone())
+                .unwrap_or_default(),
+            version: settings::SETTINGS_VERSION,
             selected_source: self.selected_source.clone(),
             selected_speed: self.can_bus_speed,
             udp_port: self.udp_port,
-            theme: self.theme_selection,
+            theme: self.theme_name.clone(),
             pixels_per_point: self.pixels_per_point,
         };
-        settings.save();
+        settings.save(&self.profile);
     }
 
     pub fn new(
         can_to_ui_rx: std::sync::mpsc::Receiver<messages::MsgFromCan>,
         ui_to_can_tx: std::sync::mpsc::Sender<messages::MsgFromUi>,
         settings: settings::Settings,
+        settings_error: Option<String>,
+        profile: config::Profile,
         cc: &eframe::CreationContext,
     ) -> Self {
-        let theme_selection = settings.theme;
-        let theme_style = theme_selection.get_style();
+        let themes = theme::Themes::load();
+        let selected_theme = themes.get(&settings.theme);
+        let theme_name = selected_theme.name.clone();
+        let theme_style = selected_theme.style();
+        let theme_colors = selected_theme.colors();
 
         egui_extras::install_image_loaders(&cc.egui_ctx);
 
-        Self {
+        let (layouts, layout_issues) = layout::LayoutsConfig::load(&profile);
+        let mut app = Self {
             connection_status: ConnectionStatus::Disconnected,
-            value_formatter: formatter::Formatter::try_load(),
+            value_formatter: formatter::Formatter::try_load(&profile),
             is_sidebar_open: true,
             command_palette: ui::command_palette::CommandPalette::new(),
             tile_tree: egui_tiles::Tree::empty("workspace_tree"),
@@ -107,19 +174,101 @@ impl DAQApp {
             next_gg_plot_num: 1,
             next_dynamics_num: 1,
             next_jitter_num: 1,
+            next_derived_signals_num: 1,
+            next_dbc_browser_num: 1,
+            next_sequences_num: 1,
+            next_rest_bus_num: 1,
+            next_fleet_num: 1,
+            next_uds_client_num: 1,
+            next_theme_editor_num: 1,
+            next_formatter_editor_num: 1,
             can_to_ui_rx,
             ui_to_can_tx,
             action_queue: Vec::new(),
             selected_source: settings.selected_source,
             theme: theme_style,
-            theme_selection,
+            theme_colors,
+            theme_name,
+            themes,
             pixels_per_point: settings.pixels_per_point,
             serial_ports: util::get_available_serial_ports(),
-            parser: ParserInfo::new_maybe(settings.dbc_path),
+            parser: ParserInfo::new_maybe(settings.dbc_paths),
             can_bus_speed: settings.selected_speed,
             udp_port: settings.udp_port,
             can_messages: Vec::new(),
+            alarms: alarms::AlarmEngine::load(),
+            alarm_panel: ui::alarms::AlarmPanel::new(),
+            dbc_watchers: Vec::new(),
+            dbc_reload_report: None,
+            config_errors: settings_error.into_iter().chain(layout_issues).collect(),
+            layouts,
+            last_layout_autosave: std::time::Instant::now(),
+            new_layout_name: String::new(),
+            detached: Vec::new(),
+            next_detached_num: 0,
+            focused_tile: None,
+            keymap: shortcuts::Keymap::load(),
+            show_shortcut_help: false,
+            next_marker_num: 1,
+            profiles: config::Profile::list(),
+            new_profile_name: String::new(),
+            profile,
+        };
+        let last_layout = app.layouts.last.clone();
+        let last_detached = app.layouts.last_detached.clone();
+        app.restore_layout(last_layout.as_ref(), &last_detached);
+        app.start_launch_transmit_sets();
+        app
+    }
+
+    // Sets marked "On launch" are started in the first Send UI of the restored layout, so the
+    // autosaved one is reused instead of another being added on every start
+    fn start_launch_transmit_sets(&mut self) {
+        // Already logged, the Send UI shows it when its sets are opened
+        let Ok(config) = transmit_sets::TransmitSetsConfig::load() else {
+            return;
+        };
+        let launch_sets: Vec<_> = config.sets.iter().filter(|s| s.start_on_launch).collect();
+        if launch_sets.is_empty() {
+            return;
         }
+
+        let parser = self.parser.as_ref().map(|p| &p.parser);
+        let start = |send_ui: &mut ui::send::SendUi| {
+            for set in &launch_sets {
+                log::info!("Starting transmit set {} on launch", set.name);
+                for warning in send_ui.start_set(set, parser) {
+                    log::warn!("{}", warning);
+                }
+            }
+        };
+
+        let restored = self
+            .tile_tree
+            .tiles
+            .iter_mut()
+            .filter_map(|(_, tile)| match tile {
+                egui_tiles::Tile::Pane(widget) => Some(widget),
+                _ => None,
+            })
+            .chain(
+                self.detached
+                    .iter_mut()
+                    .map(|detached| &mut detached.widget),
+            )
+            .find_map(|widget| match widget {
+                widgets::Widget::SendUi(send_ui) => Some(send_ui),
+                _ => None,
+            });
+        if let Some(send_ui) = restored {
+            start(send_ui);
+            return;
+        }
+
+        let mut send_ui = ui::send::SendUi::new(self.next_send_ui_num, self.ui_to_can_tx.clone());
+        self.next_send_ui_num += 1;
+        start(&mut send_ui);
+        self.add_widget_to_tree(widgets::Widget::SendUi(send_ui));
     }
 
     fn add_widget_to_tree(&mut self, widget: widgets::Widget) {
@@ -161,97 +310,533 @@ impl DAQApp {
             .send(messages::MsgFromUi::Connect(source.clone()));
     }
 
+    // Keeps one watcher per selected DBC and reloads them all once any of them changed on disk
+    fn check_dbc_changed(&mut self) {
+        let dbc_paths = self
+            .parser
+            .as_ref()
+            .map(|p| p.dbc_paths.as_slice())
+            .unwrap_or_default();
+        if !self
+            .dbc_watchers
+            .iter()
+            .map(|w| w.path())
+            .eq(dbc_paths.iter())
+        {
+            self.dbc_watchers = dbc_paths
+                .iter()
+                .map(|p| dbc::watcher::DbcWatcher::new(p.clone()))
+                .collect();
+            return;
+        }
+
+        // Poll every watcher so none of them keeps a stale pending change around
+        let changed_paths: Vec<_> = self
+            .dbc_watchers
+            .iter_mut()
+            .filter_map(|w| w.poll().then(|| w.path().to_path_buf()))
+            .collect();
+        if !changed_paths.is_empty() {
+            self.reload_dbc(changed_paths);
+        }
+    }
+
+    // `changed_paths` is only used for the report, all files are re-read and merged again
+    pub fn reload_dbc(&mut self, changed_paths: Vec<std::path::PathBuf>) {
+        let Some(old) = &self.parser else {
+            return;
+        };
+        let dbc_paths = old.dbc_paths.clone();
+        let old_msg_defs = old.parser.msg_defs();
+        log::info!("Reloading DBC(s) {:?}", dbc_paths);
+
+        let mut widget_warnings = Vec::new();
+        let result = match dbc::merged::MergedParser::from_dbc_files(&dbc_paths) {
+            Ok(parser) => {
+                let diff = dbc::diff::DbcDiff::between(&old_msg_defs, &parser.msg_defs());
+                log::info!("Reloaded DBC: {}", diff.summary());
+
+                for (_, tile) in self.tile_tree.tiles.iter_mut() {
+                    if let egui_tiles::Tile::Pane(widget) = tile {
+                        widget_warnings.extend(widget.on_dbc_reloaded(&parser));
+                    }
+                }
+                for detached in &mut self.detached {
+                    widget_warnings.extend(detached.widget.on_dbc_reloaded(&parser));
+                }
+                for warning in &widget_warnings {
+                    log::warn!("{}", warning);
+                }
+
+                let _ = self
+                    .ui_to_can_tx
+                    .send(messages::MsgFromUi::DbcSelected(dbc_paths.clone()));
+                self.parser = Some(ParserInfo::from_parser(dbc_paths, parser));
+                Ok(diff)
+            }
+            Err(e) => {
+                log::error!("Failed to reload DBC: {}", e);
+                Err(e)
+            }
+        };
+
+        self.dbc_reload_report = Some(ui::dbc_reload::DbcReloadReport {
+            reloaded_at: chrono::Local::now(),
+            changed_paths,
+            result,
+            widget_warnings,
+        });
+    }
+
+    // Replaces the whole workspace, the current widgets are dropped
+    fn restore_layout(
+        &mut self,
+        root: Option<&layout::LayoutNode>,
+        detached: &[layout::DetachedConfig],
+    ) {
+        let mut tiles = egui_tiles::Tiles::default();
+        let mut warnings = Vec::new();
+        let root = root.map(|node| self.build_layout_tile(node, &mut tiles, &mut warnings));
+        self.tile_tree = match root {
+            Some(root) => egui_tiles::Tree::new("workspace_tree", root, tiles),
+            None => egui_tiles::Tree::empty("workspace_tree"),
+        };
+
+        self.detached.clear();
+        for config in detached {
+            let mut widget = self.create_widget(&config.widget.widget_type());
+            warnings.extend(widget.apply_config(
+                config.widget.clone(),
+                self.parser.as_ref().map(|p| &p.parser),
+            ));
+            self.detach_widget(widget, config.pos, config.size);
+        }
+
+        for warning in warnings {
+            log::warn!("Layout: {}", warning);
+        }
+    }
+
+    fn detach_widget(
+        &mut self,
+        widget: widgets::Widget,
+        pos: Option<[f32; 2]>,
+        size: Option<[f32; 2]>,
+    ) {
+        let viewport_id =
+            egui::ViewportId::from_hash_of(("detached_widget", self.next_detached_num));
+        self.next_detached_num += 1;
+        self.detached.push(workspace::DetachedWidget::new(
+            viewport_id,
+            widget,
+            pos,
+            size,
+        ));
+    }
+
+    fn build_layout_tile(
+        &mut self,
+        node: &layout::LayoutNode,
+        tiles: &mut egui_tiles::Tiles<widgets::Widget>,
+        warnings: &mut Vec<String>,
+    ) -> egui_tiles::TileId {
+        let mut build_children = |app: &mut Self, children: &[layout::LayoutNode]| {
+            children
+                .iter()
+                .map(|child| app.build_layout_tile(child, tiles, warnings))
+                .collect::<Vec<_>>()
+        };
+
+        match node {
+            layout::LayoutNode::Pane(config) => {
+                let mut widget = self.create_widget(&config.widget_type());
+                warnings.extend(
+                    widget.apply_config(config.clone(), self.parser.as_ref().map(|p| &p.parser)),
+                );
+                tiles.insert_pane(widget)
+            }
+            layout::LayoutNode::Tabs { children, active } => {
+                let child_ids = build_children(self, children);
+                let active_id = active.and_then(|idx| child_ids.get(idx).copied());
+                let tabs_id = tiles.insert_tab_tile(child_ids);
+                if let Some(active_id) = active_id
+                    && let Some(egui_tiles::Tile::Container(egui_tiles::Container::Tabs(tabs))) =
+                        tiles.get_mut(tabs_id)
+                {
+                    tabs.set_active(active_id);
+                }
+                tabs_id
+            }
+            layout::LayoutNode::Horizontal { children, shares }
+            | layout::LayoutNode::Vertical { children, shares } => {
+                let child_ids = build_children(self, children);
+                let linear_id = if matches!(node, layout::LayoutNode::Horizontal { .. }) {
+                    tiles.insert_horizontal_tile(child_ids.clone())
+                } else {
+                    tiles.insert_vertical_tile(child_ids.clone())
+                };
+                if let Some(egui_tiles::Tile::Container(egui_tiles::Container::Linear(linear))) =
+                    tiles.get_mut(linear_id)
+                {
+                    for (child_id, share) in child_ids.iter().zip(shares) {
+                        linear.shares.set_share(*child_id, *share);
+                    }
+                }
+                linear_id
+            }
+            layout::LayoutNode::Grid { children } => {
+                let child_ids = build_children(self, children);
+                tiles.insert_grid_tile(child_ids)
+            }
+        }
+    }
+
+    // Keeps `layouts.last` up to date so the workspace survives a restart, or a crash
+    fn autosave_layout(&mut self) {
+        if self.last_layout_autosave.elapsed() < LAYOUT_AUTOSAVE_INTERVAL {
+            return;
+        }
+        self.last_layout_autosave = std::time::Instant::now();
+        self.save_last_layout();
+    }
+
+    fn save_last_layout(&mut self) {
+        let current = layout::LayoutNode::capture(&self.tile_tree);
+        let current_detached: Vec<_> = self.detached.iter().map(|d| d.config()).collect();
+        if current != self.layouts.last || current_detached != self.layouts.last_detached {
+            self.layouts.last = current;
+            self.layouts.last_detached = current_detached;
+            self.layouts.save(&self.profile);
+        }
+    }
+
+    // Saves the current profile as it is, then loads everything the new one bundles
+    fn switch_profile(&mut self, profile: config::Profile) {
+        self.save_settings();
+        self.save_last_layout();
+
+        log::info!("Switching to profile {}", profile.name);
+        self.profile = profile;
+        config::ProfilesConfig {
+            active: self.profile.name.clone(),
+        }
+        .save();
+
+        let (settings, settings_error) = settings::Settings::load(&self.profile);
+        self.config_errors = settings_error.into_iter().collect();
+        self.parser = ParserInfo::new_maybe(settings.dbc_paths.clone());
+        let _ = self
+            .ui_to_can_tx
+            .send(messages::MsgFromUi::DbcSelected(settings.dbc_paths));
+        self.can_bus_speed = settings.selected_speed;
+        self.udp_port = settings.udp_port;
+        self.select_theme(&settings.theme);
+        self.pixels_per_point = settings.pixels_per_point;
+        self.selected_source = settings.selected_source;
+        if self.selected_source.is_some() {
+            self.connect_can();
+        } else {
+            self.connection_status = ConnectionStatus::Disconnected;
+            let _ = self.ui_to_can_tx.send(messages::MsgFromUi::Disconnect);
+        }
+
+        self.value_formatter = formatter::Formatter::try_load(&self.profile);
+        let (layouts, layout_issues) = layout::LayoutsConfig::load(&self.profile);
+        self.layouts = layouts;
+        self.config_errors.extend(layout_issues);
+        let last_layout = self.layouts.last.clone();
+        let last_detached = self.layouts.last_detached.clone();
+        self.restore_layout(last_layout.as_ref(), &last_detached);
+    }
+
+    // Builds a widget of the given type, numbered after the ones already spawned
+    fn create_widget(&mut self, widget_type: &action::WidgetType) -> widgets::Widget {
+        let widget = match widget_type {
+            action::WidgetType::ViewerTable => widgets::Widget::ViewerTable(
+                ui::viewer_table::ViewerTable::new(self.next_can_viewer_num),
+            ),
+            action::WidgetType::ViewerList => widgets::Widget::ViewerList(
+                ui::viewer_list::ViewerList::new(self.next_can_list_num),
+            ),
+            action::WidgetType::Bootloader => {
+                widgets::Widget::Bootloader(ui::bootloader::Bootloader::new(
+                    self.next_bootloader_num,
+                    self.ui_to_can_tx.clone(),
+                ))
+            }
+            action::WidgetType::Scope {
+                msg_id,
+                msg_name,
+                signal_name,
+            } => widgets::Widget::Scope(ui::scope::Scope::new(
+                self.next_scope_num,
+                *msg_id,
+                msg_name.clone(),
+                signal_name.clone(),
+            )),
+            action::WidgetType::LogParser => {
+                widgets::Widget::LogParser(ui::log_parser::LogParser::new(self.next_log_parser_num))
+            }
+            action::WidgetType::SendUi => widgets::Widget::SendUi(ui::send::SendUi::new(
+                self.next_send_ui_num,
+                self.ui_to_can_tx.clone(),
+            )),
+            action::WidgetType::BusLoad => {
+                widgets::Widget::BusLoad(ui::bus_load::BusLoad::new(self.next_bus_load_num))
+            }
+            action::WidgetType::BatteryVoltage => widgets::Widget::BatteryVoltage(
+                ui::battery::battery_voltage::BatteryVoltage::new(self.next_battery_voltage_num),
+            ),
+            action::WidgetType::BatteryTemps => widgets::Widget::BatteryTemps(
+                ui::battery::battery_temps::BatteryTemps::new(self.next_battery_temps_num),
+            ),
+            action::WidgetType::GgPlot => {
+                widgets::Widget::GgPlot(ui::gg_plot::GgPlot::new(self.next_gg_plot_num))
+            }
+            action::WidgetType::Dynamics => {
+                widgets::Widget::Dynamics(ui::dynamics::Dynamics::new(self.next_dynamics_num))
+            }
+            action::WidgetType::Jitter => {
+                widgets::Widget::Jitter(ui::jitter::Jitter::new(self.next_jitter_num))
+            }
+            action::WidgetType::DerivedSignals => {
+                widgets::Widget::DerivedSignals(ui::derived_signals::DerivedSignals::new(
+                    self.next_derived_signals_num,
+                    self.ui_to_can_tx.clone(),
+                ))
+            }
+            action::WidgetType::DbcBrowser => widgets::Widget::DbcBrowser(
+                ui::dbc_browser::DbcBrowser::new(self.next_dbc_browser_num),
+            ),
+            action::WidgetType::Sequences => widgets::Widget::Sequences(
+                ui::sequences::Sequences::new(self.next_sequences_num, self.ui_to_can_tx.clone()),
+            ),
+            action::WidgetType::RestBus => widgets::Widget::RestBus(ui::rest_bus::RestBus::new(
+                self.next_rest_bus_num,
+                self.ui_to_can_tx.clone(),
+            )),
+            action::WidgetType::Fleet => widgets::Widget::Fleet(ui::fleet::Fleet::new(
+                self.next_fleet_num,
+                self.ui_to_can_tx.clone(),
+            )),
+            action::WidgetType::UdsClient => widgets::Widget::UdsClient(ui::uds::UdsClient::new(
+                self.next_uds_client_num,
+                self.ui_to_can_tx.clone(),
+            )),
+            action::WidgetType::ThemeEditor => widgets::Widget::ThemeEditor(
+                ui::theme_editor::ThemeEditor::new(self.next_theme_editor_num, &self.theme_name),
+            ),
+            action::WidgetType::FormatterEditor => {
+                widgets::Widget::FormatterEditor(ui::formatter_editor::FormatterEditor::new(
+                    self.next_formatter_editor_num,
+                    self.profile.clone(),
+                ))
+            }
+        };
+
+        // Increment the appropriate counter
+        match widget_type {
+            action::WidgetType::ViewerTable => {
+                self.next_can_viewer_num += 1;
+            }
+            action::WidgetType::ViewerList => {
+                self.next_can_list_num += 1;
+            }
+            action::WidgetType::Bootloader => {
+                self.next_bootloader_num += 1;
+            }
+            action::WidgetType::Scope { .. } => {
+                self.next_scope_num += 1;
+            }
+            action::WidgetType::LogParser => {
+                self.next_log_parser_num += 1;
+            }
+            action::WidgetType::SendUi => {
+                self.next_send_ui_num += 1;
+            }
+            action::WidgetType::BusLoad => {
+                self.next_bus_load_num += 1;
+            }
+            action::WidgetType::BatteryVoltage => {
+                self.next_battery_voltage_num += 1;
+            }
+            action::WidgetType::BatteryTemps => {
+                self.next_battery_temps_num += 1;
+            }
+            action::WidgetType::GgPlot => {
+                self.next_gg_plot_num += 1;
+            }
+            action::WidgetType::Dynamics => {
+                self.next_dynamics_num += 1;
+            }
+            action::WidgetType::Jitter => {
+                self.next_jitter_num += 1;
+            }
+            action::WidgetType::DerivedSignals => {
+                self.next_derived_signals_num += 1;
+            }
+            action::WidgetType::DbcBrowser => {
+                self.next_dbc_browser_num += 1;
+            }
+            action::WidgetType::Sequences => {
+                self.next_sequences_num += 1;
+            }
+            action::WidgetType::RestBus => {
+                self.next_rest_bus_num += 1;
+            }
+            action::WidgetType::Fleet => {
+                self.next_fleet_num += 1;
+            }
+            action::WidgetType::UdsClient => {
+                self.next_uds_client_num += 1;
+            }
+            action::WidgetType::ThemeEditor => {
+                self.next_theme_editor_num += 1;
+            }
+            action::WidgetType::FormatterEditor => {
+                self.next_formatter_editor_num += 1;
+            }
+        }
+        widget
+    }
+
     pub fn handle_action(&mut self, action: action::AppAction, ctx: &egui::Context) {
         match action {
             action::AppAction::SpawnWidget(widget_type) => {
-                let widget = match &widget_type {
-                    action::WidgetType::ViewerTable => widgets::Widget::ViewerTable(
-                        ui::viewer_table::ViewerTable::new(self.next_can_viewer_num),
-                    ),
-                    action::WidgetType::ViewerList => widgets::Widget::ViewerList(
-                        ui::viewer_list::ViewerList::new(self.next_can_list_num),
-                    ),
-                    action::WidgetType::Bootloader => widgets::Widget::Bootloader(
-                        ui::bootloader::Bootloader::new(self.next_bootloader_num),
-                    ),
-                    action::WidgetType::Scope {
-                        msg_id,
-                        msg_name,
-                        signal_name,
-                    } => widgets::Widget::Scope(ui::scope::Scope::new(
-                        self.next_scope_num,
-                        *msg_id,
-                        msg_name.clone(),
-                        signal_name.clone(),
-                    )),
-                    action::WidgetType::LogParser => widgets::Widget::LogParser(
-                        ui::log_parser::LogParser::new(self.next_log_parser_num),
-                    ),
-                    action::WidgetType::SendUi => widgets::Widget::SendUi(ui::send::SendUi::new(
-                        self.next_send_ui_num,
-                        self.ui_to_can_tx.clone(),
-                    )),
-                    action::WidgetType::BusLoad => {
-                        widgets::Widget::BusLoad(ui::bus_load::BusLoad::new(self.next_bus_load_num))
-                    }
-                    action::WidgetType::BatteryVoltage => widgets::Widget::BatteryVoltage(
-                        ui::battery::battery_voltage::BatteryVoltage::new(
-                            self.next_battery_voltage_num,
-                        ),
-                    ),
-                    action::WidgetType::BatteryTemps => widgets::Widget::BatteryTemps(
-                        ui::battery::battery_temps::BatteryTemps::new(self.next_battery_temps_num),
-                    ),
-                    action::WidgetType::GgPlot => {
-                        widgets::Widget::GgPlot(ui::gg_plot::GgPlot::new(self.next_gg_plot_num))
-                    }
-                    action::WidgetType::Dynamics => widgets::Widget::Dynamics(
-                        ui::dynamics::Dynamics::new(self.next_dynamics_num),
-                    ),
-                    action::WidgetType::Jitter => {
-                        widgets::Widget::Jitter(ui::jitter::Jitter::new(self.next_jitter_num))
-                    }
+                let widget = self.create_widget(&widget_type);
+                self.add_widget_to_tree(widget);
+            }
+            action::AppAction::ShowMessage {
+                msg_name,
+                new_widget,
+            } => {
+                let Some(msg) = self.msg_def_by_name(&msg_name) else {
+                    return;
                 };
+                if !new_widget && self.focused_widget().is_some_and(|w| w.take_message(&msg)) {
+                    return;
+                }
+                let mut widget = self.create_widget(&action::WidgetType::DbcBrowser);
+                widget.take_message(&msg);
                 self.add_widget_to_tree(widget);
-
-                // Increment the appropriate counter
-                match widget_type {
-                    action::WidgetType::ViewerTable => {
-                        self.next_can_viewer_num += 1;
-                    }
-                    action::WidgetType::ViewerList => {
-                        self.next_can_list_num += 1;
-                    }
-                    action::WidgetType::Bootloader => {
-                        self.next_bootloader_num += 1;
-                    }
-                    action::WidgetType::Scope { .. } => {
-                        self.next_scope_num += 1;
-                    }
-                    action::WidgetType::LogParser => {
-                        self.next_log_parser_num += 1;
-                    }
-                    action::WidgetType::SendUi => {
-                        self.next_send_ui_num += 1;
-                    }
-                    action::WidgetType::BusLoad => {
-                        self.next_bus_load_num += 1;
-                    }
-                    action::WidgetType::BatteryVoltage => {
-                        self.next_battery_voltage_num += 1;
-                    }
-                    action::WidgetType::BatteryTemps => {
-                        self.next_battery_temps_num += 1;
-                    }
-                    action::WidgetType::GgPlot => {
-                        self.next_gg_plot_num += 1;
-                    }
-                    action::WidgetType::Dynamics => {
-                        self.next_dynamics_num += 1;
-                    }
-                    action::WidgetType::Jitter => {
-                        self.next_jitter_num += 1;
+            }
+            action::AppAction::ShowSignal {
+                msg_id,
+                msg_name,
+                signal_name,
+                new_widget,
+            } => {
+                if !new_widget
+                    && let Some(msg) = self.msg_def_by_name(&msg_name)
+                    && self
+                        .focused_widget()
+                        .is_some_and(|w| w.take_signal(&msg, &signal_name))
+                {
+                    return;
+                }
+                let widget = self.create_widget(&action::WidgetType::Scope {
+                    msg_id,
+                    msg_name,
+                    signal_name,
+                });
+                self.add_widget_to_tree(widget);
+            }
+            action::AppAction::SaveLayout(name) => {
+                log::info!("Saving layout {}", name);
+                self.layouts.set(layout::NamedLayout {
+                    name,
+                    root: layout::LayoutNode::capture(&self.tile_tree),
+                    detached: self.detached.iter().map(|d| d.config()).collect(),
+                });
+                self.layouts.save(&self.profile);
+            }
+            action::AppAction::LoadLayout(name) => {
+                let Some(named) = self.layouts.get(&name) else {
+                    log::warn!("No layout named {}", name);
+                    return;
+                };
+                log::info!("Loading layout {}", name);
+                let root = named.root.clone();
+                let detached = named.detached.clone();
+                self.restore_layout(root.as_ref(), &detached);
+            }
+            action::AppAction::DetachWidget(tile_id) => {
+                if let Some(egui_tiles::Tile::Pane(widget)) = self.tile_tree.tiles.remove(tile_id) {
+                    self.detach_widget(widget, None, None);
+                }
+            }
+            action::AppAction::DockWidget(viewport_id) => {
+                if let Some(idx) = self
+                    .detached
+                    .iter()
+                    .position(|d| d.viewport_id == viewport_id)
+                {
+                    let detached = self.detached.remove(idx);
+                    self.add_widget_to_tree(detached.widget);
+                }
+            }
+            action::AppAction::DeleteLayout(name) => {
+                self.layouts.named.retain(|layout| layout.name != name);
+                self.layouts.save(&self.profile);
+            }
+            action::AppAction::Connect(source) => {
+                self.selected_source = Some(source);
+                self.connect_can();
+                self.save_settings();
+            }
+            action::AppAction::Disconnect => {
+                self.selected_source = None;
+                self.connection_status = ConnectionStatus::Disconnected;
+                let _ = self.ui_to_can_tx.send(messages::MsgFromUi::Disconnect);
+                self.save_settings();
+            }
+            action::AppAction::SelectDbc => {
+                let ui_to_can_tx = self.ui_to_can_tx.clone();
+                ui::sidebar::select_dbc(self, &ui_to_can_tx, false);
+            }
+            action::AppAction::ToggleTheme => {
+                self.toggle_theme();
+                self.save_settings();
+            }
+            action::AppAction::SelectTheme(name) => {
+                self.select_theme(&name);
+                self.save_settings();
+            }
+            action::AppAction::PreviewTheme(colors) => {
+                self.theme = colors.to_egui_style();
+                self.theme_colors = colors;
+            }
+            action::AppAction::EndThemePreview => {
+                let name = self.theme_name.clone();
+                self.select_theme(&name);
+            }
+            action::AppAction::ThemeSaved(name) => {
+                self.themes = theme::Themes::load();
+                self.select_theme(&name);
+                self.save_settings();
+            }
+            action::AppAction::ReloadFormatter => {
+                self.value_formatter = formatter::Formatter::try_load(&self.profile);
+            }
+            action::AppAction::SwitchProfile(name) => {
+                if name != self.profile.name {
+                    self.switch_profile(config::Profile::new(name));
+                }
+            }
+            action::AppAction::CreateProfile(name) => {
+                if !config::is_valid_file_name(&name) || self.profiles.contains(&name) {
+                    log::warn!("Can't create a profile named {}", name);
+                    return;
+                }
+                // Saved first so the copy has the current state
+                self.save_settings();
+                self.save_last_layout();
+                match config::Profile::create(&name, &self.profile) {
+                    Ok(profile) => {
+                        self.profiles = config::Profile::list();
+                        self.switch_profile(profile);
                     }
+                    Err(e) => log::error!("Failed to create profile {}: {}", name, e),
                 }
             }
             action::AppAction::ToggleSidebar => {
@@ -260,6 +845,9 @@ impl DAQApp {
             action::AppAction::ToggleCommandPalette => {
                 self.command_palette.toggle();
             }
+            action::AppAction::ToggleShortcutHelp => {
+                self.show_shortcut_help = !self.show_shortcut_help;
+            }
             action::AppAction::CloseActiveWidget => {
                 self.close_active_widget();
             }
@@ -277,12 +865,85 @@ impl DAQApp {
                 self.pixels_per_point = Some(current_scale - UI_SCALE_STEP);
                 self.save_settings();
             }
+            action::AppAction::TogglePauseFocused => {
+                if let Some(widget) = self.focused_widget()
+                    && let Some(paused) = widget.is_paused()
+                {
+                    widget.set_paused(!paused);
+                }
+            }
+            action::AppAction::ClearFocused => {
+                if let Some(widget) = self.focused_widget() {
+                    widget.clear();
+                }
+            }
+            action::AppAction::ToggleFreezeAll => {
+                // Freeze everything unless all of it already is
+                let freeze = self.all_widgets_mut().any(|w| w.is_paused() == Some(false));
+                for widget in self.all_widgets_mut() {
+                    widget.set_paused(freeze);
+                }
+            }
+            action::AppAction::AddMarker => {
+                let label = format!("M{}", self.next_marker_num);
+                self.next_marker_num += 1;
+                let now = chrono::Local::now();
+                log::info!("Marker {} at {}", label, now.format("%H:%M:%S%.3f"));
+                for widget in self.all_widgets_mut() {
+                    widget.add_marker(now, &label);
+                }
+            }
         }
     }
 
     pub fn toggle_theme(&mut self) {
-        self.theme_selection = self.theme_selection.next();
-        self.theme = self.theme_selection.get_style();
+        let next = self.themes.next_after(&self.theme_name).name.clone();
+        self.select_theme(&next);
+    }
+
+    pub fn select_theme(&mut self, name: &str) {
+        let selected = self.themes.get(name);
+        self.theme_name = selected.name.clone();
+        self.theme = selected.style();
+        self.theme_colors = selected.colors();
+    }
+
+    // Panes of the tile tree and the detached windows
+    fn all_widgets_mut(&mut self) -> impl Iterator<Item = &mut widgets::Widget> {
+        self.tile_tree
+            .tiles
+            .iter_mut()
+            .filter_map(|(_, tile)| match tile {
+                egui_tiles::Tile::Pane(widget) => Some(widget),
+                _ => None,
+            })
+            .chain(self.detached.iter_mut().map(|d| &mut d.widget))
+    }
+
+    fn msg_def_by_name(&self, msg_name: &str) -> Option<can_dbc::Message> {
+        self.parser
+            .as_ref()
+            .and_then(|p| p.parser.msg_defs().into_iter().find(|m| m.name == msg_name))
+    }
+
+    // The pane clicked last if it is still visible, otherwise the first visible one
+    fn focused_widget(&mut self) -> Option<&mut widgets::Widget> {
+        let active_tiles = self.tile_tree.active_tiles();
+        let tile_id = self
+            .focused_tile
+            .filter(|id| active_tiles.contains(id))
+            .or_else(|| {
+                active_tiles.into_iter().find(|id| {
+                    matches!(
+                        self.tile_tree.tiles.get(*id),
+                        Some(egui_tiles::Tile::Pane(_))
+                    )
+                })
+            })?;
+        match self.tile_tree.tiles.get_mut(tile_id) {
+            Some(egui_tiles::Tile::Pane(widget)) => Some(widget),
+            _ => None,
+        }
     }
 
     // Close the currently active widget in the tile tree
@@ -315,25 +976,37 @@ impl eframe::App for DAQApp {
                 }
                 messages::MsgFromCan::ParsedMessage(_)
                 | messages::MsgFromCan::UnparsedMessage(_)
+                | messages::MsgFromCan::DerivedSignal(_)
                 | messages::MsgFromCan::MessageSent { .. }
-                | messages::MsgFromCan::BusLoad { .. } => {
+                | messages::MsgFromCan::BusLoad { .. }
+                | messages::MsgFromCan::SequenceStatus { .. }
+                | messages::MsgFromCan::FlashProgress { .. }
+                | messages::MsgFromCan::NodeInfo { .. }
+                | messages::MsgFromCan::UdsResponse { .. }
+                | messages::MsgFromCan::IsoTpMessage(_) => {
                     // Nothing special to do here, the message will be handled
                     // in the individual widgets
                 }
             }
             self.can_messages.push(msg);
         }
+        self.alarms.process_messages(&self.can_messages);
+        self.check_dbc_changed();
         if let Some(ppp) = self.pixels_per_point {
             ctx.set_pixels_per_point(ppp);
         }
         ctx.set_style(self.theme.clone());
+        ui::theme::store_theme(ctx, self.theme_colors.clone());
 
         // Handle keyboard shortcuts
-        self.action_queue
-            .extend(shortcuts::ShortcutHandler::check_shortcuts(ctx));
+        self.action_queue.extend(self.keymap.check_shortcuts(ctx));
 
-        // Command Palette UI and action generation
-        self.action_queue.extend(self.command_palette.ui(ctx));
+        // Command Palette UI and action generation, its entries are only built while it is open
+        if self.command_palette.is_open() {
+            let entries = ui::command_palette::entries(self);
+            self.action_queue
+                .extend(self.command_palette.ui(ctx, &entries));
+        }
 
         // Drain the action queue and handle all actions
         for action in std::mem::take(&mut self.action_queue) {
@@ -342,7 +1015,13 @@ impl eframe::App for DAQApp {
 
         // Render the most recent state of the UI
         ui::sidebar::show(self, ctx);
+        self.alarm_panel.show(ctx, &mut self.alarms);
+        ui::shortcut_help::show(self, ctx);
+        ui::dbc_reload::show(self, ctx);
+        ui::config_errors::show(self, ctx);
         workspace::show(self, ctx);
+        workspace::show_detached(self, ctx);
+        self.autosave_layout();
         ctx.request_repaint();
     }
 }
@@ -0,0 +1,412 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use crate::bootloader::{self, image, protocol};
+use crate::{can, messages};
+
+// The node is pinged until it answers, so it can be power cycled into its bootloader
+const PING_INTERVAL: Duration = Duration::from_millis(100);
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+// Erasing a whole app region takes a while on most MCUs
+const ERASE_TIMEOUT: Duration = Duration::from_secs(10);
+const RESPONSE_TIMEOUT: Duration = Duration::from_millis(500);
+// Attempts per block (or verify) before giving up
+const MAX_ATTEMPTS: usize = 5;
+// A block goes out in bursts, so slcan adapters' TX buffers and the nodes' RX FIFOs keep up. The
+// gap grows with each attempt at a block, in case the bus was too busy for the last one.
+const FRAMES_PER_BURST: usize = 8;
+const BURST_GAP: Duration = Duration::from_millis(2);
+
+pub struct FlashJob {
+    pub node: bootloader::BootNode,
+    pub image: image::Image,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum FlashProgress {
+    WaitingForNode,
+    Erasing,
+    Writing {
+        bytes_done: usize,
+        bytes_total: usize,
+        retries: usize,
+    },
+    Verifying,
+    Done {
+        elapsed: Duration,
+        retries: usize,
+    },
+    Failed(String),
+}
+
+impl FlashProgress {
+    pub fn is_finished(&self) -> bool {
+        matches!(self, FlashProgress::Done { .. } | FlashProgress::Failed(_))
+    }
+
+    pub fn display(&self) -> String {
+        match self {
+            FlashProgress::WaitingForNode => "waiting for the node".to_string(),
+            FlashProgress::Erasing => "erasing".to_string(),
+            FlashProgress::Writing {
+                bytes_done,
+                bytes_total,
+                ..
+            } => format!(
+                "{:.0}%",
+                *bytes_done as f64 / (*bytes_total).max(1) as f64 * 100.0
+            ),
+            FlashProgress::Verifying => "verifying".to_string(),
+            FlashProgress::Done { elapsed, .. } => {
+                format!("done in {:.1} s", elapsed.as_secs_f64())
+            }
+            FlashProgress::Failed(e) => format!("failed: {}", e),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Phase {
+    Ping,
+    Start,
+    Block(usize),
+    Verify,
+    Boot,
+    Finished,
+}
+
+/// Runs one flash in the CAN thread, so the handshake timeouts don't depend on the UI frame rate.
+pub struct Flasher {
+    pub node: bootloader::BootNode,
+    image: image::Image,
+    phase: Phase,
+    started: Instant,
+    last_sent: Instant,
+    // Attempts at the current block or verify
+    attempts: usize,
+    retries: usize,
+    response: Option<protocol::Response>,
+    progress: FlashProgress,
+    // Frames waiting for their burst
+    pending: VecDeque<Vec<u8>>,
+    next_burst_at: Instant,
+}
+
+impl Flasher {
+    pub fn new(job: FlashJob) -> Self {
+        let now = Instant::now();
+        Self {
+            node: job.node,
+            image: job.image,
+            phase: Phase::Ping,
+            started: now,
+            last_sent: now,
+            attempts: 0,
+            retries: 0,
+            response: None,
+            progress: FlashProgress::WaitingForNode,
+            pending: VecDeque::new(),
+            next_burst_at: now,
+        }
+    }
+
+    pub fn on_frame(&mut self, msg_id_with_ext_flag: u32, data: &[u8]) {
+        if msg_id_with_ext_flag == self.node.resp_id_with_ext_flag() {
+            self.response = protocol::Response::parse(data);
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.phase == Phase::Finished
+    }
+
+    // When the next burst of a block is due, so the CAN thread doesn't sleep through it
+    pub fn time_until_next(&self) -> Option<Duration> {
+        (!self.pending.is_empty())
+            .then(|| self.next_burst_at.saturating_duration_since(Instant::now()))
+    }
+
+    /// Advances the handshake. Returns the frames to send, and the progress when it changed.
+    pub fn tick(&mut self) -> (Vec<messages::AddSendMessage>, Option<FlashProgress>) {
+        let now = Instant::now();
+        let since_sent = now - self.last_sent;
+        let old_progress = self.progress.clone();
+        let mut frames = Vec::new();
+
+        // Responses to another command than the current one are stale, ex: a late block ack
+        let response = self.response.take();
+        let response_to = |cmd: u8| response.filter(|r| r.cmd == cmd);
+
+        match self.phase {
+            Phase::Ping => {
+                let ping_response = response_to(protocol::CMD_PING);
+                if ping_response.is_some_and(|r| r.status == protocol::STATUS_IN_APP) {
+                    // Keep pinging until the bootloader itself answers
+                    log::info!("{} is running its app, asking it to reset", self.node.name);
+                    frames.push(protocol::enter());
+                } else if ping_response.is_some_and(|r| r.is_ok()) {
+                    log::info!(
+                        "{} bootloader answered (version {})",
+                        self.node.name,
+                        response.map_or(0, |r| r.payload[0])
+                    );
+                    self.phase = Phase::Start;
+                    self.progress = FlashProgress::Erasing;
+                    frames.push(protocol::start(self.image.bytes.len() as u32));
+                } else if now - self.started > HANDSHAKE_TIMEOUT {
+                    self.fail(format!(
+                        "{} didn't answer within {} s, check it is in its bootloader",
+                        self.node.name,
+                        HANDSHAKE_TIMEOUT.as_secs()
+                    ));
+                } else if self.attempts == 0 || since_sent >= PING_INTERVAL {
+                    self.attempts = 1;
+                    frames.push(protocol::ping());
+                }
+            }
+            Phase::Start => match response_to(protocol::CMD_START) {
+                Some(r) if r.is_ok() => {
+                    self.start_block(0, &mut frames);
+                }
+                Some(r) => self.fail(format!(
+                    "{} refused the image: {}",
+                    self.node.name,
+                    protocol::status_display(r.status)
+                )),
+                None if since_sent > ERASE_TIMEOUT => {
+                    self.fail(format!("{} didn't finish erasing", self.node.name));
+                }
+                None => {}
+            },
+            Phase::Block(idx) => match response_to(protocol::CMD_BLOCK_END) {
+                Some(r) if r.is_ok() && r.block() as usize == idx => {
+                    if idx + 1 < self.image.block_count() {
+                        self.start_block(idx + 1, &mut frames);
+                    } else {
+                        self.phase = Phase::Verify;
+                        self.attempts = 1;
+                        self.progress = FlashProgress::Verifying;
+                        frames.push(protocol::verify(self.image.crc));
+                    }
+                }
+                Some(r) if r.block() as usize == idx => {
+                    log::warn!(
+                        "{} rejected block {}: {}",
+                        self.node.name,
+                        idx,
+                        protocol::status_display(r.status)
+                    );
+                    self.retry_block(idx, &mut frames);
+                }
+                _ if since_sent > RESPONSE_TIMEOUT => {
+                    log::warn!("{} didn't acknowledge block {}", self.node.name, idx);
+                    self.retry_block(idx, &mut frames);
+                }
+                _ => {}
+            },
+            Phase::Verify => match response_to(protocol::CMD_VERIFY) {
+                Some(r) if r.is_ok() && r.crc() == self.image.crc => {
+                    self.phase = Phase::Boot;
+                    frames.push(protocol::boot());
+                }
+                Some(r) if r.is_ok() => self.fail(format!(
+                    "{} computed CRC 0x{:08X}, expected 0x{:08X}",
+                    self.node.name,
+                    r.crc(),
+                    self.image.crc
+                )),
+                Some(r) => self.fail(format!(
+                    "{} failed to verify: {}",
+                    self.node.name,
+                    protocol::status_display(r.status)
+                )),
+                None if since_sent > RESPONSE_TIMEOUT => {
+                    if self.attempts >= MAX_ATTEMPTS {
+                        self.fail(format!("{} didn't answer the CRC check", self.node.name));
+                    } else {
+                        self.attempts += 1;
+                        self.retries += 1;
+                        frames.push(protocol::verify(self.image.crc));
+                    }
+                }
+                None => {}
+            },
+            Phase::Boot => {
+                // The node may jump to the app before its answer makes it out, a timeout is fine
+                if response_to(protocol::CMD_BOOT).is_some() || since_sent > RESPONSE_TIMEOUT {
+                    self.phase = Phase::Finished;
+                    self.progress = FlashProgress::Done {
+                        elapsed: now - self.started,
+                        retries: self.retries,
+                    };
+                    log::info!(
+                        "Flashed {} bytes to {} in {:.1} s",
+                        self.image.bytes.len(),
+                        self.node.name,
+                        (now - self.started).as_secs_f64()
+                    );
+                }
+            }
+            Phase::Finished => {}
+        }
+
+        self.pending.extend(frames);
+        let mut frames = Vec::new();
+        if !self.pending.is_empty() && now >= self.next_burst_at {
+            let burst = self.pending.len().min(FRAMES_PER_BURST);
+            frames.extend(self.pending.drain(..burst));
+            self.next_burst_at = now + BURST_GAP * self.attempts.max(1) as u32;
+            // Response timeouts count from the last frame actually sent
+            self.last_sent = now;
+        }
+        let add_msgs = frames
+            .into_iter()
+            .map(|msg_bytes| messages::AddSendMessage {
+                handle: can::scheduler::new_handle(),
+                amount: messages::SendAmount::Once,
+                msg_id: self.node.cmd_id,
+                is_msg_id_extended: self.node.is_extended,
+                msg_bytes,
+                auto_fields: Vec::new(),
+            })
+            .collect();
+        let progress = (self.progress != old_progress).then(|| self.progress.clone());
+        (add_msgs, progress)
+    }
+
+    fn start_block(&mut self, idx: usize, frames: &mut Vec<Vec<u8>>) {
+        self.phase = Phase::Block(idx);
+        self.attempts = 1;
+        self.progress = FlashProgress::Writing {
+            bytes_done: idx * protocol::BLOCK_SIZE,
+            bytes_total: self.image.bytes.len(),
+            retries: self.retries,
+        };
+        self.push_block(idx, frames);
+    }
+
+    fn retry_block(&mut self, idx: usize, frames: &mut Vec<Vec<u8>>) {
+        if self.attempts >= MAX_ATTEMPTS {
+            self.fail(format!(
+                "{} kept rejecting block {} after {} attempts",
+                self.node.name, idx, MAX_ATTEMPTS
+            ));
+            return;
+        }
+        self.attempts += 1;
+        self.retries += 1;
+        if let FlashProgress::Writing { retries, .. } = &mut self.progress {
+            *retries = self.retries;
+        }
+        self.push_block(idx, frames);
+    }
+
+    fn push_block(&mut self, idx: usize, frames: &mut Vec<Vec<u8>>) {
+        // What is left of an earlier attempt would only corrupt this one
+        self.pending.clear();
+        let block = self.image.block(idx);
+        let block_start = idx * protocol::BLOCK_SIZE;
+        for (chunk_idx, chunk) in block.chunks(protocol::DATA_BYTES_PER_FRAME).enumerate() {
+            frames.push(protocol::data(
+                block_start + chunk_idx * protocol::DATA_BYTES_PER_FRAME,
+                chunk,
+            ));
+        }
+        frames.push(protocol::block_end(idx as u16, protocol::crc32(block)));
+    }
+
+    fn fail(&mut self, reason: String) {
+        log::error!("Flashing {} failed: {}", self.node.name, reason);
+        self.pending.clear();
+        self.phase = Phase::Finished;
+        self.progress = FlashProgress::Failed(reason);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bootloader::stand_in;
+
+    // An adapter and node RX FIFO that hold `capacity` frames and get through `frames_per_ms`,
+    // dropping what arrives while full
+    struct SlowSink {
+        node: stand_in::StandIn,
+        capacity: f64,
+        frames_per_ms: f64,
+        level: f64,
+        last: Instant,
+        dropped: usize,
+    }
+
+    impl SlowSink {
+        fn send(&mut self, node: &bootloader::BootNode, data: &[u8]) -> Option<Vec<u8>> {
+            let now = Instant::now();
+            let drained = (now - self.last).as_secs_f64() * 1000.0 * self.frames_per_ms;
+            self.level = (self.level - drained).max(0.0);
+            self.last = now;
+            if self.level + 1.0 > self.capacity {
+                self.dropped += 1;
+                return None;
+            }
+            self.level += 1.0;
+            self.node.on_frame(node.cmd_id_with_ext_flag(), data)
+        }
+    }
+
+    fn node() -> bootloader::BootNode {
+        bootloader::BootNode {
+            name: "test".to_string(),
+            cmd_id: 0x100,
+            resp_id: 0x101,
+            is_extended: false,
+            app_address: None,
+        }
+    }
+
+    #[test]
+    fn paces_blocks_for_a_slow_sink() {
+        let bytes: Vec<u8> = (0..3 * protocol::BLOCK_SIZE).map(|i| i as u8).collect();
+        let image = image::Image {
+            path: "test.bin".into(),
+            crc: protocol::crc32(&bytes),
+            bytes,
+            base_address: None,
+        };
+        let mut flasher = Flasher::new(FlashJob {
+            node: node(),
+            image,
+        });
+        // Fewer frames than a whole block, gone through faster than the bursts come
+        let mut sink = SlowSink {
+            node: stand_in::StandIn::new(node()),
+            capacity: 2.0 * FRAMES_PER_BURST as f64,
+            frames_per_ms: FRAMES_PER_BURST as f64,
+            level: 0.0,
+            last: Instant::now(),
+            dropped: 0,
+        };
+
+        let started = Instant::now();
+        while !flasher.is_finished() {
+            assert!(
+                started.elapsed() < Duration::from_secs(5),
+                "flash timed out"
+            );
+            let (frames, _) = flasher.tick();
+            assert!(frames.len() <= FRAMES_PER_BURST);
+            for frame in frames {
+                if let Some(response) = sink.send(&flasher.node, &frame.msg_bytes) {
+                    flasher.on_frame(flasher.node.resp_id_with_ext_flag(), &response);
+                }
+            }
+            std::thread::sleep(Duration::from_micros(200));
+        }
+
+        assert_eq!(sink.dropped, 0);
+        assert!(matches!(
+            flasher.progress,
+            FlashProgress::Done { retries: 0, .. }
+        ));
+    }
+}
@@ -0,0 +1,203 @@
+use crate::bootloader::protocol;
+
+/// A firmware image ready to stream, padded with 0xFF to a whole number of DATA frames.
+#[derive(Clone, Debug)]
+pub struct Image {
+    pub path: std::path::PathBuf,
+    pub bytes: Vec<u8>,
+    // Only known for .hex files, .bin files are assumed to start at the node's app address
+    pub base_address: Option<u32>,
+    pub crc: u32,
+}
+
+impl Image {
+    pub fn load(path: &std::path::Path) -> Result<Self, String> {
+        let is_hex = path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("hex"));
+        let (mut bytes, base_address) = if is_hex {
+            let text = std::fs::read_to_string(path)
+                .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+            let (bytes, base_address) = parse_intel_hex(&text)
+                .map_err(|e| format!("Failed to parse {}: {}", path.display(), e))?;
+            (bytes, Some(base_address))
+        } else {
+            let bytes = std::fs::read(path)
+                .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+            (bytes, None)
+        };
+
+        if bytes.is_empty() {
+            return Err(format!("{} is empty", path.display()));
+        }
+        if bytes.len() > protocol::MAX_IMAGE_SIZE {
+            return Err(format!(
+                "{} is {} bytes, the bootloader protocol can't address more than {}",
+                path.display(),
+                bytes.len(),
+                protocol::MAX_IMAGE_SIZE
+            ));
+        }
+        bytes.resize(
+            bytes.len().next_multiple_of(protocol::DATA_BYTES_PER_FRAME),
+            0xFF,
+        );
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            crc: protocol::crc32(&bytes),
+            bytes,
+            base_address,
+        })
+    }
+
+    pub fn block_count(&self) -> usize {
+        self.bytes.len().div_ceil(protocol::BLOCK_SIZE)
+    }
+
+    pub fn block(&self, idx: usize) -> &[u8] {
+        let start = idx * protocol::BLOCK_SIZE;
+        let end = (start + protocol::BLOCK_SIZE).min(self.bytes.len());
+        &self.bytes[start..end]
+    }
+}
+
+/// Flattens the data records of an Intel HEX file, filling gaps with 0xFF (erased flash).
+/// Returns the bytes and the address of the first one.
+fn parse_intel_hex(text: &str) -> Result<(Vec<u8>, u32), String> {
+    let mut records = Vec::new();
+    let mut upper_address = 0u32;
+
+    for (line_idx, line) in text.lines().enumerate() {
+        let line_num = line_idx + 1;
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let Some(hex) = line.strip_prefix(':') else {
+            return Err(format!("line {} doesn't start with ':'", line_num));
+        };
+        if hex.len() % 2 != 0 || hex.len() < 10 {
+            return Err(format!("line {} is malformed", line_num));
+        }
+        let raw = (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16))
+            .collect::<Result<Vec<u8>, _>>()
+            .map_err(|_| format!("line {} isn't hexadecimal", line_num))?;
+
+        let len = raw[0] as usize;
+        if raw.len() != len + 5 {
+            return Err(format!("line {} has the wrong length", line_num));
+        }
+        if raw.iter().fold(0u8, |acc, b| acc.wrapping_add(*b)) != 0 {
+            return Err(format!("line {} has a bad checksum", line_num));
+        }
+        let address = u16::from_be_bytes([raw[1], raw[2]]) as u32;
+        let data = &raw[4..4 + len];
+
+        match raw[3] {
+            // Data
+            0x00 => records.push((upper_address + address, data.to_vec())),
+            // End of file
+            0x01 => break,
+            // Extended segment address
+            0x02 if len == 2 => {
+                upper_address = (u16::from_be_bytes([data[0], data[1]]) as u32) << 4;
+            }
+            // Extended linear address
+            0x04 if len == 2 => {
+                upper_address = (u16::from_be_bytes([data[0], data[1]]) as u32) << 16;
+            }
+            // Start addresses, the bootloader jumps to the app on its own
+            0x03 | 0x05 => {}
+            other => {
+                return Err(format!(
+                    "line {} has unsupported record type 0x{:02X}",
+                    line_num, other
+                ));
+            }
+        }
+    }
+
+    let Some(base_address) = records.iter().map(|(address, _)| *address).min() else {
+        return Err("no data records".to_string());
+    };
+    let end_address = records
+        .iter()
+        .map(|(address, data)| *address as usize + data.len())
+        .max()
+        .unwrap_or(base_address as usize);
+    let size = end_address - base_address as usize;
+    if size > protocol::MAX_IMAGE_SIZE {
+        return Err(format!(
+            "data spans {} bytes from 0x{:08X}, check for stray records",
+            size, base_address
+        ));
+    }
+
+    let mut bytes = vec![0xFF; size];
+    for (address, data) in records {
+        let start = (address - base_address) as usize;
+        bytes[start..start + data.len()].copy_from_slice(&data);
+    }
+    Ok((bytes, base_address))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_extended_linear_address_records() {
+        let text = "\
+:020000040800F2
+:0400000001020304F2
+:02000800AABB91
+:0400000508000101ED
+:00000001FF
+";
+        let (bytes, base_address) = parse_intel_hex(text).unwrap();
+        assert_eq!(base_address, 0x0800_0000);
+        // The gap between the records is erased flash
+        assert_eq!(bytes, [1, 2, 3, 4, 0xFF, 0xFF, 0xFF, 0xFF, 0xAA, 0xBB]);
+    }
+
+    #[test]
+    fn joins_records_across_a_64k_boundary() {
+        let text = "\
+:020000040800F2
+:02FFFE001122CE
+:020000040801F1
+:02000000334487
+:00000001FF
+";
+        let (bytes, base_address) = parse_intel_hex(text).unwrap();
+        assert_eq!(base_address, 0x0800_FFFE);
+        assert_eq!(bytes, [0x11, 0x22, 0x33, 0x44]);
+    }
+
+    #[test]
+    fn parses_extended_segment_address_records() {
+        let (bytes, base_address) =
+            parse_intel_hex(":020000021000EC\n:01001000559A\n:00000001FF\n").unwrap();
+        assert_eq!(base_address, 0x0001_0010);
+        assert_eq!(bytes, [0x55]);
+    }
+
+    #[test]
+    fn rejects_bad_records() {
+        // Checksum off by one
+        assert!(parse_intel_hex(":0400000001020304F3\n").is_err());
+        assert!(parse_intel_hex("0400000001020304F2\n").is_err());
+        assert!(parse_intel_hex(":0400000001020304\n").is_err());
+        assert!(parse_intel_hex(":00000001FF\n").is_err());
+    }
+
+    #[test]
+    fn ignores_records_after_end_of_file() {
+        let (bytes, _) =
+            parse_intel_hex(":0400000001020304F2\n:00000001FF\n:02000800AABB91\n").unwrap();
+        assert_eq!(bytes, [1, 2, 3, 4]);
+    }
+}
@@ -0,0 +1,213 @@
+use std::time::{Duration, Instant};
+
+use crate::bootloader::{self, protocol};
+use crate::{can, config, messages};
+
+pub const MANIFEST_FILE: &str = "firmware_manifest.json";
+
+const INFO_TIMEOUT: Duration = Duration::from_millis(300);
+const INFO_ATTEMPTS: usize = 3;
+
+/// The image a node should run for a release, with what its app should report.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+pub struct ManifestEntry {
+    pub node: String,
+    pub image: std::path::PathBuf,
+    // "1.2.3", not compared when missing
+    #[serde(default)]
+    pub version: Option<String>,
+    // Commit hash, abbreviated hashes match by prefix
+    #[serde(default)]
+    pub build_hash: Option<String>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Default)]
+pub struct Manifest {
+    // Tag the images were built from, shown in the report
+    #[serde(default)]
+    pub release: String,
+    #[serde(default)]
+    pub images: Vec<ManifestEntry>,
+}
+
+impl Manifest {
+    pub fn load() -> Self {
+        config::load_json(MANIFEST_FILE).unwrap_or_default()
+    }
+
+    pub fn entry(&self, node: &str) -> Option<&ManifestEntry> {
+        self.images.iter().find(|entry| entry.node == node)
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct NodeInfo {
+    pub bootloader_version: u32,
+    pub firmware_version: u32,
+    pub build_hash: u32,
+    pub image_crc: u32,
+}
+
+impl NodeInfo {
+    pub fn firmware_version_display(&self) -> String {
+        format!(
+            "{}.{}.{}",
+            (self.firmware_version >> 16) & 0xFF,
+            (self.firmware_version >> 8) & 0xFF,
+            self.firmware_version & 0xFF
+        )
+    }
+
+    pub fn build_hash_display(&self) -> String {
+        format!("{:08x}", self.build_hash)
+    }
+
+    /// Differences with the manifest entry, empty when the node is up to date. The image CRC is
+    /// always checked, the version and hash only when the manifest gives them.
+    pub fn mismatches(&self, entry: &ManifestEntry, expected_crc: u32) -> Vec<String> {
+        let mut mismatches = Vec::new();
+        if self.image_crc != expected_crc {
+            mismatches.push(format!(
+                "image CRC 0x{:08X}, expected 0x{:08X}",
+                self.image_crc, expected_crc
+            ));
+        }
+        if let Some(version) = &entry.version
+            && *version != self.firmware_version_display()
+        {
+            mismatches.push(format!(
+                "version {}, expected {}",
+                self.firmware_version_display(),
+                version
+            ));
+        }
+        if let Some(hash) = &entry.build_hash {
+            let hash = hash.to_lowercase();
+            let reported = self.build_hash_display();
+            // The node only reports 8 digits, longer manifest hashes compare on those
+            let matches = if hash.len() >= reported.len() {
+                hash.starts_with(&reported)
+            } else {
+                reported.starts_with(&hash)
+            };
+            if !matches {
+                mismatches.push(format!("build {}, expected {}", reported, hash));
+            }
+        }
+        mismatches
+    }
+}
+
+// Node name and its info, or why it couldn't be read
+pub type NodeResult = (String, Result<NodeInfo, String>);
+
+struct NodeQuery {
+    node: bootloader::BootNode,
+    info: NodeInfo,
+    // Index in `protocol::INFO_FIELDS` of the field waiting for an answer
+    field_idx: usize,
+    sent_at: Option<Instant>,
+    attempts: usize,
+    response: Option<protocol::Response>,
+}
+
+/// Reads the INFO fields of several nodes in the CAN thread, all nodes at once and one field at a
+/// time per node.
+pub struct InfoQuery {
+    queries: Vec<NodeQuery>,
+}
+
+impl InfoQuery {
+    pub fn new(nodes: Vec<bootloader::BootNode>) -> Self {
+        Self {
+            queries: nodes
+                .into_iter()
+                .map(|node| NodeQuery {
+                    node,
+                    info: NodeInfo::default(),
+                    field_idx: 0,
+                    sent_at: None,
+                    attempts: 0,
+                    response: None,
+                })
+                .collect(),
+        }
+    }
+
+    pub fn on_frame(&mut self, msg_id_with_ext_flag: u32, data: &[u8]) {
+        for query in &mut self.queries {
+            if msg_id_with_ext_flag == query.node.resp_id_with_ext_flag() {
+                query.response = protocol::Response::parse(data);
+            }
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.queries.is_empty()
+    }
+
+    /// Returns the frames to send, and the nodes that finished with their info or why it failed.
+    pub fn tick(&mut self) -> (Vec<messages::AddSendMessage>, Vec<NodeResult>) {
+        let now = Instant::now();
+        let mut frames = Vec::new();
+        let mut finished = Vec::new();
+
+        self.queries.retain_mut(|query| {
+            let field = protocol::INFO_FIELDS[query.field_idx];
+            let response = query
+                .response
+                .take()
+                .filter(|r| r.cmd == protocol::CMD_INFO && r.info().0 == field);
+
+            if let Some(response) = response {
+                if !response.is_ok() {
+                    finished.push((
+                        query.node.name.clone(),
+                        Err(format!(
+                            "refused INFO: {}",
+                            protocol::status_display(response.status)
+                        )),
+                    ));
+                    return false;
+                }
+                let value = response.info().1;
+                match field {
+                    protocol::INFO_BOOTLOADER_VERSION => query.info.bootloader_version = value,
+                    protocol::INFO_FIRMWARE_VERSION => query.info.firmware_version = value,
+                    protocol::INFO_BUILD_HASH => query.info.build_hash = value,
+                    _ => query.info.image_crc = value,
+                }
+                query.field_idx += 1;
+                query.sent_at = None;
+                query.attempts = 0;
+                if query.field_idx == protocol::INFO_FIELDS.len() {
+                    finished.push((query.node.name.clone(), Ok(query.info)));
+                    return false;
+                }
+            }
+
+            let timed_out = query
+                .sent_at
+                .is_some_and(|sent_at| now - sent_at > INFO_TIMEOUT);
+            if query.sent_at.is_none() || timed_out {
+                if query.attempts >= INFO_ATTEMPTS {
+                    finished.push((query.node.name.clone(), Err("no response".to_string())));
+                    return false;
+                }
+                query.attempts += 1;
+                query.sent_at = Some(now);
+                frames.push(messages::AddSendMessage {
+                    handle: can::scheduler::new_handle(),
+                    amount: messages::SendAmount::Once,
+                    msg_id: query.node.cmd_id,
+                    is_msg_id_extended: query.node.is_extended,
+                    msg_bytes: protocol::info(protocol::INFO_FIELDS[query.field_idx]),
+                    auto_fields: Vec::new(),
+                });
+            }
+            true
+        });
+
+        (frames, finished)
+    }
+}
@@ -0,0 +1,52 @@
+pub mod flasher;
+pub mod image;
+pub mod inventory;
+pub mod protocol;
+pub mod stand_in;
+
+use crate::config;
+
+pub const BOOTLOADER_CONFIG_FILE: &str = "bootloader.json";
+
+/// A node that can be flashed, with the IDs its bootloader listens and answers on.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq)]
+pub struct BootNode {
+    pub name: String,
+    pub cmd_id: u32, // without the extended ID flag
+    pub resp_id: u32,
+    #[serde(default)]
+    pub is_extended: bool,
+    // Flash address the application is linked at, .hex images starting elsewhere are refused
+    #[serde(default)]
+    pub app_address: Option<u32>,
+}
+
+impl BootNode {
+    pub fn cmd_id_with_ext_flag(&self) -> u32 {
+        with_ext_flag(self.cmd_id, self.is_extended)
+    }
+
+    pub fn resp_id_with_ext_flag(&self) -> u32 {
+        with_ext_flag(self.resp_id, self.is_extended)
+    }
+}
+
+fn with_ext_flag(msg_id: u32, is_extended: bool) -> u32 {
+    if is_extended {
+        msg_id | crate::util::can::EXTENDED_ID_FLAG
+    } else {
+        msg_id
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Default)]
+pub struct BootloaderConfig {
+    #[serde(default)]
+    pub nodes: Vec<BootNode>,
+}
+
+impl BootloaderConfig {
+    pub fn load() -> Self {
+        config::load_json(BOOTLOADER_CONFIG_FILE).unwrap_or_default()
+    }
+}
@@ -0,0 +1,230 @@
+// Frames exchanged with a node's bootloader. The host sends commands on the node's `cmd_id`, the
+// node answers on its `resp_id`. Byte 0 is always the command, responses echo it in byte 0 and put
+// a status in byte 1. Multi-byte fields are little endian.
+//
+//   PING       [0x01]                              -> [0x01, status, version]
+//   START      [0x02, size u32]                    -> [0x02, status]   (after erasing)
+//   DATA       [0x03, offset u24, 4 image bytes]   (no response)
+//   BLOCK_END  [0x04, block u16, block crc32]      -> [0x04, status, block u16]
+//   VERIFY     [0x05, image crc32]                 -> [0x05, status, node's crc32]
+//   BOOT       [0x06]                              -> [0x06, status]   (then jumps to the app)
+//   INFO       [0x07, field]                       -> [0x07, status, field, value u32]
+//   ENTER      [0x08]                              -> [0x08, status]   (app resets into the bootloader)
+//
+// Apps answer PING, INFO and ENTER on the same IDs, with STATUS_IN_APP for PING, so the fleet can
+// be queried without resetting nodes and flashing doesn't need a manual power cycle. The image CRC
+// INFO reports is the one the bootloader stored after its last successful flash.
+//
+// Images are streamed in blocks of `BLOCK_SIZE` bytes: the DATA frames of a block followed by its
+// BLOCK_END. A node answering BLOCK_END with a bad status gets the whole block again. CRCs are the
+// usual CRC-32 (IEEE, as in zlib) over the image padded with 0xFF to a multiple of 4 bytes.
+
+pub const CMD_PING: u8 = 0x01;
+pub const CMD_START: u8 = 0x02;
+pub const CMD_DATA: u8 = 0x03;
+pub const CMD_BLOCK_END: u8 = 0x04;
+pub const CMD_VERIFY: u8 = 0x05;
+pub const CMD_BOOT: u8 = 0x06;
+pub const CMD_INFO: u8 = 0x07;
+pub const CMD_ENTER: u8 = 0x08;
+
+pub const INFO_BOOTLOADER_VERSION: u8 = 0x00;
+// major << 16 | minor << 8 | patch
+pub const INFO_FIRMWARE_VERSION: u8 = 0x01;
+// First 8 hex digits of the commit the app was built from
+pub const INFO_BUILD_HASH: u8 = 0x02;
+pub const INFO_IMAGE_CRC: u8 = 0x03;
+pub const INFO_FIELDS: [u8; 4] = [
+    INFO_BOOTLOADER_VERSION,
+    INFO_FIRMWARE_VERSION,
+    INFO_BUILD_HASH,
+    INFO_IMAGE_CRC,
+];
+
+pub const STATUS_OK: u8 = 0x00;
+pub const STATUS_BAD_CRC: u8 = 0x01;
+pub const STATUS_BAD_OFFSET: u8 = 0x02;
+pub const STATUS_FLASH_ERROR: u8 = 0x03;
+pub const STATUS_TOO_LARGE: u8 = 0x04;
+pub const STATUS_IN_APP: u8 = 0x05;
+
+pub const DATA_BYTES_PER_FRAME: usize = 4;
+pub const BLOCK_SIZE: usize = 256;
+// DATA frames carry a 24 bit offset
+pub const MAX_IMAGE_SIZE: usize = 1 << 24;
+
+pub fn status_display(status: u8) -> String {
+    match status {
+        STATUS_OK => "ok".to_string(),
+        STATUS_BAD_CRC => "bad CRC".to_string(),
+        STATUS_BAD_OFFSET => "bad offset".to_string(),
+        STATUS_FLASH_ERROR => "flash error".to_string(),
+        STATUS_TOO_LARGE => "image too large".to_string(),
+        STATUS_IN_APP => "running its app".to_string(),
+        other => format!("unknown status 0x{:02X}", other),
+    }
+}
+
+pub fn ping() -> Vec<u8> {
+    vec![CMD_PING]
+}
+
+pub fn start(size: u32) -> Vec<u8> {
+    let mut frame = vec![CMD_START];
+    frame.extend(size.to_le_bytes());
+    frame
+}
+
+pub fn data(offset: usize, bytes: &[u8]) -> Vec<u8> {
+    let mut frame = vec![CMD_DATA];
+    frame.extend(&(offset as u32).to_le_bytes()[..3]);
+    frame.extend(bytes);
+    frame
+}
+
+pub fn block_end(block: u16, crc: u32) -> Vec<u8> {
+    let mut frame = vec![CMD_BLOCK_END];
+    frame.extend(block.to_le_bytes());
+    frame.extend(crc.to_le_bytes());
+    frame
+}
+
+pub fn verify(crc: u32) -> Vec<u8> {
+    let mut frame = vec![CMD_VERIFY];
+    frame.extend(crc.to_le_bytes());
+    frame
+}
+
+pub fn boot() -> Vec<u8> {
+    vec![CMD_BOOT]
+}
+
+pub fn info(field: u8) -> Vec<u8> {
+    vec![CMD_INFO, field]
+}
+
+pub fn enter() -> Vec<u8> {
+    vec![CMD_ENTER]
+}
+
+/// A node response, see the table at the top of this file.
+#[derive(Clone, Copy, Debug)]
+pub struct Response {
+    pub cmd: u8,
+    pub status: u8,
+    // Bytes after the status, zero padded
+    pub payload: [u8; 6],
+}
+
+impl Response {
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        if data.len() < 2 {
+            return None;
+        }
+        let mut payload = [0u8; 6];
+        let rest = &data[2..data.len().min(8)];
+        payload[..rest.len()].copy_from_slice(rest);
+        Some(Self {
+            cmd: data[0],
+            status: data[1],
+            payload,
+        })
+    }
+
+    pub fn is_ok(&self) -> bool {
+        self.status == STATUS_OK
+    }
+
+    pub fn block(&self) -> u16 {
+        u16::from_le_bytes([self.payload[0], self.payload[1]])
+    }
+
+    pub fn crc(&self) -> u32 {
+        u32::from_le_bytes([
+            self.payload[0],
+            self.payload[1],
+            self.payload[2],
+            self.payload[3],
+        ])
+    }
+
+    // INFO responses: (field, value)
+    pub fn info(&self) -> (u8, u32) {
+        (
+            self.payload[0],
+            u32::from_le_bytes([
+                self.payload[1],
+                self.payload[2],
+                self.payload[3],
+                self.payload[4],
+            ]),
+        )
+    }
+}
+
+pub fn response(cmd: u8, status: u8, payload: &[u8]) -> Vec<u8> {
+    let mut frame = vec![cmd, status];
+    frame.extend(payload);
+    frame
+}
+
+pub fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = 0xFFFF_FFFFu32;
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crc32_check_value() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+        assert_eq!(crc32(&[]), 0);
+    }
+
+    #[test]
+    fn encodes_little_endian_fields() {
+        assert_eq!(start(0x0001_0200), [CMD_START, 0x00, 0x02, 0x01, 0x00]);
+        assert_eq!(
+            data(0x01_2345, &[1, 2, 3, 4]),
+            [CMD_DATA, 0x45, 0x23, 0x01, 1, 2, 3, 4]
+        );
+        assert_eq!(
+            block_end(0x0102, 0xCBF4_3926),
+            [CMD_BLOCK_END, 0x02, 0x01, 0x26, 0x39, 0xF4, 0xCB]
+        );
+    }
+
+    #[test]
+    fn parses_responses() {
+        let resp =
+            Response::parse(&response(CMD_VERIFY, STATUS_OK, &[0x26, 0x39, 0xF4, 0xCB])).unwrap();
+        assert!(resp.is_ok());
+        assert_eq!(resp.crc(), 0xCBF4_3926);
+
+        let resp = Response::parse(&response(
+            CMD_INFO,
+            STATUS_OK,
+            &[INFO_FIRMWARE_VERSION, 0x03, 0x02, 0x01, 0x00],
+        ))
+        .unwrap();
+        assert_eq!(resp.info(), (INFO_FIRMWARE_VERSION, 0x0001_0203));
+
+        let resp = Response::parse(&[CMD_BLOCK_END, STATUS_BAD_CRC, 0x07]).unwrap();
+        assert!(!resp.is_ok());
+        assert_eq!(resp.block(), 7);
+
+        assert!(Response::parse(&[CMD_PING]).is_none());
+    }
+}
@@ -0,0 +1,133 @@
+use crate::bootloader::{self, protocol};
+
+const STAND_IN_VERSION: u8 = 1;
+
+/// Answers the bootloader protocol like a node would, so flashing can be tried on the Loopback
+/// connection without hardware. The "flash" is a byte buffer that is dropped on BOOT, only its CRC
+/// is kept for INFO.
+pub struct StandIn {
+    node: bootloader::BootNode,
+    // Starts in its app, like a node on a powered car
+    in_app: bool,
+    flash: Vec<u8>,
+    // Set by START, cleared by BOOT
+    expected_size: Option<usize>,
+    // CRC of the last image booted, 0 until then
+    image_crc: u32,
+}
+
+impl StandIn {
+    pub fn new(node: bootloader::BootNode) -> Self {
+        Self {
+            node,
+            in_app: true,
+            flash: Vec::new(),
+            expected_size: None,
+            image_crc: 0,
+        }
+    }
+
+    /// Returns the response to send on the node's `resp_id`, if the frame is a command for it.
+    pub fn on_frame(&mut self, msg_id_with_ext_flag: u32, data: &[u8]) -> Option<Vec<u8>> {
+        if msg_id_with_ext_flag != self.node.cmd_id_with_ext_flag() {
+            return None;
+        }
+        let (&cmd, args) = data.split_first()?;
+
+        // Common to the app and the bootloader
+        match cmd {
+            protocol::CMD_INFO => {
+                let field = *args.first()?;
+                let value = match field {
+                    protocol::INFO_BOOTLOADER_VERSION => STAND_IN_VERSION as u32,
+                    protocol::INFO_IMAGE_CRC => self.image_crc,
+                    // The stand-in can't know what it was built from
+                    _ => 0,
+                };
+                let mut payload = vec![field];
+                payload.extend(value.to_le_bytes());
+                return Some(protocol::response(cmd, protocol::STATUS_OK, &payload));
+            }
+            protocol::CMD_PING if self.in_app => {
+                return Some(protocol::response(
+                    cmd,
+                    protocol::STATUS_IN_APP,
+                    &[STAND_IN_VERSION],
+                ));
+            }
+            protocol::CMD_ENTER if self.in_app => {
+                log::info!(
+                    "Stand-in for {} resetting into its bootloader",
+                    self.node.name
+                );
+                self.in_app = false;
+                return Some(protocol::response(cmd, protocol::STATUS_OK, &[]));
+            }
+            _ if self.in_app => return None,
+            _ => {}
+        }
+
+        match cmd {
+            protocol::CMD_PING => Some(protocol::response(
+                cmd,
+                protocol::STATUS_OK,
+                &[STAND_IN_VERSION],
+            )),
+            protocol::CMD_START => {
+                let size = u32::from_le_bytes(args.get(..4)?.try_into().ok()?) as usize;
+                if size > protocol::MAX_IMAGE_SIZE {
+                    return Some(protocol::response(cmd, protocol::STATUS_TOO_LARGE, &[]));
+                }
+                self.flash = vec![0xFF; size];
+                self.expected_size = Some(size);
+                Some(protocol::response(cmd, protocol::STATUS_OK, &[]))
+            }
+            protocol::CMD_DATA => {
+                let offset =
+                    u32::from_le_bytes([*args.first()?, *args.get(1)?, *args.get(2)?, 0]) as usize;
+                let bytes = args.get(3..).unwrap_or(&[]);
+                if let Some(dest) = self.flash.get_mut(offset..offset + bytes.len()) {
+                    dest.copy_from_slice(bytes);
+                }
+                None
+            }
+            protocol::CMD_BLOCK_END => {
+                let block_bytes = args.get(..2)?;
+                let block = u16::from_le_bytes(block_bytes.try_into().ok()?) as usize;
+                let crc = u32::from_le_bytes(args.get(2..6)?.try_into().ok()?);
+                let start = block * protocol::BLOCK_SIZE;
+                let end = (start + protocol::BLOCK_SIZE).min(self.flash.len());
+                let status = if self.expected_size.is_none() || start >= end {
+                    protocol::STATUS_BAD_OFFSET
+                } else if protocol::crc32(&self.flash[start..end]) != crc {
+                    protocol::STATUS_BAD_CRC
+                } else {
+                    protocol::STATUS_OK
+                };
+                Some(protocol::response(cmd, status, block_bytes))
+            }
+            protocol::CMD_VERIFY => Some(protocol::response(
+                cmd,
+                protocol::STATUS_OK,
+                &protocol::crc32(&self.flash).to_le_bytes(),
+            )),
+            protocol::CMD_BOOT => {
+                log::info!(
+                    "Stand-in bootloader for {} booting a {} byte image",
+                    self.node.name,
+                    self.flash.len()
+                );
+                self.image_crc = protocol::crc32(&self.flash);
+                self.expected_size = None;
+                self.flash.clear();
+                self.in_app = true;
+                Some(protocol::response(cmd, protocol::STATUS_OK, &[]))
+            }
+            _ => None,
+        }
+    }
+
+    pub fn resp_id(&self) -> (u32, bool) {
+        (self.node.resp_id, self.node.is_extended)
+    }
+}
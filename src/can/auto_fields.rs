@@ -0,0 +1,251 @@
+// Signals the CAN thread fills in at every transmission, for receivers that reject frames
+// without a live rolling counter and a valid checksum.
+
+#[derive(Clone, Copy, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum ChecksumKind {
+    // Poly 0x1D, init 0xFF, final XOR 0xFF, used by AUTOSAR E2E profile 1
+    Crc8SaeJ1850,
+    Xor,
+    Sum,
+}
+
+impl ChecksumKind {
+    pub const ALL: [ChecksumKind; 3] = [
+        ChecksumKind::Crc8SaeJ1850,
+        ChecksumKind::Xor,
+        ChecksumKind::Sum,
+    ];
+
+    pub fn display(&self) -> &'static str {
+        match self {
+            ChecksumKind::Crc8SaeJ1850 => "CRC8 SAE J1850",
+            ChecksumKind::Xor => "XOR",
+            ChecksumKind::Sum => "Sum",
+        }
+    }
+
+    fn compute(&self, bytes: impl Iterator<Item = u8>) -> u8 {
+        match self {
+            ChecksumKind::Crc8SaeJ1850 => {
+                let mut crc = 0xFFu8;
+                for byte in bytes {
+                    crc ^= byte;
+                    for _ in 0..8 {
+                        crc = if crc & 0x80 != 0 {
+                            (crc << 1) ^ 0x1D
+                        } else {
+                            crc << 1
+                        };
+                    }
+                }
+                crc ^ 0xFF
+            }
+            ChecksumKind::Xor => bytes.fold(0, |acc, b| acc ^ b),
+            ChecksumKind::Sum => bytes.fold(0, |acc, b| acc.wrapping_add(b)),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+pub enum AutoKind {
+    // Increments at every transmission and wraps at the signal's width
+    Counter,
+    // Covers the given byte indices, or every byte no checksum occupies when empty, so two
+    // checksums don't depend on the order they are written in
+    Checksum {
+        kind: ChecksumKind,
+        #[serde(default)]
+        bytes: Vec<usize>,
+    },
+}
+
+#[derive(Clone, Debug)]
+pub struct AutoField {
+    // Bit positions of the signal from its LSB to its MSB, see `dbc::layout::signal_bits`
+    pub bits: Vec<u64>,
+    pub kind: AutoKind,
+}
+
+/// Writes the counters for the `sent_count`th transmission, then the checksums, so checksums
+/// cover the counter.
+pub fn apply(fields: &[AutoField], sent_count: u64, msg_bytes: &mut [u8]) {
+    for field in fields {
+        if field.kind == AutoKind::Counter {
+            let wrap = 1u64.checked_shl(field.bits.len() as u32).unwrap_or(0);
+            let value = if wrap == 0 {
+                sent_count
+            } else {
+                sent_count % wrap
+            };
+            write_bits(msg_bytes, &field.bits, value);
+        }
+    }
+
+    let checksum_bytes: Vec<usize> = fields
+        .iter()
+        .filter(|field| matches!(field.kind, AutoKind::Checksum { .. }))
+        .flat_map(|field| field.bits.iter().map(|&b| (b / 8) as usize))
+        .collect();
+    for field in fields {
+        if let AutoKind::Checksum { kind, bytes } = &field.kind {
+            let value = if bytes.is_empty() {
+                kind.compute(
+                    (0..msg_bytes.len())
+                        .filter(|i| !checksum_bytes.contains(i))
+                        .map(|i| msg_bytes[i]),
+                )
+            } else {
+                kind.compute(bytes.iter().filter_map(|&i| msg_bytes.get(i).copied()))
+            };
+            write_bits(msg_bytes, &field.bits, value as u64);
+        }
+    }
+}
+
+fn write_bits(msg_bytes: &mut [u8], bits: &[u64], value: u64) {
+    for (i, &pos) in bits.iter().enumerate() {
+        let Some(byte) = msg_bytes.get_mut((pos / 8) as usize) else {
+            continue;
+        };
+        let mask = 1u8 << (pos % 8);
+        if i < 64 && (value >> i) & 1 == 1 {
+            *byte |= mask;
+        } else {
+            *byte &= !mask;
+        }
+    }
+}
+
+// CAN FD's longest payload, a checksum can't cover bytes past it
+const MAX_FRAME_BYTES: usize = 64;
+
+/// Parses byte lists like "0-5, 7" as typed in the Send UI.
+pub fn parse_byte_list(text: &str) -> Result<Vec<usize>, String> {
+    let mut bytes = Vec::new();
+    for part in text.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+        let parse = |s: &str| {
+            let s = s.trim();
+            match s.parse::<usize>() {
+                Ok(index) if index < MAX_FRAME_BYTES => Ok(index),
+                Ok(_) => Err(format!(
+                    "byte {} is past the end of a frame, the last is {}",
+                    s,
+                    MAX_FRAME_BYTES - 1
+                )),
+                Err(_) => Err(format!("\"{}\" is not a byte index", s)),
+            }
+        };
+        match part.split_once('-') {
+            Some((start, end)) => {
+                let (start, end) = (parse(start)?, parse(end)?);
+                if start > end {
+                    return Err(format!(
+                        "\"{}\" goes backwards, write {}-{}",
+                        part, end, start
+                    ));
+                }
+                bytes.extend(start..=end);
+            }
+            None => bytes.push(parse(part)?),
+        }
+    }
+    Ok(bytes)
+}
+
+pub fn format_byte_list(bytes: &[usize]) -> String {
+    bytes
+        .iter()
+        .map(|b| b.to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CHECK_INPUT: &[u8] = b"123456789";
+
+    #[test]
+    fn checksum_check_values() {
+        let compute = |kind: ChecksumKind| kind.compute(CHECK_INPUT.iter().copied());
+        assert_eq!(compute(ChecksumKind::Crc8SaeJ1850), 0x4B);
+        assert_eq!(compute(ChecksumKind::Xor), 0x31);
+        assert_eq!(compute(ChecksumKind::Sum), 0xDD);
+    }
+
+    fn counter_and_crc(bytes: Vec<usize>) -> [AutoField; 2] {
+        [
+            AutoField {
+                bits: (0..4).collect(),
+                kind: AutoKind::Counter,
+            },
+            AutoField {
+                bits: (56..64).collect(),
+                kind: AutoKind::Checksum {
+                    kind: ChecksumKind::Crc8SaeJ1850,
+                    bytes,
+                },
+            },
+        ]
+    }
+
+    #[test]
+    fn counter_wraps_at_its_width() {
+        let fields = counter_and_crc(Vec::new());
+        let mut msg_bytes = [0xF0, 0, 0, 0, 0, 0, 0, 0];
+        apply(&fields, 15, &mut msg_bytes);
+        assert_eq!(msg_bytes[0], 0xFF);
+        apply(&fields, 16, &mut msg_bytes);
+        assert_eq!(msg_bytes[0], 0xF0);
+    }
+
+    #[test]
+    fn checksum_covers_the_counter() {
+        // Without a byte list the checksum covers every byte but its own
+        let mut msg_bytes = [0xAF, 0x22, 0x33, 0, 0, 0, 0, 0];
+        apply(&counter_and_crc(Vec::new()), 17, &mut msg_bytes);
+        assert_eq!(msg_bytes, [0xA1, 0x22, 0x33, 0, 0, 0, 0, 0xAC]);
+
+        let mut msg_bytes = [0xAF, 0x22, 0x33, 0, 0, 0, 0, 0];
+        apply(&counter_and_crc(vec![0, 1, 2]), 17, &mut msg_bytes);
+        assert_eq!(msg_bytes[7], 0x0E);
+    }
+
+    #[test]
+    fn checksums_skip_each_other() {
+        let checksum = |byte: u64, kind| AutoField {
+            bits: (byte * 8..byte * 8 + 8).collect(),
+            kind: AutoKind::Checksum {
+                kind,
+                bytes: Vec::new(),
+            },
+        };
+        let xor = checksum(6, ChecksumKind::Xor);
+        let sum = checksum(7, ChecksumKind::Sum);
+
+        let mut forward = [0x01, 0x02, 0x04, 0x08, 0x10, 0x20, 0, 0];
+        let mut reverse = forward;
+        apply(&[xor.clone(), sum.clone()], 0, &mut forward);
+        apply(&[sum, xor], 0, &mut reverse);
+        assert_eq!(forward, [0x01, 0x02, 0x04, 0x08, 0x10, 0x20, 0x3F, 0x3F]);
+        assert_eq!(forward, reverse);
+    }
+
+    #[test]
+    fn parses_byte_lists() {
+        assert_eq!(parse_byte_list("0-2, 5"), Ok(vec![0, 1, 2, 5]));
+        assert_eq!(parse_byte_list(" 7 ,, 63"), Ok(vec![7, 63]));
+        assert_eq!(parse_byte_list(""), Ok(Vec::new()));
+        assert_eq!(format_byte_list(&[0, 1, 2, 5]), "0, 1, 2, 5");
+    }
+
+    #[test]
+    fn rejects_bad_byte_lists() {
+        assert!(parse_byte_list("7-0").is_err());
+        assert!(parse_byte_list("64").is_err());
+        assert!(parse_byte_list("60-64").is_err());
+        assert!(parse_byte_list("x").is_err());
+        assert!(parse_byte_list("-1").is_err());
+    }
+}
@@ -1,5 +1,5 @@
 use crate::connection::{CanBusSpeed, ConnectionSource};
-use crate::util;
+use crate::{bootloader, dbc, util};
 use rand::prelude::*;
 use serialport::{ClearBuffer, SerialPort};
 use slcan::sync::CanSocket;
@@ -35,6 +35,11 @@ pub trait Driver {
 
     fn write_frame(&mut self, frame: CanFrame) -> DriverResult<()>;
 
+    // Longest `read_frames` may block, drivers that never block can ignore it
+    fn set_read_timeout(&mut self, _timeout: Duration) -> DriverResult<()> {
+        Ok(())
+    }
+
     fn is_connected(&self) -> bool;
 
     fn bus_speed(&self) -> Option<CanBusSpeed>;
@@ -171,12 +176,13 @@ impl Driver for UdpDriver {
         match self.socket.recv_from(&mut buf) {
             Ok((num_bytes, _src_port)) => parse_udp_buffer(&buf, num_bytes),
             Err(e) => {
-                log::warn!("{}", e);
                 if e.kind() == std::io::ErrorKind::WouldBlock
                     || e.kind() == std::io::ErrorKind::TimedOut
                 {
+                    // Expected every time the scheduler bounds the read, not worth logging
                     Err(DriverError::ReadError(DriverReadError::Timeout))
                 } else {
+                    log::warn!("{}", e);
                     self.connected = false;
                     Err(DriverError::ReadError(DriverReadError::IoError(format!(
                         "UDP I/O error: {}",
@@ -192,6 +198,12 @@ impl Driver for UdpDriver {
         Ok(())
     }
 
+    fn set_read_timeout(&mut self, timeout: Duration) -> DriverResult<()> {
+        self.socket.set_read_timeout(Some(timeout)).map_err(|e| {
+            DriverError::ConnectionFailed(format!("Failed to set read timeout: {}", e))
+        })
+    }
+
     fn is_connected(&self) -> bool {
         self.connected
     }
@@ -208,15 +220,15 @@ impl Driver for UdpDriver {
 
 struct SimulatedDriver {
     connected: bool,
-    pub parser: Option<can_decode::Parser>,
+    pub parser: Option<dbc::merged::MergedParser>,
 }
 
 impl SimulatedDriver {
-    fn new(connected: bool, dbc_path: Option<std::path::PathBuf>) -> DriverResult<Self> {
+    fn new(connected: bool, dbc_paths: Vec<std::path::PathBuf>) -> DriverResult<Self> {
         if connected {
             Ok(Self {
                 connected,
-                parser: dbc_path.and_then(|path| can_decode::Parser::from_dbc_file(&path).ok()),
+                parser: dbc::merged::MergedParser::from_dbc_files(&dbc_paths).ok(),
             })
         } else {
             Err(DriverError::ConnectionFailed(
@@ -298,6 +310,8 @@ impl Driver for SimulatedDriver {
 struct LoopbackDriver {
     connected: bool,
     queued_frames: VecDeque<CanFrame>,
+    // Answer for the nodes in the bootloader config, to try flashing without hardware
+    stand_ins: Vec<bootloader::stand_in::StandIn>,
 }
 
 impl LoopbackDriver {
@@ -305,6 +319,11 @@ impl LoopbackDriver {
         Self {
             connected: true,
             queued_frames: VecDeque::new(),
+            stand_ins: bootloader::BootloaderConfig::load()
+                .nodes
+                .into_iter()
+                .map(bootloader::stand_in::StandIn::new)
+                .collect(),
         }
     }
 }
@@ -325,14 +344,36 @@ impl Driver for LoopbackDriver {
     }
 
     fn write_frame(&mut self, frame: CanFrame) -> DriverResult<()> {
-        if self.connected {
-            self.queued_frames.push_back(frame);
-            Ok(())
-        } else {
-            Err(DriverError::WriteError(
+        if !self.connected {
+            return Err(DriverError::WriteError(
                 "Loopback driver is disconnected".into(),
-            ))
+            ));
+        }
+
+        let mut responses: Vec<CanFrame> = Vec::new();
+        if let CanFrame::Can2(frame2) = &frame {
+            let msg_id = util::can::slcan_to_u32_with_extid_flag(&frame2.id());
+            let data = frame2.data().unwrap_or(&[]);
+            for stand_in in &mut self.stand_ins {
+                let Some(response) = stand_in.on_frame(msg_id, data) else {
+                    continue;
+                };
+                let (resp_id, is_extended) = stand_in.resp_id();
+                let id = if is_extended {
+                    slcan::ExtendedId::new(resp_id).map(slcan::Id::Extended)
+                } else {
+                    slcan::StandardId::new(resp_id as u16).map(slcan::Id::Standard)
+                };
+                if let Some(response_frame) =
+                    id.and_then(|id| slcan::Can2Frame::new_data(id, &response))
+                {
+                    responses.push(response_frame.into());
+                }
+            }
         }
+        self.queued_frames.push_back(frame);
+        self.queued_frames.extend(responses);
+        Ok(())
     }
 
     fn is_connected(&self) -> bool {
@@ -425,9 +466,9 @@ pub fn create_driver(source: &ConnectionSource) -> DriverResult<Box<dyn Driver>>
     match source {
         ConnectionSource::Serial(path, speed) => Ok(Box::new(SerialDriver::new(path, *speed)?)),
         ConnectionSource::Udp(port) => Ok(Box::new(UdpDriver::new(*port)?)),
-        ConnectionSource::Simulated(connected, dbc_path) => Ok(Box::new(SimulatedDriver::new(
+        ConnectionSource::Simulated(connected, dbc_paths) => Ok(Box::new(SimulatedDriver::new(
             *connected,
-            dbc_path.clone(),
+            dbc_paths.clone(),
         )?)),
         ConnectionSource::Loopback => Ok(Box::new(LoopbackDriver::new())),
     }
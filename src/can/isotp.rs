@@ -0,0 +1,540 @@
+// ISO 15765-2 (ISO-TP) over classic CAN: payloads up to 4095 bytes are split into a first frame
+// and consecutive frames, paced by the receiver's flow control frames.
+
+use std::time::{Duration, Instant};
+
+pub const MAX_PAYLOAD: usize = 4095;
+// Most ECUs only accept full 8 byte frames
+const PADDING: u8 = 0xCC;
+const FRAME_LEN: usize = 8;
+// N_Bs / N_Cr, how long to wait for a flow control or the next consecutive frame
+const FRAME_TIMEOUT: Duration = Duration::from_millis(1000);
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FlowStatus {
+    ContinueToSend,
+    Wait,
+    Overflow,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Frame {
+    Single(Vec<u8>),
+    First {
+        total_len: usize,
+        data: Vec<u8>,
+    },
+    Consecutive {
+        seq: u8,
+        data: Vec<u8>,
+    },
+    FlowControl {
+        status: FlowStatus,
+        // 0 means no limit
+        block_size: u8,
+        st_min: Duration,
+    },
+}
+
+impl Frame {
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        let pci = *data.first()?;
+        match pci >> 4 {
+            0x0 => {
+                let len = (pci & 0x0F) as usize;
+                (len > 0 && data.len() > len).then(|| Frame::Single(data[1..=len].to_vec()))
+            }
+            0x1 => {
+                let total_len = (((pci & 0x0F) as usize) << 8) | *data.get(1)? as usize;
+                // Anything that fits a single frame must be sent as one
+                (total_len > 7).then(|| Frame::First {
+                    total_len,
+                    data: data[2..].to_vec(),
+                })
+            }
+            0x2 => Some(Frame::Consecutive {
+                seq: pci & 0x0F,
+                data: data[1..].to_vec(),
+            }),
+            0x3 => {
+                let status = match pci & 0x0F {
+                    0 => FlowStatus::ContinueToSend,
+                    1 => FlowStatus::Wait,
+                    2 => FlowStatus::Overflow,
+                    _ => return None,
+                };
+                let st_min = match *data.get(2).unwrap_or(&0) {
+                    ms @ 0x00..=0x7F => Duration::from_millis(ms as u64),
+                    us @ 0xF1..=0xF9 => Duration::from_micros((us - 0xF0) as u64 * 100),
+                    // Reserved values mean the longest STmin
+                    _ => Duration::from_millis(0x7F),
+                };
+                Some(Frame::FlowControl {
+                    status,
+                    block_size: *data.get(1).unwrap_or(&0),
+                    st_min,
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+fn pad(mut frame: Vec<u8>) -> Vec<u8> {
+    frame.resize(FRAME_LEN, PADDING);
+    frame
+}
+
+/// Flow control frame accepting the rest of the payload in one block, as fast as possible.
+pub fn flow_control_cts() -> Vec<u8> {
+    pad(vec![0x30, 0x00, 0x00])
+}
+
+pub enum Reassembly {
+    // Not an ISO-TP frame we were expecting
+    Ignored,
+    // First frame received, the sender waits for a flow control
+    Started,
+    InProgress,
+    Complete(Vec<u8>),
+    Failed(String),
+}
+
+/// Collects the frames of one direction of a link back into payloads.
+#[derive(Default)]
+pub struct Reassembler {
+    total_len: usize,
+    buf: Vec<u8>,
+    next_seq: u8,
+    active: bool,
+    last_frame: Option<Instant>,
+}
+
+impl Reassembler {
+    pub fn on_frame(&mut self, data: &[u8]) -> Reassembly {
+        let Some(frame) = Frame::parse(data) else {
+            return Reassembly::Ignored;
+        };
+        match frame {
+            Frame::Single(payload) => {
+                self.active = false;
+                Reassembly::Complete(payload)
+            }
+            Frame::First { total_len, data } => {
+                self.total_len = total_len;
+                self.buf = data;
+                self.next_seq = 1;
+                self.active = true;
+                self.last_frame = Some(Instant::now());
+                Reassembly::Started
+            }
+            Frame::Consecutive { seq, data } if self.active => {
+                if seq != self.next_seq {
+                    self.active = false;
+                    return Reassembly::Failed(format!(
+                        "expected consecutive frame {}, got {}",
+                        self.next_seq, seq
+                    ));
+                }
+                self.next_seq = (self.next_seq + 1) & 0x0F;
+                self.last_frame = Some(Instant::now());
+                let missing = self.total_len - self.buf.len();
+                self.buf.extend(&data[..data.len().min(missing)]);
+                if self.buf.len() == self.total_len {
+                    self.active = false;
+                    Reassembly::Complete(std::mem::take(&mut self.buf))
+                } else {
+                    Reassembly::InProgress
+                }
+            }
+            Frame::Consecutive { .. } | Frame::FlowControl { .. } => Reassembly::Ignored,
+        }
+    }
+
+    pub fn timed_out(&self) -> bool {
+        self.active
+            && self
+                .last_frame
+                .is_some_and(|last| last.elapsed() > FRAME_TIMEOUT)
+    }
+}
+
+enum SendState {
+    WaitingForFlowControl {
+        since: Instant,
+    },
+    Sending {
+        // Frames left before the next flow control, None when unlimited
+        block_left: Option<u8>,
+        st_min: Duration,
+        next_at: Instant,
+    },
+    Done,
+}
+
+/// Splits a payload into frames, sending consecutive frames as flow control allows.
+pub struct Segmenter {
+    payload: Vec<u8>,
+    offset: usize,
+    next_seq: u8,
+    state: SendState,
+}
+
+impl Segmenter {
+    /// Returns the segmenter and the single or first frame to send right away.
+    pub fn new(payload: Vec<u8>) -> Result<(Self, Vec<u8>), String> {
+        if payload.is_empty() || payload.len() > MAX_PAYLOAD {
+            return Err(format!(
+                "ISO-TP payloads are 1 to {} bytes, got {}",
+                MAX_PAYLOAD,
+                payload.len()
+            ));
+        }
+
+        if payload.len() <= 7 {
+            let mut frame = vec![payload.len() as u8];
+            frame.extend(&payload);
+            return Ok((
+                Self {
+                    payload,
+                    offset: 0,
+                    next_seq: 0,
+                    state: SendState::Done,
+                },
+                pad(frame),
+            ));
+        }
+
+        let len = payload.len();
+        let mut frame = vec![0x10 | (len >> 8) as u8, len as u8];
+        frame.extend(&payload[..6]);
+        Ok((
+            Self {
+                payload,
+                offset: 6,
+                next_seq: 1,
+                state: SendState::WaitingForFlowControl {
+                    since: Instant::now(),
+                },
+            },
+            frame,
+        ))
+    }
+
+    pub fn is_done(&self) -> bool {
+        matches!(self.state, SendState::Done)
+    }
+
+    pub fn on_frame(&mut self, data: &[u8]) -> Result<(), String> {
+        let SendState::WaitingForFlowControl { .. } = self.state else {
+            return Ok(());
+        };
+        let Some(Frame::FlowControl {
+            status,
+            block_size,
+            st_min,
+        }) = Frame::parse(data)
+        else {
+            return Ok(());
+        };
+        match status {
+            FlowStatus::ContinueToSend => {
+                self.state = SendState::Sending {
+                    block_left: (block_size > 0).then_some(block_size),
+                    st_min,
+                    next_at: Instant::now(),
+                };
+                Ok(())
+            }
+            FlowStatus::Wait => {
+                self.state = SendState::WaitingForFlowControl {
+                    since: Instant::now(),
+                };
+                Ok(())
+            }
+            FlowStatus::Overflow => Err("receiver overflow".to_string()),
+        }
+    }
+
+    /// Consecutive frames due now.
+    pub fn tick(&mut self) -> Result<Vec<Vec<u8>>, String> {
+        let now = Instant::now();
+        let mut frames = Vec::new();
+        loop {
+            match &mut self.state {
+                SendState::WaitingForFlowControl { since } => {
+                    if now - *since > FRAME_TIMEOUT {
+                        return Err("no flow control from the receiver".to_string());
+                    }
+                    break;
+                }
+                SendState::Sending {
+                    block_left,
+                    st_min,
+                    next_at,
+                } => {
+                    if *next_at > now {
+                        break;
+                    }
+                    let end = (self.offset + 7).min(self.payload.len());
+                    let mut frame = vec![0x20 | self.next_seq];
+                    frame.extend(&self.payload[self.offset..end]);
+                    frames.push(pad(frame));
+                    self.offset = end;
+                    self.next_seq = (self.next_seq + 1) & 0x0F;
+
+                    if self.offset == self.payload.len() {
+                        self.state = SendState::Done;
+                        break;
+                    }
+                    if let Some(left) = block_left {
+                        *left -= 1;
+                        if *left == 0 {
+                            self.state = SendState::WaitingForFlowControl { since: now };
+                            break;
+                        }
+                    }
+                    // Without a separation time the whole block goes out in one tick
+                    if !st_min.is_zero() {
+                        *next_at = now + *st_min;
+                    }
+                }
+                SendState::Done => break,
+            }
+        }
+        Ok(frames)
+    }
+
+    pub fn time_until_next(&self) -> Option<Duration> {
+        match self.state {
+            SendState::Sending { next_at, .. } => {
+                Some(next_at.saturating_duration_since(Instant::now()))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// One request and its response over a pair of IDs, as a diagnostic client does.
+pub struct Exchange {
+    rx_id_with_ext_flag: u32,
+    segmenter: Segmenter,
+    reassembler: Reassembler,
+    deadline: Instant,
+    outcome: Option<Result<Vec<u8>, String>>,
+}
+
+impl Exchange {
+    /// Returns the exchange and the frames to send right away.
+    pub fn new(
+        rx_id_with_ext_flag: u32,
+        request: Vec<u8>,
+        timeout: Duration,
+    ) -> Result<(Self, Vec<Vec<u8>>), String> {
+        let (segmenter, first_frame) = Segmenter::new(request)?;
+        Ok((
+            Self {
+                rx_id_with_ext_flag,
+                segmenter,
+                reassembler: Reassembler::default(),
+                deadline: Instant::now() + timeout,
+                outcome: None,
+            },
+            vec![first_frame],
+        ))
+    }
+
+    /// Returns the flow control to send when the response is a multi-frame one.
+    pub fn on_frame(&mut self, msg_id_with_ext_flag: u32, data: &[u8]) -> Vec<Vec<u8>> {
+        if msg_id_with_ext_flag != self.rx_id_with_ext_flag || self.outcome.is_some() {
+            return Vec::new();
+        }
+        if !self.segmenter.is_done() {
+            if let Err(e) = self.segmenter.on_frame(data) {
+                self.outcome = Some(Err(e));
+            }
+            return Vec::new();
+        }
+        match self.reassembler.on_frame(data) {
+            Reassembly::Started => vec![flow_control_cts()],
+            Reassembly::Complete(payload) => {
+                self.outcome = Some(Ok(payload));
+                Vec::new()
+            }
+            Reassembly::Failed(e) => {
+                self.outcome = Some(Err(e));
+                Vec::new()
+            }
+            Reassembly::Ignored | Reassembly::InProgress => Vec::new(),
+        }
+    }
+
+    /// Returns the consecutive frames of the request that are due.
+    pub fn tick(&mut self) -> Vec<Vec<u8>> {
+        if self.outcome.is_some() {
+            return Vec::new();
+        }
+        match self.segmenter.tick() {
+            Ok(frames) => {
+                if Instant::now() > self.deadline && !self.reassembler.active {
+                    self.outcome = Some(Err("no response".to_string()));
+                } else if self.reassembler.timed_out() {
+                    self.outcome = Some(Err("response stopped halfway".to_string()));
+                }
+                frames
+            }
+            Err(e) => {
+                self.outcome = Some(Err(e));
+                Vec::new()
+            }
+        }
+    }
+
+    pub fn take_outcome(&mut self) -> Option<Result<Vec<u8>, String>> {
+        self.outcome.take()
+    }
+
+    // For servers that answered "response pending" and will send the real response later
+    pub fn wait_again(&mut self, timeout: Duration) {
+        self.reassembler = Reassembler::default();
+        self.deadline = Instant::now() + timeout;
+    }
+
+    pub fn time_until_next(&self) -> Option<Duration> {
+        self.segmenter.time_until_next()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Feeds every frame of a segmenter into a reassembler, answering first frames and
+    // finished blocks with the given flow control
+    fn round_trip(payload: Vec<u8>, flow_control: &[u8]) -> Vec<u8> {
+        let (mut segmenter, first) = Segmenter::new(payload).unwrap();
+        let mut reassembler = Reassembler::default();
+        let mut pending = vec![first];
+        loop {
+            for frame in pending.drain(..) {
+                assert_eq!(frame.len(), FRAME_LEN);
+                match reassembler.on_frame(&frame) {
+                    Reassembly::Complete(payload) => {
+                        assert!(segmenter.is_done());
+                        return payload;
+                    }
+                    Reassembly::Started | Reassembly::InProgress => {}
+                    Reassembly::Ignored => panic!("frame ignored: {:02X?}", frame),
+                    Reassembly::Failed(e) => panic!("{}", e),
+                }
+            }
+            if segmenter.time_until_next().is_none() {
+                segmenter.on_frame(flow_control).unwrap();
+            }
+            pending = segmenter.tick().unwrap();
+        }
+    }
+
+    #[test]
+    fn single_frame_round_trip() {
+        let (segmenter, frame) = Segmenter::new(vec![0x22, 0xF1, 0x90]).unwrap();
+        assert!(segmenter.is_done());
+        assert_eq!(frame, [0x03, 0x22, 0xF1, 0x90, 0xCC, 0xCC, 0xCC, 0xCC]);
+        assert!(matches!(
+            Reassembler::default().on_frame(&frame),
+            Reassembly::Complete(payload) if payload == [0x22, 0xF1, 0x90]
+        ));
+    }
+
+    #[test]
+    fn multi_frame_round_trip() {
+        // Long enough for the sequence number to wrap past 15
+        let payload: Vec<u8> = (0..200).map(|i| i as u8).collect();
+        let (_, first) = Segmenter::new(payload.clone()).unwrap();
+        assert_eq!(&first[..2], [0x10, 200]);
+        assert_eq!(round_trip(payload.clone(), &flow_control_cts()), payload);
+    }
+
+    #[test]
+    fn multi_frame_round_trip_with_block_size() {
+        let payload: Vec<u8> = (0..100).map(|i| (i * 7) as u8).collect();
+        // Two frames per block, so the sender waits for a flow control after each pair
+        let fc = [0x30, 0x02, 0x00, 0xCC, 0xCC, 0xCC, 0xCC, 0xCC];
+        assert_eq!(round_trip(payload.clone(), &fc), payload);
+    }
+
+    #[test]
+    fn segmenter_waits_for_flow_control() {
+        let (mut segmenter, _) = Segmenter::new(vec![0; 20]).unwrap();
+        assert!(segmenter.tick().unwrap().is_empty());
+
+        segmenter.on_frame(&[0x30, 0x01, 0x00]).unwrap();
+        let frames = segmenter.tick().unwrap();
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0][0], 0x21);
+        assert!(segmenter.tick().unwrap().is_empty());
+
+        segmenter.on_frame(&flow_control_cts()).unwrap();
+        let frames = segmenter.tick().unwrap();
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0][0], 0x22);
+        assert!(segmenter.is_done());
+    }
+
+    #[test]
+    fn overflow_aborts_the_send() {
+        let (mut segmenter, _) = Segmenter::new(vec![0; 20]).unwrap();
+        assert_eq!(
+            segmenter.on_frame(&[0x32, 0x00, 0x00]),
+            Err("receiver overflow".to_string())
+        );
+    }
+
+    #[test]
+    fn payload_length_limits() {
+        assert!(Segmenter::new(Vec::new()).is_err());
+        assert!(Segmenter::new(vec![0; MAX_PAYLOAD]).is_ok());
+        assert!(Segmenter::new(vec![0; MAX_PAYLOAD + 1]).is_err());
+    }
+
+    #[test]
+    fn out_of_order_consecutive_frame_fails() {
+        let mut reassembler = Reassembler::default();
+        assert!(matches!(
+            reassembler.on_frame(&[0x10, 20, 0, 1, 2, 3, 4, 5]),
+            Reassembly::Started
+        ));
+        assert!(matches!(
+            reassembler.on_frame(&[0x22, 6, 7, 8, 9, 10, 11, 12]),
+            Reassembly::Failed(_)
+        ));
+        // The failed transfer is dropped, later consecutive frames are not ours
+        assert!(matches!(
+            reassembler.on_frame(&[0x21, 6, 7, 8, 9, 10, 11, 12]),
+            Reassembly::Ignored
+        ));
+    }
+
+    #[test]
+    fn parses_flow_control_separation_time() {
+        let st_min = |byte: u8| match Frame::parse(&[0x30, 0x00, byte]) {
+            Some(Frame::FlowControl { st_min, .. }) => st_min,
+            other => panic!("{:?}", other),
+        };
+        assert_eq!(st_min(0x00), Duration::ZERO);
+        assert_eq!(st_min(0x14), Duration::from_millis(20));
+        assert_eq!(st_min(0xF1), Duration::from_micros(100));
+        assert_eq!(st_min(0xF9), Duration::from_micros(900));
+        assert_eq!(st_min(0x80), Duration::from_millis(0x7F));
+    }
+
+    #[test]
+    fn rejects_malformed_frames() {
+        // Single frame longer than the data
+        assert_eq!(Frame::parse(&[0x05, 1, 2]), None);
+        // First frame short enough to be a single frame
+        assert_eq!(Frame::parse(&[0x10, 0x07, 1, 2, 3, 4, 5, 6]), None);
+        assert_eq!(Frame::parse(&[0x33, 0x00, 0x00]), None);
+        assert_eq!(Frame::parse(&[]), None);
+    }
+}
@@ -1,4 +1,7 @@
+pub mod auto_fields;
 pub mod bus_load;
 pub mod driver;
+pub mod isotp;
+pub mod scheduler;
 pub mod state;
 pub mod thread;
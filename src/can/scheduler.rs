@@ -0,0 +1,199 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::time::{Duration, Instant};
+
+use crate::{can, messages};
+
+/// Identifies one sender, so several Send UIs (or sequences) can send the same ID at their own
+/// periods without replacing each other.
+pub type SendHandle = u64;
+
+static NEXT_HANDLE: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(1);
+
+// Handles are allocated by whoever adds the sender, the CAN thread doesn't need to reply with one
+pub fn new_handle() -> SendHandle {
+    NEXT_HANDLE.fetch_add(1, std::sync::atomic::Ordering::Relaxed)
+}
+
+/// Measured time between two sends of the same sender, to compare against the requested period.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PeriodStats {
+    pub count: u64,
+    pub last_ms: f64,
+    pub mean_ms: f64,
+    pub min_ms: f64,
+    pub max_ms: f64,
+}
+
+impl PeriodStats {
+    fn record(&mut self, interval: Duration) {
+        let ms = interval.as_secs_f64() * 1000.0;
+        self.count += 1;
+        self.last_ms = ms;
+        if self.count == 1 {
+            self.mean_ms = ms;
+            self.min_ms = ms;
+            self.max_ms = ms;
+        } else {
+            self.mean_ms += (ms - self.mean_ms) / self.count as f64;
+            self.min_ms = self.min_ms.min(ms);
+            self.max_ms = self.max_ms.max(ms);
+        }
+    }
+}
+
+struct Sender {
+    amount: messages::SendAmount,
+    msg_id: u32,
+    is_msg_id_extended: bool,
+    msg_bytes: Vec<u8>,
+    auto_fields: Vec<can::auto_fields::AutoField>,
+    sent_count: u64,
+    next_due: Instant,
+    last_sent: Option<Instant>,
+    stats: PeriodStats,
+}
+
+pub struct DueMessage {
+    pub handle: SendHandle,
+    pub msg_id: u32,
+    pub msg_bytes: Vec<u8>,
+    pub is_msg_id_extended: bool,
+    // None when this was the last send
+    pub amount_left: Option<messages::SendAmount>,
+    pub stats: PeriodStats,
+}
+
+/// Periodic senders ordered by their next due time on the monotonic clock. Periods are kept
+/// relative to the previous due time, so late sends don't make the following ones drift.
+#[derive(Default)]
+pub struct Scheduler {
+    senders: HashMap<SendHandle, Sender>,
+    // Entries whose due time no longer matches their sender are stale and skipped when popped
+    queue: BinaryHeap<Reverse<(Instant, SendHandle)>>,
+}
+
+impl Scheduler {
+    // Adding an existing handle replaces its sender and sends it right away. Its rolling counters
+    // carry on, so receivers don't see a jump when only the values changed.
+    pub fn add(&mut self, add_msg: messages::AddSendMessage) {
+        let now = Instant::now();
+        let sent_count = self
+            .senders
+            .get(&add_msg.handle)
+            .map_or(0, |sender| sender.sent_count);
+        self.senders.insert(
+            add_msg.handle,
+            Sender {
+                amount: add_msg.amount,
+                msg_id: add_msg.msg_id,
+                is_msg_id_extended: add_msg.is_msg_id_extended,
+                msg_bytes: add_msg.msg_bytes,
+                auto_fields: add_msg.auto_fields,
+                sent_count,
+                next_due: now,
+                last_sent: None,
+                stats: PeriodStats::default(),
+            },
+        );
+        self.queue.push(Reverse((now, add_msg.handle)));
+    }
+
+    pub fn remove(&mut self, handle: SendHandle) {
+        self.senders.remove(&handle);
+    }
+
+    // Changes the payload without restarting the period, None keeps the auto fields
+    pub fn update_bytes(
+        &mut self,
+        handle: SendHandle,
+        msg_bytes: Vec<u8>,
+        auto_fields: Option<Vec<can::auto_fields::AutoField>>,
+    ) {
+        if let Some(sender) = self.senders.get_mut(&handle) {
+            sender.msg_bytes = msg_bytes;
+            if let Some(auto_fields) = auto_fields {
+                sender.auto_fields = auto_fields;
+            }
+        }
+    }
+
+    /// Time until the next sender is due, for bounding how long a read may block.
+    pub fn time_until_next(&mut self) -> Option<Duration> {
+        self.drop_stale();
+        self.queue
+            .peek()
+            .map(|Reverse((due, _))| due.saturating_duration_since(Instant::now()))
+    }
+
+    /// Pops every sender that is due and reschedules the ones with sends left.
+    pub fn pop_due(&mut self) -> Vec<DueMessage> {
+        let now = Instant::now();
+        let mut due_msgs = Vec::new();
+
+        loop {
+            self.drop_stale();
+            let Some(Reverse((due, handle))) = self.queue.peek().copied() else {
+                break;
+            };
+            if due > now {
+                break;
+            }
+            self.queue.pop();
+
+            let sender = self
+                .senders
+                .get_mut(&handle)
+                .expect("stale entries dropped");
+            if let Some(last_sent) = sender.last_sent {
+                sender.stats.record(now - last_sent);
+            }
+            sender.last_sent = Some(now);
+
+            let mut msg_bytes = sender.msg_bytes.clone();
+            can::auto_fields::apply(&sender.auto_fields, sender.sent_count, &mut msg_bytes);
+            sender.sent_count += 1;
+
+            let amount_left = sender.amount.subtract_one();
+            due_msgs.push(DueMessage {
+                handle,
+                msg_id: sender.msg_id,
+                msg_bytes,
+                is_msg_id_extended: sender.is_msg_id_extended,
+                amount_left,
+                stats: sender.stats,
+            });
+
+            match amount_left {
+                Some(amount) => {
+                    sender.amount = amount;
+                    // A zero period would keep the sender due forever and never leave this loop
+                    let period = Duration::from_millis(amount.period_ms().max(1) as u64);
+                    sender.next_due += period;
+                    // Fell more than a period behind (ex: a stalled driver), skip the missed sends
+                    // instead of bursting them out
+                    if sender.next_due < now {
+                        sender.next_due = now + period;
+                    }
+                    self.queue.push(Reverse((sender.next_due, handle)));
+                }
+                None => {
+                    self.senders.remove(&handle);
+                }
+            }
+        }
+
+        due_msgs
+    }
+
+    fn drop_stale(&mut self) {
+        while let Some(Reverse((due, handle))) = self.queue.peek() {
+            match self.senders.get(handle) {
+                Some(sender) if sender.next_due == *due => break,
+                _ => {
+                    self.queue.pop();
+                }
+            }
+        }
+    }
+}
@@ -1,4 +1,4 @@
-use crate::{can, connection, messages};
+use crate::{bootloader, can, connection, dbc, derived, messages, rest_bus, sequence, uds};
 
 pub struct State {
     pub can_to_ui_tx: std::sync::mpsc::Sender<messages::MsgFromCan>,
@@ -6,23 +6,18 @@ pub struct State {
     pub driver: Option<Box<dyn can::driver::Driver>>,
     pub current_source: Option<connection::ConnectionSource>,
     pub is_connected: bool,
-    pub parser: Option<can_decode::Parser>,
-    pub send_msgs: std::collections::HashMap<u32, SendMsgInfo>, // msg_id -> SendMsg
+    pub parser: Option<dbc::merged::MergedParser>,
+    pub scheduler: can::scheduler::Scheduler,
     pub bus_load_tracker: can::bus_load::BusLoadTracker,
     pub last_bus_load_update: std::time::Instant,
-}
-
-pub struct SendMsgInfo {
-    pub amount: messages::SendAmount,
-    pub is_msg_id_extended: bool,
-    pub msg_bytes: Vec<u8>,
-    pub last_sent: Option<chrono::DateTime<chrono::Local>>,
-}
-
-pub struct SendTickInfo {
-    pub msg_id: u32,
-    pub msg_bytes: Vec<u8>,
-    pub is_msg_id_extended: bool,
+    pub derived: derived::DerivedEngine,
+    pub sequences: Vec<sequence::SequenceRunner>,
+    pub reply_rules: std::collections::HashMap<rest_bus::RulesOwner, Vec<rest_bus::ReplyRule>>,
+    pub flasher: Option<bootloader::flasher::Flasher>,
+    pub info_query: Option<bootloader::inventory::InfoQuery>,
+    pub uds_requests: Vec<uds::Request>,
+    // Both directions of every configured diagnostic link, reassembled for the viewers
+    pub isotp_monitor: std::collections::HashMap<u32, can::isotp::Reassembler>,
 }
 
 impl State {
@@ -38,78 +33,261 @@ impl State {
             current_source,
             is_connected: false,
             parser: None,
-            send_msgs: std::collections::HashMap::new(),
+            scheduler: can::scheduler::Scheduler::default(),
             bus_load_tracker: can::bus_load::BusLoadTracker::new(),
             last_bus_load_update: std::time::Instant::now(),
+            derived: derived::DerivedEngine::new(&derived::DerivedConfig::load().signals),
+            sequences: Vec::new(),
+            reply_rules: std::collections::HashMap::new(),
+            flasher: None,
+            info_query: None,
+            uds_requests: Vec::new(),
+            isotp_monitor: uds::DiagnosticsConfig::load()
+                .ecus
+                .iter()
+                .flat_map(|ecu| [ecu.tx_id_with_ext_flag(), ecu.rx_id_with_ext_flag()])
+                .map(|id| (id, can::isotp::Reassembler::default()))
+                .collect(),
         }
     }
 
     pub fn add_send_message(&mut self, add_msg: messages::AddSendMessage) {
-        let msg_id = add_msg.msg_id;
-        let send_msg = SendMsgInfo::from_add_send_message(add_msg);
-        self.send_msgs.insert(msg_id, send_msg);
-    }
-
-    pub fn delete_send_message(&mut self, msg_id: u32) {
-        self.send_msgs.remove(&msg_id);
-    }
-
-    // Returns a list of messages that should be sent this tick, and updates
-    // internal state accordingly (last sent time, amount left, remove messages
-    // that are done, etc.)
-    pub fn send_this_tick(&mut self) -> Vec<SendTickInfo> {
-        let now = chrono::Local::now();
-        let mut msgs_to_send = Vec::new();
-        let mut msgs_to_remove = Vec::new();
-
-        for (msg_id, send_msg) in self.send_msgs.iter_mut() {
-            if send_msg.should_send() {
-                msgs_to_send.push(SendTickInfo {
-                    msg_id: *msg_id,
-                    msg_bytes: send_msg.msg_bytes.clone(),
-                    is_msg_id_extended: send_msg.is_msg_id_extended,
-                });
-                send_msg.last_sent = Some(now);
-                if let Some(new_amount) = send_msg.amount.subtract_one() {
-                    send_msg.amount = new_amount;
-                } else {
-                    msgs_to_remove.push(*msg_id);
+        self.scheduler.add(add_msg);
+    }
+
+    pub fn update_send_bytes(
+        &mut self,
+        handle: can::scheduler::SendHandle,
+        msg_bytes: Vec<u8>,
+        auto_fields: Vec<can::auto_fields::AutoField>,
+    ) {
+        self.scheduler
+            .update_bytes(handle, msg_bytes, Some(auto_fields));
+    }
+
+    pub fn delete_send_message(&mut self, handle: can::scheduler::SendHandle) {
+        self.scheduler.remove(handle);
+    }
+
+    pub fn set_reply_rules(
+        &mut self,
+        owner: rest_bus::RulesOwner,
+        rules: Vec<rest_bus::ReplyRule>,
+    ) {
+        if rules.is_empty() {
+            self.reply_rules.remove(&owner);
+        } else {
+            self.reply_rules.insert(owner, rules);
+        }
+    }
+
+    // Queues the replies to a received frame, they go out with the next scheduler pass
+    pub fn trigger_replies(&mut self, msg_id_with_ext_flag: u32) {
+        for rule in self.reply_rules.values().flatten() {
+            if rule.trigger_msg_id_with_ext_flag == msg_id_with_ext_flag {
+                self.scheduler.add(rule.reply.clone());
+            }
+        }
+    }
+
+    pub fn start_sequence(&mut self, seq: sequence::Sequence) {
+        self.stop_sequence(&seq.name);
+        log::info!("Starting sequence {}", seq.name);
+        self.sequences.push(sequence::SequenceRunner::new(seq));
+    }
+
+    pub fn stop_sequence(&mut self, name: &str) {
+        let Some(idx) = self.sequences.iter().position(|r| r.sequence.name == name) else {
+            return;
+        };
+        let mut runner = self.sequences.remove(idx);
+        for command in runner.stop_commands() {
+            self.apply_send_command(command);
+        }
+    }
+
+    // Runs the sequences before the send scheduler, so a step's messages go out the same tick
+    pub fn tick_sequences(&mut self) {
+        let mut commands = Vec::new();
+        for runner in &mut self.sequences {
+            let (runner_commands, status_changed) = runner.tick(self.parser.as_ref());
+            commands.extend(runner_commands);
+            if status_changed {
+                if let sequence::SequenceStatus::Failed(e) = &runner.status {
+                    log::error!("Sequence {} failed at {}", runner.sequence.name, e);
                 }
+                self.can_to_ui_tx
+                    .send(messages::MsgFromCan::SequenceStatus {
+                        name: runner.sequence.name.clone(),
+                        status: runner.status.clone(),
+                    })
+                    .expect("Failed to send sequence status");
             }
         }
+        for command in commands {
+            self.apply_send_command(command);
+        }
+    }
 
-        for msg_id in msgs_to_remove {
-            self.send_msgs.remove(&msg_id);
+    pub fn start_flash(&mut self, job: bootloader::flasher::FlashJob) {
+        self.abort_flash();
+        log::info!(
+            "Flashing {} ({} bytes) to {}",
+            job.image.path.display(),
+            job.image.bytes.len(),
+            job.node.name
+        );
+        self.flasher = Some(bootloader::flasher::Flasher::new(job));
+    }
+
+    pub fn abort_flash(&mut self) {
+        if let Some(flasher) = self.flasher.take() {
+            log::warn!("Aborted flashing {}", flasher.node.name);
+            self.can_to_ui_tx
+                .send(messages::MsgFromCan::FlashProgress {
+                    node: flasher.node.name,
+                    progress: bootloader::flasher::FlashProgress::Failed("aborted".to_string()),
+                })
+                .expect("Failed to send flash progress");
         }
+    }
 
-        msgs_to_send
+    // Queues the flasher's frames before the send scheduler runs, like the sequences
+    pub fn tick_flasher(&mut self) {
+        let Some(flasher) = &mut self.flasher else {
+            return;
+        };
+        let (frames, progress) = flasher.tick();
+        if let Some(progress) = progress {
+            self.can_to_ui_tx
+                .send(messages::MsgFromCan::FlashProgress {
+                    node: flasher.node.name.clone(),
+                    progress,
+                })
+                .expect("Failed to send flash progress");
+        }
+        if flasher.is_finished() {
+            self.flasher = None;
+        }
+        for frame in frames {
+            self.add_send_message(frame);
+        }
     }
-}
 
-impl SendMsgInfo {
-    pub fn from_add_send_message(add_msg: messages::AddSendMessage) -> Self {
-        Self {
-            amount: add_msg.amount,
-            is_msg_id_extended: add_msg.is_msg_id_extended,
-            msg_bytes: add_msg.msg_bytes,
-            last_sent: None,
-        }
-    }
-
-    pub fn should_send(&self) -> bool {
-        match self.last_sent {
-            None => true,
-            Some(last_sent) => {
-                let period = match &self.amount {
-                    messages::SendAmount::Infinite { period } => *period,
-                    messages::SendAmount::Once => 0,
-                    messages::SendAmount::Finite { amount: _, period } => *period,
-                };
-                chrono::Local::now()
-                    .signed_duration_since(last_sent)
-                    .num_milliseconds()
-                    >= period as i64
+    pub fn tick_info_query(&mut self) {
+        let Some(query) = &mut self.info_query else {
+            return;
+        };
+        let (frames, finished) = query.tick();
+        for (node, info) in finished {
+            self.can_to_ui_tx
+                .send(messages::MsgFromCan::NodeInfo { node, info })
+                .expect("Failed to send node info");
+        }
+        if query.is_finished() {
+            self.info_query = None;
+        }
+        for frame in frames {
+            self.add_send_message(frame);
+        }
+    }
+
+    pub fn start_uds_request(&mut self, client: uds::ClientId, ecu: uds::Ecu, payload: Vec<u8>) {
+        // Links used by a request are watched even when they aren't in the config file
+        for id in [ecu.tx_id_with_ext_flag(), ecu.rx_id_with_ext_flag()] {
+            self.isotp_monitor.entry(id).or_default();
+        }
+        match uds::Request::new(client, ecu, payload) {
+            Ok((request, frames)) => {
+                self.uds_requests.push(request);
+                for frame in frames {
+                    self.add_send_message(frame);
+                }
+            }
+            Err(e) => self
+                .can_to_ui_tx
+                .send(messages::MsgFromCan::UdsResponse {
+                    client,
+                    result: Err(e),
+                })
+                .expect("Failed to send UDS response"),
+        }
+    }
+
+    pub fn on_uds_frame(&mut self, msg_id_with_ext_flag: u32, data: &[u8]) {
+        let mut frames = Vec::new();
+        for request in &mut self.uds_requests {
+            frames.extend(request.on_frame(msg_id_with_ext_flag, data));
+        }
+        for frame in frames {
+            self.add_send_message(frame);
+        }
+
+        let Some(reassembler) = self.isotp_monitor.get_mut(&msg_id_with_ext_flag) else {
+            return;
+        };
+        match reassembler.on_frame(data) {
+            can::isotp::Reassembly::Complete(payload) => self
+                .can_to_ui_tx
+                .send(messages::MsgFromCan::IsoTpMessage(messages::IsoTpMessage {
+                    timestamp: chrono::Local::now(),
+                    msg_id: msg_id_with_ext_flag & !crate::util::can::EXTENDED_ID_FLAG,
+                    payload,
+                }))
+                .expect("Failed to send ISO-TP message"),
+            can::isotp::Reassembly::Failed(e) => {
+                log::warn!(
+                    "Dropped ISO-TP message on 0x{:X}: {}",
+                    msg_id_with_ext_flag & !crate::util::can::EXTENDED_ID_FLAG,
+                    e
+                );
+            }
+            _ => {}
+        }
+    }
+
+    pub fn tick_uds_requests(&mut self) {
+        let mut frames = Vec::new();
+        let can_to_ui_tx = &self.can_to_ui_tx;
+        self.uds_requests.retain_mut(|request| {
+            let (request_frames, outcome) = request.tick();
+            frames.extend(request_frames);
+            let Some(result) = outcome else {
+                return true;
+            };
+            can_to_ui_tx
+                .send(messages::MsgFromCan::UdsResponse {
+                    client: request.client,
+                    result,
+                })
+                .expect("Failed to send UDS response");
+            false
+        });
+        for frame in frames {
+            self.add_send_message(frame);
+        }
+    }
+
+    // Consecutive frames paced by a separation time, and flash bursts, must not wait for a read
+    // timeout
+    pub fn time_until_next(&mut self) -> Option<std::time::Duration> {
+        let next_send = self.scheduler.time_until_next();
+        let next_burst = self.flasher.as_ref().and_then(|f| f.time_until_next());
+        self.uds_requests
+            .iter()
+            .filter_map(|request| request.time_until_next())
+            .chain(next_send)
+            .chain(next_burst)
+            .min()
+    }
+
+    fn apply_send_command(&mut self, command: sequence::SendCommand) {
+        match command {
+            sequence::SendCommand::Add(add_msg) => self.add_send_message(add_msg),
+            sequence::SendCommand::UpdateBytes { handle, msg_bytes } => {
+                self.scheduler.update_bytes(handle, msg_bytes, None);
             }
+            sequence::SendCommand::Delete { handle } => self.delete_send_message(handle),
         }
     }
 }
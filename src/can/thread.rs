@@ -1,11 +1,13 @@
-use crate::{can, connection, messages, util};
+use crate::{bootloader, can, connection, dbc, derived, messages, util};
 
 const NO_CONNECTION_SLEEP_MS: u64 = 200;
 const READ_RETRY_SLEEP_MS: u64 = 2;
+// Reads block at most this long so UI messages are picked up even on a quiet bus
+const MAX_READ_TIMEOUT_MS: u64 = 50;
 const BUS_LOAD_UPDATE_MS: u128 = 200;
 
 // Returns the number of payload data bytes in the CAN frame if it was a Can2 frame
-fn process_can_frame(frame: slcan::CanFrame, state: &can::state::State) -> usize {
+fn process_can_frame(frame: slcan::CanFrame, state: &mut can::state::State) -> usize {
     match frame {
         slcan::CanFrame::Can2(frame2) => {
             let decode_msg_id = util::can::slcan_to_u32_with_extid_flag(&frame2.id());
@@ -14,6 +16,14 @@ fn process_can_frame(frame: slcan::CanFrame, state: &can::state::State) -> usize
             let data = frame2.data().unwrap_or(&[]);
             let timestamp = chrono::Local::now();
             let raw_bytes = data.to_vec();
+            state.trigger_replies(decode_msg_id);
+            if let Some(flasher) = &mut state.flasher {
+                flasher.on_frame(decode_msg_id, data);
+            }
+            if let Some(query) = &mut state.info_query {
+                query.on_frame(decode_msg_id, data);
+            }
+            state.on_uds_frame(decode_msg_id, data);
 
             let decoded = state
                 .parser
@@ -22,6 +32,12 @@ fn process_can_frame(frame: slcan::CanFrame, state: &can::state::State) -> usize
 
             match decoded {
                 Some(decoded) => {
+                    for runner in &mut state.sequences {
+                        runner.on_decoded(&decoded);
+                    }
+                    let time_s = timestamp.timestamp_micros() as f64 / 1e6;
+                    let derived_values = state.derived.on_decoded(time_s, &decoded);
+
                     let parsed_msg = messages::ParsedMessage {
                         timestamp,
                         raw_bytes,
@@ -31,6 +47,20 @@ fn process_can_frame(frame: slcan::CanFrame, state: &can::state::State) -> usize
                         .can_to_ui_tx
                         .send(messages::MsgFromCan::ParsedMessage(parsed_msg))
                         .expect("Failed to send parsed CAN message");
+
+                    for value in derived_values {
+                        state
+                            .can_to_ui_tx
+                            .send(messages::MsgFromCan::DerivedSignal(
+                                messages::DerivedSignal {
+                                    timestamp,
+                                    name: value.name,
+                                    value: value.value,
+                                    unit: value.unit,
+                                },
+                            ))
+                            .expect("Failed to send derived signal");
+                    }
                 }
                 None => {
                     if state.parser.is_some() {
@@ -89,13 +119,17 @@ pub fn start_can_thread(
             // Process UI messages first (DBC load, new message to send, etc.)
             while let Ok(msg) = state.ui_to_can_rx.try_recv() {
                 match msg {
-                    messages::MsgFromUi::DbcSelected(path) => {
-                        match can_decode::Parser::from_dbc_file(&path) {
+                    messages::MsgFromUi::DbcSelected(paths) if paths.is_empty() => {
+                        state.parser = None;
+                        log::info!("DBC cleared");
+                    }
+                    messages::MsgFromUi::DbcSelected(paths) => {
+                        match dbc::merged::MergedParser::from_dbc_files(&paths) {
                             Ok(parser) => {
                                 state.parser = Some(parser);
-                                log::info!("Loaded DBC from {:?}", path);
+                                log::info!("Loaded DBC from {:?}", paths);
                             }
-                            Err(e) => log::error!("Failed to load DBC {:?}: {e}", path),
+                            Err(e) => log::error!("Failed to load DBC {:?}: {e}", paths),
                         }
                     }
                     messages::MsgFromUi::Connect(source) => {
@@ -110,15 +144,67 @@ pub fn start_can_thread(
                             .expect("Failed to send disconnected message");
                         state.current_source = Some(source);
                     }
+                    messages::MsgFromUi::Disconnect => {
+                        if let Some(mut old_driver) = state.driver.take() {
+                            let _ = old_driver.close();
+                        }
+                        state.is_connected = false;
+                        state
+                            .can_to_ui_tx
+                            .send(messages::MsgFromCan::Disconnection)
+                            .expect("Failed to send disconnected message");
+                        // No source, so the loop doesn't connect again
+                        state.current_source = None;
+                    }
                     messages::MsgFromUi::AddSendMessage(add_send_msg) => {
                         state.add_send_message(add_send_msg);
                     }
-                    messages::MsgFromUi::DeleteSendMessage { msg_id } => {
-                        state.delete_send_message(msg_id);
+                    messages::MsgFromUi::UpdateSendBytes {
+                        handle,
+                        msg_bytes,
+                        auto_fields,
+                    } => {
+                        state.update_send_bytes(handle, msg_bytes, auto_fields);
+                    }
+                    messages::MsgFromUi::DeleteSendMessage { handle } => {
+                        state.delete_send_message(handle);
+                    }
+                    messages::MsgFromUi::DerivedSignalsChanged(defs) => {
+                        state.derived = derived::DerivedEngine::new(&defs);
+                        log::info!("Loaded {} derived signal(s)", defs.len());
+                    }
+                    messages::MsgFromUi::StartSequence(seq) => {
+                        state.start_sequence(seq);
+                    }
+                    messages::MsgFromUi::StopSequence { name } => {
+                        state.stop_sequence(&name);
+                    }
+                    messages::MsgFromUi::SetReplyRules { owner, rules } => {
+                        state.set_reply_rules(owner, rules);
+                    }
+                    messages::MsgFromUi::StartFlash(job) => {
+                        state.start_flash(job);
+                    }
+                    messages::MsgFromUi::AbortFlash => {
+                        state.abort_flash();
+                    }
+                    messages::MsgFromUi::QueryNodes(nodes) => {
+                        state.info_query = Some(bootloader::inventory::InfoQuery::new(nodes));
+                    }
+                    messages::MsgFromUi::UdsRequest {
+                        client,
+                        ecu,
+                        payload,
+                    } => {
+                        state.start_uds_request(client, ecu, payload);
                     }
                 }
             }
-            let msgs_to_send = state.send_this_tick();
+            state.tick_sequences();
+            state.tick_flasher();
+            state.tick_info_query();
+            state.tick_uds_requests();
+            let msgs_to_send = state.scheduler.pop_due();
             for msg in msgs_to_send {
                 if let Some(ref mut active_driver) = state.driver {
                     let id = if msg.is_msg_id_extended {
@@ -147,15 +233,11 @@ pub fn start_can_thread(
                                     state
                                         .can_to_ui_tx
                                         .send(messages::MsgFromCan::MessageSent {
+                                            handle: msg.handle,
                                             msg_id: msg.msg_id,
                                             timestamp: chrono::Local::now(),
-                                            amount_left: state
-                                                .send_msgs
-                                                .get(&msg.msg_id)
-                                                .map(|info| info.amount),
-                                            // If the message is removed after the send, this
-                                            // will return None, which is what we want to indicate
-                                            // no more sends left
+                                            amount_left: msg.amount_left,
+                                            stats: msg.stats,
                                         })
                                         .expect("Failed to send message sent confirmation");
                                 }
@@ -220,16 +302,28 @@ pub fn start_can_thread(
                 }
             }
 
+            // Never block past the next scheduled send, sockets don't accept a zero timeout
+            let read_timeout = state
+                .time_until_next()
+                .unwrap_or(std::time::Duration::MAX)
+                .clamp(
+                    std::time::Duration::from_millis(1),
+                    std::time::Duration::from_millis(MAX_READ_TIMEOUT_MS),
+                );
+
             // Try to read a frame from the driver
             let Some(ref mut active_driver) = state.driver else {
                 std::thread::sleep(std::time::Duration::from_millis(NO_CONNECTION_SLEEP_MS));
                 continue;
             };
+            if let Err(e) = active_driver.set_read_timeout(read_timeout) {
+                log::warn!("Failed to set read timeout: {:?}", e);
+            }
 
             match active_driver.read_frames() {
                 Ok(frames) => {
                     for frame in frames {
-                        let data_bytes = process_can_frame(frame, &state);
+                        let data_bytes = process_can_frame(frame, &mut state);
                         state.bus_load_tracker.record_frame(data_bytes);
                     }
                     // Send bus load updates periodically
@@ -262,9 +356,12 @@ pub fn start_can_thread(
                     match error_type {
                         can::driver::DriverReadError::Timeout => {
                             // Normal timeout, just retry
-                            std::thread::sleep(std::time::Duration::from_millis(
-                                READ_RETRY_SLEEP_MS,
-                            ));
+                            let retry_sleep = std::time::Duration::from_millis(READ_RETRY_SLEEP_MS);
+                            std::thread::sleep(
+                                state
+                                    .time_until_next()
+                                    .map_or(retry_sleep, |until_next| until_next.min(retry_sleep)),
+                            );
                         }
                         other => {
                             // Actual error, disconnect
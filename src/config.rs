@@ -0,0 +1,304 @@
+use crate::{formatter, layout, settings};
+use std::path::PathBuf;
+
+const APP_DIR_NAME: &str = "daqapp2";
+// Overrides the per-user directory, ex: to keep the config on a USB stick
+const CONFIG_DIR_ENV: &str = "DAQAPP_CONFIG_DIR";
+const PROFILES_DIR: &str = "profiles";
+const PROFILES_FILE: &str = "profiles.json";
+pub const DEFAULT_PROFILE: &str = "Default";
+
+// Everything a profile bundles, the other config files are shared by all profiles
+const PROFILE_FILES: [&str; 3] = [
+    settings::SETTINGS_PATH,
+    formatter::FORMATTER_CONFIG_FILE,
+    layout::LAYOUTS_FILE,
+];
+
+// Ex: ~/.config/daqapp2, ~/Library/Application Support/daqapp2 or %APPDATA%\daqapp2
+pub fn config_dir() -> &'static std::path::Path {
+    static DIR: std::sync::OnceLock<PathBuf> = std::sync::OnceLock::new();
+    DIR.get_or_init(|| {
+        if let Some(dir) = std::env::var_os(CONFIG_DIR_ENV) {
+            return dir.into();
+        }
+        let env_dir = |name| std::env::var_os(name).map(PathBuf::from);
+        let base = if cfg!(target_os = "windows") {
+            env_dir("APPDATA")
+        } else if cfg!(target_os = "macos") {
+            env_dir("HOME").map(|home| home.join("Library/Application Support"))
+        } else {
+            env_dir("XDG_CONFIG_HOME").or_else(|| env_dir("HOME").map(|home| home.join(".config")))
+        };
+        match base {
+            Some(base) => base.join(APP_DIR_NAME),
+            None => {
+                log::warn!("No per-user config directory, using the working directory");
+                PathBuf::from(".")
+            }
+        }
+    })
+}
+
+// Where config files were kept before the per-user directory, and where the repo's themes are
+fn legacy_dirs() -> Vec<PathBuf> {
+    let mut dirs = vec![PathBuf::from(".")];
+    if let Some(exe_dir) = std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(|dir| dir.to_path_buf()))
+    {
+        dirs.push(exe_dir);
+    }
+    dirs
+}
+
+// The first of `paths` that can be read, the error is the one of the first path
+fn read_first(paths: Vec<PathBuf>) -> std::io::Result<String> {
+    let mut first_err = None;
+    for (i, path) in paths.iter().enumerate() {
+        match std::fs::read_to_string(path) {
+            Ok(contents) => {
+                if i > 0 {
+                    log::info!(
+                        "Using {} until it is saved to {}",
+                        path.display(),
+                        paths[0].display()
+                    );
+                }
+                return Ok(contents);
+            }
+            Err(e) => {
+                first_err.get_or_insert(e);
+            }
+        }
+    }
+    Err(first_err.unwrap_or_else(|| std::io::ErrorKind::NotFound.into()))
+}
+
+fn write_creating_dirs(path: &std::path::Path, contents: impl AsRef<[u8]>) -> std::io::Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(path, contents)
+}
+
+// The config directory, then the legacy ones
+pub fn search_dirs() -> Vec<PathBuf> {
+    std::iter::once(config_dir().to_path_buf())
+        .chain(legacy_dirs())
+        .collect()
+}
+
+// Shared config file, from the config directory, or the working directory as a fallback
+pub fn read_to_string(file_name: &str) -> std::io::Result<String> {
+    let paths = search_dirs()
+        .into_iter()
+        .map(|dir| dir.join(file_name))
+        .collect();
+    read_first(paths)
+}
+
+// Always to the config directory
+pub fn write(file_name: &str, contents: impl AsRef<[u8]>) -> std::io::Result<()> {
+    write_creating_dirs(&config_dir().join(file_name), contents)
+}
+
+// Always to the config directory, for logs that grow over time
+pub fn append(file_name: &str, contents: impl AsRef<[u8]>) -> std::io::Result<()> {
+    use std::io::Write as _;
+
+    std::fs::create_dir_all(config_dir())?;
+    std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(config_dir().join(file_name))?
+        .write_all(contents.as_ref())
+}
+
+// Where a config file is copied before something that can't be kept in it is dropped
+pub fn backup_file_name(file_name: &str) -> String {
+    format!("{}.bak", file_name)
+}
+
+// Parses a config file read from `dir`, `Ok(None)` when there is no file. One that doesn't parse
+// is copied aside first, so saving defaults over it later loses nothing.
+fn parse_json<T>(
+    file_name: &str,
+    dir: &std::path::Path,
+    read: std::io::Result<String>,
+    parse: impl FnOnce(&str) -> serde_json::Result<T>,
+    write: impl FnOnce(&str, &str) -> std::io::Result<()>,
+) -> Result<Option<T>, String> {
+    let Ok(json) = read else {
+        return Ok(None);
+    };
+    let e = match parse(&json) {
+        Ok(value) => return Ok(Some(value)),
+        Err(e) => e,
+    };
+    let backup = backup_file_name(file_name);
+    let kept = match write(&backup, &json) {
+        Ok(()) => format!(
+            "it was copied to {}, fix it there and rename it back to keep its contents",
+            dir.join(&backup).display()
+        ),
+        Err(e) => format!(
+            "copying it to {} failed ({}), the next save overwrites it",
+            backup, e
+        ),
+    };
+    let error = format!(
+        "{} couldn't be parsed, defaults are used and {}. Error: {}",
+        dir.join(file_name).display(),
+        kept,
+        e
+    );
+    log::error!("{}", error);
+    Err(error)
+}
+
+/// A shared JSON config file, the default when there is none. A file that doesn't parse is backed
+/// up and the error returned, so callers can fall back to the default without losing it.
+pub fn load_json<T: serde::de::DeserializeOwned + Default>(file_name: &str) -> Result<T, String> {
+    load_json_with(file_name, |json| serde_json::from_str(json)).map(Option::unwrap_or_default)
+}
+
+// For files that need migrating before they are parsed, `Ok(None)` when there is none
+pub fn load_json_with<T>(
+    file_name: &str,
+    parse: impl FnOnce(&str) -> serde_json::Result<T>,
+) -> Result<Option<T>, String> {
+    parse_json(
+        file_name,
+        config_dir(),
+        read_to_string(file_name),
+        parse,
+        |name, json| write(name, json),
+    )
+}
+
+pub fn save_json(file_name: &str, value: &impl serde::Serialize) {
+    // Config types only hold strings, numbers and string-keyed maps
+    let json = serde_json::to_string_pretty(value).expect("Failed to serialize config");
+    write(file_name, json).unwrap_or_else(|e| log::error!("Failed to write {}: {}", file_name, e));
+}
+
+/// A set of settings (DBCs, source, theme), formatter and layouts that is switched as a whole,
+/// ex: one per car generation, or bench and track.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Profile {
+    pub name: String,
+}
+
+impl Profile {
+    pub fn new(name: impl Into<String>) -> Self {
+        Self { name: name.into() }
+    }
+
+    pub fn dir(&self) -> PathBuf {
+        config_dir().join(PROFILES_DIR).join(&self.name)
+    }
+
+    pub fn read_to_string(&self, file_name: &str) -> std::io::Result<String> {
+        let mut paths = vec![self.dir().join(file_name)];
+        // Before profiles these files were shared, the default profile takes them over
+        if self.name == DEFAULT_PROFILE {
+            paths.extend(search_dirs().into_iter().map(|dir| dir.join(file_name)));
+        }
+        read_first(paths)
+    }
+
+    pub fn write(&self, file_name: &str, contents: impl AsRef<[u8]>) -> std::io::Result<()> {
+        write_creating_dirs(&self.dir().join(file_name), contents)
+    }
+
+    /// A JSON file of this profile, see `config::load_json`.
+    pub fn load_json<T: serde::de::DeserializeOwned + Default>(
+        &self,
+        file_name: &str,
+    ) -> Result<T, String> {
+        self.load_json_with(file_name, |json| serde_json::from_str(json))
+            .map(Option::unwrap_or_default)
+    }
+
+    pub fn load_json_with<T>(
+        &self,
+        file_name: &str,
+        parse: impl FnOnce(&str) -> serde_json::Result<T>,
+    ) -> Result<Option<T>, String> {
+        parse_json(
+            file_name,
+            &self.dir(),
+            self.read_to_string(file_name),
+            parse,
+            |name, json| self.write(name, json),
+        )
+    }
+
+    pub fn save_json(&self, file_name: &str, value: &impl serde::Serialize) {
+        let json = serde_json::to_string_pretty(value).expect("Failed to serialize config");
+        self.write(file_name, json)
+            .unwrap_or_else(|e| log::error!("Failed to write {}: {}", file_name, e));
+    }
+
+    // Starts out as a copy of `from`
+    pub fn create(name: &str, from: &Profile) -> std::io::Result<Self> {
+        let profile = Self::new(name);
+        std::fs::create_dir_all(profile.dir())?;
+        for file_name in PROFILE_FILES {
+            if let Ok(contents) = from.read_to_string(file_name) {
+                profile.write(file_name, contents)?;
+            }
+        }
+        Ok(profile)
+    }
+
+    // The default profile first, it always exists
+    pub fn list() -> Vec<String> {
+        let mut names: Vec<String> = std::fs::read_dir(config_dir().join(PROFILES_DIR))
+            .map(|entries| {
+                entries
+                    .filter_map(|entry| entry.ok())
+                    .filter(|entry| entry.path().is_dir())
+                    .filter_map(|entry| entry.file_name().into_string().ok())
+                    .filter(|name| name != DEFAULT_PROFILE)
+                    .collect()
+            })
+            .unwrap_or_default();
+        names.sort();
+        names.insert(0, DEFAULT_PROFILE.to_string());
+        names
+    }
+}
+
+// Profile and theme names become file names
+pub fn is_valid_file_name(name: &str) -> bool {
+    !name.is_empty()
+        && name != "."
+        && name != ".."
+        && !name.contains(['/', '\\', ':', '*', '?', '"', '<', '>', '|'])
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct ProfilesConfig {
+    // The profile used on the next start
+    pub active: String,
+}
+
+impl Default for ProfilesConfig {
+    fn default() -> Self {
+        Self {
+            active: DEFAULT_PROFILE.to_string(),
+        }
+    }
+}
+
+impl ProfilesConfig {
+    pub fn load() -> Self {
+        load_json(PROFILES_FILE).unwrap_or_default()
+    }
+
+    pub fn save(&self) {
+        save_json(PROFILES_FILE, self);
+    }
+}
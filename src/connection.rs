@@ -2,7 +2,7 @@
 pub enum ConnectionSource {
     Serial(String, CanBusSpeed),
     Udp(u16),
-    Simulated(bool, Option<std::path::PathBuf>), // true for connected, false for disconnected, path to dbc file for sim
+    Simulated(bool, Vec<std::path::PathBuf>), // true for connected, false for disconnected, dbc files for sim
     Loopback,
 }
 
@@ -1,4 +1,4 @@
-use crate::{daq_log_parse::consts, util};
+use crate::{daq_log_parse::consts, dbc, util};
 use bytemuck::{Pod, Zeroable};
 
 #[derive(Debug)]
@@ -19,8 +19,8 @@ struct RawFrame {
 
 pub fn parse_log_files(
     in_folder: &std::path::Path,
-    parser_bus_0: &can_decode::Parser,
-    parser_bus_1: &can_decode::Parser,
+    parser_bus_0: &dbc::merged::MergedParser,
+    parser_bus_1: &dbc::merged::MergedParser,
 ) -> Vec<ParsedMessage> {
     let mut all_parsed = Vec::new();
     let mut file_paths = std::fs::read_dir(in_folder)
@@ -43,8 +43,8 @@ pub fn parse_log_files(
 
 fn parse_log_file(
     in_file: &std::path::Path,
-    parser_bus_0: &can_decode::Parser,
-    parser_bus_1: &can_decode::Parser,
+    parser_bus_0: &dbc::merged::MergedParser,
+    parser_bus_1: &dbc::merged::MergedParser,
 ) -> Vec<ParsedMessage> {
     let mut content = std::fs::read(in_file).unwrap();
 
@@ -1,10 +1,11 @@
 use crate::{
     daq_log_parse::{consts, correlate},
-    util,
+    dbc, derived, formatter, util,
 };
 
 const HEADER_ROW_COUNT: usize = 7;
 const HEADER_COLUMN_COUNT: usize = 3; // real time, daq timestamp, then per-row header label
+const DERIVED_BUS_NAME: &str = "Derived";
 const HEADER_LABELS: [&str; HEADER_ROW_COUNT] = [
     "Bus",
     "Node",
@@ -46,14 +47,22 @@ pub struct TableBuilder {
     // Key is (bus name, msg name, signal name), value is column index
     indexer: std::collections::HashMap<(String, String, String), usize>,
     next_col_idx: usize,
+    // Per header column, the formatter's unit conversion
+    conversions: Vec<formatter::Conversion>,
+    formatter: Option<formatter::Formatter>,
+
+    derived: Option<derived::DerivedEngine>,
 }
 
 impl TableBuilder {
-    pub fn new() -> Self {
+    pub fn new(formatter: Option<formatter::Formatter>) -> Self {
         Self {
             header_columns: Vec::new(),
             next_col_idx: HEADER_COLUMN_COUNT,
             indexer: std::collections::HashMap::new(),
+            conversions: Vec::new(),
+            formatter,
+            derived: None,
         }
     }
 
@@ -61,12 +70,24 @@ impl TableBuilder {
         HEADER_COLUMN_COUNT + self.header_columns.len()
     }
 
-    fn push_column(&mut self, key: (String, String, String), column: TableColumn) {
+    fn push_column(&mut self, key: (String, String, String), mut column: TableColumn) {
+        let conversion = formatter::try_conversion(
+            &self.formatter,
+            &column.message,
+            &column.signal,
+            &column.signal_unit,
+        );
+        column.signal_unit = conversion.unit.clone();
         self.indexer.insert(key, self.next_col_idx);
         self.header_columns.push(column);
+        self.conversions.push(conversion);
         self.next_col_idx += 1;
     }
 
+    fn converted(&self, col_idx: usize, value: f64) -> f64 {
+        self.conversions[col_idx - HEADER_COLUMN_COUNT].apply(value)
+    }
+
     fn build_header_rows(&self) -> Vec<Vec<String>> {
         let mut rows = vec![
             vec!["".to_string(), "".to_string(), HEADER_LABELS[0].to_string()],
@@ -93,7 +114,7 @@ impl TableBuilder {
         rows
     }
 
-    pub fn create_header(&mut self, parser: &can_decode::Parser, bus_name: &str) {
+    pub fn create_header(&mut self, parser: &dbc::merged::MergedParser, bus_name: &str) {
         let mut message_defs = parser.msg_defs();
         message_defs.sort_by_key(|m| util::can::can_dbc_to_u32_without_extid_flag(&m.id));
 
@@ -105,17 +126,13 @@ impl TableBuilder {
             };
 
             let msg_id_u32 = util::can::can_dbc_to_u32_with_extid_flag(&msg.id);
-            let msg_desc = parser
-                .msg_desc(msg_id_u32)
-                .map(|d| d.to_string())
-                .unwrap_or_default();
+            let msg_desc = parser.msg_desc(msg_id_u32).unwrap_or_default();
 
             for (i, sig) in msg.signals.iter().enumerate() {
                 let key = (bus_id.to_string(), msg.name.clone(), sig.name.clone());
                 if !self.indexer.contains_key(&key) {
                     let sig_desc = parser
                         .signal_desc(msg_id_u32, &sig.name)
-                        .map(|d| d.to_string())
                         .unwrap_or_default();
 
                     self.push_column(
@@ -139,8 +156,38 @@ impl TableBuilder {
         }
     }
 
+    // Derived signals go after all DBC signals, the signal description holds the expression
+    pub fn create_derived_header(&mut self, defs: &[derived::DerivedSignalDef]) {
+        if defs.is_empty() {
+            return;
+        }
+
+        for def in defs {
+            let key = (
+                DERIVED_BUS_NAME.to_string(),
+                derived::DERIVED_MSG_NAME.to_string(),
+                def.name.clone(),
+            );
+            if !self.indexer.contains_key(&key) {
+                self.push_column(
+                    key,
+                    TableColumn {
+                        bus: DERIVED_BUS_NAME.to_string(),
+                        node: String::new(),
+                        message: derived::DERIVED_MSG_NAME.to_string(),
+                        message_desc: String::new(),
+                        signal: def.name.clone(),
+                        signal_desc: def.expr.clone(),
+                        signal_unit: def.unit.clone(),
+                    },
+                );
+            }
+        }
+        self.derived = Some(derived::DerivedEngine::new(defs));
+    }
+
     pub fn create_and_write_tables(
-        &self,
+        &mut self,
         out_folder: &std::path::Path,
         output_prefix: &str,
         correlated_chunks: Vec<correlate::CorrelationChunkResult>,
@@ -170,6 +217,11 @@ impl TableBuilder {
                 wtr.write_record(&row).unwrap();
             }
 
+            // Chunks are not continuous in time, so filters must not carry over between them
+            if let Some(engine) = self.derived.as_mut() {
+                engine.reset();
+            }
+
             let mut msg_iter = chunk.parsed_msgs.iter().peekable();
             for row_idx in 0..num_rows {
                 let row_time = first_row_time + row_idx * consts::BIN_WIDTH_MS;
@@ -198,10 +250,27 @@ impl TableBuilder {
                             row[col_idx] = if let Some(enum_label) = &sig_value.value.enum_label {
                                 format!("{} ({})", enum_label, sig_value.value.int_rounded())
                             } else {
-                                sig_value.value.physical.to_string()
+                                self.converted(col_idx, sig_value.value.physical)
+                                    .to_string()
                             };
                         }
                     }
+
+                    // Every bus feeds the same engine, see `derived::LatestValues` for messages of
+                    // the same name on two buses
+                    if let Some(engine) = self.derived.as_mut() {
+                        let time_s = msg.timestamp as f64 / 1000.0;
+                        for value in engine.on_decoded(time_s, decoded) {
+                            let key = (
+                                DERIVED_BUS_NAME.to_string(),
+                                derived::DERIVED_MSG_NAME.to_string(),
+                                value.name,
+                            );
+                            if let Some(&col_idx) = self.indexer.get(&key) {
+                                row[col_idx] = self.converted(col_idx, value.value).to_string();
+                            }
+                        }
+                    }
                 }
 
                 wtr.write_record(&row).unwrap();
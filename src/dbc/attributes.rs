@@ -0,0 +1,98 @@
+// Helpers for the `BA_` attribute values, which `can_dbc` keeps as one flat list per file
+
+pub const CYCLE_TIME_ATTRIBUTE: &str = "GenMsgCycleTime";
+// Raw value, before factor and offset
+pub const START_VALUE_ATTRIBUTE: &str = "GenSigStartValue";
+
+/// (message or signal name, attribute name, value) for everything set on the message or its signals
+pub fn msg_attributes(dbc: &can_dbc::Dbc, msg: &can_dbc::Message) -> Vec<(String, String, String)> {
+    let mut attributes = Vec::new();
+    for attr in &dbc.attribute_values {
+        match &attr.value {
+            can_dbc::AttributeValuedForObjectType::MessageDefinition(id, Some(value))
+                if *id == msg.id =>
+            {
+                attributes.push((msg.name.clone(), attr.name.clone(), value_to_string(value)));
+            }
+            can_dbc::AttributeValuedForObjectType::Signal(id, sig_name, value) if *id == msg.id => {
+                attributes.push((sig_name.clone(), attr.name.clone(), value_to_string(value)));
+            }
+            _ => {}
+        }
+    }
+    attributes
+}
+
+/// Value of a single message attribute, ex: "GenMsgCycleTime", or its default (`BA_DEF_DEF_`)
+/// when the message doesn't set it.
+pub fn msg_attribute<'a>(
+    dbc: &'a can_dbc::Dbc,
+    msg_id: &can_dbc::MessageId,
+    name: &str,
+) -> Option<&'a can_dbc::AttributeValue> {
+    dbc.attribute_values
+        .iter()
+        .find_map(|attr| match &attr.value {
+            can_dbc::AttributeValuedForObjectType::MessageDefinition(id, Some(value))
+                if id == msg_id && attr.name == name =>
+            {
+                Some(value)
+            }
+            _ => None,
+        })
+        .or_else(|| attribute_default(dbc, name))
+}
+
+/// The message's `GenMsgCycleTime` in ms, None when neither it nor the default is set, or it isn't
+/// periodic.
+pub fn cycle_time_ms(dbc: &can_dbc::Dbc, msg: &can_dbc::Message) -> Option<f64> {
+    msg_attribute(dbc, &msg.id, CYCLE_TIME_ATTRIBUTE)
+        .and_then(value_to_f64)
+        .filter(|ms| *ms >= 1.0)
+}
+
+/// Value of a single signal attribute, ex: "GenSigStartValue", or its default.
+pub fn signal_attribute<'a>(
+    dbc: &'a can_dbc::Dbc,
+    msg_id: &can_dbc::MessageId,
+    signal_name: &str,
+    name: &str,
+) -> Option<&'a can_dbc::AttributeValue> {
+    dbc.attribute_values
+        .iter()
+        .find_map(|attr| match &attr.value {
+            can_dbc::AttributeValuedForObjectType::Signal(id, sig_name, value)
+                if id == msg_id && sig_name == signal_name && attr.name == name =>
+            {
+                Some(value)
+            }
+            _ => None,
+        })
+        .or_else(|| attribute_default(dbc, name))
+}
+
+// Attribute names are unique across object types, so the name alone finds the default
+fn attribute_default<'a>(dbc: &'a can_dbc::Dbc, name: &str) -> Option<&'a can_dbc::AttributeValue> {
+    dbc.attribute_defaults
+        .iter()
+        .find(|default| default.name == name)
+        .map(|default| &default.value)
+}
+
+pub fn value_to_string(value: &can_dbc::AttributeValue) -> String {
+    match value {
+        can_dbc::AttributeValue::U64(v) => v.to_string(),
+        can_dbc::AttributeValue::I64(v) => v.to_string(),
+        can_dbc::AttributeValue::Double(v) => v.to_string(),
+        can_dbc::AttributeValue::String(v) => format!("\"{}\"", v),
+    }
+}
+
+pub fn value_to_f64(value: &can_dbc::AttributeValue) -> Option<f64> {
+    match value {
+        can_dbc::AttributeValue::U64(v) => Some(*v as f64),
+        can_dbc::AttributeValue::I64(v) => Some(*v as f64),
+        can_dbc::AttributeValue::Double(v) => Some(*v),
+        can_dbc::AttributeValue::String(_) => None,
+    }
+}
@@ -0,0 +1,148 @@
+use crate::util;
+
+pub struct MsgChange {
+    pub name: String,
+    // Human readable, ex: "+ signal cell_num", "~ signal voltage: start bit 8 → 16"
+    pub details: Vec<String>,
+}
+
+#[derive(Default)]
+pub struct DbcDiff {
+    pub added_msgs: Vec<String>,
+    pub removed_msgs: Vec<String>,
+    pub changed_msgs: Vec<MsgChange>,
+}
+
+impl DbcDiff {
+    /// Messages are matched by name, so a message that moved to a new ID shows up as changed
+    /// rather than as one removal plus one addition.
+    pub fn between(old: &[can_dbc::Message], new: &[can_dbc::Message]) -> Self {
+        let mut diff = Self::default();
+
+        for new_msg in new {
+            match old.iter().find(|m| m.name == new_msg.name) {
+                None => diff.added_msgs.push(new_msg.name.clone()),
+                Some(old_msg) => {
+                    let details = msg_changes(old_msg, new_msg);
+                    if !details.is_empty() {
+                        diff.changed_msgs.push(MsgChange {
+                            name: new_msg.name.clone(),
+                            details,
+                        });
+                    }
+                }
+            }
+        }
+        for old_msg in old {
+            if !new.iter().any(|m| m.name == old_msg.name) {
+                diff.removed_msgs.push(old_msg.name.clone());
+            }
+        }
+
+        diff.added_msgs.sort();
+        diff.removed_msgs.sort();
+        diff.changed_msgs.sort_by(|a, b| a.name.cmp(&b.name));
+        diff
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added_msgs.is_empty() && self.removed_msgs.is_empty() && self.changed_msgs.is_empty()
+    }
+
+    pub fn summary(&self) -> String {
+        if self.is_empty() {
+            return "no message or signal changes".to_string();
+        }
+        format!(
+            "{} message(s) added, {} removed, {} changed",
+            self.added_msgs.len(),
+            self.removed_msgs.len(),
+            self.changed_msgs.len()
+        )
+    }
+}
+
+/// Human readable differences between two definitions of a message, empty when they match.
+pub fn msg_changes(old: &can_dbc::Message, new: &can_dbc::Message) -> Vec<String> {
+    let mut details = Vec::new();
+
+    if old.id != new.id {
+        details.push(format!(
+            "ID 0x{:03X} → 0x{:03X}",
+            util::can::can_dbc_to_u32_without_extid_flag(&old.id),
+            util::can::can_dbc_to_u32_without_extid_flag(&new.id)
+        ));
+    }
+    if old.size != new.size {
+        details.push(format!("length {} → {} bytes", old.size, new.size));
+    }
+    if old.transmitter != new.transmitter {
+        details.push(format!(
+            "transmitter {} → {}",
+            util::can::transmitter_name(&old.transmitter),
+            util::can::transmitter_name(&new.transmitter)
+        ));
+    }
+
+    for new_sig in &new.signals {
+        match old.signals.iter().find(|s| s.name == new_sig.name) {
+            None => details.push(format!("+ signal {}", new_sig.name)),
+            Some(old_sig) => {
+                let changes = signal_changes(old_sig, new_sig);
+                if !changes.is_empty() {
+                    details.push(format!("~ signal {}: {}", new_sig.name, changes.join(", ")));
+                }
+            }
+        }
+    }
+    for old_sig in &old.signals {
+        if !new.signals.iter().any(|s| s.name == old_sig.name) {
+            details.push(format!("- signal {}", old_sig.name));
+        }
+    }
+
+    details
+}
+
+fn signal_changes(old: &can_dbc::Signal, new: &can_dbc::Signal) -> Vec<String> {
+    let mut changes = Vec::new();
+    if old.start_bit != new.start_bit {
+        changes.push(format!("start bit {} → {}", old.start_bit, new.start_bit));
+    }
+    if old.size != new.size {
+        changes.push(format!("size {} → {} bits", old.size, new.size));
+    }
+    if old.byte_order != new.byte_order {
+        changes.push(format!("{:?} → {:?}", old.byte_order, new.byte_order));
+    }
+    if old.value_type != new.value_type {
+        changes.push(format!("{:?} → {:?}", old.value_type, new.value_type));
+    }
+    if old.factor != new.factor || old.offset != new.offset {
+        changes.push(format!(
+            "scale {}x+{} → {}x+{}",
+            old.factor, old.offset, new.factor, new.offset
+        ));
+    }
+    let old_range = (
+        util::can::can_dbc_numeric_to_f64(&old.min),
+        util::can::can_dbc_numeric_to_f64(&old.max),
+    );
+    let new_range = (
+        util::can::can_dbc_numeric_to_f64(&new.min),
+        util::can::can_dbc_numeric_to_f64(&new.max),
+    );
+    if old_range != new_range {
+        changes.push(format!(
+            "range [{}, {}] → [{}, {}]",
+            old_range.0, old_range.1, new_range.0, new_range.1
+        ));
+    }
+    if old.unit != new.unit {
+        changes.push(format!("unit \"{}\" → \"{}\"", old.unit, new.unit));
+    }
+    if old.multiplexer_indicator != new.multiplexer_indicator {
+        changes.push("multiplexing".to_string());
+    }
+    changes
+}
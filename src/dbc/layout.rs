@@ -0,0 +1,48 @@
+// Bit positions are numbered the way DBC files do: byte * 8 + bit, where bit 0 is the LSB
+// of the byte. A position can be placed on a byte grid with `position / 8` and `position % 8`.
+
+/// Every bit position the signal occupies, ordered from its LSB to its MSB.
+pub fn signal_bits(signal: &can_dbc::Signal) -> Vec<u64> {
+    match signal.byte_order {
+        can_dbc::ByteOrder::LittleEndian => {
+            (signal.start_bit..signal.start_bit + signal.size).collect()
+        }
+        can_dbc::ByteOrder::BigEndian => {
+            // Motorola start bit is the MSB, walk down the byte and continue at the top of the
+            // next one (the "sawtooth" numbering)
+            let mut bits = Vec::with_capacity(signal.size as usize);
+            let mut pos = signal.start_bit;
+            for _ in 0..signal.size {
+                bits.push(pos);
+                if pos % 8 == 0 {
+                    pos += 15;
+                } else {
+                    pos -= 1;
+                }
+            }
+            bits.reverse();
+            bits
+        }
+    }
+}
+
+/// Returns pairs of signal names that share at least one bit. Multiplexed signals are skipped
+/// since they overlap on purpose.
+pub fn overlapping_signals(msg: &can_dbc::Message) -> Vec<(String, String)> {
+    let plain_signals: Vec<_> = msg
+        .signals
+        .iter()
+        .filter(|s| s.multiplexer_indicator == can_dbc::MultiplexIndicator::Plain)
+        .map(|s| (s, signal_bits(s)))
+        .collect();
+
+    let mut overlaps = Vec::new();
+    for (i, (a, a_bits)) in plain_signals.iter().enumerate() {
+        for (b, b_bits) in &plain_signals[i + 1..] {
+            if a_bits.iter().any(|bit| b_bits.contains(bit)) {
+                overlaps.push((a.name.clone(), b.name.clone()));
+            }
+        }
+    }
+    overlaps
+}
@@ -0,0 +1,252 @@
+use crate::{dbc, util};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    // Will decode wrong or not at all
+    Error,
+    // Decodes fine, but the definition is incomplete
+    Warning,
+}
+
+#[derive(Clone, Debug)]
+pub struct LintIssue {
+    pub severity: Severity,
+    // File and message the issue was found in, ex: "VCAN.dbc / main_hb"
+    pub location: String,
+    pub text: String,
+}
+
+impl std::fmt::Display for LintIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        };
+        write!(f, "{}: {}: {}", severity, self.location, self.text)
+    }
+}
+
+pub fn has_errors(issues: &[LintIssue]) -> bool {
+    issues.iter().any(|i| i.severity == Severity::Error)
+}
+
+/// Checks every file of a merged DBC on its own plus the clashes between them. Errors come first.
+pub fn lint(parser: &dbc::merged::MergedParser) -> Vec<LintIssue> {
+    let mut issues = Vec::new();
+
+    for (path, file) in parser.dbcs() {
+        let file_name = dbc::merged::file_name(path);
+        lint_duplicate_ids(&file_name, file, &mut issues);
+        for msg in &file.messages {
+            lint_msg(
+                &format!("{} / {}", file_name, msg.name),
+                file,
+                msg,
+                &mut issues,
+            );
+        }
+    }
+    for conflict in parser.conflicts() {
+        issues.push(LintIssue {
+            severity: Severity::Error,
+            location: "merged DBCs".to_string(),
+            text: conflict.clone(),
+        });
+    }
+
+    issues.sort_by_key(|i| i.severity);
+    issues
+}
+
+/// Messages whose ID is used on both buses must have the same layout, otherwise the Log Parser
+/// decodes one of the buses with the wrong definition.
+pub fn lint_cross_bus(
+    bus_0: &dbc::merged::MergedParser,
+    bus_1: &dbc::merged::MergedParser,
+) -> Vec<LintIssue> {
+    let mut issues = Vec::new();
+    let bus_1_defs = bus_1.msg_defs();
+
+    for msg_0 in bus_0.msg_defs() {
+        let Some(msg_1) = bus_1_defs.iter().find(|m| m.id == msg_0.id) else {
+            continue;
+        };
+        let location = format!(
+            "ID 0x{:03X}",
+            util::can::can_dbc_to_u32_without_extid_flag(&msg_0.id)
+        );
+
+        if msg_0.name != msg_1.name {
+            issues.push(LintIssue {
+                severity: Severity::Error,
+                location,
+                text: format!("{} on bus 0 but {} on bus 1", msg_0.name, msg_1.name),
+            });
+            continue;
+        }
+
+        let changes = dbc::diff::msg_changes(&msg_0, msg_1);
+        if !changes.is_empty() {
+            issues.push(LintIssue {
+                severity: Severity::Error,
+                location,
+                text: format!(
+                    "{} differs between bus 0 and bus 1: {}",
+                    msg_0.name,
+                    changes.join(", ")
+                ),
+            });
+        }
+    }
+    issues
+}
+
+fn lint_duplicate_ids(file_name: &str, file: &can_dbc::Dbc, issues: &mut Vec<LintIssue>) {
+    for (i, msg) in file.messages.iter().enumerate() {
+        if let Some(other) = file.messages[i + 1..].iter().find(|m| m.id == msg.id) {
+            issues.push(LintIssue {
+                severity: Severity::Error,
+                location: file_name.to_string(),
+                text: format!(
+                    "ID 0x{:03X} is used by both {} and {}",
+                    util::can::can_dbc_to_u32_without_extid_flag(&msg.id),
+                    msg.name,
+                    other.name
+                ),
+            });
+        }
+    }
+}
+
+fn lint_msg(
+    location: &str,
+    file: &can_dbc::Dbc,
+    msg: &can_dbc::Message,
+    issues: &mut Vec<LintIssue>,
+) {
+    let mut push = |severity, text| {
+        issues.push(LintIssue {
+            severity,
+            location: location.to_string(),
+            text,
+        })
+    };
+
+    for (a, b) in dbc::layout::overlapping_signals(msg) {
+        push(
+            Severity::Error,
+            format!("signals {} and {} share bits", a, b),
+        );
+    }
+
+    let bit_count = msg.size * 8;
+    for sig in &msg.signals {
+        if dbc::layout::signal_bits(sig)
+            .iter()
+            .any(|&bit| bit >= bit_count)
+        {
+            push(
+                Severity::Error,
+                format!(
+                    "signal {} runs past the message length of {} bytes",
+                    sig.name, msg.size
+                ),
+            );
+        }
+    }
+
+    let cycle_time =
+        dbc::attributes::msg_attribute(file, &msg.id, dbc::attributes::CYCLE_TIME_ATTRIBUTE)
+            .and_then(dbc::attributes::value_to_f64);
+    if cycle_time.is_none_or(|t| t <= 0.0) {
+        push(
+            Severity::Warning,
+            format!("no {} attribute", dbc::attributes::CYCLE_TIME_ATTRIBUTE),
+        );
+    }
+
+    for sig in &msg.signals {
+        let value_descs = file.value_descriptions_for_signal(msg.id, &sig.name);
+
+        // Single bit signals and enums are unitless by nature
+        if sig.unit.is_empty() && sig.size > 1 && value_descs.is_none() {
+            push(
+                Severity::Warning,
+                format!("signal {} has no unit", sig.name),
+            );
+        }
+
+        let (raw_min, raw_max) = raw_range(sig);
+        for desc in value_descs.unwrap_or_default() {
+            if desc.id < raw_min || desc.id > raw_max {
+                push(
+                    Severity::Warning,
+                    format!(
+                        "signal {} value {} (\"{}\") can't be sent in {} bits",
+                        sig.name, desc.id, desc.description, sig.size
+                    ),
+                );
+            }
+        }
+    }
+}
+
+// Range of the raw (unscaled) value, which is what value descriptions refer to
+fn raw_range(sig: &can_dbc::Signal) -> (f64, f64) {
+    let bits = sig.size as i32;
+    match sig.value_type {
+        can_dbc::ValueType::Signed => (-(2f64.powi(bits - 1)), 2f64.powi(bits - 1) - 1.0),
+        can_dbc::ValueType::Unsigned => (0.0, 2f64.powi(bits) - 1.0),
+    }
+}
+
+/// `--lint-dbc <bus 0 files...> [--bus1 <bus 1 files...>]`, prints every issue and returns the
+/// process exit code: 0 when clean or only warnings, 1 on errors, 2 when a file can't be loaded.
+pub fn run_cli(args: &[String]) -> i32 {
+    let (bus_0_args, bus_1_args) = match args.iter().position(|a| a == "--bus1") {
+        Some(i) => (&args[..i], &args[i + 1..]),
+        None => (args, &[][..]),
+    };
+    let to_paths = |args: &[String]| {
+        args.iter()
+            .map(std::path::PathBuf::from)
+            .collect::<Vec<_>>()
+    };
+
+    let bus_0 = match dbc::merged::MergedParser::from_dbc_files(&to_paths(bus_0_args)) {
+        Ok(parser) => parser,
+        Err(e) => {
+            eprintln!("Failed to load bus 0 DBC: {}", e);
+            return 2;
+        }
+    };
+    let mut issues = lint(&bus_0);
+
+    if !bus_1_args.is_empty() {
+        let bus_1 = match dbc::merged::MergedParser::from_dbc_files(&to_paths(bus_1_args)) {
+            Ok(parser) => parser,
+            Err(e) => {
+                eprintln!("Failed to load bus 1 DBC: {}", e);
+                return 2;
+            }
+        };
+        issues.extend(lint(&bus_1));
+        issues.extend(lint_cross_bus(&bus_0, &bus_1));
+        issues.sort_by_key(|i| i.severity);
+    }
+
+    for issue in &issues {
+        println!("{}", issue);
+    }
+    let error_count = issues
+        .iter()
+        .filter(|i| i.severity == Severity::Error)
+        .count();
+    println!(
+        "{} error(s), {} warning(s)",
+        error_count,
+        issues.len() - error_count
+    );
+
+    if error_count > 0 { 1 } else { 0 }
+}
@@ -0,0 +1,178 @@
+use crate::util;
+
+struct DbcSource {
+    path: std::path::PathBuf,
+    parser: can_decode::Parser,
+    // Full file contents for what `can_decode` doesn't expose (value tables, attributes, nodes)
+    dbc: can_dbc::Dbc,
+}
+
+// Several DBC files decoded as one. When two files define the same ID, the file listed first
+// wins and the clash is reported in `conflicts`.
+pub struct MergedParser {
+    sources: Vec<DbcSource>,
+    msg_defs: Vec<can_dbc::Message>,
+    // Parallel to `msg_defs`, index into `sources`
+    msg_sources: Vec<usize>,
+    // Key is the ID with the extended ID flag, value is the index into `msg_defs`
+    by_id: std::collections::HashMap<u32, usize>,
+    conflicts: Vec<String>,
+}
+
+impl MergedParser {
+    pub fn from_dbc_files(paths: &[std::path::PathBuf]) -> Result<Self, String> {
+        if paths.is_empty() {
+            return Err("no DBC files selected".to_string());
+        }
+
+        let mut sources = Vec::with_capacity(paths.len());
+        for path in paths {
+            let parser = can_decode::Parser::from_dbc_file(path)
+                .map_err(|e| format!("{}: {}", path.display(), e))?;
+            let text =
+                std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+            let dbc = can_dbc::Dbc::try_from(text.as_str())
+                .map_err(|e| format!("{}: {:?}", path.display(), e))?;
+            sources.push(DbcSource {
+                path: path.clone(),
+                parser,
+                dbc,
+            });
+        }
+
+        let mut merged = Self {
+            sources: Vec::new(),
+            msg_defs: Vec::new(),
+            msg_sources: Vec::new(),
+            by_id: std::collections::HashMap::new(),
+            conflicts: Vec::new(),
+        };
+        for (source_idx, source) in sources.iter().enumerate() {
+            for msg in source.parser.msg_defs() {
+                merged.add_msg(&sources, source_idx, msg);
+            }
+        }
+        merged.sources = sources;
+        Ok(merged)
+    }
+
+    fn add_msg(&mut self, sources: &[DbcSource], source_idx: usize, msg: can_dbc::Message) {
+        let id = util::can::can_dbc_to_u32_with_extid_flag(&msg.id);
+        let file_name = |idx: usize| file_name(&sources[idx].path);
+
+        if let Some(&existing) = self.by_id.get(&id) {
+            let existing_source = self.msg_sources[existing];
+            self.conflicts.push(format!(
+                "ID 0x{:03X} is {} in {} and {} in {}, using {}",
+                util::can::can_dbc_to_u32_without_extid_flag(&msg.id),
+                self.msg_defs[existing].name,
+                file_name(existing_source),
+                msg.name,
+                file_name(source_idx),
+                file_name(existing_source)
+            ));
+            return;
+        }
+
+        if let Some(existing) = self.msg_defs.iter().position(|m| m.name == msg.name) {
+            self.conflicts.push(format!(
+                "{} is 0x{:03X} in {} and 0x{:03X} in {}, both are decoded",
+                msg.name,
+                util::can::can_dbc_to_u32_without_extid_flag(&self.msg_defs[existing].id),
+                file_name(self.msg_sources[existing]),
+                util::can::can_dbc_to_u32_without_extid_flag(&msg.id),
+                file_name(source_idx)
+            ));
+        }
+
+        self.by_id.insert(id, self.msg_defs.len());
+        self.msg_defs.push(msg);
+        self.msg_sources.push(source_idx);
+    }
+
+    pub fn paths(&self) -> impl Iterator<Item = &std::path::Path> {
+        self.sources.iter().map(|s| s.path.as_path())
+    }
+
+    pub fn dbcs(&self) -> impl Iterator<Item = (&std::path::Path, &can_dbc::Dbc)> {
+        self.sources.iter().map(|s| (s.path.as_path(), &s.dbc))
+    }
+
+    pub fn conflicts(&self) -> &[String] {
+        &self.conflicts
+    }
+
+    fn source_for(&self, msg_id_with_ext_flag: u32) -> Option<&DbcSource> {
+        self.by_id
+            .get(&msg_id_with_ext_flag)
+            .map(|&idx| &self.sources[self.msg_sources[idx]])
+    }
+
+    // Lookups from decoded messages only have the ID without the flag, try both kinds
+    fn def_idx_without_ext_flag(&self, msg_id: u32) -> Option<usize> {
+        self.by_id
+            .get(&msg_id)
+            .or_else(|| self.by_id.get(&(msg_id | util::can::EXTENDED_ID_FLAG)))
+            .copied()
+    }
+
+    /// File the given message (ID without the extended ID flag) was taken from.
+    pub fn source_of(&self, msg_id: u32) -> Option<&std::path::Path> {
+        self.def_idx_without_ext_flag(msg_id)
+            .map(|idx| self.sources[self.msg_sources[idx]].path.as_path())
+    }
+
+    /// Full definitions of the file the given message (ID with the extended ID flag) was taken from.
+    pub fn dbc_of(&self, msg_id_with_ext_flag: u32) -> Option<&can_dbc::Dbc> {
+        self.source_for(msg_id_with_ext_flag).map(|s| &s.dbc)
+    }
+
+    pub fn decode_msg(
+        &self,
+        msg_id_with_ext_flag: u32,
+        data: &[u8],
+    ) -> Option<can_decode::DecodedMessage> {
+        self.source_for(msg_id_with_ext_flag)?
+            .parser
+            .decode_msg(msg_id_with_ext_flag, data)
+    }
+
+    pub fn encode_msg(
+        &self,
+        msg_id_with_ext_flag: u32,
+        values: &std::collections::HashMap<String, f64>,
+    ) -> Option<Vec<u8>> {
+        self.source_for(msg_id_with_ext_flag)?
+            .parser
+            .encode_msg(msg_id_with_ext_flag, values)
+    }
+
+    pub fn msg_defs(&self) -> Vec<can_dbc::Message> {
+        self.msg_defs.clone()
+    }
+
+    pub fn msg_def(&self, msg_id: u32) -> Option<&can_dbc::Message> {
+        self.def_idx_without_ext_flag(msg_id & util::can::EXTENDED_ID_MASK)
+            .map(|idx| &self.msg_defs[idx])
+    }
+
+    pub fn msg_desc(&self, msg_id_with_ext_flag: u32) -> Option<String> {
+        self.source_for(msg_id_with_ext_flag)?
+            .parser
+            .msg_desc(msg_id_with_ext_flag)
+            .map(|d| d.to_string())
+    }
+
+    pub fn signal_desc(&self, msg_id_with_ext_flag: u32, signal_name: &str) -> Option<String> {
+        self.source_for(msg_id_with_ext_flag)?
+            .parser
+            .signal_desc(msg_id_with_ext_flag, signal_name)
+            .map(|d| d.to_string())
+    }
+}
+
+pub fn file_name(path: &std::path::Path) -> String {
+    path.file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| path.display().to_string())
+}
@@ -0,0 +1,6 @@
+pub mod attributes;
+pub mod diff;
+pub mod layout;
+pub mod lint;
+pub mod merged;
+pub mod watcher;
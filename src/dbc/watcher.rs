@@ -0,0 +1,52 @@
+const POLL_INTERVAL_MS: u128 = 500;
+
+// Polls the modification time instead of using OS file notifications, since DBC generators
+// often replace the file (new inode) rather than writing it in place
+pub struct DbcWatcher {
+    path: std::path::PathBuf,
+    last_modified: Option<std::time::SystemTime>,
+    change_pending: bool,
+    last_poll: std::time::Instant,
+}
+
+impl DbcWatcher {
+    pub fn new(path: std::path::PathBuf) -> Self {
+        let last_modified = modified_time(&path);
+        Self {
+            path,
+            last_modified,
+            change_pending: false,
+            last_poll: std::time::Instant::now(),
+        }
+    }
+
+    pub fn path(&self) -> &std::path::Path {
+        &self.path
+    }
+
+    /// Returns true once after the file changed on disk, but only when it has stayed unchanged
+    /// for a full poll interval so a generator that is still writing isn't read half-way.
+    pub fn poll(&mut self) -> bool {
+        if self.last_poll.elapsed().as_millis() < POLL_INTERVAL_MS {
+            return false;
+        }
+        self.last_poll = std::time::Instant::now();
+
+        let modified = modified_time(&self.path);
+        if modified != self.last_modified {
+            self.last_modified = modified;
+            self.change_pending = true;
+            return false;
+        }
+
+        if self.change_pending && modified.is_some() {
+            self.change_pending = false;
+            return true;
+        }
+        false
+    }
+}
+
+fn modified_time(path: &std::path::Path) -> Option<std::time::SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
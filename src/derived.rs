@@ -0,0 +1,665 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use crate::config;
+
+pub const DERIVED_CONFIG_FILE: &str = "derived_signals.json";
+
+// Derived signals are published as signals of this pseudo message, so they can be referenced
+// as `derived.<name>` from other expressions, alarm rules and the Scope
+pub const DERIVED_MSG_NAME: &str = "derived";
+// Outside the 29-bit ID range so it can never collide with a real message
+pub const DERIVED_MSG_ID: u32 = u32::MAX;
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq)]
+pub struct DerivedSignalDef {
+    pub name: String,
+    // Ex: "charging_telemetry.pack_voltage * charging_telemetry.pack_current"
+    pub expr: String,
+    #[serde(default)]
+    pub unit: String,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Default)]
+pub struct DerivedConfig {
+    #[serde(default)]
+    pub signals: Vec<DerivedSignalDef>,
+}
+
+impl DerivedConfig {
+    pub fn load() -> Self {
+        config::load_json(DERIVED_CONFIG_FILE).unwrap_or_default()
+    }
+
+    pub fn save(&self) {
+        config::save_json(DERIVED_CONFIG_FILE, self);
+    }
+}
+
+pub fn is_valid_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Checks an expression without evaluating it, for showing errors while editing.
+pub fn validate(expr: &str) -> Result<(), String> {
+    Parser::parse(expr).map(|_| ())
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum BinOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Pow,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Func {
+    Abs,
+    Sqrt,
+    Min,
+    Max,
+    // ma(x, n): mean of the last n samples of x
+    MovingAvg,
+    // ddt(x): rate of change of x per second
+    Derivative,
+    // integ(x): running integral of x over seconds
+    Integral,
+}
+
+impl Func {
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "abs" => Some(Func::Abs),
+            "sqrt" => Some(Func::Sqrt),
+            "min" => Some(Func::Min),
+            "max" => Some(Func::Max),
+            "ma" => Some(Func::MovingAvg),
+            "ddt" => Some(Func::Derivative),
+            "integ" => Some(Func::Integral),
+            _ => None,
+        }
+    }
+
+    fn arg_count(&self) -> usize {
+        match self {
+            Func::Min | Func::Max | Func::MovingAvg => 2,
+            _ => 1,
+        }
+    }
+}
+
+enum Expr {
+    Num(f64),
+    Signal {
+        msg: String,
+        signal: String,
+    },
+    Neg(Box<Expr>),
+    Bin(BinOp, Box<Expr>, Box<Expr>),
+    // `slot` indexes into the filter states of the owning signal
+    Call {
+        func: Func,
+        args: Vec<Expr>,
+        slot: usize,
+    },
+}
+
+enum FilterState {
+    Stateless,
+    MovingAvg {
+        window: usize,
+        samples: VecDeque<f64>,
+        sum: f64,
+    },
+    Derivative {
+        last: Option<(f64, f64)>,
+        rate: Option<f64>,
+    },
+    Integral {
+        last: Option<(f64, f64)>,
+        total: f64,
+    },
+}
+
+impl FilterState {
+    // The last output, for evaluations that didn't bring a new input sample
+    fn held_output(&self) -> Option<f64> {
+        match self {
+            FilterState::Stateless => None,
+            FilterState::MovingAvg { samples, sum, .. } => {
+                (!samples.is_empty()).then(|| *sum / samples.len() as f64)
+            }
+            FilterState::Derivative { rate, .. } => *rate,
+            FilterState::Integral { last, total } => last.map(|_| *total),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum Token {
+    Num(f64),
+    Ident(String),
+    Op(char),
+    LParen,
+    RParen,
+    Comma,
+}
+
+fn tokenize(src: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let chars: Vec<char> = src.chars().collect();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+        } else if c.is_ascii_digit()
+            || (c == '.' && chars.get(i + 1).is_some_and(char::is_ascii_digit))
+        {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                i += 1;
+            }
+            // Exponent, ex: 1e-3
+            if i < chars.len() && (chars[i] == 'e' || chars[i] == 'E') {
+                i += 1;
+                if i < chars.len() && (chars[i] == '+' || chars[i] == '-') {
+                    i += 1;
+                }
+                while i < chars.len() && chars[i].is_ascii_digit() {
+                    i += 1;
+                }
+            }
+            let text: String = chars[start..i].iter().collect();
+            let num = text
+                .parse::<f64>()
+                .map_err(|_| format!("`{}` is not a number", text))?;
+            tokens.push(Token::Num(num));
+        } else if c.is_ascii_alphabetic() || c == '_' {
+            let start = i;
+            while i < chars.len()
+                && (chars[i].is_ascii_alphanumeric() || chars[i] == '_' || chars[i] == '.')
+            {
+                i += 1;
+            }
+            tokens.push(Token::Ident(chars[start..i].iter().collect()));
+        } else {
+            tokens.push(match c {
+                '+' | '-' | '*' | '/' | '^' => Token::Op(c),
+                '(' => Token::LParen,
+                ')' => Token::RParen,
+                ',' => Token::Comma,
+                other => return Err(format!("unexpected character `{}`", other)),
+            });
+            i += 1;
+        }
+    }
+    Ok(tokens)
+}
+
+// Recursive descent, lowest to highest precedence:
+//   expr  := term (('+' | '-') term)*
+//   term  := unary (('*' | '/') unary)*
+//   unary := '-' unary | power
+//   power := atom ('^' unary)?
+//   atom  := number | msg.signal | func '(' expr (',' expr)* ')' | '(' expr ')'
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    states: Vec<FilterState>,
+}
+
+impl Parser {
+    fn parse(src: &str) -> Result<(Expr, Vec<FilterState>), String> {
+        let mut parser = Parser {
+            tokens: tokenize(src)?,
+            pos: 0,
+            states: Vec::new(),
+        };
+        if parser.tokens.is_empty() {
+            return Err("expression is empty".to_string());
+        }
+        let expr = parser.expr()?;
+        if let Some(token) = parser.peek() {
+            return Err(format!("unexpected {:?} after end of expression", token));
+        }
+        Ok((expr, parser.states))
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<(), String> {
+        match self.next() {
+            Some(token) if token == expected => Ok(()),
+            Some(token) => Err(format!("expected {:?}, found {:?}", expected, token)),
+            None => Err(format!("expected {:?}, found end of expression", expected)),
+        }
+    }
+
+    fn expr(&mut self) -> Result<Expr, String> {
+        let mut lhs = self.term()?;
+        while let Some(Token::Op(op @ ('+' | '-'))) = self.peek() {
+            let op = if *op == '+' { BinOp::Add } else { BinOp::Sub };
+            self.pos += 1;
+            let rhs = self.term()?;
+            lhs = Expr::Bin(op, Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+
+    fn term(&mut self) -> Result<Expr, String> {
+        let mut lhs = self.unary()?;
+        while let Some(Token::Op(op @ ('*' | '/'))) = self.peek() {
+            let op = if *op == '*' { BinOp::Mul } else { BinOp::Div };
+            self.pos += 1;
+            let rhs = self.unary()?;
+            lhs = Expr::Bin(op, Box::new(lhs), Box::new(rhs));
+        }
+        Ok(lhs)
+    }
+
+    fn unary(&mut self) -> Result<Expr, String> {
+        if self.peek() == Some(&Token::Op('-')) {
+            self.pos += 1;
+            return Ok(Expr::Neg(Box::new(self.unary()?)));
+        }
+        self.power()
+    }
+
+    fn power(&mut self) -> Result<Expr, String> {
+        let base = self.atom()?;
+        if self.peek() == Some(&Token::Op('^')) {
+            self.pos += 1;
+            let exponent = self.unary()?;
+            return Ok(Expr::Bin(BinOp::Pow, Box::new(base), Box::new(exponent)));
+        }
+        Ok(base)
+    }
+
+    fn atom(&mut self) -> Result<Expr, String> {
+        match self.next() {
+            Some(Token::Num(n)) => Ok(Expr::Num(n)),
+            Some(Token::LParen) => {
+                let inner = self.expr()?;
+                self.expect(Token::RParen)?;
+                Ok(inner)
+            }
+            Some(Token::Ident(ident)) => {
+                if self.peek() == Some(&Token::LParen) {
+                    return self.call(&ident);
+                }
+                if ident == "pi" {
+                    return Ok(Expr::Num(std::f64::consts::PI));
+                }
+                match ident.split_once('.') {
+                    Some((msg, signal)) if !msg.is_empty() && is_valid_name(signal) => {
+                        Ok(Expr::Signal {
+                            msg: msg.to_string(),
+                            signal: signal.to_string(),
+                        })
+                    }
+                    _ => Err(format!("`{}` is not of the form <msg>.<signal>", ident)),
+                }
+            }
+            Some(token) => Err(format!("unexpected {:?}", token)),
+            None => Err("unexpected end of expression".to_string()),
+        }
+    }
+
+    fn call(&mut self, name: &str) -> Result<Expr, String> {
+        let func = Func::from_name(name).ok_or_else(|| format!("unknown function `{}`", name))?;
+        self.expect(Token::LParen)?;
+        let mut args = vec![self.expr()?];
+        while self.peek() == Some(&Token::Comma) {
+            self.pos += 1;
+            args.push(self.expr()?);
+        }
+        self.expect(Token::RParen)?;
+        if args.len() != func.arg_count() {
+            return Err(format!(
+                "`{}` takes {} argument(s), got {}",
+                name,
+                func.arg_count(),
+                args.len()
+            ));
+        }
+
+        let state = match func {
+            Func::MovingAvg => {
+                let Expr::Num(window) = args[1] else {
+                    return Err("the window of `ma` must be a number".to_string());
+                };
+                if window < 1.0 {
+                    return Err("the window of `ma` must be at least 1".to_string());
+                }
+                FilterState::MovingAvg {
+                    window: window as usize,
+                    samples: VecDeque::new(),
+                    sum: 0.0,
+                }
+            }
+            Func::Derivative => FilterState::Derivative {
+                last: None,
+                rate: None,
+            },
+            Func::Integral => FilterState::Integral {
+                last: None,
+                total: 0.0,
+            },
+            _ => FilterState::Stateless,
+        };
+        self.states.push(state);
+        Ok(Expr::Call {
+            func,
+            args,
+            slot: self.states.len() - 1,
+        })
+    }
+}
+
+// By message name only, expressions don't name a bus. A message with the same name on two buses
+// feeds the same values, whichever frame came last wins.
+type LatestValues = HashMap<String, HashMap<String, f64>>;
+
+impl Expr {
+    // `updated` are the messages that changed since the last evaluation, filters whose input
+    // reads none of them keep their output instead of taking a repeated sample
+    fn eval(
+        &self,
+        time_s: f64,
+        latest: &LatestValues,
+        updated: &HashSet<&str>,
+        states: &mut [FilterState],
+    ) -> Option<f64> {
+        match self {
+            Expr::Num(n) => Some(*n),
+            Expr::Signal { msg, signal } => latest.get(msg)?.get(signal).copied(),
+            Expr::Neg(inner) => Some(-inner.eval(time_s, latest, updated, states)?),
+            Expr::Bin(op, lhs, rhs) => {
+                let lhs = lhs.eval(time_s, latest, updated, states)?;
+                let rhs = rhs.eval(time_s, latest, updated, states)?;
+                Some(match op {
+                    BinOp::Add => lhs + rhs,
+                    BinOp::Sub => lhs - rhs,
+                    BinOp::Mul => lhs * rhs,
+                    BinOp::Div => lhs / rhs,
+                    BinOp::Pow => lhs.powf(rhs),
+                })
+            }
+            Expr::Call { func, args, slot } => {
+                // Ex: in `ddt(a.x) + b.y` a frame of b must not feed ddt a change of zero
+                if !matches!(states[*slot], FilterState::Stateless) && !args[0].reads_any(updated) {
+                    return states[*slot].held_output();
+                }
+                let x = args[0].eval(time_s, latest, updated, states)?;
+                let y = match args.get(1) {
+                    Some(arg) => arg.eval(time_s, latest, updated, states)?,
+                    None => 0.0,
+                };
+                match (func, &mut states[*slot]) {
+                    (Func::Abs, _) => Some(x.abs()),
+                    (Func::Sqrt, _) => Some(x.sqrt()),
+                    (Func::Min, _) => Some(x.min(y)),
+                    (Func::Max, _) => Some(x.max(y)),
+                    (
+                        Func::MovingAvg,
+                        FilterState::MovingAvg {
+                            window,
+                            samples,
+                            sum,
+                        },
+                    ) => {
+                        samples.push_back(x);
+                        *sum += x;
+                        while samples.len() > *window {
+                            *sum -= samples.pop_front().unwrap_or_default();
+                        }
+                        Some(*sum / samples.len() as f64)
+                    }
+                    (Func::Derivative, FilterState::Derivative { last, rate }) => {
+                        if let Some((last_t, last_x)) = *last
+                            && time_s > last_t
+                        {
+                            *rate = Some((x - last_x) / (time_s - last_t));
+                        }
+                        *last = Some((time_s, x));
+                        *rate
+                    }
+                    (Func::Integral, FilterState::Integral { last, total }) => {
+                        if let Some((last_t, last_x)) = *last
+                            && time_s > last_t
+                        {
+                            // Trapezoidal rule
+                            *total += (x + last_x) / 2.0 * (time_s - last_t);
+                        }
+                        *last = Some((time_s, x));
+                        Some(*total)
+                    }
+                    _ => None,
+                }
+            }
+        }
+    }
+
+    fn reads_any(&self, msgs: &HashSet<&str>) -> bool {
+        match self {
+            Expr::Num(_) => false,
+            Expr::Signal { msg, .. } => msgs.contains(msg.as_str()),
+            Expr::Neg(inner) => inner.reads_any(msgs),
+            Expr::Bin(_, lhs, rhs) => lhs.reads_any(msgs) || rhs.reads_any(msgs),
+            Expr::Call { args, .. } => args.iter().any(|a| a.reads_any(msgs)),
+        }
+    }
+
+    fn collect_msgs(&self, out: &mut HashSet<String>) {
+        match self {
+            Expr::Num(_) => {}
+            Expr::Signal { msg, .. } => {
+                out.insert(msg.clone());
+            }
+            Expr::Neg(inner) => inner.collect_msgs(out),
+            Expr::Bin(_, lhs, rhs) => {
+                lhs.collect_msgs(out);
+                rhs.collect_msgs(out);
+            }
+            Expr::Call { args, .. } => args.iter().for_each(|a| a.collect_msgs(out)),
+        }
+    }
+}
+
+struct CompiledSignal {
+    def: DerivedSignalDef,
+    expr: Result<Expr, String>,
+    states: Vec<FilterState>,
+    // Messages (including `derived`) that cause this signal to be re-evaluated
+    msgs: HashSet<String>,
+}
+
+impl CompiledSignal {
+    fn new(def: DerivedSignalDef) -> Self {
+        let (expr, states) = match Parser::parse(&def.expr) {
+            Ok((expr, states)) => (Ok(expr), states),
+            Err(e) => {
+                log::error!("Invalid derived signal `{}`: {}", def.name, e);
+                (Err(e), Vec::new())
+            }
+        };
+        let mut msgs = HashSet::new();
+        if let Ok(expr) = &expr {
+            expr.collect_msgs(&mut msgs);
+        }
+        Self {
+            def,
+            expr,
+            states,
+            msgs,
+        }
+    }
+}
+
+pub struct DerivedValue {
+    pub name: String,
+    pub value: f64,
+    pub unit: String,
+}
+
+pub struct DerivedEngine {
+    signals: Vec<CompiledSignal>,
+    latest: LatestValues,
+}
+
+impl DerivedEngine {
+    pub fn new(defs: &[DerivedSignalDef]) -> Self {
+        Self {
+            signals: defs.iter().cloned().map(CompiledSignal::new).collect(),
+            latest: LatestValues::new(),
+        }
+    }
+
+    pub fn defs(&self) -> impl Iterator<Item = &DerivedSignalDef> {
+        self.signals.iter().map(|s| &s.def)
+    }
+
+    /// Forgets every input value and filter state, ex: between discontinuous log chunks.
+    pub fn reset(&mut self) {
+        *self = Self::new(&self.defs().cloned().collect::<Vec<_>>());
+    }
+
+    /// Feeds one decoded message and returns the derived signals that were re-evaluated because
+    /// of it. Signals referencing other derived signals are evaluated in definition order.
+    pub fn on_decoded(
+        &mut self,
+        time_s: f64,
+        decoded: &can_decode::DecodedMessage,
+    ) -> Vec<DerivedValue> {
+        self.on_values(
+            time_s,
+            &decoded.name,
+            decoded
+                .signals
+                .iter()
+                .map(|(name, signal)| (name.as_str(), signal.value.physical)),
+        )
+    }
+
+    fn on_values<'a>(
+        &mut self,
+        time_s: f64,
+        msg_name: &str,
+        signals: impl Iterator<Item = (&'a str, f64)>,
+    ) -> Vec<DerivedValue> {
+        if !self.signals.iter().any(|s| s.msgs.contains(msg_name)) {
+            return Vec::new();
+        }
+
+        let values = self.latest.entry(msg_name.to_string()).or_default();
+        for (sig_name, value) in signals {
+            values.insert(sig_name.to_string(), value);
+        }
+
+        let mut results = Vec::new();
+        let mut updated = HashSet::from([msg_name]);
+        for signal in self.signals.iter_mut() {
+            let Ok(expr) = &signal.expr else {
+                continue;
+            };
+            if !signal.msgs.iter().any(|msg| updated.contains(msg.as_str())) {
+                continue;
+            }
+            let Some(value) = expr.eval(time_s, &self.latest, &updated, &mut signal.states) else {
+                continue;
+            };
+            self.latest
+                .entry(DERIVED_MSG_NAME.to_string())
+                .or_default()
+                .insert(signal.def.name.clone(), value);
+            updated.insert(DERIVED_MSG_NAME);
+            results.push(DerivedValue {
+                name: signal.def.name.clone(),
+                value,
+                unit: signal.def.unit.clone(),
+            });
+        }
+        results
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn engine(expr: &str) -> DerivedEngine {
+        DerivedEngine::new(&[DerivedSignalDef {
+            name: "out".to_string(),
+            expr: expr.to_string(),
+            unit: String::new(),
+        }])
+    }
+
+    fn feed(engine: &mut DerivedEngine, time_s: f64, msg: &str, value: f64) -> Option<f64> {
+        let signal = if msg == "a" { "x" } else { "y" };
+        engine
+            .on_values(time_s, msg, std::iter::once((signal, value)))
+            .first()
+            .map(|v| v.value)
+    }
+
+    // `a` at 1 Hz, `b` at 10 Hz in between
+    fn feed_interleaved(engine: &mut DerivedEngine, a_values: &[f64]) -> Vec<Option<f64>> {
+        let mut outputs = Vec::new();
+        for (second, a) in a_values.iter().enumerate() {
+            let second = second as f64;
+            outputs.push(feed(engine, second, "a", *a));
+            for tenth in 1..10 {
+                outputs.push(feed(engine, second + tenth as f64 / 10.0, "b", 0.0));
+            }
+        }
+        outputs
+    }
+
+    #[test]
+    fn derivative_ignores_frames_of_other_messages() {
+        let mut engine = engine("ddt(a.x) + b.y");
+        let outputs = feed_interleaved(&mut engine, &[0.0, 10.0, 30.0]);
+        // Nothing until b.y is known and ddt has two samples of a
+        assert!(outputs[..10].iter().all(|v| v.is_none()));
+        assert!(outputs[10..20].iter().all(|v| *v == Some(10.0)));
+        assert_eq!(outputs[20], Some(20.0));
+    }
+
+    #[test]
+    fn integral_only_adds_area_between_samples_of_its_input() {
+        let mut engine = engine("integ(a.x) + b.y");
+        feed_interleaved(&mut engine, &[0.0, 10.0]);
+        // Trapezoid between (0 s, 0) and (1 s, 10), held through the later b frames
+        assert_eq!(feed(&mut engine, 1.95, "b", 0.0), Some(5.0));
+    }
+
+    #[test]
+    fn moving_average_only_takes_new_samples() {
+        let mut engine = engine("ma(a.x, 3) + b.y");
+        let outputs = feed_interleaved(&mut engine, &[1.0, 3.0]);
+        assert_eq!(outputs.last().copied().flatten(), Some(2.0));
+    }
+
+    #[test]
+    fn stateless_expressions_use_latest_values() {
+        let mut engine = engine("a.x * 2 + b.y");
+        assert_eq!(feed(&mut engine, 0.0, "a", 1.0), None);
+        assert_eq!(feed(&mut engine, 0.1, "b", 0.5), Some(2.5));
+        assert_eq!(feed(&mut engine, 0.2, "b", 1.5), Some(3.5));
+    }
+}
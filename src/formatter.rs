@@ -1,7 +1,10 @@
+use crate::{config, theme, units};
+use eframe::egui;
 use indexmap::IndexMap;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 
 pub const FORMATTER_CONFIG_FILE: &str = "formatter_config.json";
+const DEFAULT_DECIMALS: usize = 2;
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Formatting {
@@ -10,13 +13,200 @@ pub enum Formatting {
     Decimal(usize), // Number of decimal places
 }
 
+/// Everything a rule can set, ex:
+/// `{ "convert": "km/h", "format": 1 }` shows a m/s signal in km/h with one decimal,
+/// `{ "from": "Pa", "convert": "psi" }` for signals whose DBC unit is missing or misspelled,
+/// `{ "scale": 0.001, "unit": "kW" }` for anything the built-in units don't cover,
+/// `{ "colors": [{ "above": 55, "color": "error" }] }` to make a fault stand out.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SignalFormat {
+    // Decimal places, "hex" or "binary". Enum label or 2 decimals when unset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub format: Option<Formatting>,
+    // Unit to convert to, from the DBC's unit or `from`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub convert: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from: Option<String>,
+    // Applied after `convert`: value * scale + offset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scale: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub offset: Option<f64>,
+    // Shown instead of the DBC's or `convert`'s unit
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub unit: Option<String>,
+    // The first one the value meets colors it
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub colors: Vec<ColorRule>,
+}
+
+/// Ex: `{ "above": 55, "color": "error" }` or `{ "label": "READY", "color": "success" }`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ColorRule {
+    // Every condition that is set must hold, values are compared after unit conversion
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub above: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub below: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub equals: Option<f64>,
+    // The DBC's value description, ex: "READY"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    // "error", "warning", "success" or "info" to follow the theme, or "#rrggbb"
+    pub color: String,
+}
+
+impl ColorRule {
+    fn matches(&self, value: f64, label: Option<&str>) -> bool {
+        self.above.is_none_or(|above| value > above)
+            && self.below.is_none_or(|below| value < below)
+            && self
+                .equals
+                .is_none_or(|equals| (value - equals).abs() < 1e-9)
+            && self.label.as_deref().is_none_or(|l| label == Some(l))
+    }
+
+    pub fn resolve(&self, theme: &theme::ThemeColors) -> egui::Color32 {
+        match self.color.to_lowercase().as_str() {
+            "error" | "red" => theme.error_color(),
+            "warning" | "yellow" | "orange" => theme.warning_color(),
+            "success" | "green" => theme.success_color(),
+            "info" | "blue" => theme.info_color(),
+            _ => theme::ThemeColors::parse_hex(&self.color),
+        }
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        let named = [
+            "error", "red", "warning", "yellow", "orange", "success", "green", "info", "blue",
+        ];
+        if named.contains(&self.color.to_lowercase().as_str()) {
+            return Ok(());
+        }
+        let hex = self.color.strip_prefix('#').unwrap_or_default();
+        if matches!(hex.len(), 6 | 8) && hex.chars().all(|c| c.is_ascii_hexdigit()) {
+            Ok(())
+        } else {
+            Err(format!(
+                "unknown color \"{}\", use error, warning, success, info or #rrggbb",
+                self.color
+            ))
+        }
+    }
+}
+
+/// A rule in the config file, the short form only sets the display format.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum SignalRule {
+    Format(Formatting),
+    Full(SignalFormat),
+}
+
+impl From<SignalRule> for SignalFormat {
+    fn from(rule: SignalRule) -> Self {
+        match rule {
+            SignalRule::Format(formatting) => SignalFormat {
+                format: Some(formatting),
+                ..Default::default()
+            },
+            SignalRule::Full(format) => format,
+        }
+    }
+}
+
 // Message name/pattern -> signal name/pattern -> formatting
-type FormatterConfig = IndexMap<String, IndexMap<String, Formatting>>;
+pub type FormatterConfig = IndexMap<String, IndexMap<String, SignalRule>>;
 type CompiledFormatterConfig = Vec<(
     globset::GlobMatcher,
-    Vec<(globset::GlobMatcher, Formatting)>,
+    Vec<(globset::GlobMatcher, SignalFormat)>,
 )>;
 
+/// How a physical value is shown: `value * scale + offset` in `unit`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Conversion {
+    pub scale: f64,
+    pub offset: f64,
+    pub unit: String,
+}
+
+impl Conversion {
+    pub fn identity(unit: &str) -> Self {
+        Self {
+            scale: 1.0,
+            offset: 0.0,
+            unit: unit.to_string(),
+        }
+    }
+
+    pub fn apply(&self, value: f64) -> f64 {
+        value * self.scale + self.offset
+    }
+
+    // Ex: "speed (km/h)", or the name alone without a unit
+    pub fn label(&self, name: &str) -> String {
+        if self.unit.is_empty() {
+            name.to_string()
+        } else {
+            format!("{} ({})", name, self.unit)
+        }
+    }
+}
+
+impl SignalFormat {
+    // Unit conversions that don't apply to `dbc_unit` are skipped, the value stays in the DBC's unit
+    pub fn conversion(&self, dbc_unit: &str) -> Conversion {
+        let mut conversion = Conversion::identity(dbc_unit);
+        if let Some(to) = &self.convert {
+            let from = self.from.as_deref().unwrap_or(dbc_unit);
+            if let Some((scale, offset)) = units::linear(from, to) {
+                conversion = Conversion {
+                    scale,
+                    offset,
+                    unit: to.clone(),
+                };
+            }
+        }
+        if self.scale.is_some() || self.offset.is_some() {
+            let scale = self.scale.unwrap_or(1.0);
+            conversion.scale *= scale;
+            conversion.offset = conversion.offset * scale + self.offset.unwrap_or(0.0);
+        }
+        if let Some(unit) = &self.unit {
+            conversion.unit = unit.clone();
+        }
+        conversion
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        for unit in [&self.convert, &self.from].into_iter().flatten() {
+            if !units::is_known(unit) {
+                return Err(format!(
+                    "unknown unit \"{}\", known units are {}",
+                    unit,
+                    units::known_units()
+                ));
+            }
+        }
+        if let (Some(from), Some(to)) = (&self.from, &self.convert)
+            && units::linear(from, to).is_none()
+        {
+            return Err(format!("can't convert {} to {}", from, to));
+        }
+        if self.from.is_some() && self.convert.is_none() {
+            return Err("\"from\" needs \"convert\"".to_string());
+        }
+        for color in &self.colors {
+            color.validate()?;
+        }
+        Ok(())
+    }
+}
+
 impl Serialize for Formatting {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -93,33 +283,68 @@ impl Formatting {
     }
 }
 
+#[derive(Clone)]
 pub struct Formatter {
     compiled_config: CompiledFormatterConfig,
 }
 
 impl Formatter {
-    pub fn new(config: FormatterConfig) -> Result<Self, globset::Error> {
+    pub fn new(config: FormatterConfig) -> Result<Self, String> {
         let mut compiled_config = Vec::new();
-        for (msg_pattern, signal_map) in &config {
-            let msg_glob = globset::Glob::new(msg_pattern)?.compile_matcher();
+        for (msg_pattern, signal_map) in config {
+            let msg_glob = globset::Glob::new(&msg_pattern)
+                .map_err(|e| e.to_string())?
+                .compile_matcher();
             let mut compiled_signal_map = Vec::new();
-            for (signal_pattern, formatting) in signal_map {
-                let signal_glob = globset::Glob::new(signal_pattern)?.compile_matcher();
-                compiled_signal_map.push((signal_glob, formatting.clone()));
+            for (signal_pattern, rule) in signal_map {
+                let signal_glob = globset::Glob::new(&signal_pattern)
+                    .map_err(|e| e.to_string())?
+                    .compile_matcher();
+                let format = SignalFormat::from(rule);
+                format
+                    .validate()
+                    .map_err(|e| format!("{} / {}: {}", msg_pattern, signal_pattern, e))?;
+                compiled_signal_map.push((signal_glob, format));
             }
             compiled_config.push((msg_glob, compiled_signal_map));
         }
         Ok(Self { compiled_config })
     }
 
-    pub fn new_from_file(path: &str) -> Result<Self, Box<dyn std::error::Error>> {
-        let config_str = std::fs::read_to_string(path)?;
+    // Rules matching the signal, in config order
+    fn matching_rules<'a>(
+        &'a self,
+        msg_name: &'a str,
+        signal_name: &'a str,
+    ) -> impl Iterator<Item = &'a SignalFormat> {
+        self.compiled_config
+            .iter()
+            .filter(move |(msg_glob, _)| msg_glob.is_match(msg_name))
+            .flat_map(|(_, signal_vec)| signal_vec)
+            .filter(move |(signal_glob, _)| signal_glob.is_match(signal_name))
+            .map(|(_, format)| format)
+    }
+
+    pub fn new_from_profile(profile: &config::Profile) -> Result<Self, Box<dyn std::error::Error>> {
+        let config_str = profile.read_to_string(FORMATTER_CONFIG_FILE)?;
         let config: FormatterConfig = serde_json::from_str(&config_str)?;
         Self::new(config).map_err(|e| e.into())
     }
 
-    pub fn try_load() -> Option<Self> {
-        Self::new_from_file(FORMATTER_CONFIG_FILE)
+    // The rules as written, for editing. A missing file is an empty config
+    pub fn load_config(profile: &config::Profile) -> Result<FormatterConfig, String> {
+        profile
+            .load_json_with(FORMATTER_CONFIG_FILE, |json| serde_json::from_str(json))
+            .map(|config| config.unwrap_or_else(FormatterConfig::new))
+    }
+
+    pub fn save_config(profile: &config::Profile, config: &FormatterConfig) -> std::io::Result<()> {
+        let json = serde_json::to_string_pretty(config).expect("Failed to serialize formatter");
+        profile.write(FORMATTER_CONFIG_FILE, json)
+    }
+
+    pub fn try_load(profile: &config::Profile) -> Option<Self> {
+        Self::new_from_profile(profile)
             .map_err(|e| {
                 log::error!(
                     "Failed to load formatter config from {}: {}",
@@ -133,7 +358,7 @@ impl Formatter {
 
     /// Formats a signal value based on the message and signal name, using the first matching pattern in the config.
     /// If no pattern matches, returns a default formatted string (enum label if available, otherwise physical value with 2 decimal places).
-    /// If there is a unit associated with the signal, it will be appended to the formatted value.
+    /// Unit conversions and scaling apply to decimal values, the displayed unit is appended to the formatted value.
     /// Hex and binary formatting require the signal definition to determine the number of bits and value type.
     pub fn format(
         &self,
@@ -143,57 +368,79 @@ impl Formatter {
         unit: Option<&str>,
         value: &can_decode::DecodedSignalValue,
     ) -> String {
-        for (msg_glob, signal_vec) in &self.compiled_config {
-            if msg_glob.is_match(msg_name) {
-                for (signal_glob, formatting) in signal_vec {
-                    if signal_glob.is_match(signal_name) {
-                        let have_enough_info = match formatting {
-                            Formatting::Hex | Formatting::Binary => sig_def.is_some(),
-                            Formatting::Decimal(_) => true,
-                        };
-                        if have_enough_info {
-                            let raw = match formatting {
-                                Formatting::Hex => format_hex(sig_def.as_ref().unwrap(), value),
-                                Formatting::Binary => {
-                                    format_binary(sig_def.as_ref().unwrap(), value)
-                                }
-                                Formatting::Decimal(places) => {
-                                    format!("{:.*}", *places, value.physical)
-                                }
-                            };
-                            let maybe_unit = unit
-                                .or_else(|| sig_def.map(|s| s.unit.as_str()))
-                                .filter(|u| !u.is_empty());
-                            if let Some(u) = maybe_unit
-                                && !u.is_empty()
-                            {
-                                return format!("{} {}", raw, u);
-                            } else {
-                                return raw;
-                            }
-                        }
-                    }
+        let dbc_unit = unit
+            .or_else(|| sig_def.map(|s| s.unit.as_str()))
+            .unwrap_or_default();
+        for rule in self.matching_rules(msg_name, signal_name) {
+            let raw = match rule.format {
+                Some(Formatting::Hex) => match sig_def {
+                    Some(sig_def) => format_hex(sig_def, value),
+                    None => continue,
+                },
+                Some(Formatting::Binary) => match sig_def {
+                    Some(sig_def) => format_binary(sig_def, value),
+                    None => continue,
+                },
+                Some(Formatting::Decimal(places)) => {
+                    let conversion = rule.conversion(dbc_unit);
+                    return with_unit(
+                        format!("{:.*}", places, conversion.apply(value.physical)),
+                        &conversion.unit,
+                    );
                 }
-            }
+                None if value.enum_label.is_some() => return default_format(None, value),
+                None => {
+                    let conversion = rule.conversion(dbc_unit);
+                    return with_unit(
+                        format!("{:.*}", DEFAULT_DECIMALS, conversion.apply(value.physical)),
+                        &conversion.unit,
+                    );
+                }
+            };
+            // Raw bits, only the unit can be overridden
+            return with_unit(raw, rule.unit.as_deref().unwrap_or(dbc_unit));
         }
 
-        let maybe_unit = unit
-            .or_else(|| sig_def.map(|s| s.unit.as_str()))
-            .filter(|u| !u.is_empty());
-        default_format(maybe_unit, value)
+        default_format(Some(dbc_unit), value)
     }
 
     pub fn expected_decimals(&self, msg_name: &str, signal_name: &str) -> usize {
-        for (msg_glob, signal_vec) in &self.compiled_config {
-            if msg_glob.is_match(msg_name) {
-                for (signal_glob, formatting) in signal_vec {
-                    if signal_glob.is_match(signal_name) {
-                        return formatting.expected_decimals();
-                    }
-                }
-            }
-        }
-        2 // Default to 2 decimal places if no match is found
+        self.matching_rules(msg_name, signal_name)
+            .find_map(|rule| rule.format.as_ref())
+            .map_or(DEFAULT_DECIMALS, |f| f.expected_decimals())
+    }
+
+    /// What the first matching rule does to values of a signal, for plots and exports.
+    pub fn conversion(&self, msg_name: &str, signal_name: &str, dbc_unit: &str) -> Conversion {
+        self.matching_rules(msg_name, signal_name)
+            .next()
+            .map_or_else(
+                || Conversion::identity(dbc_unit),
+                |rule| rule.conversion(dbc_unit),
+            )
+    }
+
+    /// The color of the first color rule the value meets, from the first matching rule that has
+    /// any. `physical` is in the DBC's unit, `label` its value description.
+    pub fn color(
+        &self,
+        msg_name: &str,
+        signal_name: &str,
+        dbc_unit: &str,
+        physical: f64,
+        label: Option<&str>,
+        theme: &theme::ThemeColors,
+    ) -> Option<egui::Color32> {
+        let rule = self
+            .matching_rules(msg_name, signal_name)
+            .find(|rule| !rule.colors.is_empty())?;
+        let value = self
+            .conversion(msg_name, signal_name, dbc_unit)
+            .apply(physical);
+        rule.colors
+            .iter()
+            .find(|color| color.matches(value, label))
+            .map(|color| color.resolve(theme))
     }
 }
 
@@ -215,6 +462,40 @@ pub fn try_format(
     }
 }
 
+pub fn try_conversion(
+    formatter: &Option<Formatter>,
+    msg_name: &str,
+    signal_name: &str,
+    dbc_unit: &str,
+) -> Conversion {
+    match formatter {
+        Some(fmt) => fmt.conversion(msg_name, signal_name, dbc_unit),
+        None => Conversion::identity(dbc_unit),
+    }
+}
+
+pub fn try_color(
+    formatter: &Option<Formatter>,
+    msg_name: &str,
+    signal_name: &str,
+    dbc_unit: &str,
+    physical: f64,
+    label: Option<&str>,
+    theme: &theme::ThemeColors,
+) -> Option<egui::Color32> {
+    formatter
+        .as_ref()?
+        .color(msg_name, signal_name, dbc_unit, physical, label, theme)
+}
+
+fn with_unit(value: String, unit: &str) -> String {
+    if unit.is_empty() {
+        value
+    } else {
+        format!("{} {}", value, unit)
+    }
+}
+
 pub fn default_format(unit: Option<&str>, value: &can_decode::DecodedSignalValue) -> String {
     if let Some(enum_label) = &value.enum_label {
         format!("{} ({})", enum_label, value.int_rounded())
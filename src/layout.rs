@@ -0,0 +1,311 @@
+use crate::{config, widgets};
+
+pub const LAYOUTS_FILE: &str = "layouts.json";
+
+/// The tile tree with each widget's settings, rebuilt with fresh widgets when loaded.
+#[derive(serde::Serialize, serde::Deserialize, Clone, PartialEq)]
+pub enum LayoutNode {
+    Pane(widgets::WidgetConfig),
+    Tabs {
+        children: Vec<LayoutNode>,
+        // Index in `children`
+        active: Option<usize>,
+    },
+    Horizontal {
+        children: Vec<LayoutNode>,
+        shares: Vec<f32>,
+    },
+    Vertical {
+        children: Vec<LayoutNode>,
+        shares: Vec<f32>,
+    },
+    Grid {
+        children: Vec<LayoutNode>,
+    },
+}
+
+impl LayoutNode {
+    pub fn capture(tree: &egui_tiles::Tree<widgets::Widget>) -> Option<Self> {
+        tree.root
+            .and_then(|root| Self::capture_tile(&tree.tiles, root))
+    }
+
+    fn capture_tile(
+        tiles: &egui_tiles::Tiles<widgets::Widget>,
+        tile_id: egui_tiles::TileId,
+    ) -> Option<Self> {
+        let node = match tiles.get(tile_id)? {
+            egui_tiles::Tile::Pane(widget) => LayoutNode::Pane(widget.config()),
+            egui_tiles::Tile::Container(egui_tiles::Container::Tabs(tabs)) => {
+                let children = Self::capture_children(tiles, tabs.children.iter().copied());
+                let active = tabs
+                    .active
+                    .and_then(|active| children.iter().position(|(child, _)| *child == active));
+                LayoutNode::Tabs {
+                    children: children.into_iter().map(|(_, node)| node).collect(),
+                    active,
+                }
+            }
+            egui_tiles::Tile::Container(egui_tiles::Container::Linear(linear)) => {
+                let children = Self::capture_children(tiles, linear.children.iter().copied());
+                let shares = children
+                    .iter()
+                    .map(|(child, _)| linear.shares[*child])
+                    .collect();
+                let children = children.into_iter().map(|(_, node)| node).collect();
+                match linear.dir {
+                    egui_tiles::LinearDir::Horizontal => {
+                        LayoutNode::Horizontal { children, shares }
+                    }
+                    egui_tiles::LinearDir::Vertical => LayoutNode::Vertical { children, shares },
+                }
+            }
+            egui_tiles::Tile::Container(egui_tiles::Container::Grid(grid)) => {
+                let children = Self::capture_children(tiles, grid.children().copied());
+                LayoutNode::Grid {
+                    children: children.into_iter().map(|(_, node)| node).collect(),
+                }
+            }
+        };
+        Some(node)
+    }
+
+    // Children with the node each one became, the IDs are needed to look up shares and tabs
+    fn capture_children(
+        tiles: &egui_tiles::Tiles<widgets::Widget>,
+        children: impl Iterator<Item = egui_tiles::TileId>,
+    ) -> Vec<(egui_tiles::TileId, LayoutNode)> {
+        children
+            .filter_map(|child| Self::capture_tile(tiles, child).map(|node| (child, node)))
+            .collect()
+    }
+}
+
+/// A widget popped out into its own window.
+#[derive(serde::Serialize, serde::Deserialize, Clone, PartialEq)]
+pub struct DetachedConfig {
+    pub widget: widgets::WidgetConfig,
+    // Outer window position and inner size in points, the OS may not honor the position
+    #[serde(default)]
+    pub pos: Option<[f32; 2]>,
+    #[serde(default)]
+    pub size: Option<[f32; 2]>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone)]
+pub struct NamedLayout {
+    pub name: String,
+    // None for an empty workspace
+    pub root: Option<LayoutNode>,
+    #[serde(default)]
+    pub detached: Vec<DetachedConfig>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Default)]
+pub struct LayoutsConfig {
+    // The workspace as it was when the app last ran, restored on startup
+    #[serde(default)]
+    pub last: Option<LayoutNode>,
+    #[serde(default)]
+    pub last_detached: Vec<DetachedConfig>,
+    #[serde(default)]
+    pub named: Vec<NamedLayout>,
+}
+
+impl LayoutsConfig {
+    /// The layouts, and what couldn't be restored to show to the user. Tiles and windows of widgets
+    /// that don't parse are left out instead of failing the whole file, after backing it up.
+    pub fn load(profile: &config::Profile) -> (Self, Vec<String>) {
+        let mut raw = String::new();
+        let mut skipped = Vec::new();
+        let loaded = profile.load_json_with(LAYOUTS_FILE, |json| {
+            raw = json.to_string();
+            let mut value = serde_json::from_str(json)?;
+            prune_unknown_widgets(&mut value, &mut skipped);
+            serde_json::from_value(value)
+        });
+        let config = match loaded {
+            Ok(config) => config.unwrap_or_default(),
+            Err(e) => return (Self::default(), vec![e]),
+        };
+        if skipped.is_empty() {
+            return (config, Vec::new());
+        }
+
+        let backup = config::backup_file_name(LAYOUTS_FILE);
+        let kept = match profile.write(&backup, &raw) {
+            Ok(()) => format!(
+                "the file was copied to {}",
+                profile.dir().join(&backup).display()
+            ),
+            Err(e) => format!("copying the file to {} failed ({})", backup, e),
+        };
+        let issue = format!(
+            "{} widget(s) in {} couldn't be restored and are dropped on the next save, {}: {}",
+            skipped.len(),
+            LAYOUTS_FILE,
+            kept,
+            skipped.join("; ")
+        );
+        log::warn!("{}", issue);
+        (config, vec![issue])
+    }
+
+    pub fn save(&self, profile: &config::Profile) {
+        profile.save_json(LAYOUTS_FILE, self);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&NamedLayout> {
+        self.named.iter().find(|layout| layout.name == name)
+    }
+
+    // Saving under an existing name replaces that layout
+    pub fn set(&mut self, layout: NamedLayout) {
+        match self.named.iter_mut().find(|l| l.name == layout.name) {
+            Some(existing) => *existing = layout,
+            None => self.named.push(layout),
+        }
+    }
+}
+
+// Drops the tiles and windows whose widget doesn't parse, ex: one renamed since the file was saved,
+// describing each in `skipped`
+fn prune_unknown_widgets(layouts: &mut serde_json::Value, skipped: &mut Vec<String>) {
+    let Some(fields) = layouts.as_object_mut() else {
+        return;
+    };
+    prune_layout(fields, "last", "last_detached", skipped);
+    if let Some(named) = fields.get_mut("named").and_then(|n| n.as_array_mut()) {
+        for layout in named.iter_mut().filter_map(|l| l.as_object_mut()) {
+            prune_layout(layout, "root", "detached", skipped);
+        }
+    }
+}
+
+fn prune_layout(
+    layout: &mut serde_json::Map<String, serde_json::Value>,
+    root_key: &str,
+    detached_key: &str,
+    skipped: &mut Vec<String>,
+) {
+    if let Some(root) = layout.get_mut(root_key)
+        && !prune_node(root, skipped)
+    {
+        *root = serde_json::Value::Null;
+    }
+    if let Some(detached) = layout.get_mut(detached_key).and_then(|d| d.as_array_mut()) {
+        detached.retain(|window| {
+            window
+                .get("widget")
+                .is_some_and(|widget| widget_parses(widget, skipped))
+        });
+    }
+}
+
+// False when the node is a pane to drop. Containers drop the shares of dropped children too
+fn prune_node(node: &mut serde_json::Value, skipped: &mut Vec<String>) -> bool {
+    let Some((kind, body)) = node.as_object_mut().and_then(|n| n.iter_mut().next()) else {
+        return true;
+    };
+    if kind == "Pane" {
+        return widget_parses(body, skipped);
+    }
+    let Some(body) = body.as_object_mut() else {
+        return true;
+    };
+    let keep: Vec<bool> = match body.get_mut("children").and_then(|c| c.as_array_mut()) {
+        Some(children) => children
+            .iter_mut()
+            .map(|child| prune_node(child, skipped))
+            .collect(),
+        None => return true,
+    };
+    if keep.iter().all(|k| *k) {
+        return true;
+    }
+
+    for key in ["children", "shares"] {
+        if let Some(list) = body.get_mut(key).and_then(|l| l.as_array_mut())
+            && list.len() == keep.len()
+        {
+            let mut keep = keep.iter();
+            list.retain(|_| *keep.next().unwrap_or(&true));
+        }
+    }
+    if let Some(active) = body.get("active").and_then(|a| a.as_u64()) {
+        let active = active as usize;
+        let active = keep
+            .get(active)
+            .is_some_and(|k| *k)
+            .then(|| keep[..active].iter().filter(|k| **k).count());
+        body.insert("active".into(), serde_json::json!(active));
+    }
+    true
+}
+
+fn widget_parses(widget: &serde_json::Value, skipped: &mut Vec<String>) -> bool {
+    match serde_json::from_value::<widgets::WidgetConfig>(widget.clone()) {
+        Ok(_) => true,
+        Err(e) => {
+            let name = widget.get("widget").and_then(|w| w.as_str()).unwrap_or("?");
+            skipped.push(format!("{} ({})", name, e));
+            false
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pruned(mut json: serde_json::Value) -> (serde_json::Value, usize) {
+        let mut skipped = Vec::new();
+        prune_unknown_widgets(&mut json, &mut skipped);
+        (json, skipped.len())
+    }
+
+    #[test]
+    fn drops_only_unknown_tiles() {
+        let (json, skipped) = pruned(serde_json::json!({
+            "last": { "Horizontal": {
+                "children": [
+                    { "Pane": { "widget": "Removed" } },
+                    { "Tabs": {
+                        "children": [
+                            { "Pane": { "widget": "Removed" } },
+                            { "Pane": { "widget": "ViewerList" } }
+                        ],
+                        "active": 1
+                    } }
+                ],
+                "shares": [1.0, 2.0]
+            } },
+            "last_detached": [{ "widget": { "widget": "Removed" } }, { "widget": { "widget": "ViewerList" } }]
+        }));
+        assert_eq!(skipped, 3);
+        let config: LayoutsConfig = serde_json::from_value(json).unwrap();
+        assert_eq!(config.last_detached.len(), 1);
+        let Some(LayoutNode::Horizontal { children, shares }) = config.last else {
+            panic!("root should stay a horizontal container");
+        };
+        assert_eq!(shares, [2.0]);
+        assert!(matches!(
+            &children[..],
+            [LayoutNode::Tabs { children, active: Some(0) }] if children.len() == 1
+        ));
+    }
+
+    #[test]
+    fn drops_unknown_root_pane() {
+        let (json, skipped) = pruned(serde_json::json!({
+            "named": [{ "name": "bench", "root": { "Pane": { "widget": "Removed" } } }]
+        }));
+        assert_eq!(skipped, 1);
+        let config: LayoutsConfig = serde_json::from_value(json).unwrap();
+        assert!(
+            config
+                .get("bench")
+                .is_some_and(|layout| layout.root.is_none())
+        );
+    }
+}
@@ -1,16 +1,27 @@
 mod action;
+mod alarms;
 mod app;
 mod assets;
+mod bootloader;
 mod can;
+mod config;
 mod connection;
 mod daq_log_parse;
+mod dbc;
+mod derived;
 mod formatter;
 mod frozen;
+mod layout;
 mod messages;
+mod rest_bus;
+mod sequence;
 mod settings;
 mod shortcuts;
 mod theme;
+mod transmit_sets;
+mod uds;
 mod ui;
+mod units;
 mod util;
 mod widgets;
 mod workspace;
@@ -20,13 +31,36 @@ fn main() -> eframe::Result<()> {
         .filter_level(log::LevelFilter::Info)
         .init();
 
+    // Headless DBC check for CI, ex: `daqapp-rust --lint-dbc VCAN.dbc charger.dbc --bus1 MCAN.dbc`
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().is_some_and(|a| a == "--lint-dbc") {
+        std::process::exit(dbc::lint::run_cli(&args[1..]));
+    }
+
     let (can_to_ui_tx, can_to_ui_rx) = std::sync::mpsc::channel::<messages::MsgFromCan>();
     let (ui_to_can_tx, ui_to_can_rx) = std::sync::mpsc::channel::<messages::MsgFromUi>();
 
-    let settings = settings::Settings::load();
-    if let Some(ref dbc_path) = settings.dbc_path {
+    // `--profile <name>` for this run only, otherwise the profile used last
+    let profile = match args.iter().position(|a| a == "--profile") {
+        Some(i) => match args.get(i + 1) {
+            Some(name) if config::is_valid_file_name(name) => config::Profile::new(name),
+            _ => {
+                eprintln!("--profile needs a profile name");
+                std::process::exit(2);
+            }
+        },
+        None => config::Profile::new(config::ProfilesConfig::load().active),
+    };
+    log::info!(
+        "Using profile {} in {}",
+        profile.name,
+        config::config_dir().display()
+    );
+
+    let (settings, settings_error) = settings::Settings::load(&profile);
+    if !settings.dbc_paths.is_empty() {
         ui_to_can_tx
-            .send(messages::MsgFromUi::DbcSelected(dbc_path.clone()))
+            .send(messages::MsgFromUi::DbcSelected(settings.dbc_paths.clone()))
             .expect("Failed to send DBC path to CAN thread");
     }
     if let Some(ref selected_source) = settings.selected_source {
@@ -53,6 +87,8 @@ fn main() -> eframe::Result<()> {
                 can_to_ui_rx,
                 ui_to_can_tx,
                 settings,
+                settings_error,
+                profile,
                 cc,
             )))
         }),
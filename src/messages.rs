@@ -1,22 +1,57 @@
-use crate::connection;
+use crate::{bootloader, can, connection, derived, rest_bus, sequence, uds};
 
 pub enum MsgFromUi {
-    DbcSelected(std::path::PathBuf),
+    DbcSelected(Vec<std::path::PathBuf>),
     Connect(connection::ConnectionSource),
+    Disconnect,
     AddSendMessage(AddSendMessage),
-    DeleteSendMessage { msg_id: u32 },
+    // New values for a sender, its period and statistics carry on
+    UpdateSendBytes {
+        handle: can::scheduler::SendHandle,
+        msg_bytes: Vec<u8>,
+        auto_fields: Vec<can::auto_fields::AutoField>,
+    },
+    DeleteSendMessage {
+        handle: can::scheduler::SendHandle,
+    },
+    DerivedSignalsChanged(Vec<derived::DerivedSignalDef>),
+    // Starting a sequence that is already running restarts it
+    StartSequence(sequence::Sequence),
+    StopSequence {
+        name: String,
+    },
+    // Replaces the owner's reply rules, an empty list removes them
+    SetReplyRules {
+        owner: rest_bus::RulesOwner,
+        rules: Vec<rest_bus::ReplyRule>,
+    },
+    // Only one flash runs at a time, starting another aborts the current one
+    StartFlash(bootloader::flasher::FlashJob),
+    AbortFlash,
+    // Reads the firmware info of every node, replacing a query still running
+    QueryNodes(Vec<bootloader::BootNode>),
+    // The response comes back as `MsgFromCan::UdsResponse` with the same client
+    UdsRequest {
+        client: uds::ClientId,
+        ecu: uds::Ecu,
+        payload: Vec<u8>,
+    },
 }
 
 pub enum MsgFromCan {
     ParsedMessage(ParsedMessage),
     UnparsedMessage(UnparsedMessage),
+    DerivedSignal(DerivedSignal),
     Disconnection,
     ConnectionSuccessful,
     ConnectionFailed(String),
     MessageSent {
+        handle: can::scheduler::SendHandle,
         msg_id: u32,
         timestamp: chrono::DateTime<chrono::Local>,
+        // None once the sender is done
         amount_left: Option<SendAmount>,
+        stats: can::scheduler::PeriodStats,
     },
     BusLoad {
         load_1s: f32,
@@ -24,9 +59,26 @@ pub enum MsgFromCan {
         load_10s: f32,
         load_30s: f32,
     },
+    SequenceStatus {
+        name: String,
+        status: sequence::SequenceStatus,
+    },
+    FlashProgress {
+        node: String,
+        progress: bootloader::flasher::FlashProgress,
+    },
+    NodeInfo {
+        node: String,
+        info: Result<bootloader::inventory::NodeInfo, String>,
+    },
+    UdsResponse {
+        client: uds::ClientId,
+        result: Result<Vec<u8>, String>,
+    },
+    IsoTpMessage(IsoTpMessage),
 }
 
-#[derive(Clone, Copy, Debug)]
+#[derive(Clone, Copy, Debug, serde::Serialize, serde::Deserialize)]
 pub enum SendAmount {
     Infinite { period: usize },
     Once,
@@ -51,6 +103,14 @@ impl SendAmount {
         }
     }
 
+    pub fn period_ms(&self) -> usize {
+        match self {
+            SendAmount::Infinite { period } => *period,
+            SendAmount::Once => 0,
+            SendAmount::Finite { period, .. } => *period,
+        }
+    }
+
     pub fn display(&self) -> String {
         match self {
             SendAmount::Infinite { period } => format!("∞ ({} ms period)", period),
@@ -62,11 +122,16 @@ impl SendAmount {
     }
 }
 
+#[derive(Clone)]
 pub struct AddSendMessage {
+    // Adding with a handle that is already sending replaces that sender
+    pub handle: can::scheduler::SendHandle,
     pub amount: SendAmount,
     pub msg_id: u32, // without the extended ID flag
     pub is_msg_id_extended: bool,
     pub msg_bytes: Vec<u8>,
+    // Counters and checksums written into `msg_bytes` at every send
+    pub auto_fields: Vec<can::auto_fields::AutoField>,
 }
 
 #[derive(Clone)]
@@ -76,9 +141,25 @@ pub struct ParsedMessage {
     pub decoded: can_decode::DecodedMessage,
 }
 
+#[derive(Clone)]
+pub struct DerivedSignal {
+    pub timestamp: chrono::DateTime<chrono::Local>,
+    pub name: String,
+    pub value: f64,
+    pub unit: String,
+}
+
 #[derive(Clone)]
 pub struct UnparsedMessage {
     pub timestamp: chrono::DateTime<chrono::Local>,
     pub raw_bytes: Vec<u8>,
     pub msg_id: u32, // without the extended ID flag
 }
+
+// A payload reassembled from the ISO-TP frames of a diagnostic link
+#[derive(Clone)]
+pub struct IsoTpMessage {
+    pub timestamp: chrono::DateTime<chrono::Local>,
+    pub msg_id: u32, // without the extended ID flag
+    pub payload: Vec<u8>,
+}
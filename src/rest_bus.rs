@@ -0,0 +1,42 @@
+use crate::{can, config, messages};
+
+pub const REST_BUS_CONFIG_FILE: &str = "rest_bus.json";
+
+/// "When `when` arrives, send `reply` once", by message name. The reply must be a message of one
+/// of the simulated nodes so its current values are known.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq)]
+pub struct RuleDef {
+    pub when: String,
+    pub reply: String,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Default)]
+pub struct RestBusConfig {
+    // Transmitter node names from the DBC
+    #[serde(default)]
+    pub nodes: Vec<String>,
+    #[serde(default)]
+    pub rules: Vec<RuleDef>,
+}
+
+impl RestBusConfig {
+    pub fn load() -> Self {
+        config::load_json(REST_BUS_CONFIG_FILE).unwrap_or_default()
+    }
+
+    pub fn save(&self) {
+        config::save_json(REST_BUS_CONFIG_FILE, self);
+    }
+}
+
+/// A rule resolved to IDs and bytes, matched by the CAN thread on every received frame so replies
+/// don't wait for the next UI frame.
+#[derive(Clone)]
+pub struct ReplyRule {
+    pub trigger_msg_id_with_ext_flag: u32,
+    // Sent with `SendAmount::Once`
+    pub reply: messages::AddSendMessage,
+}
+
+// Rules are grouped by the widget that set them, so each rest bus only replaces its own
+pub type RulesOwner = can::scheduler::SendHandle;
@@ -0,0 +1,400 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use crate::{alarms, can, config, dbc, messages, util};
+
+pub const SEQUENCES_CONFIG_FILE: &str = "sequences.json";
+
+// Ex: precharge handshake
+// { "type": "send", "msg": "vcu_status", "signals": { "precharge_req": 1 }, "period_ms": 100 },
+// { "type": "wait_until", "msg": "pack_status", "signal": "precharge_done", "op": "==",
+//   "value": 1, "timeout_ms": 5000 },
+// { "type": "ramp", "msg": "vcu_status", "signal": "torque_req", "from": 0, "to": 20,
+//   "duration_ms": 2000 }
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Step {
+    // Starts sending a DBC message, or changes the signals of one the sequence already sends.
+    // Without a period it is sent once.
+    Send {
+        msg: String,
+        #[serde(default)]
+        signals: BTreeMap<String, f64>,
+        #[serde(default)]
+        period_ms: Option<usize>,
+    },
+    Stop {
+        msg: String,
+    },
+    Wait {
+        ms: u64,
+    },
+    // Linearly moves one signal of a periodically sent message
+    Ramp {
+        msg: String,
+        signal: String,
+        from: f64,
+        to: f64,
+        duration_ms: u64,
+    },
+    WaitUntil {
+        msg: String,
+        signal: String,
+        op: alarms::Comparison,
+        value: f64,
+        timeout_ms: u64,
+    },
+    // Jumps back to `to_step` (0 is the first step), forever when `times` is not set
+    Loop {
+        #[serde(default)]
+        to_step: usize,
+        #[serde(default)]
+        times: Option<usize>,
+    },
+}
+
+impl Step {
+    pub fn describe(&self) -> String {
+        match self {
+            Step::Send {
+                msg,
+                signals,
+                period_ms,
+            } => {
+                let signals = signals
+                    .iter()
+                    .map(|(name, value)| format!("{}={}", name, value))
+                    .collect::<Vec<_>>()
+                    .join(", ");
+                match period_ms {
+                    Some(period) => format!("send {} [{}] every {} ms", msg, signals, period),
+                    None => format!("send {} [{}] once", msg, signals),
+                }
+            }
+            Step::Stop { msg } => format!("stop {}", msg),
+            Step::Wait { ms } => format!("wait {} ms", ms),
+            Step::Ramp {
+                msg,
+                signal,
+                from,
+                to,
+                duration_ms,
+            } => format!(
+                "ramp {}.{} from {} to {} over {} ms",
+                msg, signal, from, to, duration_ms
+            ),
+            Step::WaitUntil {
+                msg,
+                signal,
+                op,
+                value,
+                timeout_ms,
+            } => format!(
+                "wait until {}.{} {} {} (timeout {} ms)",
+                msg,
+                signal,
+                op.symbol(),
+                value,
+                timeout_ms
+            ),
+            Step::Loop { to_step, times } => match times {
+                Some(times) => format!("loop to step {} ({} times)", to_step + 1, times),
+                None => format!("loop to step {} forever", to_step + 1),
+            },
+        }
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+pub struct Sequence {
+    pub name: String,
+    #[serde(default)]
+    pub steps: Vec<Step>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Default)]
+pub struct SequenceConfig {
+    #[serde(default)]
+    pub sequences: Vec<Sequence>,
+}
+
+impl SequenceConfig {
+    pub fn load() -> Self {
+        config::load_json(SEQUENCES_CONFIG_FILE).unwrap_or_default()
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum SequenceStatus {
+    Running { step: usize },
+    // Messages started with a period keep being sent until the sequence is stopped
+    Finished,
+    Failed(String),
+}
+
+// What the runner wants the send scheduler to do
+pub enum SendCommand {
+    Add(messages::AddSendMessage),
+    // Changes the payload without restarting the period, for ramps
+    UpdateBytes {
+        handle: can::scheduler::SendHandle,
+        msg_bytes: Vec<u8>,
+    },
+    Delete {
+        handle: can::scheduler::SendHandle,
+    },
+}
+
+struct ActiveMsg {
+    handle: can::scheduler::SendHandle,
+    msg_id_with_ext_flag: u32,
+    values: HashMap<String, f64>,
+    msg_bytes: Vec<u8>,
+    is_periodic: bool,
+}
+
+/// Runs one sequence inside the CAN thread, so waits and ramps don't depend on the UI frame rate.
+pub struct SequenceRunner {
+    pub sequence: Sequence,
+    pub status: SequenceStatus,
+    step_started: std::time::Instant,
+    loops_done: HashMap<usize, usize>,
+    active_msgs: HashMap<String, ActiveMsg>,
+    // Latest received values of the signals `WaitUntil` steps look at
+    watched_msgs: HashSet<String>,
+    latest: HashMap<(String, String), f64>,
+}
+
+impl SequenceRunner {
+    pub fn new(sequence: Sequence) -> Self {
+        let watched_msgs = sequence
+            .steps
+            .iter()
+            .filter_map(|step| match step {
+                Step::WaitUntil { msg, .. } => Some(msg.clone()),
+                _ => None,
+            })
+            .collect();
+        let status = if sequence.steps.is_empty() {
+            SequenceStatus::Finished
+        } else {
+            SequenceStatus::Running { step: 0 }
+        };
+        Self {
+            sequence,
+            status,
+            step_started: std::time::Instant::now(),
+            loops_done: HashMap::new(),
+            active_msgs: HashMap::new(),
+            watched_msgs,
+            latest: HashMap::new(),
+        }
+    }
+
+    pub fn on_decoded(&mut self, decoded: &can_decode::DecodedMessage) {
+        if !self.watched_msgs.contains(&decoded.name) {
+            return;
+        }
+        for (sig_name, signal) in decoded.signals.iter() {
+            self.latest.insert(
+                (decoded.name.clone(), sig_name.clone()),
+                signal.value.physical,
+            );
+        }
+    }
+
+    /// Advances through every step that can complete right now. Returns the commands for the
+    /// send scheduler and whether the status changed.
+    pub fn tick(&mut self, parser: Option<&dbc::merged::MergedParser>) -> (Vec<SendCommand>, bool) {
+        let mut commands = Vec::new();
+        let status_before = self.status.clone();
+
+        while let SequenceStatus::Running { step } = self.status {
+            match self.run_step(step, parser, &mut commands) {
+                Ok(true) => self.goto(step + 1),
+                Ok(false) => break,
+                Err(e) => {
+                    self.status = SequenceStatus::Failed(format!("step {}: {}", step + 1, e));
+                    commands.extend(self.stop_commands());
+                }
+            }
+        }
+
+        let changed = self.status != status_before;
+        (commands, changed)
+    }
+
+    /// Stops everything the sequence is still sending.
+    pub fn stop_commands(&mut self) -> Vec<SendCommand> {
+        self.active_msgs
+            .drain()
+            .map(|(_, msg)| SendCommand::Delete { handle: msg.handle })
+            .collect()
+    }
+
+    fn goto(&mut self, step: usize) {
+        self.step_started = std::time::Instant::now();
+        self.status = if step < self.sequence.steps.len() {
+            SequenceStatus::Running { step }
+        } else {
+            SequenceStatus::Finished
+        };
+    }
+
+    // Ok(true) when the step is done and the next one can start
+    fn run_step(
+        &mut self,
+        step_idx: usize,
+        parser: Option<&dbc::merged::MergedParser>,
+        commands: &mut Vec<SendCommand>,
+    ) -> Result<bool, String> {
+        let elapsed_ms = self.step_started.elapsed().as_millis() as u64;
+
+        match self.sequence.steps[step_idx].clone() {
+            Step::Send {
+                msg,
+                signals,
+                period_ms,
+            } => {
+                let parser = parser.ok_or("no DBC loaded")?;
+                if !self.active_msgs.contains_key(&msg) {
+                    let msg_def = parser
+                        .msg_defs()
+                        .into_iter()
+                        .find(|m| m.name == msg)
+                        .ok_or_else(|| format!("{} is not in the DBC", msg))?;
+                    self.active_msgs.insert(
+                        msg.clone(),
+                        ActiveMsg {
+                            handle: can::scheduler::new_handle(),
+                            msg_id_with_ext_flag: util::can::can_dbc_to_u32_with_extid_flag(
+                                &msg_def.id,
+                            ),
+                            values: msg_def
+                                .signals
+                                .iter()
+                                .map(|s| (s.name.clone(), 0.0))
+                                .collect(),
+                            msg_bytes: Vec::new(),
+                            is_periodic: false,
+                        },
+                    );
+                }
+
+                let active = self.active_msgs.get_mut(&msg).expect("inserted above");
+                for (name, value) in signals {
+                    if !active.values.contains_key(&name) {
+                        return Err(format!("{} has no signal {}", msg, name));
+                    }
+                    active.values.insert(name, value);
+                }
+                active.msg_bytes = parser
+                    .encode_msg(active.msg_id_with_ext_flag, &active.values)
+                    .ok_or_else(|| format!("failed to encode {}", msg))?;
+                active.is_periodic = period_ms.is_some();
+
+                commands.push(SendCommand::Add(messages::AddSendMessage {
+                    handle: active.handle,
+                    amount: match period_ms {
+                        Some(period) => messages::SendAmount::Infinite { period },
+                        None => messages::SendAmount::Once,
+                    },
+                    msg_id: active.msg_id_with_ext_flag & util::can::EXTENDED_ID_MASK,
+                    is_msg_id_extended: active.msg_id_with_ext_flag & util::can::EXTENDED_ID_FLAG
+                        != 0,
+                    msg_bytes: active.msg_bytes.clone(),
+                    auto_fields: Vec::new(),
+                }));
+                Ok(true)
+            }
+            Step::Stop { msg } => {
+                if let Some(active) = self.active_msgs.remove(&msg) {
+                    commands.push(SendCommand::Delete {
+                        handle: active.handle,
+                    });
+                }
+                Ok(true)
+            }
+            Step::Wait { ms } => Ok(elapsed_ms >= ms),
+            Step::Ramp {
+                msg,
+                signal,
+                from,
+                to,
+                duration_ms,
+            } => {
+                let parser = parser.ok_or("no DBC loaded")?;
+                let active = self
+                    .active_msgs
+                    .get_mut(&msg)
+                    .filter(|a| a.is_periodic)
+                    .ok_or_else(|| format!("{} must be sent with a period before a ramp", msg))?;
+                if !active.values.contains_key(&signal) {
+                    return Err(format!("{} has no signal {}", msg, signal));
+                }
+
+                let progress = if duration_ms == 0 {
+                    1.0
+                } else {
+                    (elapsed_ms as f64 / duration_ms as f64).min(1.0)
+                };
+                active
+                    .values
+                    .insert(signal.clone(), from + (to - from) * progress);
+                let msg_bytes = parser
+                    .encode_msg(active.msg_id_with_ext_flag, &active.values)
+                    .ok_or_else(|| format!("failed to encode {}", msg))?;
+                if msg_bytes != active.msg_bytes {
+                    active.msg_bytes = msg_bytes.clone();
+                    commands.push(SendCommand::UpdateBytes {
+                        handle: active.handle,
+                        msg_bytes,
+                    });
+                }
+                Ok(progress >= 1.0)
+            }
+            Step::WaitUntil {
+                msg,
+                signal,
+                op,
+                value,
+                timeout_ms,
+            } => {
+                let current = self.latest.get(&(msg.clone(), signal.clone()));
+                if current.is_some_and(|&current| op.eval(current, value)) {
+                    return Ok(true);
+                }
+                if elapsed_ms >= timeout_ms {
+                    return Err(match current {
+                        Some(current) => format!(
+                            "timed out waiting for {}.{} {} {} (last {})",
+                            msg,
+                            signal,
+                            op.symbol(),
+                            value,
+                            current
+                        ),
+                        None => format!("timed out waiting for {}, it was never received", msg),
+                    });
+                }
+                Ok(false)
+            }
+            Step::Loop { to_step, times } => {
+                if to_step >= step_idx {
+                    return Err(format!(
+                        "can only loop back, step {} is not before it",
+                        to_step + 1
+                    ));
+                }
+                let done = self.loops_done.entry(step_idx).or_default();
+                if times.is_some_and(|times| *done >= times) {
+                    // Reset so an outer loop runs this one again
+                    *done = 0;
+                    return Ok(true);
+                }
+                *done += 1;
+                self.goto(to_step);
+                Ok(false)
+            }
+        }
+    }
+}
@@ -1,47 +1,158 @@
-use crate::{connection, theme};
+use crate::{config, connection, theme};
 
 pub const SETTINGS_PATH: &str = "settings.json";
+// Bumped whenever a field is renamed or changes meaning, with a step in `migrate` for it
+pub const SETTINGS_VERSION: u32 = 2;
 const DEFAULT_UDP_PORT: u16 = 5005;
 const DEFAULT_CAN_SPEED: connection::CanBusSpeed = connection::CanBusSpeed::Kbps500;
 
 #[derive(serde::Serialize, serde::Deserialize)]
+#[serde(default)]
 pub struct Settings {
-    pub dbc_path: Option<std::path::PathBuf>,
+    pub version: u32,
+    // Merged in order, the first file wins when two define the same ID
+    pub dbc_paths: Vec<std::path::PathBuf>,
     pub selected_source: Option<connection::ConnectionSource>,
     pub selected_speed: connection::CanBusSpeed,
     pub udp_port: u16,
-    pub theme: theme::ThemeSelection,
+    // A built-in theme or the file name of one in the themes directory
+    pub theme: String,
     pub pixels_per_point: Option<f32>,
 }
 
 impl Default for Settings {
     fn default() -> Self {
         Self {
-            dbc_path: None,
+            version: SETTINGS_VERSION,
+            dbc_paths: Vec::new(),
             selected_source: None,
             selected_speed: DEFAULT_CAN_SPEED,
             udp_port: DEFAULT_UDP_PORT,
-            theme: theme::ThemeSelection::Default,
+            theme: theme::DEFAULT_THEME.to_string(),
             pixels_per_point: None,
         }
     }
 }
 
+// Brings settings written by an older version up to `SETTINGS_VERSION`, one step at a time
+fn migrate(mut json: serde_json::Value) -> serde_json::Value {
+    // Files from before versioning are version 1
+    let version = json.get("version").and_then(|v| v.as_u64()).unwrap_or(1);
+    if version > SETTINGS_VERSION as u64 {
+        log::warn!(
+            "{} is from a newer version ({}), settings it doesn't know are dropped on save",
+            SETTINGS_PATH,
+            version
+        );
+        return json;
+    }
+    let Some(fields) = json.as_object_mut() else {
+        return json;
+    };
+
+    // 1 -> 2: a single `dbc_path` became `dbc_paths`
+    if version < 2
+        && let Some(dbc_path) = fields.remove("dbc_path")
+        && !dbc_path.is_null()
+    {
+        let has_paths = fields
+            .get("dbc_paths")
+            .and_then(|p| p.as_array())
+            .is_some_and(|p| !p.is_empty());
+        if !has_paths {
+            fields.insert("dbc_paths".into(), serde_json::Value::Array(vec![dbc_path]));
+        }
+    }
+    // 1 -> 2: a simulated source's single DBC path or null became a list
+    if version < 2
+        && let Some(simulated) = fields
+            .get_mut("selected_source")
+            .and_then(|source| source.get_mut("Simulated"))
+            .and_then(|simulated| simulated.as_array_mut())
+        && let Some(paths) = simulated.get_mut(1)
+        && !paths.is_array()
+    {
+        *paths = match paths.take() {
+            serde_json::Value::Null => serde_json::Value::Array(Vec::new()),
+            path => serde_json::Value::Array(vec![path]),
+        };
+    }
+
+    fields.insert("version".into(), SETTINGS_VERSION.into());
+    json
+}
+
 impl Settings {
-    pub fn load() -> Self {
-        if let Ok(json) = std::fs::read_to_string(SETTINGS_PATH) {
-            serde_json::from_str(&json).unwrap_or_default()
-        } else {
-            let default = Settings::default();
-            default.save();
-            default
+    /// Defaults when the file is missing. When it can't be parsed it is backed up before anything
+    /// overwrites it, and the error is returned to show to the user.
+    pub fn load(profile: &config::Profile) -> (Self, Option<String>) {
+        let parsed = profile.load_json_with(SETTINGS_PATH, |json| {
+            serde_json::from_str::<serde_json::Value>(json)
+                .map(migrate)
+                .and_then(serde_json::from_value)
+        });
+        match parsed {
+            Ok(Some(settings)) => (settings, None),
+            Ok(None) => {
+                let default = Settings::default();
+                default.save(profile);
+                (default, None)
+            }
+            Err(e) => (Settings::default(), Some(e)),
         }
     }
 
-    pub fn save(&self) {
-        // Expect okay. If it doesn't fail in testing, it shouldn't fail later.
-        let json = serde_json::to_string_pretty(self).expect("Failed to serialize settings");
-        std::fs::write(SETTINGS_PATH, json)
-            .unwrap_or_else(|e| log::error!("Failed to write {}: {}", SETTINGS_PATH, e));
+    pub fn save(&self, profile: &config::Profile) {
+        profile.save_json(SETTINGS_PATH, self);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn load(json: serde_json::Value) -> Settings {
+        serde_json::from_value(migrate(json)).unwrap()
+    }
+
+    #[test]
+    fn migrates_single_dbc_path() {
+        let settings = load(serde_json::json!({ "dbc_path": "car.dbc" }));
+        assert_eq!(settings.version, SETTINGS_VERSION);
+        assert_eq!(settings.dbc_paths, [std::path::PathBuf::from("car.dbc")]);
+    }
+
+    #[test]
+    fn migrates_simulated_source() {
+        let settings =
+            load(serde_json::json!({ "selected_source": { "Simulated": [true, "car.dbc"] } }));
+        assert_eq!(
+            settings.selected_source,
+            Some(connection::ConnectionSource::Simulated(
+                true,
+                vec!["car.dbc".into()]
+            ))
+        );
+        let settings =
+            load(serde_json::json!({ "selected_source": { "Simulated": [false, null] } }));
+        assert_eq!(
+            settings.selected_source,
+            Some(connection::ConnectionSource::Simulated(false, Vec::new()))
+        );
+    }
+
+    #[test]
+    fn keeps_current_simulated_source() {
+        let json = serde_json::json!({
+            "version": SETTINGS_VERSION,
+            "selected_source": { "Simulated": [true, ["a.dbc", "b.dbc"]] }
+        });
+        assert_eq!(
+            load(json).selected_source,
+            Some(connection::ConnectionSource::Simulated(
+                true,
+                vec!["a.dbc".into(), "b.dbc".into()]
+            ))
+        );
     }
 }
@@ -1,40 +1,223 @@
-use crate::action::AppAction;
+use crate::action::{AppAction, WidgetType};
+use crate::config;
 use eframe::egui;
 
-pub struct ShortcutHandler;
+pub const SHORTCUTS_FILE: &str = "shortcuts.json";
 
-impl ShortcutHandler {
-    pub fn check_shortcuts(ctx: &egui::Context) -> Vec<AppAction> {
-        let mut actions = Vec::new();
+/// What a shortcut can do, the subset of `AppAction` that makes sense without a target.
+#[derive(serde::Serialize, serde::Deserialize, Clone, PartialEq, Debug)]
+pub enum Command {
+    ToggleSidebar,
+    ToggleCommandPalette,
+    ToggleShortcutHelp,
+    CloseActiveWidget,
+    IncreaseScale,
+    DecreaseScale,
+    ToggleTheme,
+    LoadDbc,
+    Disconnect,
+    SpawnWidget(WidgetType),
+    LoadLayout(String),
+    // Act on the focused widget
+    TogglePause,
+    Clear,
+    // Pauses every viewer and scope, or resumes them all if they already are
+    ToggleFreezeAll,
+    AddMarker,
+}
 
-        // Get input state
-        let input = ctx.input(|i| i.clone());
+impl Command {
+    pub fn action(&self) -> AppAction {
+        match self {
+            Command::ToggleSidebar => AppAction::ToggleSidebar,
+            Command::ToggleCommandPalette => AppAction::ToggleCommandPalette,
+            Command::ToggleShortcutHelp => AppAction::ToggleShortcutHelp,
+            Command::CloseActiveWidget => AppAction::CloseActiveWidget,
+            Command::IncreaseScale => AppAction::IncreaseScale,
+            Command::DecreaseScale => AppAction::DecreaseScale,
+            Command::ToggleTheme => AppAction::ToggleTheme,
+            Command::LoadDbc => AppAction::SelectDbc,
+            Command::Disconnect => AppAction::Disconnect,
+            Command::SpawnWidget(widget_type) => AppAction::SpawnWidget(widget_type.clone()),
+            Command::LoadLayout(name) => AppAction::LoadLayout(name.clone()),
+            Command::TogglePause => AppAction::TogglePauseFocused,
+            Command::Clear => AppAction::ClearFocused,
+            Command::ToggleFreezeAll => AppAction::ToggleFreezeAll,
+            Command::AddMarker => AppAction::AddMarker,
+        }
+    }
 
-        // CMD+S = toggle sidebar
-        if input.modifiers.command_only() && input.key_pressed(egui::Key::S) {
-            actions.push(AppAction::ToggleSidebar);
+    pub fn describe(&self) -> String {
+        match self {
+            Command::ToggleSidebar => "Toggle sidebar".into(),
+            Command::ToggleCommandPalette => "Command palette".into(),
+            Command::ToggleShortcutHelp => "Show keyboard shortcuts".into(),
+            Command::CloseActiveWidget => "Close active widget".into(),
+            Command::IncreaseScale => "Zoom in".into(),
+            Command::DecreaseScale => "Zoom out".into(),
+            Command::ToggleTheme => "Next theme".into(),
+            Command::LoadDbc => "Load DBC".into(),
+            Command::Disconnect => "Disconnect".into(),
+            Command::SpawnWidget(widget_type) => format!("Add {:?}", widget_type),
+            Command::LoadLayout(name) => format!("Load layout {}", name),
+            Command::TogglePause => "Pause/resume the focused widget".into(),
+            Command::Clear => "Clear the focused widget".into(),
+            Command::ToggleFreezeAll => "Freeze/unfreeze all viewers and scopes".into(),
+            Command::AddMarker => "Drop an event marker".into(),
         }
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone)]
+pub struct BindingConfig {
+    // Ex: "Cmd+Shift+P". Cmd is Ctrl outside macOS, key names are egui's ("A", "F1", "Minus")
+    pub chord: String,
+    pub command: Command,
+}
 
-        // CMD+W = close window
-        if input.modifiers.command_only() && input.key_pressed(egui::Key::W) {
-            actions.push(AppAction::CloseActiveWidget);
+#[derive(serde::Serialize, serde::Deserialize)]
+pub struct KeymapConfig {
+    #[serde(default)]
+    pub bindings: Vec<BindingConfig>,
+}
+
+impl Default for KeymapConfig {
+    fn default() -> Self {
+        let bind = |chord: &str, command| BindingConfig {
+            chord: chord.into(),
+            command,
+        };
+        Self {
+            bindings: vec![
+                bind("Cmd+B", Command::ToggleSidebar),
+                bind("Cmd+W", Command::CloseActiveWidget),
+                bind("Cmd+P", Command::ToggleCommandPalette),
+                bind("Cmd+Equals", Command::IncreaseScale),
+                bind("Cmd+Minus", Command::DecreaseScale),
+                bind("F1", Command::ToggleShortcutHelp),
+                bind("Cmd+Period", Command::TogglePause),
+                bind("Cmd+K", Command::Clear),
+                bind("Cmd+Shift+Period", Command::ToggleFreezeAll),
+                bind("Cmd+M", Command::AddMarker),
+            ],
         }
+    }
+}
 
-        // CMD+P = command palette
-        if input.modifiers.command_only() && input.key_pressed(egui::Key::P) {
-            actions.push(AppAction::ToggleCommandPalette);
+impl KeymapConfig {
+    pub fn load() -> Self {
+        match config::load_json_with(SHORTCUTS_FILE, |json| serde_json::from_str(json)) {
+            Ok(Some(config)) => config,
+            // Written out so there is a file to edit
+            Ok(None) => {
+                let default = Self::default();
+                default.save();
+                default
+            }
+            Err(_) => Self::default(),
         }
+    }
 
-        // CMD+Plus = increase scale
-        if input.modifiers.command_only() && input.key_pressed(egui::Key::Equals) {
-            actions.push(AppAction::IncreaseScale);
+    pub fn save(&self) {
+        config::save_json(SHORTCUTS_FILE, self);
+    }
+}
+
+pub fn parse_chord(chord: &str) -> Result<egui::KeyboardShortcut, String> {
+    let mut modifiers = egui::Modifiers::NONE;
+    let mut key = None;
+    for part in chord.split('+').map(str::trim) {
+        match part.to_lowercase().as_str() {
+            "cmd" | "command" => modifiers = modifiers | egui::Modifiers::COMMAND,
+            "ctrl" | "control" => modifiers = modifiers | egui::Modifiers::CTRL,
+            "alt" | "option" => modifiers = modifiers | egui::Modifiers::ALT,
+            "shift" => modifiers = modifiers | egui::Modifiers::SHIFT,
+            _ if key.is_some() => return Err(format!("\"{}\" has more than one key", chord)),
+            _ => {
+                key = Some(
+                    egui::Key::from_name(part)
+                        .ok_or_else(|| format!("\"{}\": unknown key \"{}\"", chord, part))?,
+                )
+            }
         }
+    }
+    let key = key.ok_or_else(|| format!("\"{}\" has no key", chord))?;
+    Ok(egui::KeyboardShortcut::new(modifiers, key))
+}
+
+fn modifier_count(modifiers: egui::Modifiers) -> usize {
+    [
+        modifiers.alt,
+        modifiers.ctrl,
+        modifiers.shift,
+        modifiers.command || modifiers.mac_cmd,
+    ]
+    .into_iter()
+    .filter(|m| *m)
+    .count()
+}
+
+pub struct Binding {
+    pub shortcut: egui::KeyboardShortcut,
+    pub command: Command,
+}
 
-        // CMD+Minus = decrease scale
-        if input.modifiers.command_only() && input.key_pressed(egui::Key::Minus) {
-            actions.push(AppAction::DecreaseScale);
+pub struct Keymap {
+    // Most modifiers first, egui matches Cmd+P on Cmd+Shift+P too
+    pub bindings: Vec<Binding>,
+    // Chords that didn't parse, and chords bound twice where only the first binding is used
+    pub issues: Vec<String>,
+}
+
+impl Keymap {
+    pub fn load() -> Self {
+        let keymap_config = KeymapConfig::load();
+        let mut bindings: Vec<Binding> = Vec::new();
+        let mut issues = Vec::new();
+        for binding in keymap_config.bindings {
+            let shortcut = match parse_chord(&binding.chord) {
+                Ok(shortcut) => shortcut,
+                Err(e) => {
+                    issues.push(e);
+                    continue;
+                }
+            };
+            if let Some(existing) = bindings.iter().find(|b| b.shortcut == shortcut) {
+                issues.push(format!(
+                    "{} is bound to both \"{}\" and \"{}\", only the first is used",
+                    binding.chord,
+                    existing.command.describe(),
+                    binding.command.describe()
+                ));
+                continue;
+            }
+            bindings.push(Binding {
+                shortcut,
+                command: binding.command,
+            });
+        }
+        bindings.sort_by_key(|b| std::cmp::Reverse(modifier_count(b.shortcut.modifiers)));
+        for issue in &issues {
+            log::warn!("{}: {}", SHORTCUTS_FILE, issue);
         }
+        Self { bindings, issues }
+    }
+
+    pub fn check_shortcuts(&self, ctx: &egui::Context) -> Vec<AppAction> {
+        ctx.input_mut(|i| {
+            self.bindings
+                .iter()
+                .filter(|b| i.consume_shortcut(&b.shortcut))
+                .map(|b| b.command.action())
+                .collect()
+        })
+    }
 
-        actions
+    // For hints in the UI, ex: "Ctrl+P"
+    pub fn chord_for(&self, ctx: &egui::Context, command: &Command) -> Option<String> {
+        self.bindings
+            .iter()
+            .find(|b| b.command == *command)
+            .map(|b| ctx.format_shortcut(&b.shortcut))
     }
 }
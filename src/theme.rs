@@ -1,46 +1,12 @@
+use crate::config;
 use eframe::egui;
 
-const NORD_THEME_PATH: &str = "themes/nord.toml";
-const CATPPUCCIN_THEME_PATH: &str = "themes/catppuccin.toml";
+// Every *.toml in it is a theme, named after the file
+pub const THEMES_DIR: &str = "themes";
+pub const DEFAULT_THEME: &str = "Default";
+pub const SUNLIGHT_THEME: &str = "Sunlight";
 
-#[derive(Copy, Clone, serde::Serialize, serde::Deserialize, Debug)]
-pub enum ThemeSelection {
-    Default,
-    Nord,
-    Catppuccin,
-}
-
-impl ThemeSelection {
-    pub fn get_name(&self) -> &'static str {
-        match self {
-            ThemeSelection::Default => "Default",
-            ThemeSelection::Nord => "Nord",
-            ThemeSelection::Catppuccin => "Catppuccin",
-        }
-    }
-
-    pub fn get_style(&self) -> egui::Style {
-        match self {
-            ThemeSelection::Default => egui::Style::default(),
-            ThemeSelection::Nord => ThemeColors::load_from_file(NORD_THEME_PATH)
-                .map(|t| t.to_egui_style())
-                .unwrap_or_default(),
-            ThemeSelection::Catppuccin => ThemeColors::load_from_file(CATPPUCCIN_THEME_PATH)
-                .map(|t| t.to_egui_style())
-                .unwrap_or_default(),
-        }
-    }
-
-    pub fn next(&self) -> Self {
-        match self {
-            ThemeSelection::Default => ThemeSelection::Nord,
-            ThemeSelection::Nord => ThemeSelection::Catppuccin,
-            ThemeSelection::Catppuccin => ThemeSelection::Default,
-        }
-    }
-}
-
-#[derive(Debug, serde::Deserialize, Clone)]
+#[derive(Debug, Clone, serde::Serialize, serde::Deserialize)]
 pub struct ThemeColors {
     pub background: String,
     pub panel_bg: String,
@@ -49,9 +15,58 @@ pub struct ThemeColors {
     pub button: String,
     pub button_hover: String,
     pub button_text: String,
+    // Semantic colors — optional so old TOMLs without them don't break
+    #[serde(default)]
+    pub error: Option<String>,
+    #[serde(default)]
+    pub warning: Option<String>,
+    #[serde(default)]
+    pub success: Option<String>,
+    #[serde(default)]
+    pub info: Option<String>,
+    // Starts from egui's light visuals, so what isn't set here is light too
+    #[serde(default)]
+    pub light: bool,
+}
+
+impl Default for ThemeColors {
+    fn default() -> Self {
+        Self {
+            background: "#1e1e1e".to_string(),
+            panel_bg: "#252526".to_string(),
+            text: "#d4d4d4".to_string(),
+            accent: "#3c3c3c".to_string(),
+            button: "#3c3c3c".to_string(),
+            button_hover: "#505050".to_string(),
+            button_text: "#cccccc".to_string(),
+            error: Some("#f44747".to_string()),
+            warning: Some("#ce9178".to_string()),
+            success: Some("#6a9955".to_string()),
+            info: Some("#569cd6".to_string()),
+            light: false,
+        }
+    }
 }
 
 impl ThemeColors {
+    // Black on white with saturated status colors, for trackside laptops in direct sun
+    pub fn sunlight() -> Self {
+        Self {
+            background: "#ffffff".to_string(),
+            panel_bg: "#f0f0f0".to_string(),
+            text: "#000000".to_string(),
+            accent: "#003cb4".to_string(),
+            button: "#d0d0d0".to_string(),
+            button_hover: "#a8a8a8".to_string(),
+            button_text: "#000000".to_string(),
+            error: Some("#c00000".to_string()),
+            warning: Some("#a04600".to_string()),
+            success: Some("#006000".to_string()),
+            info: Some("#003cb4".to_string()),
+            light: true,
+        }
+    }
+
     pub fn parse_hex(hex: &str) -> egui::Color32 {
         let hex = hex.trim_start_matches('#');
 
@@ -73,14 +88,67 @@ impl ThemeColors {
         }
     }
 
+    // Inverse of `parse_hex`, the alpha is only written when it isn't opaque
+    pub fn to_hex(color: egui::Color32) -> String {
+        let [r, g, b, a] = color.to_srgba_unmultiplied();
+        if a == 255 {
+            format!("#{:02x}{:02x}{:02x}", r, g, b)
+        } else {
+            format!("#{:02x}{:02x}{:02x}{:02x}", r, g, b, a)
+        }
+    }
+
+    // Convenience getters — use fallback colors if the TOML field was absent
+    pub fn error_color(&self) -> egui::Color32 {
+        self.error
+            .as_deref()
+            .map(Self::parse_hex)
+            .unwrap_or(egui::Color32::from_rgb(224, 108, 117))
+    }
+
+    pub fn warning_color(&self) -> egui::Color32 {
+        self.warning
+            .as_deref()
+            .map(Self::parse_hex)
+            .unwrap_or(egui::Color32::from_rgb(209, 154, 102))
+    }
+
+    pub fn success_color(&self) -> egui::Color32 {
+        self.success
+            .as_deref()
+            .map(Self::parse_hex)
+            .unwrap_or(egui::Color32::from_rgb(152, 195, 121))
+    }
+
+    pub fn info_color(&self) -> egui::Color32 {
+        self.info
+            .as_deref()
+            .map(Self::parse_hex)
+            .unwrap_or(egui::Color32::from_rgb(97, 175, 239))
+    }
+
+    pub fn text_color(&self) -> egui::Color32 {
+        Self::parse_hex(&self.text)
+    }
+
+    pub fn panel_color(&self) -> egui::Color32 {
+        Self::parse_hex(&self.panel_bg)
+    }
+
+    pub fn accent_color(&self) -> egui::Color32 {
+        Self::parse_hex(&self.accent)
+    }
+
     pub fn to_egui_style(&self) -> egui::Style {
         let mut style = egui::Style::default();
+        if self.light {
+            style.visuals = egui::Visuals::light();
+        }
 
         let bg = Self::parse_hex(&self.background);
         let panel = Self::parse_hex(&self.panel_bg);
         let text = Self::parse_hex(&self.text);
         let accent = Self::parse_hex(&self.accent);
-
         let button = Self::parse_hex(&self.button);
         let button_hover = Self::parse_hex(&self.button_hover);
         let button_text = Self::parse_hex(&self.button_text);
@@ -91,6 +159,12 @@ impl ThemeColors {
         style.visuals.faint_bg_color = panel;
         style.visuals.override_text_color = Some(text);
         style.visuals.selection.bg_fill = bg;
+        if self.error.is_some() {
+            style.visuals.error_fg_color = self.error_color();
+        }
+        if self.warning.is_some() {
+            style.visuals.warn_fg_color = self.warning_color();
+        }
 
         // --- Global button visuals ---
         style.visuals.widgets.inactive.weak_bg_fill = button;
@@ -109,9 +183,115 @@ impl ThemeColors {
         style
     }
 
-    pub fn load_from_file(path: &str) -> Option<Self> {
-        std::fs::read_to_string(path)
-            .ok()
-            .and_then(|data| toml::from_str::<Self>(&data).ok())
+    pub fn load_from_file(path: &std::path::Path) -> Result<Self, String> {
+        let data = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        toml::from_str::<Self>(&data).map_err(|e| e.to_string())
+    }
+
+    // Into the config directory's themes, where it overrides a theme of the same name
+    pub fn save(&self, name: &str) -> std::io::Result<()> {
+        let data = toml::to_string_pretty(self).map_err(std::io::Error::other)?;
+        config::write(&format!("{}/{}.toml", THEMES_DIR, name), data)
+    }
+}
+
+pub struct Theme {
+    pub name: String,
+    // None for egui's own style
+    pub colors: Option<ThemeColors>,
+}
+
+impl Theme {
+    pub fn style(&self) -> egui::Style {
+        self.colors
+            .as_ref()
+            .map(|c| c.to_egui_style())
+            .unwrap_or_default()
+    }
+
+    // What widgets draw with, the dark defaults for egui's own style
+    pub fn colors(&self) -> ThemeColors {
+        self.colors.clone().unwrap_or_default()
+    }
+}
+
+/// The built-in themes followed by every theme file found, in the order `ToggleTheme` cycles.
+pub struct Themes {
+    pub themes: Vec<Theme>,
+    // Files that didn't parse
+    pub issues: Vec<String>,
+}
+
+impl Themes {
+    pub fn load() -> Self {
+        let mut themes = vec![
+            Theme {
+                name: DEFAULT_THEME.to_string(),
+                colors: None,
+            },
+            Theme {
+                name: SUNLIGHT_THEME.to_string(),
+                colors: Some(ThemeColors::sunlight()),
+            },
+        ];
+        let mut issues = Vec::new();
+        let mut found: Vec<Theme> = Vec::new();
+        // The config directory first so a saved theme wins over the repo's copy
+        for dir in config::search_dirs() {
+            let Ok(entries) = std::fs::read_dir(dir.join(THEMES_DIR)) else {
+                continue;
+            };
+            for path in entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+            {
+                if path.extension().is_none_or(|ext| ext != "toml") {
+                    continue;
+                }
+                let Some(name) = path.file_stem().and_then(|stem| stem.to_str()) else {
+                    continue;
+                };
+                let taken = |t: &Theme| t.name.eq_ignore_ascii_case(name);
+                if themes.iter().any(taken) || found.iter().any(taken) {
+                    continue;
+                }
+                match ThemeColors::load_from_file(&path) {
+                    Ok(colors) => found.push(Theme {
+                        name: name.to_string(),
+                        colors: Some(colors),
+                    }),
+                    Err(e) => issues.push(format!("{}: {}", path.display(), e)),
+                }
+            }
+        }
+        found.sort_by_key(|t| t.name.to_lowercase());
+        themes.extend(found);
+        for issue in &issues {
+            log::warn!("Failed to load theme {}", issue);
+        }
+        Self { themes, issues }
+    }
+
+    // Case-insensitive so settings from when themes were an enum ("Nord") find nord.toml
+    pub fn find(&self, name: &str) -> Option<&Theme> {
+        self.themes
+            .iter()
+            .find(|t| t.name.eq_ignore_ascii_case(name))
+    }
+
+    pub fn get(&self, name: &str) -> &Theme {
+        self.find(name).unwrap_or_else(|| {
+            log::warn!("No theme named {}, using {}", name, DEFAULT_THEME);
+            &self.themes[0]
+        })
+    }
+
+    pub fn next_after(&self, name: &str) -> &Theme {
+        let i = self
+            .themes
+            .iter()
+            .position(|t| t.name.eq_ignore_ascii_case(name))
+            .map_or(0, |i| (i + 1) % self.themes.len());
+        &self.themes[i]
     }
 }
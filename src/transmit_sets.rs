@@ -0,0 +1,84 @@
+use std::collections::BTreeMap;
+
+use crate::{can, config, messages};
+
+pub const TRANSMIT_SETS_FILE: &str = "transmit_sets.json";
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+pub struct SavedMessage {
+    pub msg_name: String,
+    pub msg_id: u32, // without the extended ID flag
+    #[serde(default)]
+    pub is_msg_id_extended: bool,
+    // Raw frames are sent as saved, DBC messages are looked up by name and re-encoded from
+    // `signal_values` so a set keeps working after the DBC changes
+    #[serde(default)]
+    pub is_raw: bool,
+    #[serde(default)]
+    pub msg_bytes: Vec<u8>,
+    #[serde(default)]
+    pub signal_values: BTreeMap<String, f64>,
+    // Signals the CAN thread fills in as rolling counters or checksums
+    #[serde(default)]
+    pub auto_signals: BTreeMap<String, can::auto_fields::AutoKind>,
+    #[serde(default)]
+    pub adjustable_values_enabled: bool,
+    pub amount: messages::SendAmount,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+pub struct TransmitSet {
+    pub name: String,
+    // Started when the app opens, in the first Send UI of the restored layout or a new one
+    #[serde(default)]
+    pub start_on_launch: bool,
+    #[serde(default)]
+    pub messages: Vec<SavedMessage>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Default)]
+pub struct TransmitSetsConfig {
+    #[serde(default)]
+    pub sets: Vec<TransmitSet>,
+}
+
+impl TransmitSetsConfig {
+    // An unparsable file is backed up and the error returned, see `config::load_json`
+    pub fn load() -> Result<Self, String> {
+        config::load_json(TRANSMIT_SETS_FILE)
+    }
+
+    pub fn save(&self) {
+        config::save_json(TRANSMIT_SETS_FILE, self);
+    }
+
+    /// Applies a change to the sets on disk, saves them and returns the result. Each Send UI keeps
+    /// a copy to show, changing that copy would overwrite what another one saved since. Nothing is
+    /// saved when the file can't be read, so the sets in it aren't replaced.
+    pub fn update(change: impl FnOnce(&mut Self)) -> Result<Self, String> {
+        let mut config = Self::load()?;
+        change(&mut config);
+        config.save();
+        Ok(config)
+    }
+
+    // Replaces the set with the same name, if any
+    pub fn upsert(&mut self, set: TransmitSet) {
+        match self.sets.iter_mut().find(|s| s.name == set.name) {
+            Some(existing) => *existing = set,
+            None => self.sets.push(set),
+        }
+    }
+}
+
+// Single sets can be exported to share them between laptops
+pub fn export_set(set: &TransmitSet, path: &std::path::Path) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(set).map_err(|e| e.to_string())?;
+    std::fs::write(path, json).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+pub fn import_set(path: &std::path::Path) -> Result<TransmitSet, String> {
+    let json = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    serde_json::from_str(&json).map_err(|e| format!("Failed to parse {}: {}", path.display(), e))
+}
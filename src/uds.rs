@@ -0,0 +1,502 @@
+use std::time::Duration;
+
+use crate::can::isotp;
+use crate::{can, config, messages};
+
+pub const DIAGNOSTICS_CONFIG_FILE: &str = "diagnostics.json";
+
+// P2, how long a server has to answer
+const P2_TIMEOUT: Duration = Duration::from_millis(1000);
+// P2*, how long it has after answering "response pending"
+const P2_STAR_TIMEOUT: Duration = Duration::from_secs(5);
+
+const SID_NEGATIVE_RESPONSE: u8 = 0x7F;
+const POSITIVE_RESPONSE_OFFSET: u8 = 0x40;
+const NRC_RESPONSE_PENDING: u8 = 0x78;
+
+pub const SID_SESSION_CONTROL: u8 = 0x10;
+pub const SID_ECU_RESET: u8 = 0x11;
+pub const SID_CLEAR_DTC: u8 = 0x14;
+pub const SID_READ_DTC: u8 = 0x19;
+pub const SID_READ_DID: u8 = 0x22;
+pub const SID_TESTER_PRESENT: u8 = 0x3E;
+
+const READ_DTC_BY_STATUS_MASK: u8 = 0x02;
+
+/// An ECU's diagnostic link, requests go out on `tx_id` and responses come back on `rx_id`.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq)]
+pub struct Ecu {
+    pub name: String,
+    pub tx_id: u32, // without the extended ID flag
+    pub rx_id: u32,
+    #[serde(default)]
+    pub is_extended: bool,
+}
+
+impl Ecu {
+    pub fn tx_id_with_ext_flag(&self) -> u32 {
+        with_ext_flag(self.tx_id, self.is_extended)
+    }
+
+    pub fn rx_id_with_ext_flag(&self) -> u32 {
+        with_ext_flag(self.rx_id, self.is_extended)
+    }
+}
+
+fn with_ext_flag(msg_id: u32, is_extended: bool) -> u32 {
+    if is_extended {
+        msg_id | crate::util::can::EXTENDED_ID_FLAG
+    } else {
+        msg_id
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Default)]
+pub struct DiagnosticsConfig {
+    #[serde(default)]
+    pub ecus: Vec<Ecu>,
+}
+
+impl DiagnosticsConfig {
+    pub fn load() -> Self {
+        config::load_json(DIAGNOSTICS_CONFIG_FILE).unwrap_or_default()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Service {
+    SessionControl { session: u8 },
+    EcuReset { reset_type: u8 },
+    ReadDataByIdentifier { did: u16 },
+    ReadDtcByStatusMask { mask: u8 },
+    // 0xFFFFFF clears every group
+    ClearDtc { group: u32 },
+}
+
+impl Service {
+    pub fn request(&self) -> Vec<u8> {
+        match *self {
+            Service::SessionControl { session } => vec![SID_SESSION_CONTROL, session],
+            Service::EcuReset { reset_type } => vec![SID_ECU_RESET, reset_type],
+            Service::ReadDataByIdentifier { did } => {
+                vec![SID_READ_DID, (did >> 8) as u8, did as u8]
+            }
+            Service::ReadDtcByStatusMask { mask } => {
+                vec![SID_READ_DTC, READ_DTC_BY_STATUS_MASK, mask]
+            }
+            Service::ClearDtc { group } => vec![
+                SID_CLEAR_DTC,
+                (group >> 16) as u8,
+                (group >> 8) as u8,
+                group as u8,
+            ],
+        }
+    }
+
+    /// Lines describing a positive response, or the negative response code / why it's malformed.
+    pub fn describe_response(&self, response: &[u8]) -> Result<Vec<String>, String> {
+        let sid = self.request()[0];
+        match response {
+            [SID_NEGATIVE_RESPONSE, _, nrc, ..] => {
+                return Err(format!(
+                    "negative response: {} (0x{:02X})",
+                    nrc_name(*nrc),
+                    nrc
+                ));
+            }
+            [first, ..] if *first == sid + POSITIVE_RESPONSE_OFFSET => {}
+            _ => return Err(format!("unexpected response {}", hex(response))),
+        }
+        let body = &response[1..];
+
+        match *self {
+            Service::SessionControl { .. } => match body {
+                [session, p2_hi, p2_lo, p2s_hi, p2s_lo, ..] => Ok(vec![
+                    format!(
+                        "Session 0x{:02X} ({}) active",
+                        session,
+                        session_name(*session)
+                    ),
+                    format!(
+                        "P2 {} ms, P2* {} ms",
+                        u16::from_be_bytes([*p2_hi, *p2_lo]),
+                        u16::from_be_bytes([*p2s_hi, *p2s_lo]) as u32 * 10
+                    ),
+                ]),
+                [session, ..] => Ok(vec![format!(
+                    "Session 0x{:02X} ({}) active",
+                    session,
+                    session_name(*session)
+                )]),
+                [] => Err("session control response without a session".to_string()),
+            },
+            Service::EcuReset { .. } => match body {
+                [reset_type, ..] => Ok(vec![format!(
+                    "Reset 0x{:02X} ({}) accepted",
+                    reset_type,
+                    reset_name(*reset_type)
+                )]),
+                [] => Err("reset response without a reset type".to_string()),
+            },
+            Service::ReadDataByIdentifier { did } => {
+                if body.len() < 2 || u16::from_be_bytes([body[0], body[1]]) != did {
+                    return Err(format!("response for another DID: {}", hex(body)));
+                }
+                let data = &body[2..];
+                let mut lines = vec![format!("DID 0x{:04X}: {}", did, hex(data))];
+                let printable = !data.is_empty()
+                    && data
+                        .iter()
+                        .all(|b| b.is_ascii_graphic() || *b == b' ' || *b == 0);
+                if printable {
+                    let text: String = data
+                        .iter()
+                        .filter(|b| **b != 0)
+                        .map(|b| *b as char)
+                        .collect();
+                    lines.push(format!("\"{}\"", text));
+                }
+                Ok(lines)
+            }
+            Service::ReadDtcByStatusMask { .. } => {
+                // Sub-function echo and the status bits the ECU supports, then 4 bytes per DTC
+                let [_, _availability, records @ ..] = body else {
+                    return Err("DTC response too short".to_string());
+                };
+                let dtcs: Vec<String> = records
+                    .chunks_exact(4)
+                    .map(|r| {
+                        let code = ((r[0] as u32) << 16) | ((r[1] as u32) << 8) | r[2] as u32;
+                        format!(
+                            "{} status 0x{:02X} ({})",
+                            dtc_display(code),
+                            r[3],
+                            dtc_status_display(r[3])
+                        )
+                    })
+                    .collect();
+                if dtcs.is_empty() {
+                    Ok(vec!["No DTCs".to_string()])
+                } else {
+                    Ok(dtcs)
+                }
+            }
+            Service::ClearDtc { .. } => Ok(vec!["DTCs cleared".to_string()]),
+        }
+    }
+}
+
+pub fn session_name(session: u8) -> &'static str {
+    match session {
+        0x01 => "default",
+        0x02 => "programming",
+        0x03 => "extended",
+        _ => "vendor specific",
+    }
+}
+
+pub fn reset_name(reset_type: u8) -> &'static str {
+    match reset_type {
+        0x01 => "hard",
+        0x02 => "key off/on",
+        0x03 => "soft",
+        _ => "vendor specific",
+    }
+}
+
+pub fn nrc_name(nrc: u8) -> &'static str {
+    match nrc {
+        0x10 => "general reject",
+        0x11 => "service not supported",
+        0x12 => "sub-function not supported",
+        0x13 => "incorrect message length or format",
+        0x14 => "response too long",
+        0x21 => "busy, repeat request",
+        0x22 => "conditions not correct",
+        0x24 => "request sequence error",
+        0x31 => "request out of range",
+        0x33 => "security access denied",
+        0x35 => "invalid key",
+        0x36 => "exceeded number of attempts",
+        0x37 => "required time delay not expired",
+        0x72 => "general programming failure",
+        0x78 => "response pending",
+        0x7E => "sub-function not supported in active session",
+        0x7F => "service not supported in active session",
+        _ => "unknown",
+    }
+}
+
+fn service_name(sid: u8) -> Option<&'static str> {
+    Some(match sid {
+        SID_SESSION_CONTROL => "DiagnosticSessionControl",
+        SID_ECU_RESET => "ECUReset",
+        SID_CLEAR_DTC => "ClearDiagnosticInformation",
+        SID_READ_DTC => "ReadDTCInformation",
+        SID_READ_DID => "ReadDataByIdentifier",
+        0x27 => "SecurityAccess",
+        0x28 => "CommunicationControl",
+        0x2E => "WriteDataByIdentifier",
+        0x31 => "RoutineControl",
+        0x34 => "RequestDownload",
+        0x36 => "TransferData",
+        0x37 => "RequestTransferExit",
+        SID_TESTER_PRESENT => "TesterPresent",
+        _ => return None,
+    })
+}
+
+/// Names a reassembled payload for the viewers, ex: "ReadDataByIdentifier response".
+pub fn describe_payload(payload: &[u8]) -> String {
+    match payload {
+        [SID_NEGATIVE_RESPONSE, sid, nrc, ..] => format!(
+            "{} negative response ({})",
+            service_name(*sid).unwrap_or("UDS"),
+            nrc_name(*nrc)
+        ),
+        [sid, ..] if *sid >= POSITIVE_RESPONSE_OFFSET => {
+            match service_name(sid - POSITIVE_RESPONSE_OFFSET) {
+                Some(name) => format!("{} response", name),
+                None => "ISO-TP payload".to_string(),
+            }
+        }
+        [sid, ..] => match service_name(*sid) {
+            Some(name) => format!("{} request", name),
+            None => "ISO-TP payload".to_string(),
+        },
+        [] => "ISO-TP payload".to_string(),
+    }
+}
+
+// "P0123", the top two bits pick the system letter
+fn dtc_display(code: u32) -> String {
+    let letter = ['P', 'C', 'B', 'U'][(code >> 22) as usize & 0x3];
+    format!(
+        "{}{:X}{:03X}-{:02X}",
+        letter,
+        (code >> 20) & 0x3,
+        (code >> 8) & 0xFFF,
+        code & 0xFF
+    )
+}
+
+fn dtc_status_display(status: u8) -> String {
+    const BITS: [&str; 8] = [
+        "test failed",
+        "failed this cycle",
+        "pending",
+        "confirmed",
+        "not completed since clear",
+        "failed since clear",
+        "not completed this cycle",
+        "warning indicator",
+    ];
+    let set: Vec<&str> = BITS
+        .iter()
+        .enumerate()
+        .filter(|(bit, _)| status & (1 << bit) != 0)
+        .map(|(_, name)| *name)
+        .collect();
+    if set.is_empty() {
+        "none".to_string()
+    } else {
+        set.join(", ")
+    }
+}
+
+pub fn hex(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|b| format!("{:02X}", b))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Periodic TesterPresent that keeps a non-default session open, without asking for a response.
+pub fn tester_present(ecu: &Ecu, handle: can::scheduler::SendHandle) -> messages::AddSendMessage {
+    let (_, frame) = isotp::Segmenter::new(vec![SID_TESTER_PRESENT, 0x80])
+        .expect("TesterPresent fits a single frame");
+    messages::AddSendMessage {
+        handle,
+        amount: messages::SendAmount::Infinite { period: 2000 },
+        msg_id: ecu.tx_id,
+        is_msg_id_extended: ecu.is_extended,
+        msg_bytes: frame,
+        auto_fields: Vec::new(),
+    }
+}
+
+// Identifies the widget a response goes back to
+pub type ClientId = can::scheduler::SendHandle;
+
+/// One UDS request running in the CAN thread, following "response pending" answers.
+pub struct Request {
+    pub client: ClientId,
+    ecu: Ecu,
+    exchange: isotp::Exchange,
+}
+
+impl Request {
+    /// Returns the request and its first frame, or why it can't be sent.
+    pub fn new(
+        client: ClientId,
+        ecu: Ecu,
+        payload: Vec<u8>,
+    ) -> Result<(Self, Vec<messages::AddSendMessage>), String> {
+        let (exchange, frames) =
+            isotp::Exchange::new(ecu.rx_id_with_ext_flag(), payload, P2_TIMEOUT)?;
+        let request = Self {
+            client,
+            ecu,
+            exchange,
+        };
+        let frames = request.to_send_messages(frames);
+        Ok((request, frames))
+    }
+
+    pub fn on_frame(
+        &mut self,
+        msg_id_with_ext_flag: u32,
+        data: &[u8],
+    ) -> Vec<messages::AddSendMessage> {
+        let frames = self.exchange.on_frame(msg_id_with_ext_flag, data);
+        self.to_send_messages(frames)
+    }
+
+    /// Returns the frames to send, and the response once there is one.
+    pub fn tick(
+        &mut self,
+    ) -> (
+        Vec<messages::AddSendMessage>,
+        Option<Result<Vec<u8>, String>>,
+    ) {
+        let frames = self.exchange.tick();
+        let outcome = match self.exchange.take_outcome() {
+            Some(Ok(response)) if is_response_pending(&response) => {
+                self.exchange.wait_again(P2_STAR_TIMEOUT);
+                None
+            }
+            outcome => outcome,
+        };
+        (self.to_send_messages(frames), outcome)
+    }
+
+    pub fn time_until_next(&self) -> Option<Duration> {
+        self.exchange.time_until_next()
+    }
+
+    fn to_send_messages(&self, frames: Vec<Vec<u8>>) -> Vec<messages::AddSendMessage> {
+        frames
+            .into_iter()
+            .map(|msg_bytes| messages::AddSendMessage {
+                handle: can::scheduler::new_handle(),
+                amount: messages::SendAmount::Once,
+                msg_id: self.ecu.tx_id,
+                is_msg_id_extended: self.ecu.is_extended,
+                msg_bytes,
+                auto_fields: Vec::new(),
+            })
+            .collect()
+    }
+}
+
+fn is_response_pending(response: &[u8]) -> bool {
+    matches!(
+        response,
+        [SID_NEGATIVE_RESPONSE, _, NRC_RESPONSE_PENDING, ..]
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builds_requests() {
+        assert_eq!(
+            Service::ReadDataByIdentifier { did: 0xF190 }.request(),
+            [0x22, 0xF1, 0x90]
+        );
+        assert_eq!(
+            Service::ClearDtc { group: 0xFFFFFF }.request(),
+            [0x14, 0xFF, 0xFF, 0xFF]
+        );
+        assert_eq!(
+            Service::ReadDtcByStatusMask { mask: 0x08 }.request(),
+            [0x19, 0x02, 0x08]
+        );
+    }
+
+    #[test]
+    fn describes_negative_responses() {
+        let service = Service::EcuReset { reset_type: 0x01 };
+        assert_eq!(
+            service.describe_response(&[0x7F, 0x11, 0x22]),
+            Err("negative response: conditions not correct (0x22)".to_string())
+        );
+        assert_eq!(
+            service.describe_response(&[0x7F, 0x11, 0x99]),
+            Err("negative response: unknown (0x99)".to_string())
+        );
+        assert_eq!(
+            describe_payload(&[0x7F, 0x22, 0x31]),
+            "ReadDataByIdentifier negative response (request out of range)"
+        );
+    }
+
+    #[test]
+    fn response_pending_is_only_nrc_0x78() {
+        assert!(is_response_pending(&[0x7F, 0x22, 0x78]));
+        assert!(!is_response_pending(&[0x7F, 0x22, 0x31]));
+        assert!(!is_response_pending(&[0x62, 0x7F, 0x78]));
+    }
+
+    #[test]
+    fn rejects_responses_to_other_services() {
+        let service = Service::SessionControl { session: 0x03 };
+        assert_eq!(
+            service.describe_response(&[0x51, 0x01]),
+            Err("unexpected response 51 01".to_string())
+        );
+        assert!(service.describe_response(&[]).is_err());
+    }
+
+    #[test]
+    fn describes_positive_responses() {
+        let session = Service::SessionControl { session: 0x03 };
+        assert_eq!(
+            session.describe_response(&[0x50, 0x03, 0x00, 0x32, 0x01, 0xF4]),
+            Ok(vec![
+                "Session 0x03 (extended) active".to_string(),
+                "P2 50 ms, P2* 5000 ms".to_string(),
+            ])
+        );
+
+        let did = Service::ReadDataByIdentifier { did: 0xF190 };
+        assert_eq!(
+            did.describe_response(&[0x62, 0xF1, 0x90, b'P', b'E', b'R']),
+            Ok(vec![
+                "DID 0xF190: 50 45 52".to_string(),
+                "\"PER\"".to_string(),
+            ])
+        );
+        assert!(did.describe_response(&[0x62, 0xF1, 0x91, 0x00]).is_err());
+    }
+
+    #[test]
+    fn describes_dtcs() {
+        let service = Service::ReadDtcByStatusMask { mask: 0xFF };
+        assert_eq!(
+            service.describe_response(&[0x59, 0x02, 0xFF, 0x01, 0x23, 0x45, 0x09]),
+            Ok(vec![
+                "P0123-45 status 0x09 (test failed, confirmed)".to_string()
+            ])
+        );
+        assert_eq!(
+            service.describe_response(&[0x59, 0x02, 0xFF]),
+            Ok(vec!["No DTCs".to_string()])
+        );
+        // Top two bits pick the letter
+        assert_eq!(dtc_display(0xC10000), "U0100-00");
+    }
+}
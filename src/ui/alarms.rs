@@ -0,0 +1,277 @@
+use crate::alarms;
+use eframe::egui;
+
+pub struct AlarmPanel {
+    rules_window_open: bool,
+    draft: alarms::AlarmRule,
+    draft_group_by: String,
+}
+
+impl AlarmPanel {
+    pub fn new() -> Self {
+        Self {
+            rules_window_open: false,
+            draft: Self::empty_draft(),
+            draft_group_by: String::new(),
+        }
+    }
+
+    fn empty_draft() -> alarms::AlarmRule {
+        alarms::AlarmRule {
+            name: String::new(),
+            rule: String::new(),
+            severity: alarms::Severity::Warning,
+            group_by: Vec::new(),
+            enabled: true,
+        }
+    }
+
+    // Always shown at the bottom of the window so a fired alarm is visible from any tab
+    pub fn show(&mut self, ctx: &egui::Context, engine: &mut alarms::AlarmEngine) {
+        let unacked = engine.unacknowledged_count();
+        egui::TopBottomPanel::bottom("alarm_panel")
+            .resizable(!engine.entries.is_empty())
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    let header = if engine.entries.is_empty() {
+                        egui::RichText::new("🔔 No alarms").weak()
+                    } else if unacked > 0 {
+                        egui::RichText::new(format!(
+                            "🔔 {} alarm(s), {} unacknowledged",
+                            engine.entries.len(),
+                            unacked
+                        ))
+                        .strong()
+                        .color(ui.visuals().error_fg_color)
+                    } else {
+                        egui::RichText::new(format!("🔔 {} alarm(s)", engine.entries.len()))
+                            .strong()
+                            .color(ui.visuals().warn_fg_color)
+                    };
+                    ui.label(header);
+
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        if ui.button("⚙ Rules").clicked() {
+                            self.rules_window_open = !self.rules_window_open;
+                        }
+                        let sound_label = if engine.config.sound_enabled {
+                            "🔊"
+                        } else {
+                            "🔇"
+                        };
+                        if ui
+                            .button(sound_label)
+                            .on_hover_text("Toggle alarm sound")
+                            .clicked()
+                        {
+                            engine.config.sound_enabled = !engine.config.sound_enabled;
+                            engine.config.save();
+                        }
+                        if ui
+                            .add_enabled(
+                                engine
+                                    .entries
+                                    .iter()
+                                    .any(|e| e.state == alarms::AlarmState::Returned),
+                                egui::Button::new("Clear returned"),
+                            )
+                            .clicked()
+                        {
+                            engine.clear_returned();
+                        }
+                        if ui
+                            .add_enabled(unacked > 0, egui::Button::new("Acknowledge all"))
+                            .clicked()
+                        {
+                            engine.acknowledge_all();
+                        }
+                    });
+                });
+
+                if engine.entries.is_empty() {
+                    return;
+                }
+
+                ui.separator();
+                let mut to_ack = None;
+                egui::ScrollArea::vertical()
+                    .max_height(150.0)
+                    .show(ui, |ui| {
+                        for (idx, entry) in engine.entries.iter().enumerate() {
+                            ui.horizontal(|ui| {
+                                let (state_text, color) = match entry.state {
+                                    alarms::AlarmState::Active => {
+                                        ("ACTIVE", ui.visuals().error_fg_color)
+                                    }
+                                    alarms::AlarmState::Acknowledged => {
+                                        ("ACKED", ui.visuals().warn_fg_color)
+                                    }
+                                    alarms::AlarmState::Returned => {
+                                        ("RETURNED", ui.visuals().weak_text_color())
+                                    }
+                                };
+                                ui.label(
+                                    egui::RichText::new(state_text)
+                                        .monospace()
+                                        .strong()
+                                        .color(color),
+                                );
+                                if entry.severity == alarms::Severity::Critical {
+                                    ui.label(
+                                        egui::RichText::new("CRITICAL")
+                                            .monospace()
+                                            .color(ui.visuals().error_fg_color),
+                                    );
+                                }
+                                ui.label(
+                                    egui::RichText::new(
+                                        entry.fired_at.format("%H:%M:%S").to_string(),
+                                    )
+                                    .italics()
+                                    .color(ui.visuals().weak_text_color()),
+                                );
+                                ui.label(egui::RichText::new(&entry.rule_name).strong());
+                                ui.label(&entry.detail);
+                                ui.label(format!("now {:.3}", entry.last_value));
+                                ui.with_layout(
+                                    egui::Layout::right_to_left(egui::Align::Center),
+                                    |ui| {
+                                        if entry.state != alarms::AlarmState::Acknowledged
+                                            && ui.small_button("Acknowledge").clicked()
+                                        {
+                                            to_ack = Some(idx);
+                                        }
+                                    },
+                                );
+                            });
+                        }
+                    });
+                if let Some(idx) = to_ack {
+                    engine.acknowledge(idx);
+                }
+            });
+
+        self.rules_window(ctx, engine);
+    }
+
+    fn rules_window(&mut self, ctx: &egui::Context, engine: &mut alarms::AlarmEngine) {
+        let mut open = self.rules_window_open;
+        let mut changed = false;
+        egui::Window::new("Alarm Rules")
+            .open(&mut open)
+            .default_width(500.0)
+            .show(ctx, |ui| {
+                ui.label(
+                    egui::RichText::new(
+                        "Syntax: <msg>.<signal> <op> <value> [for <n> ms|s]  (globs allowed, op is one of < <= > >= == !=)",
+                    )
+                    .weak(),
+                );
+                ui.separator();
+
+                let mut to_delete = None;
+                for idx in 0..engine.config.rules.len() {
+                    let error = engine.rule_error(idx).map(|e| e.to_string());
+                    let rule = &mut engine.config.rules[idx];
+                    ui.horizontal(|ui| {
+                        changed |= ui.checkbox(&mut rule.enabled, "").changed();
+                        ui.label(egui::RichText::new(&rule.name).strong());
+                        ui.label(egui::RichText::new(&rule.rule).monospace());
+                        if !rule.group_by.is_empty() {
+                            ui.label(
+                                egui::RichText::new(format!("per {}", rule.group_by.join(", ")))
+                                    .weak(),
+                            );
+                        }
+                        if rule.severity == alarms::Severity::Critical {
+                            ui.label(
+                                egui::RichText::new("critical").color(ui.visuals().error_fg_color),
+                            );
+                        }
+                        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                            if ui.small_button("🗑").clicked() {
+                                to_delete = Some(idx);
+                            }
+                        });
+                    });
+                    if let Some(error) = error {
+                        ui.label(
+                            egui::RichText::new(format!("Invalid rule: {}", error))
+                                .color(ui.visuals().error_fg_color),
+                        );
+                    }
+                }
+                if let Some(idx) = to_delete {
+                    engine.config.rules.remove(idx);
+                    changed = true;
+                }
+
+                ui.separator();
+                ui.label(egui::RichText::new("New rule").strong());
+                egui::Grid::new("new_alarm_rule_grid")
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        ui.label("Name:");
+                        ui.text_edit_singleline(&mut self.draft.name);
+                        ui.end_row();
+                        ui.label("Rule:");
+                        ui.add(
+                            egui::TextEdit::singleline(&mut self.draft.rule)
+                                .hint_text("cell_telemetry.voltage < 3.0 for 500 ms"),
+                        );
+                        ui.end_row();
+                        ui.label("Group by:");
+                        ui.add(
+                            egui::TextEdit::singleline(&mut self.draft_group_by)
+                                .hint_text("module_num, cell_num"),
+                        );
+                        ui.end_row();
+                        ui.label("Severity:");
+                        ui.horizontal(|ui| {
+                            ui.selectable_value(
+                                &mut self.draft.severity,
+                                alarms::Severity::Warning,
+                                "Warning",
+                            );
+                            ui.selectable_value(
+                                &mut self.draft.severity,
+                                alarms::Severity::Critical,
+                                "Critical",
+                            );
+                        });
+                        ui.end_row();
+                    });
+
+                let draft_error = alarms::Condition::parse(&self.draft.rule).err();
+                if let Some(error) = &draft_error
+                    && !self.draft.rule.is_empty()
+                {
+                    ui.label(egui::RichText::new(error).color(ui.visuals().error_fg_color));
+                }
+                if ui
+                    .add_enabled(draft_error.is_none(), egui::Button::new("Add rule"))
+                    .clicked()
+                {
+                    let mut rule = std::mem::replace(&mut self.draft, Self::empty_draft());
+                    if rule.name.trim().is_empty() {
+                        rule.name = rule.rule.clone();
+                    }
+                    rule.group_by = self
+                        .draft_group_by
+                        .split(',')
+                        .map(|s| s.trim().to_string())
+                        .filter(|s| !s.is_empty())
+                        .collect();
+                    self.draft_group_by.clear();
+                    engine.config.rules.push(rule);
+                    changed = true;
+                }
+            });
+        self.rules_window_open = open;
+
+        if changed {
+            engine.recompile();
+            engine.config.save();
+        }
+    }
+}
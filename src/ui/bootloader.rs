@@ -1,23 +1,287 @@
+use crate::{bootloader, config, messages};
 use eframe::egui;
 
 pub struct Bootloader {
     pub title: String,
+    ui_to_can_tx: std::sync::mpsc::Sender<messages::MsgFromUi>,
+    config: bootloader::BootloaderConfig,
+    selected_node: Option<usize>,
+    image: Option<bootloader::image::Image>,
+    error: Option<String>,
+    // Node being flashed from this widget and its last progress report
+    flashing: Option<(String, bootloader::flasher::FlashProgress)>,
+}
+
+impl Drop for Bootloader {
+    fn drop(&mut self) {
+        // Don't leave a flash running that nothing reports on
+        if self.is_flashing()
+            && let Err(e) = self.ui_to_can_tx.send(messages::MsgFromUi::AbortFlash)
+        {
+            log::error!("Failed to send AbortFlash: {}", e);
+        }
+    }
 }
 
 impl Bootloader {
-    pub fn new(instance_num: usize) -> Self {
+    pub fn new(
+        instance_num: usize,
+        ui_to_can_tx: std::sync::mpsc::Sender<messages::MsgFromUi>,
+    ) -> Self {
+        let config = bootloader::BootloaderConfig::load();
         Self {
             title: format!("Bootloader #{}", instance_num),
+            ui_to_can_tx,
+            selected_node: (!config.nodes.is_empty()).then_some(0),
+            config,
+            image: None,
+            error: None,
+            flashing: None,
         }
     }
 
+    fn is_flashing(&self) -> bool {
+        self.flashing
+            .as_ref()
+            .is_some_and(|(_, progress)| !progress.is_finished())
+    }
+
+    fn pick_image(&mut self) {
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter("Firmware images", &["bin", "hex"])
+            .pick_file()
+        else {
+            return;
+        };
+        match bootloader::image::Image::load(&path) {
+            Ok(image) => {
+                self.image = Some(image);
+                self.error = None;
+            }
+            Err(e) => {
+                log::error!("{}", e);
+                self.error = Some(e);
+            }
+        }
+    }
+
+    fn start_flash(&mut self) {
+        let (Some(node), Some(image)) = (
+            self.selected_node
+                .and_then(|idx| self.config.nodes.get(idx)),
+            &self.image,
+        ) else {
+            return;
+        };
+
+        // Catch images linked for another node (or for the bootloader itself) before erasing
+        if let (Some(image_address), Some(app_address)) = (image.base_address, node.app_address)
+            && image_address != app_address
+        {
+            self.error = Some(format!(
+                "The image starts at 0x{:08X} but {} runs its app from 0x{:08X}",
+                image_address, node.name, app_address
+            ));
+            return;
+        }
+
+        self.error = None;
+        self.flashing = Some((
+            node.name.clone(),
+            bootloader::flasher::FlashProgress::WaitingForNode,
+        ));
+        self.ui_to_can_tx
+            .send(messages::MsgFromUi::StartFlash(
+                bootloader::flasher::FlashJob {
+                    node: node.clone(),
+                    image: image.clone(),
+                },
+            ))
+            .expect("Failed to send StartFlash");
+    }
+
     pub fn show(&mut self, ui: &mut egui::Ui) -> egui_tiles::UiResponse {
-        ui.heading(format!("🔧 {}", self.title));
+        ui.horizontal(|ui| {
+            ui.heading(format!("🔧 {}", self.title));
+            if ui
+                .add_enabled(!self.is_flashing(), egui::Button::new("⟳ Reload"))
+                .on_hover_text(format!("Read {} again", bootloader::BOOTLOADER_CONFIG_FILE))
+                .clicked()
+            {
+                self.config = bootloader::BootloaderConfig::load();
+                self.selected_node = self
+                    .selected_node
+                    .filter(|idx| *idx < self.config.nodes.len())
+                    .or((!self.config.nodes.is_empty()).then_some(0));
+            }
+        });
         ui.separator();
-        ui.label("Bootloader interface will be implemented here");
-        ui.label("• Flash firmware updates");
-        ui.label("• Verify checksums");
-        ui.label("• Monitor progress");
+
+        if self.config.nodes.is_empty() {
+            ui.label(
+                egui::RichText::new(format!(
+                    "No nodes defined. Add them with their bootloader IDs to {} in {}.",
+                    bootloader::BOOTLOADER_CONFIG_FILE,
+                    config::config_dir().display()
+                ))
+                .italics()
+                .weak(),
+            );
+            return egui_tiles::UiResponse::None;
+        }
+
+        let is_flashing = self.is_flashing();
+        ui.add_enabled_ui(!is_flashing, |ui| {
+            egui::Grid::new((&self.title, "bootloader_grid"))
+                .num_columns(2)
+                .spacing([12.0, 6.0])
+                .show(ui, |ui| {
+                    ui.label("Node");
+                    let selected_text = self
+                        .selected_node
+                        .and_then(|idx| self.config.nodes.get(idx))
+                        .map_or(String::new(), |node| node.name.clone());
+                    egui::ComboBox::from_id_salt((&self.title, "node"))
+                        .selected_text(selected_text)
+                        .show_ui(ui, |ui| {
+                            for (idx, node) in self.config.nodes.iter().enumerate() {
+                                ui.selectable_value(
+                                    &mut self.selected_node,
+                                    Some(idx),
+                                    format!(
+                                        "{} (cmd 0x{:03X}, resp 0x{:03X})",
+                                        node.name, node.cmd_id, node.resp_id
+                                    ),
+                                );
+                            }
+                        });
+                    ui.end_row();
+
+                    ui.label("Image");
+                    ui.horizontal(|ui| {
+                        if ui.button("📂 Open").clicked() {
+                            self.pick_image();
+                        }
+                        if let Some(image) = &self.image {
+                            ui.label(
+                                image
+                                    .path
+                                    .file_name()
+                                    .map_or(String::new(), |n| n.to_string_lossy().to_string()),
+                            )
+                            .on_hover_text(image.path.display().to_string());
+                        }
+                    });
+                    ui.end_row();
+
+                    if let Some(image) = &self.image {
+                        ui.label("Size");
+                        ui.label(format!(
+                            "{} bytes, {} block(s)",
+                            image.bytes.len(),
+                            image.block_count()
+                        ));
+                        ui.end_row();
+
+                        ui.label("CRC-32");
+                        ui.label(egui::RichText::new(format!("0x{:08X}", image.crc)).monospace());
+                        ui.end_row();
+
+                        if let Some(address) = image.base_address {
+                            ui.label("Address");
+                            ui.label(egui::RichText::new(format!("0x{:08X}", address)).monospace());
+                            ui.end_row();
+                        }
+                    }
+                });
+        });
+
+        ui.add_space(6.0);
+        ui.horizontal(|ui| {
+            if is_flashing {
+                if ui.button("⏹ Abort").clicked() {
+                    self.ui_to_can_tx
+                        .send(messages::MsgFromUi::AbortFlash)
+                        .expect("Failed to send AbortFlash");
+                }
+            } else if ui
+                .add_enabled(
+                    self.selected_node.is_some() && self.image.is_some(),
+                    egui::Button::new("⚡ Flash"),
+                )
+                .on_disabled_hover_text("Pick a node and an image first")
+                .clicked()
+            {
+                self.start_flash();
+            }
+        });
+
+        if let Some(error) = &self.error {
+            ui.colored_label(ui.visuals().error_fg_color, error);
+        }
+
+        if let Some((node, progress)) = &self.flashing {
+            ui.separator();
+            match progress {
+                bootloader::flasher::FlashProgress::WaitingForNode => {
+                    ui.add(egui::Spinner::new());
+                    ui.label(format!(
+                        "Waiting for {} to enter its bootloader, power cycle it if it doesn't answer",
+                        node
+                    ));
+                }
+                bootloader::flasher::FlashProgress::Erasing => {
+                    ui.add(egui::Spinner::new());
+                    ui.label(format!("{} is erasing its flash", node));
+                }
+                bootloader::flasher::FlashProgress::Writing {
+                    bytes_done,
+                    bytes_total,
+                    retries,
+                } => {
+                    ui.add(
+                        egui::ProgressBar::new(*bytes_done as f32 / (*bytes_total).max(1) as f32)
+                            .show_percentage(),
+                    );
+                    let mut text = format!("{} / {} bytes", bytes_done, bytes_total);
+                    if *retries > 0 {
+                        text += &format!(", {} block(s) resent", retries);
+                    }
+                    ui.label(text);
+                }
+                bootloader::flasher::FlashProgress::Verifying => {
+                    ui.add(egui::ProgressBar::new(1.0).show_percentage());
+                    ui.label("Verifying the CRC");
+                }
+                bootloader::flasher::FlashProgress::Done { elapsed, retries } => {
+                    ui.colored_label(
+                        egui::Color32::GREEN,
+                        format!(
+                            "✔ Flashed {} in {:.1} s ({} retries), it is booting the new firmware",
+                            node,
+                            elapsed.as_secs_f64(),
+                            retries
+                        ),
+                    );
+                }
+                bootloader::flasher::FlashProgress::Failed(reason) => {
+                    ui.colored_label(
+                        ui.visuals().error_fg_color,
+                        format!("✖ Flashing {} failed: {}", node, reason),
+                    );
+                }
+            }
+        }
+
         egui_tiles::UiResponse::None
     }
+
+    pub fn handle_can_message(&mut self, msg: &messages::MsgFromCan) {
+        if let messages::MsgFromCan::FlashProgress { node, progress } = msg
+            && let Some((flashing_node, last_progress)) = &mut self.flashing
+            && flashing_node == node
+        {
+            *last_progress = progress.clone();
+        }
+    }
 }
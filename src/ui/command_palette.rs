@@ -1,6 +1,178 @@
-use crate::action;
+use crate::{action, app, connection, shortcuts, util};
 use eframe::egui;
 
+// Searching every signal of a big DBC can match thousands of entries
+const MAX_SHOWN: usize = 100;
+
+pub struct PaletteEntry {
+    pub label: String,
+    // Shown dimmed after the label, and searched too
+    pub detail: String,
+    pub action: action::AppAction,
+}
+
+impl PaletteEntry {
+    fn new(label: impl Into<String>, action: action::AppAction) -> Self {
+        Self {
+            label: label.into(),
+            detail: String::new(),
+            action,
+        }
+    }
+}
+
+// Everything the palette can do right now
+pub fn entries(app: &app::DAQApp) -> Vec<PaletteEntry> {
+    let mut entries = Vec::new();
+
+    if app.selected_source.is_some() {
+        entries.push(PaletteEntry::new(
+            "Disconnect",
+            action::AppAction::Disconnect,
+        ));
+    }
+    let dbc_paths = app
+        .parser
+        .as_ref()
+        .map(|p| p.dbc_paths.clone())
+        .unwrap_or_default();
+    let sources = app
+        .serial_ports
+        .iter()
+        .map(|p| connection::ConnectionSource::Serial(p.port_name.clone(), app.can_bus_speed))
+        .chain([
+            connection::ConnectionSource::Udp(app.udp_port),
+            connection::ConnectionSource::Simulated(true, dbc_paths.clone()),
+            connection::ConnectionSource::Simulated(false, dbc_paths),
+            connection::ConnectionSource::Loopback,
+        ]);
+    for source in sources {
+        let label = if app.selected_source.as_ref() == Some(&source) {
+            format!("Reconnect to {}", source.display_name())
+        } else {
+            format!("Connect to {}", source.display_name())
+        };
+        entries.push(PaletteEntry::new(label, action::AppAction::Connect(source)));
+    }
+
+    entries.push(PaletteEntry::new("Load DBC", action::AppAction::SelectDbc));
+    entries.push(PaletteEntry::new(
+        format!("Next theme ({})", app.theme_name),
+        action::AppAction::ToggleTheme,
+    ));
+    for theme in &app.themes.themes {
+        if theme.name != app.theme_name {
+            entries.push(PaletteEntry::new(
+                format!("Use theme {}", theme.name),
+                action::AppAction::SelectTheme(theme.name.clone()),
+            ));
+        }
+    }
+    entries.push(PaletteEntry::new(
+        "Toggle sidebar",
+        action::AppAction::ToggleSidebar,
+    ));
+    entries.push(PaletteEntry::new(
+        "Close active widget",
+        action::AppAction::CloseActiveWidget,
+    ));
+
+    for command in [
+        shortcuts::Command::ToggleShortcutHelp,
+        shortcuts::Command::TogglePause,
+        shortcuts::Command::Clear,
+        shortcuts::Command::ToggleFreezeAll,
+        shortcuts::Command::AddMarker,
+    ] {
+        entries.push(PaletteEntry::new(command.describe(), command.action()));
+    }
+
+    for name in &app.profiles {
+        if *name != app.profile.name {
+            entries.push(PaletteEntry::new(
+                format!("Switch to profile {}", name),
+                action::AppAction::SwitchProfile(name.clone()),
+            ));
+        }
+    }
+
+    for named in &app.layouts.named {
+        entries.push(PaletteEntry::new(
+            format!("Load layout {}", named.name),
+            action::AppAction::LoadLayout(named.name.clone()),
+        ));
+    }
+
+    for (label, widget_type) in action::AppAction::cmd_palette_list() {
+        entries.push(PaletteEntry::new(
+            label,
+            action::AppAction::SpawnWidget(widget_type),
+        ));
+    }
+
+    if let Some(parser) = &app.parser {
+        for msg in parser.parser.msg_defs() {
+            let msg_id = util::can::can_dbc_to_u32_without_extid_flag(&msg.id);
+            for sig in &msg.signals {
+                entries.push(PaletteEntry {
+                    label: format!("📈 {}.{}", msg.name, sig.name),
+                    detail: sig.unit.clone(),
+                    action: action::AppAction::ShowSignal {
+                        msg_id,
+                        msg_name: msg.name.clone(),
+                        signal_name: sig.name.clone(),
+                        new_widget: false,
+                    },
+                });
+            }
+            entries.push(PaletteEntry {
+                label: format!("✉ {}", msg.name),
+                detail: format!("0x{:03X}", msg_id),
+                action: action::AppAction::ShowMessage {
+                    msg_name: msg.name,
+                    new_widget: false,
+                },
+            });
+        }
+    }
+
+    entries
+}
+
+// Subsequence match ignoring case and spaces, None if some character of the query is missing.
+// Runs of consecutive characters and matches at the start of a word score higher.
+fn fuzzy_score(query: &str, text: &str) -> Option<i32> {
+    let text: Vec<char> = text.to_lowercase().chars().collect();
+    let mut score = 0;
+    let mut pos = 0;
+    let mut last_match = None;
+    for q in query.to_lowercase().chars().filter(|c| !c.is_whitespace()) {
+        let idx = pos + text[pos..].iter().position(|&c| c == q)?;
+        score += 1;
+        if idx > 0 && last_match == Some(idx - 1) {
+            score += 5;
+        }
+        if idx == 0 || !text[idx - 1].is_alphanumeric() {
+            score += 3;
+        }
+        last_match = Some(idx);
+        pos = idx + 1;
+    }
+    // Prefer the shorter of two otherwise equal matches
+    Some(score * 100 - text.len() as i32)
+}
+
+// Shift opens a new widget instead of handing the pick to the focused one
+fn in_new_widget(action: &action::AppAction) -> action::AppAction {
+    let mut action = action.clone();
+    if let action::AppAction::ShowMessage { new_widget, .. }
+    | action::AppAction::ShowSignal { new_widget, .. } = &mut action
+    {
+        *new_widget = true;
+    }
+    action
+}
+
 pub struct CommandPalette {
     show_command_palette: bool,
     palette_search: String,
@@ -16,6 +188,10 @@ impl CommandPalette {
         }
     }
 
+    pub fn is_open(&self) -> bool {
+        self.show_command_palette
+    }
+
     pub fn toggle(&mut self) {
         self.show_command_palette = !self.show_command_palette;
         if self.show_command_palette {
@@ -24,47 +200,60 @@ impl CommandPalette {
         }
     }
 
-    pub fn ui(&mut self, ctx: &egui::Context) -> Vec<action::AppAction> {
+    fn pick(&mut self, entry: &PaletteEntry, shift: bool) -> action::AppAction {
+        self.show_command_palette = false;
+        if shift {
+            in_new_widget(&entry.action)
+        } else {
+            entry.action.clone()
+        }
+    }
+
+    pub fn ui(&mut self, ctx: &egui::Context, entries: &[PaletteEntry]) -> Vec<action::AppAction> {
         if !self.show_command_palette {
             return Vec::new();
         }
 
         let mut action_queue = Vec::new();
 
-        let cmd_list = action::AppAction::cmd_palette_list();
-
-        // filtering Logic
-        let filtered_options: Vec<_> = cmd_list
-            .iter()
-            .filter(|(label, _)| {
-                if self.palette_search.is_empty() {
-                    true
-                } else {
-                    label
-                        .to_lowercase()
-                        .contains(&self.palette_search.to_lowercase())
-                }
-            })
-            .collect();
+        // Best match first, an empty search keeps the order of `entries`
+        let filtered_options: Vec<&PaletteEntry> = if self.palette_search.trim().is_empty() {
+            entries.iter().take(MAX_SHOWN).collect()
+        } else {
+            let mut scored: Vec<_> = entries
+                .iter()
+                .filter_map(|entry| {
+                    let text = format!("{} {}", entry.label, entry.detail);
+                    fuzzy_score(&self.palette_search, &text).map(|score| (score, entry))
+                })
+                .collect();
+            scored.sort_by_key(|(score, _)| std::cmp::Reverse(*score));
+            scored
+                .into_iter()
+                .take(MAX_SHOWN)
+                .map(|(_, entry)| entry)
+                .collect()
+        };
 
         if !filtered_options.is_empty() {
             self.palette_index = self.palette_index.min(filtered_options.len() - 1);
         }
 
         // handle keyboard navigation (maybe needs to be smarter?)
+        let mut index_changed = false;
         ctx.input_mut(|i| {
             if i.key_pressed(egui::Key::ArrowDown) && !filtered_options.is_empty() {
                 self.palette_index = (self.palette_index + 1) % filtered_options.len();
+                index_changed = true;
             }
             if i.key_pressed(egui::Key::ArrowUp) && !filtered_options.is_empty() {
                 self.palette_index =
                     (self.palette_index + filtered_options.len() - 1) % filtered_options.len();
+                index_changed = true;
             }
             if i.key_pressed(egui::Key::Enter) && !filtered_options.is_empty() {
-                action_queue.push(action::AppAction::SpawnWidget(
-                    filtered_options[self.palette_index].1.clone(),
-                ));
-                self.show_command_palette = false;
+                let shift = i.modifiers.shift;
+                action_queue.push(self.pick(filtered_options[self.palette_index], shift));
             }
             if i.key_pressed(egui::Key::Escape) {
                 self.show_command_palette = false;
@@ -77,12 +266,15 @@ impl CommandPalette {
             .collapsible(false)
             .resizable(false)
             .title_bar(false)
-            .fixed_size([300.0, 400.0])
+            .fixed_size([400.0, 400.0])
             .show(ctx, |ui| {
                 // Search Input
                 ui.horizontal(|ui| {
                     ui.label("🔍");
-                    let response = ui.text_edit_singleline(&mut self.palette_search);
+                    let response = ui.add(
+                        egui::TextEdit::singleline(&mut self.palette_search)
+                            .hint_text("Command, message or signal"),
+                    );
 
                     response.request_focus(); // focus the search box when palette is open
 
@@ -90,38 +282,51 @@ impl CommandPalette {
                         self.palette_index = 0; // reset selection to top when search changes
                     }
                 });
+                ui.label(
+                    egui::RichText::new(
+                        "Signals go to the focused widget, or a new Scope. Shift+Enter always \
+                         opens a new one.",
+                    )
+                    .small()
+                    .weak(),
+                );
                 ui.separator();
 
                 // Results List
-                ui.vertical_centered_justified(|ui| {
-                    if filtered_options.is_empty() {
-                        ui.label("No commands found");
-                    } else {
-                        for (i, (label, widget_type)) in filtered_options.iter().enumerate() {
-                            let is_selected = i == self.palette_index;
-                            let selection = ui.visuals().selection;
-
-                            // todo this styling is kinda janky, theres probably an easy way to do this
-                            let button = egui::Button::new(*label)
-                                .fill(if is_selected {
-                                    selection.bg_fill
-                                } else {
-                                    egui::Color32::TRANSPARENT
-                                })
-                                .stroke(if is_selected {
-                                    selection.stroke
-                                } else {
-                                    egui::Stroke::NONE
-                                });
-
-                            let response = ui.add(button);
-                            if response.clicked() {
-                                action_queue
-                                    .push(action::AppAction::SpawnWidget((*widget_type).clone()));
-                                self.show_command_palette = false;
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    ui.vertical_centered_justified(|ui| {
+                        if filtered_options.is_empty() {
+                            ui.label("No commands found");
+                        } else {
+                            for (i, entry) in filtered_options.iter().enumerate() {
+                                let is_selected = i == self.palette_index;
+                                let selection = ui.visuals().selection;
+
+                                // todo this styling is kinda janky, theres probably an easy way to do this
+                                let button = egui::Button::new(entry.label.as_str())
+                                    .right_text(egui::RichText::new(&entry.detail).weak())
+                                    .fill(if is_selected {
+                                        selection.bg_fill
+                                    } else {
+                                        egui::Color32::TRANSPARENT
+                                    })
+                                    .stroke(if is_selected {
+                                        selection.stroke
+                                    } else {
+                                        egui::Stroke::NONE
+                                    });
+
+                                let response = ui.add(button);
+                                if is_selected && index_changed {
+                                    response.scroll_to_me(None);
+                                }
+                                if response.clicked() {
+                                    let shift = ui.input(|i| i.modifiers.shift);
+                                    action_queue.push(self.pick(entry, shift));
+                                }
                             }
                         }
-                    }
+                    });
                 });
             });
 
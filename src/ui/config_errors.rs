@@ -0,0 +1,27 @@
+use crate::app;
+use eframe::egui;
+
+pub fn show(app: &mut app::DAQApp, ctx: &egui::Context) {
+    if app.config_errors.is_empty() {
+        return;
+    }
+
+    let mut dismissed = false;
+    egui::Window::new("Config Not Loaded")
+        .collapsible(false)
+        .resizable(false)
+        .default_width(420.0)
+        .anchor(egui::Align2::CENTER_TOP, egui::vec2(0.0, 8.0))
+        .show(ctx, |ui| {
+            for error in &app.config_errors {
+                ui.label(egui::RichText::new(error).color(ui.visuals().error_fg_color));
+            }
+            if ui.button("Dismiss").clicked() {
+                dismissed = true;
+            }
+        });
+
+    if dismissed {
+        app.config_errors.clear();
+    }
+}
@@ -0,0 +1,384 @@
+use crate::{app, dbc, util};
+use eframe::egui;
+
+use super::dbc_msg_picker::no_dbc_placeholder;
+
+const CELL_SIZE: egui::Vec2 = egui::vec2(30.0, 20.0);
+
+pub struct DbcBrowser {
+    pub title: String,
+    search: String,
+    // Followed by name so the selection survives a DBC reload
+    selected_msg: Option<String>,
+    dbc_warning: Option<String>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, PartialEq)]
+pub struct DbcBrowserConfig {
+    pub search: String,
+    pub selected_msg: Option<String>,
+}
+
+impl DbcBrowser {
+    pub fn config(&self) -> DbcBrowserConfig {
+        DbcBrowserConfig {
+            search: self.search.clone(),
+            selected_msg: self.selected_msg.clone(),
+        }
+    }
+
+    pub fn apply_config(&mut self, config: DbcBrowserConfig) {
+        self.search = config.search;
+        self.selected_msg = config.selected_msg;
+    }
+
+    pub fn new(instance_num: usize) -> Self {
+        Self {
+            title: format!("DBC Browser #{}", instance_num),
+            search: String::new(),
+            selected_msg: None,
+            dbc_warning: None,
+        }
+    }
+
+    pub fn select_msg(&mut self, msg_name: &str) {
+        self.selected_msg = Some(msg_name.to_string());
+        self.dbc_warning = None;
+        // Otherwise the message could be filtered out of the list
+        self.search.clear();
+    }
+
+    pub fn on_dbc_reloaded(&mut self, parser: &dbc::merged::MergedParser) -> Vec<String> {
+        let Some(selected_msg) = &self.selected_msg else {
+            return Vec::new();
+        };
+        if parser.msg_defs().iter().any(|m| m.name == *selected_msg) {
+            return Vec::new();
+        }
+
+        let warning = format!(
+            "{}: message {} no longer exists in the DBC",
+            self.title, selected_msg
+        );
+        self.selected_msg = None;
+        self.dbc_warning = Some(warning.clone());
+        vec![warning]
+    }
+
+    fn matches_search(&self, msg: &can_dbc::Message) -> bool {
+        if self.search.is_empty() {
+            return true;
+        }
+        let low_search = self.search.to_lowercase();
+        msg.name.to_lowercase().contains(&low_search)
+            || format!(
+                "{:03X}",
+                util::can::can_dbc_to_u32_without_extid_flag(&msg.id)
+            )
+            .to_lowercase()
+            .contains(&low_search)
+            || msg
+                .signals
+                .iter()
+                .any(|s| s.name.to_lowercase().contains(&low_search))
+    }
+
+    pub fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        parser: Option<&app::ParserInfo>,
+    ) -> egui_tiles::UiResponse {
+        let Some(parser) = parser else {
+            no_dbc_placeholder(ui);
+            return egui_tiles::UiResponse::None;
+        };
+        let msg_defs = parser.parser.msg_defs();
+
+        egui::SidePanel::left(egui::Id::new(("dbc_browser_msgs", &self.title)))
+            .resizable(true)
+            .default_width(240.0)
+            .show_inside(ui, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Search:");
+                    ui.text_edit_singleline(&mut self.search);
+                });
+                ui.separator();
+
+                let mut by_node: std::collections::BTreeMap<&str, Vec<&can_dbc::Message>> =
+                    std::collections::BTreeMap::new();
+                for msg in msg_defs.iter().filter(|m| self.matches_search(m)) {
+                    by_node
+                        .entry(util::can::transmitter_name(&msg.transmitter))
+                        .or_default()
+                        .push(msg);
+                }
+
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    for (node, mut msgs) in by_node {
+                        msgs.sort_by_key(|m| util::can::can_dbc_to_u32_without_extid_flag(&m.id));
+                        egui::CollapsingHeader::new(format!("{} ({})", node, msgs.len()))
+                            .id_salt(("dbc_browser_node", &self.title, node))
+                            .default_open(!self.search.is_empty())
+                            .show(ui, |ui| {
+                                for msg in msgs {
+                                    let selected = self.selected_msg.as_ref() == Some(&msg.name);
+                                    let label = format!(
+                                        "0x{:03X} {}",
+                                        util::can::can_dbc_to_u32_without_extid_flag(&msg.id),
+                                        msg.name
+                                    );
+                                    if ui.selectable_label(selected, label).clicked() {
+                                        self.selected_msg = Some(msg.name.clone());
+                                        self.dbc_warning = None;
+                                    }
+                                }
+                            });
+                    }
+                });
+            });
+
+        egui::CentralPanel::default().show_inside(ui, |ui| {
+            if let Some(warning) = &self.dbc_warning {
+                ui.label(egui::RichText::new(warning).color(ui.visuals().warn_fg_color));
+            }
+
+            let Some(msg) = self
+                .selected_msg
+                .as_ref()
+                .and_then(|name| msg_defs.iter().find(|m| m.name == *name))
+            else {
+                ui.centered_and_justified(|ui| {
+                    ui.label(
+                        egui::RichText::new("Select a message to see its definition.")
+                            .italics()
+                            .weak(),
+                    );
+                });
+                return;
+            };
+
+            egui::ScrollArea::vertical().show(ui, |ui| {
+                self.show_msg(ui, &parser.parser, msg);
+            });
+        });
+
+        egui_tiles::UiResponse::None
+    }
+
+    fn show_msg(
+        &self,
+        ui: &mut egui::Ui,
+        parser: &dbc::merged::MergedParser,
+        msg: &can_dbc::Message,
+    ) {
+        let msg_id = util::can::can_dbc_to_u32_with_extid_flag(&msg.id);
+        let is_extended = matches!(msg.id, can_dbc::MessageId::Extended(_));
+
+        ui.label(
+            egui::RichText::new(format!(
+                "{} (0x{:03X})",
+                msg.name,
+                util::can::can_dbc_to_u32_without_extid_flag(&msg.id)
+            ))
+            .strong()
+            .size(16.0),
+        );
+        ui.label(format!(
+            "{} ID, {} bytes, sent by {}",
+            if is_extended { "Extended" } else { "Standard" },
+            msg.size,
+            util::can::transmitter_name(&msg.transmitter)
+        ));
+        if let Some(source) = parser.source_of(msg_id & util::can::EXTENDED_ID_MASK) {
+            ui.label(
+                egui::RichText::new(format!("Defined in {}", dbc::merged::file_name(source)))
+                    .color(ui.visuals().weak_text_color()),
+            );
+        }
+        if let Some(desc) = parser.msg_desc(msg_id) {
+            ui.label(egui::RichText::new(desc).italics());
+        }
+
+        ui.separator();
+        ui.label(egui::RichText::new("Bit layout").strong());
+        self.bit_grid(ui, msg);
+        for (a, b) in dbc::layout::overlapping_signals(msg) {
+            ui.label(
+                egui::RichText::new(format!("⚠ {} and {} share bits", a, b))
+                    .color(ui.visuals().warn_fg_color),
+            );
+        }
+
+        ui.separator();
+        ui.label(egui::RichText::new("Signals").strong());
+        egui::Grid::new(("dbc_browser_signals", &self.title))
+            .striped(true)
+            .show(ui, |ui| {
+                for header in [
+                    "Name",
+                    "Start",
+                    "Size",
+                    "Order",
+                    "Type",
+                    "Factor",
+                    "Offset",
+                    "Min",
+                    "Max",
+                    "Unit",
+                    "Receivers",
+                ] {
+                    ui.label(egui::RichText::new(header).strong());
+                }
+                ui.end_row();
+
+                for (i, sig) in msg.signals.iter().enumerate() {
+                    ui.label(
+                        egui::RichText::new(&sig.name).color(signal_color(i, msg.signals.len())),
+                    )
+                    .on_hover_text(parser.signal_desc(msg_id, &sig.name).unwrap_or_default());
+                    ui.label(sig.start_bit.to_string());
+                    ui.label(sig.size.to_string());
+                    ui.label(match sig.byte_order {
+                        can_dbc::ByteOrder::LittleEndian => "Intel",
+                        can_dbc::ByteOrder::BigEndian => "Motorola",
+                    });
+                    ui.label(match sig.value_type {
+                        can_dbc::ValueType::Signed => "signed",
+                        can_dbc::ValueType::Unsigned => "unsigned",
+                    });
+                    ui.label(sig.factor.to_string());
+                    ui.label(sig.offset.to_string());
+                    ui.label(util::can::can_dbc_numeric_to_f64(&sig.min).to_string());
+                    ui.label(util::can::can_dbc_numeric_to_f64(&sig.max).to_string());
+                    ui.label(&sig.unit);
+                    ui.label(sig.receivers.join(", "));
+                    ui.end_row();
+                }
+            });
+
+        let Some(dbc) = parser.dbc_of(msg_id) else {
+            return;
+        };
+
+        let value_tables: Vec<_> = msg
+            .signals
+            .iter()
+            .filter_map(|sig| {
+                dbc.value_descriptions_for_signal(msg.id, &sig.name)
+                    .filter(|descs| !descs.is_empty())
+                    .map(|descs| (sig, descs))
+            })
+            .collect();
+        if !value_tables.is_empty() {
+            ui.separator();
+            ui.label(egui::RichText::new("Value tables").strong());
+            for (sig, descs) in value_tables {
+                egui::CollapsingHeader::new(&sig.name)
+                    .id_salt(("dbc_browser_values", &self.title, &sig.name))
+                    .show(ui, |ui| {
+                        for desc in descs {
+                            ui.label(
+                                egui::RichText::new(format!("{} = {}", desc.id, desc.description))
+                                    .monospace(),
+                            );
+                        }
+                    });
+            }
+        }
+
+        let attributes = dbc::attributes::msg_attributes(dbc, msg);
+        if !attributes.is_empty() {
+            ui.separator();
+            ui.label(egui::RichText::new("Attributes").strong());
+            egui::Grid::new(("dbc_browser_attributes", &self.title))
+                .striped(true)
+                .show(ui, |ui| {
+                    for (owner, name, value) in attributes {
+                        ui.label(owner);
+                        ui.label(name);
+                        ui.label(value);
+                        ui.end_row();
+                    }
+                });
+        }
+    }
+
+    // Byte per row, bit 7 on the left like most DBC editors. The MSB of each signal is outlined.
+    fn bit_grid(&self, ui: &mut egui::Ui, msg: &can_dbc::Message) {
+        let bit_count = msg.size as usize * 8;
+        let mut owners: Vec<Option<usize>> = vec![None; bit_count];
+        let mut msbs = Vec::new();
+        for (i, sig) in msg.signals.iter().enumerate() {
+            let bits = dbc::layout::signal_bits(sig);
+            for &bit in &bits {
+                if let Some(owner) = owners.get_mut(bit as usize) {
+                    *owner = Some(i);
+                }
+            }
+            msbs.extend(bits.last().copied());
+        }
+
+        egui::Grid::new(("dbc_browser_bits", &self.title))
+            .spacing(egui::vec2(2.0, 2.0))
+            .show(ui, |ui| {
+                ui.label("");
+                for bit in (0..8).rev() {
+                    ui.label(bit.to_string());
+                }
+                ui.end_row();
+
+                for byte in 0..msg.size as usize {
+                    ui.label(format!("Byte {}", byte));
+                    for bit in (0..8).rev() {
+                        let pos = byte * 8 + bit;
+                        let owner = owners[pos].map(|i| &msg.signals[i]);
+
+                        let (rect, response) =
+                            ui.allocate_exact_size(CELL_SIZE, egui::Sense::hover());
+                        let fill = match owners[pos] {
+                            Some(i) => signal_color(i, msg.signals.len()),
+                            None => ui.visuals().faint_bg_color,
+                        };
+                        let painter = ui.painter();
+                        painter.rect_filled(rect, 2.0, fill);
+                        if msbs.contains(&(pos as u64)) {
+                            painter.rect_stroke(
+                                rect,
+                                2.0,
+                                egui::Stroke::new(2.0, ui.visuals().strong_text_color()),
+                                egui::StrokeKind::Inside,
+                            );
+                        }
+                        painter.text(
+                            rect.center(),
+                            egui::Align2::CENTER_CENTER,
+                            pos.to_string(),
+                            egui::FontId::monospace(10.0),
+                            if owner.is_some() {
+                                egui::Color32::BLACK
+                            } else {
+                                ui.visuals().weak_text_color()
+                            },
+                        );
+                        if let Some(sig) = owner {
+                            response.on_hover_text(format!(
+                                "{} ({} bits, {})",
+                                sig.name,
+                                sig.size,
+                                match sig.byte_order {
+                                    can_dbc::ByteOrder::LittleEndian => "Intel",
+                                    can_dbc::ByteOrder::BigEndian => "Motorola",
+                                }
+                            ));
+                        }
+                    }
+                    ui.end_row();
+                }
+            });
+    }
+}
+
+fn signal_color(index: usize, count: usize) -> egui::Color32 {
+    let hue = 360.0 * index as f64 / count.max(1) as f64;
+    util::hsv_to_color32(hue, 0.45, 0.95)
+}
@@ -0,0 +1,27 @@
+use crate::dbc;
+use eframe::egui;
+
+/// Lists DBC check results, shared by the sidebar and the Log Parser.
+pub fn show_issues(ui: &mut egui::Ui, issues: &[dbc::lint::LintIssue]) {
+    if issues.is_empty() {
+        ui.label("✔ No issues found");
+        return;
+    }
+
+    egui::ScrollArea::vertical()
+        .id_salt("dbc_lint_issues")
+        .max_height(300.0)
+        .show(ui, |ui| {
+            for issue in issues {
+                let (icon, color) = match issue.severity {
+                    dbc::lint::Severity::Error => ("❌", ui.visuals().error_fg_color),
+                    dbc::lint::Severity::Warning => ("⚠", ui.visuals().warn_fg_color),
+                };
+                ui.label(egui::RichText::new(format!("{} {}", icon, issue.location)).color(color))
+                    .on_hover_text(&issue.text);
+                ui.indent(ui.id().with(&issue.text), |ui| {
+                    ui.label(&issue.text);
+                });
+            }
+        });
+}
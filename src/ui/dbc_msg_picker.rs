@@ -1,4 +1,4 @@
-use crate::util;
+use crate::{dbc, util};
 use eframe::egui;
 
 /// Shared DBC message search UI state used by Send UI, Jitter, etc.
@@ -11,7 +11,7 @@ pub struct DbcMsgPickerState {
 impl DbcMsgPickerState {
     /// Refresh [`Self::search_results`] from [`Self::search_text`] using the same rules as before:
     /// empty clears results, `*` lists all messages, otherwise filter by name and hex ID substring.
-    pub fn refresh_results(&mut self, parser: &can_decode::Parser) {
+    pub fn refresh_results(&mut self, parser: &dbc::merged::MergedParser) {
         if self.search_text.is_empty() {
             self.search_results.clear();
         } else if self.search_text.trim() == "*" {
@@ -39,7 +39,7 @@ impl DbcMsgPickerState {
     pub fn show(
         &mut self,
         ui: &mut egui::Ui,
-        parser: &can_decode::Parser,
+        parser: &dbc::merged::MergedParser,
         selected_msg_is_none: bool,
     ) -> Option<can_dbc::Message> {
         ui.horizontal(|ui| {
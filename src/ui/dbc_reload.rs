@@ -0,0 +1,103 @@
+use crate::{app, dbc};
+use eframe::egui;
+
+pub struct DbcReloadReport {
+    pub reloaded_at: chrono::DateTime<chrono::Local>,
+    // Only the files that changed on disk, the whole set was merged again
+    pub changed_paths: Vec<std::path::PathBuf>,
+    // Err holds the parse error, in which case the previous definitions are still in use
+    pub result: Result<dbc::diff::DbcDiff, String>,
+    pub widget_warnings: Vec<String>,
+}
+
+pub fn show(app: &mut app::DAQApp, ctx: &egui::Context) {
+    let Some(report) = &app.dbc_reload_report else {
+        return;
+    };
+
+    let mut dismissed = false;
+    egui::Window::new("DBC Reloaded")
+        .collapsible(true)
+        .resizable(true)
+        .default_width(420.0)
+        .anchor(egui::Align2::RIGHT_TOP, egui::vec2(-8.0, 8.0))
+        .show(ctx, |ui| {
+            let file_names = report
+                .changed_paths
+                .iter()
+                .map(|p| dbc::merged::file_name(p))
+                .collect::<Vec<_>>()
+                .join(", ");
+            ui.label(
+                egui::RichText::new(format!(
+                    "{} changed on disk at {}",
+                    file_names,
+                    report.reloaded_at.format("%H:%M:%S")
+                ))
+                .strong(),
+            );
+
+            match &report.result {
+                Err(e) => {
+                    ui.label(
+                        egui::RichText::new(format!(
+                            "Failed to parse, still using the previous version: {}",
+                            e
+                        ))
+                        .color(ui.visuals().error_fg_color),
+                    );
+                }
+                Ok(diff) => {
+                    ui.label(diff.summary());
+                    egui::ScrollArea::vertical()
+                        .max_height(300.0)
+                        .show(ui, |ui| {
+                            for name in &diff.added_msgs {
+                                ui.label(
+                                    egui::RichText::new(format!("+ {}", name))
+                                        .monospace()
+                                        .color(egui::Color32::from_rgb(100, 200, 100)),
+                                );
+                            }
+                            for name in &diff.removed_msgs {
+                                ui.label(
+                                    egui::RichText::new(format!("- {}", name))
+                                        .monospace()
+                                        .color(ui.visuals().error_fg_color),
+                                );
+                            }
+                            for change in &diff.changed_msgs {
+                                egui::CollapsingHeader::new(
+                                    egui::RichText::new(format!("~ {}", change.name))
+                                        .monospace()
+                                        .color(ui.visuals().warn_fg_color),
+                                )
+                                .id_salt(("dbc_reload_change", &change.name))
+                                .show(ui, |ui| {
+                                    for detail in &change.details {
+                                        ui.label(egui::RichText::new(detail).monospace());
+                                    }
+                                });
+                            }
+                        });
+                }
+            }
+
+            if !report.widget_warnings.is_empty() {
+                ui.separator();
+                ui.label(egui::RichText::new("Affected widgets").strong());
+                for warning in &report.widget_warnings {
+                    ui.label(egui::RichText::new(warning).color(ui.visuals().warn_fg_color));
+                }
+            }
+
+            ui.separator();
+            if ui.button("Dismiss").clicked() {
+                dismissed = true;
+            }
+        });
+
+    if dismissed {
+        app.dbc_reload_report = None;
+    }
+}
@@ -0,0 +1,186 @@
+use crate::{action, derived, messages};
+use eframe::egui;
+
+pub struct DerivedSignals {
+    pub title: String,
+    ui_to_can_tx: std::sync::mpsc::Sender<messages::MsgFromUi>,
+    config: derived::DerivedConfig,
+    latest_values: std::collections::HashMap<String, f64>,
+    new_name: String,
+    new_expr: String,
+    new_unit: String,
+}
+
+impl DerivedSignals {
+    pub fn new(
+        instance_num: usize,
+        ui_to_can_tx: std::sync::mpsc::Sender<messages::MsgFromUi>,
+    ) -> Self {
+        Self {
+            title: format!("Derived Signals #{}", instance_num),
+            ui_to_can_tx,
+            config: derived::DerivedConfig::load(),
+            latest_values: std::collections::HashMap::new(),
+            new_name: String::new(),
+            new_expr: String::new(),
+            new_unit: String::new(),
+        }
+    }
+
+    fn apply_changes(&mut self) {
+        self.config.save();
+        self.latest_values.clear();
+        let _ = self
+            .ui_to_can_tx
+            .send(messages::MsgFromUi::DerivedSignalsChanged(
+                self.config.signals.clone(),
+            ));
+    }
+
+    fn new_signal_error(&self) -> Option<String> {
+        if !derived::is_valid_name(&self.new_name) {
+            return Some("Name must be letters, digits and underscores".to_string());
+        }
+        if self.config.signals.iter().any(|s| s.name == self.new_name) {
+            return Some(format!("`{}` already exists", self.new_name));
+        }
+        derived::validate(&self.new_expr).err()
+    }
+
+    pub fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        action_queue: &mut Vec<action::AppAction>,
+    ) -> egui_tiles::UiResponse {
+        ui.heading(format!("🧮 {}", self.title));
+        ui.label(
+            egui::RichText::new(
+                "Reference signals as msg.signal, derived ones as derived.name. \
+                 Operators: + - * / ^. Functions: abs(x), sqrt(x), min(a, b), max(a, b), \
+                 ma(x, n) moving average of n samples, ddt(x) derivative per second, \
+                 integ(x) integral over seconds.",
+            )
+            .weak(),
+        );
+        ui.separator();
+
+        let mut to_delete = None;
+        let mut changed = false;
+        egui::Grid::new(("derived_signals_grid", &self.title))
+            .num_columns(6)
+            .striped(true)
+            .show(ui, |ui| {
+                ui.label(egui::RichText::new("Name").strong());
+                ui.label(egui::RichText::new("Expression").strong());
+                ui.label(egui::RichText::new("Unit").strong());
+                ui.label(egui::RichText::new("Value").strong());
+                ui.label("");
+                ui.label("");
+                ui.end_row();
+
+                for (idx, signal) in self.config.signals.iter_mut().enumerate() {
+                    ui.label(egui::RichText::new(&signal.name).monospace());
+                    let error = derived::validate(&signal.expr).err();
+                    let expr_edit = ui.add(
+                        egui::TextEdit::singleline(&mut signal.expr)
+                            .code_editor()
+                            .desired_width(300.0),
+                    );
+                    // Only apply expression edits once they parse, so typing doesn't reset filters
+                    changed |= expr_edit.lost_focus() && error.is_none();
+                    if let Some(error) = &error {
+                        expr_edit.on_hover_text(error);
+                    }
+                    changed |= ui
+                        .add(egui::TextEdit::singleline(&mut signal.unit).desired_width(50.0))
+                        .lost_focus();
+                    match (&error, self.latest_values.get(&signal.name)) {
+                        (Some(_), _) => {
+                            ui.label(
+                                egui::RichText::new("invalid").color(ui.visuals().error_fg_color),
+                            );
+                        }
+                        (None, Some(value)) => {
+                            ui.label(egui::RichText::new(format!("{:.3}", value)).monospace());
+                        }
+                        (None, None) => {
+                            ui.label(egui::RichText::new("-").weak());
+                        }
+                    }
+                    if ui
+                        .small_button("📊")
+                        .on_hover_text("Open in Scope")
+                        .clicked()
+                    {
+                        action_queue.push(action::AppAction::SpawnWidget(
+                            action::WidgetType::Scope {
+                                msg_id: derived::DERIVED_MSG_ID,
+                                msg_name: derived::DERIVED_MSG_NAME.to_string(),
+                                signal_name: signal.name.clone(),
+                            },
+                        ));
+                    }
+                    if ui.small_button("🗑").clicked() {
+                        to_delete = Some(idx);
+                    }
+                    ui.end_row();
+                }
+            });
+
+        if let Some(idx) = to_delete {
+            self.config.signals.remove(idx);
+            changed = true;
+        }
+
+        ui.separator();
+        ui.label(egui::RichText::new("New derived signal").strong());
+        ui.horizontal(|ui| {
+            ui.add(
+                egui::TextEdit::singleline(&mut self.new_name)
+                    .hint_text("pack_power")
+                    .desired_width(120.0),
+            );
+            ui.label("=");
+            ui.add(
+                egui::TextEdit::singleline(&mut self.new_expr)
+                    .code_editor()
+                    .hint_text("charging_telemetry.pack_voltage * charging_telemetry.pack_current")
+                    .desired_width(300.0),
+            );
+            ui.add(
+                egui::TextEdit::singleline(&mut self.new_unit)
+                    .hint_text("W")
+                    .desired_width(50.0),
+            );
+            let error = self.new_signal_error();
+            if ui
+                .add_enabled(error.is_none(), egui::Button::new("Add"))
+                .clicked()
+            {
+                self.config.signals.push(derived::DerivedSignalDef {
+                    name: std::mem::take(&mut self.new_name),
+                    expr: std::mem::take(&mut self.new_expr),
+                    unit: std::mem::take(&mut self.new_unit),
+                });
+                changed = true;
+            } else if let Some(error) = error
+                && !(self.new_name.is_empty() && self.new_expr.is_empty())
+            {
+                ui.label(egui::RichText::new(error).color(ui.visuals().error_fg_color));
+            }
+        });
+
+        if changed {
+            self.apply_changes();
+        }
+
+        egui_tiles::UiResponse::None
+    }
+
+    pub fn handle_can_message(&mut self, msg: &messages::MsgFromCan) {
+        if let messages::MsgFromCan::DerivedSignal(derived_signal) = msg {
+            self.latest_values
+                .insert(derived_signal.name.clone(), derived_signal.value);
+        }
+    }
+}
@@ -0,0 +1,380 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use crate::bootloader::{self, flasher, inventory};
+use crate::{config, messages};
+use eframe::egui;
+
+pub struct Fleet {
+    pub title: String,
+    ui_to_can_tx: std::sync::mpsc::Sender<messages::MsgFromUi>,
+    config: bootloader::BootloaderConfig,
+    manifest: inventory::Manifest,
+    // Manifest images by node, loaded with every query so their CRCs match what's on disk
+    images: HashMap<String, Result<bootloader::image::Image, String>>,
+    infos: HashMap<String, Result<inventory::NodeInfo, String>>,
+    // Nodes whose info hasn't come back yet
+    querying: HashSet<String>,
+    batch: Option<Batch>,
+    report: Option<String>,
+}
+
+struct Batch {
+    queue: VecDeque<String>,
+    current: Option<(String, flasher::FlashProgress)>,
+    // Node -> flash time or why it failed
+    results: Vec<(String, Result<std::time::Duration, String>)>,
+    // Every node is queried again once the queue is empty, the report is built from those answers
+    verifying: bool,
+}
+
+impl Drop for Fleet {
+    fn drop(&mut self) {
+        if self.batch.as_ref().is_some_and(|b| b.current.is_some())
+            && let Err(e) = self.ui_to_can_tx.send(messages::MsgFromUi::AbortFlash)
+        {
+            log::error!("Failed to send AbortFlash: {}", e);
+        }
+    }
+}
+
+impl Fleet {
+    pub fn new(
+        instance_num: usize,
+        ui_to_can_tx: std::sync::mpsc::Sender<messages::MsgFromUi>,
+    ) -> Self {
+        Self {
+            title: format!("Fleet #{}", instance_num),
+            ui_to_can_tx,
+            config: bootloader::BootloaderConfig::load(),
+            manifest: inventory::Manifest::load(),
+            images: HashMap::new(),
+            infos: HashMap::new(),
+            querying: HashSet::new(),
+            batch: None,
+            report: None,
+        }
+    }
+
+    fn query(&mut self) {
+        self.infos.clear();
+        self.querying = self.config.nodes.iter().map(|n| n.name.clone()).collect();
+        self.images = self
+            .manifest
+            .images
+            .iter()
+            .map(|entry| {
+                (
+                    entry.node.clone(),
+                    bootloader::image::Image::load(&entry.image),
+                )
+            })
+            .collect();
+        self.ui_to_can_tx
+            .send(messages::MsgFromUi::QueryNodes(self.config.nodes.clone()))
+            .expect("Failed to send QueryNodes");
+    }
+
+    // Differences with the manifest, or why the node can't be compared
+    fn status(&self, node: &str) -> Result<Vec<String>, String> {
+        let Some(entry) = self.manifest.entry(node) else {
+            return Err("not in the manifest".to_string());
+        };
+        let image = match self.images.get(node) {
+            Some(Ok(image)) => image,
+            Some(Err(e)) => return Err(e.clone()),
+            None => return Err("not queried yet".to_string()),
+        };
+        match self.infos.get(node) {
+            Some(Ok(info)) => Ok(info.mismatches(entry, image.crc)),
+            Some(Err(e)) => Err(e.clone()),
+            None => Err("not queried yet".to_string()),
+        }
+    }
+
+    fn out_of_date(&self) -> Vec<String> {
+        self.config
+            .nodes
+            .iter()
+            .filter(|node| self.status(&node.name).is_ok_and(|m| !m.is_empty()))
+            .map(|node| node.name.clone())
+            .collect()
+    }
+
+    fn start_batch(&mut self) {
+        self.report = None;
+        self.batch = Some(Batch {
+            queue: self.out_of_date().into(),
+            current: None,
+            results: Vec::new(),
+            verifying: false,
+        });
+        self.flash_next();
+    }
+
+    fn flash_next(&mut self) {
+        let Some(batch) = &mut self.batch else {
+            return;
+        };
+        batch.current = None;
+
+        while let Some(name) = batch.queue.pop_front() {
+            let node = self.config.nodes.iter().find(|n| n.name == name);
+            let image = self.images.get(&name).and_then(|i| i.as_ref().ok());
+            let (Some(node), Some(image)) = (node, image) else {
+                batch
+                    .results
+                    .push((name, Err("node or image disappeared".to_string())));
+                continue;
+            };
+            log::info!("Batch flashing {} with {}", name, image.path.display());
+            batch.current = Some((name, flasher::FlashProgress::WaitingForNode));
+            self.ui_to_can_tx
+                .send(messages::MsgFromUi::StartFlash(flasher::FlashJob {
+                    node: node.clone(),
+                    image: image.clone(),
+                }))
+                .expect("Failed to send StartFlash");
+            return;
+        }
+
+        batch.verifying = true;
+        self.query();
+    }
+
+    fn build_report(&self, batch: &Batch) -> String {
+        let mut lines = vec![format!(
+            "Firmware report {} against release {}",
+            chrono::Local::now().format("%Y-%m-%d %H:%M"),
+            if self.manifest.release.is_empty() {
+                "(untagged)"
+            } else {
+                &self.manifest.release
+            }
+        )];
+        for node in &self.config.nodes {
+            let flashed = batch
+                .results
+                .iter()
+                .find(|(name, _)| *name == node.name)
+                .map(|(_, result)| result);
+            let state = match self.status(&node.name) {
+                Ok(mismatches) if mismatches.is_empty() => "up to date".to_string(),
+                Ok(mismatches) => format!("OUT OF DATE ({})", mismatches.join(", ")),
+                Err(e) => format!("UNKNOWN ({})", e),
+            };
+            let line = match flashed {
+                Some(Ok(elapsed)) => format!(
+                    "{}: flashed in {:.1} s, {}",
+                    node.name,
+                    elapsed.as_secs_f64(),
+                    state
+                ),
+                Some(Err(e)) => format!("{}: FLASH FAILED ({}), {}", node.name, e, state),
+                None => format!("{}: {}", node.name, state),
+            };
+            lines.push(line);
+        }
+        lines.join("\n")
+    }
+
+    pub fn show(&mut self, ui: &mut egui::Ui) -> egui_tiles::UiResponse {
+        let batch_running = self.batch.is_some();
+        ui.horizontal(|ui| {
+            ui.heading(format!("🚗 {}", self.title));
+            ui.add_enabled_ui(!batch_running, |ui| {
+                if ui
+                    .button("⟳ Reload")
+                    .on_hover_text(format!(
+                        "Read {} and {} again",
+                        bootloader::BOOTLOADER_CONFIG_FILE,
+                        inventory::MANIFEST_FILE
+                    ))
+                    .clicked()
+                {
+                    self.config = bootloader::BootloaderConfig::load();
+                    self.manifest = inventory::Manifest::load();
+                    self.infos.clear();
+                    self.images.clear();
+                }
+            });
+        });
+        if !self.manifest.release.is_empty() {
+            ui.label(format!("Release {}", self.manifest.release));
+        }
+        ui.separator();
+
+        if self.config.nodes.is_empty() {
+            ui.label(
+                egui::RichText::new(format!(
+                    "No nodes defined. Add them to {} and the expected images to {} in {}.",
+                    bootloader::BOOTLOADER_CONFIG_FILE,
+                    inventory::MANIFEST_FILE,
+                    config::config_dir().display()
+                ))
+                .italics()
+                .weak(),
+            );
+            return egui_tiles::UiResponse::None;
+        }
+
+        ui.horizontal(|ui| {
+            ui.add_enabled_ui(!batch_running, |ui| {
+                if ui.button("🔍 Query all").clicked() {
+                    self.report = None;
+                    self.query();
+                }
+                let out_of_date = self.out_of_date().len();
+                if ui
+                    .add_enabled(
+                        out_of_date > 0 && self.querying.is_empty(),
+                        egui::Button::new(format!("⚡ Flash out of date ({})", out_of_date)),
+                    )
+                    .on_hover_text("Flash the nodes one after another, then query every node again")
+                    .clicked()
+                {
+                    self.start_batch();
+                }
+            });
+            if batch_running && ui.button("⏹ Abort").clicked() {
+                if let Some(batch) = &mut self.batch {
+                    batch.queue.clear();
+                }
+                self.ui_to_can_tx
+                    .send(messages::MsgFromUi::AbortFlash)
+                    .expect("Failed to send AbortFlash");
+            }
+            if !self.querying.is_empty() {
+                ui.add(egui::Spinner::new());
+            }
+        });
+
+        if let Some(batch) = &self.batch {
+            match &batch.current {
+                Some((node, progress)) => {
+                    ui.label(format!(
+                        "Flashing {}: {} ({} left in the queue)",
+                        node,
+                        progress.display(),
+                        batch.queue.len()
+                    ));
+                }
+                None if batch.verifying => {
+                    ui.label("Querying every node again to check the result");
+                }
+                None => {}
+            }
+        }
+        ui.separator();
+
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            egui::Grid::new((&self.title, "fleet_grid"))
+                .num_columns(6)
+                .striped(true)
+                .spacing([12.0, 4.0])
+                .show(ui, |ui| {
+                    for header in [
+                        "Node",
+                        "Bootloader",
+                        "Firmware",
+                        "Build",
+                        "Image CRC",
+                        "Status",
+                    ] {
+                        ui.label(egui::RichText::new(header).strong());
+                    }
+                    ui.end_row();
+
+                    for node in &self.config.nodes {
+                        ui.label(&node.name);
+                        match self.infos.get(&node.name) {
+                            Some(Ok(info)) => {
+                                ui.label(info.bootloader_version.to_string());
+                                ui.label(info.firmware_version_display());
+                                ui.label(
+                                    egui::RichText::new(info.build_hash_display()).monospace(),
+                                );
+                                ui.label(
+                                    egui::RichText::new(format!("0x{:08X}", info.image_crc))
+                                        .monospace(),
+                                );
+                            }
+                            _ => {
+                                for _ in 0..4 {
+                                    ui.label("-");
+                                }
+                            }
+                        }
+
+                        if self.querying.contains(&node.name) {
+                            ui.label(egui::RichText::new("querying...").weak());
+                        } else {
+                            match self.status(&node.name) {
+                                Ok(mismatches) if mismatches.is_empty() => {
+                                    ui.colored_label(egui::Color32::GREEN, "✔ up to date");
+                                }
+                                Ok(mismatches) => {
+                                    ui.colored_label(ui.visuals().warn_fg_color, "⚠ out of date")
+                                        .on_hover_text(mismatches.join("\n"));
+                                }
+                                Err(e) => {
+                                    ui.colored_label(ui.visuals().error_fg_color, e);
+                                }
+                            }
+                        }
+                        ui.end_row();
+                    }
+                });
+
+            if let Some(report) = &self.report {
+                ui.separator();
+                ui.horizontal(|ui| {
+                    ui.label(egui::RichText::new("Report").strong());
+                    if ui.button("📋 Copy").clicked() {
+                        ui.ctx().copy_text(report.clone());
+                    }
+                });
+                ui.label(egui::RichText::new(report).monospace());
+            }
+        });
+
+        egui_tiles::UiResponse::None
+    }
+
+    pub fn handle_can_message(&mut self, msg: &messages::MsgFromCan) {
+        match msg {
+            messages::MsgFromCan::NodeInfo { node, info } if self.querying.remove(node) => {
+                self.infos.insert(node.clone(), info.clone());
+                let verified = self
+                    .batch
+                    .as_ref()
+                    .is_some_and(|b| b.verifying && self.querying.is_empty());
+                if verified && let Some(batch) = self.batch.take() {
+                    let report = self.build_report(&batch);
+                    log::info!("{}", report);
+                    self.report = Some(report);
+                }
+            }
+            messages::MsgFromCan::FlashProgress { node, progress } => {
+                let Some((current, last_progress)) =
+                    self.batch.as_mut().and_then(|b| b.current.as_mut())
+                else {
+                    return;
+                };
+                if current != node {
+                    return;
+                }
+                *last_progress = progress.clone();
+                let result = match progress {
+                    flasher::FlashProgress::Done { elapsed, .. } => Ok(*elapsed),
+                    flasher::FlashProgress::Failed(e) => Err(e.clone()),
+                    _ => return,
+                };
+                if let Some(batch) = &mut self.batch {
+                    batch.results.push((node.clone(), result));
+                }
+                self.flash_next();
+            }
+            _ => {}
+        }
+    }
+}
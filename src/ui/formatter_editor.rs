@@ -0,0 +1,789 @@
+use crate::{action, app, config, formatter, messages, ui};
+use eframe::egui;
+
+// Matches listed in the preview, a `*` rule can match every signal of the DBC
+const MAX_PREVIEW_ROWS: usize = 50;
+
+struct SignalRuleEdit {
+    pattern: String,
+    format: formatter::SignalFormat,
+}
+
+struct MsgRuleEdit {
+    pattern: String,
+    signals: Vec<SignalRuleEdit>,
+}
+
+// The rules checked as a whole, rebuilt after every edit
+struct Checked {
+    msg_globs: Vec<Option<globset::GlobMatcher>>,
+    msg_errors: Vec<Option<String>>,
+    signal_globs: Vec<Vec<Option<globset::GlobMatcher>>>,
+    signal_errors: Vec<Vec<Option<String>>>,
+    // None while any rule has an error
+    preview: Option<formatter::Formatter>,
+}
+
+impl Checked {
+    fn error_count(&self) -> usize {
+        self.msg_errors.iter().flatten().count()
+            + self.signal_errors.iter().flatten().flatten().count()
+    }
+
+    // First rule a signal falls under, the one the formatter uses
+    fn first_match(&self, msg_name: &str, signal_name: &str) -> Option<(usize, usize)> {
+        self.msg_globs
+            .iter()
+            .enumerate()
+            .filter(|(_, glob)| glob.as_ref().is_some_and(|g| g.is_match(msg_name)))
+            .find_map(|(i, _)| {
+                self.signal_globs[i]
+                    .iter()
+                    .position(|glob| glob.as_ref().is_some_and(|g| g.is_match(signal_name)))
+                    .map(|j| (i, j))
+            })
+    }
+}
+
+fn compile_glob(pattern: &str) -> Result<globset::GlobMatcher, String> {
+    globset::Glob::new(pattern)
+        .map(|glob| glob.compile_matcher())
+        .map_err(|e| e.kind().to_string())
+}
+
+enum Edit {
+    MoveMsg(usize, isize),
+    DeleteMsg(usize),
+    MoveSignal(usize, usize, isize),
+    DeleteSignal(usize, usize),
+    AddSignal(usize),
+}
+
+pub struct FormatterEditor {
+    pub title: String,
+    // Where the rules were loaded from and are saved to
+    profile: config::Profile,
+    rules: Vec<MsgRuleEdit>,
+    checked: Checked,
+    load_error: Option<String>,
+    status: Option<Result<String, String>>,
+    dirty: bool,
+    // Rule shown in the preview
+    selected: Option<(usize, usize)>,
+    latest_msgs: std::collections::HashMap<String, messages::ParsedMessage>,
+}
+
+impl FormatterEditor {
+    pub fn new(instance_num: usize, profile: config::Profile) -> Self {
+        let mut editor = Self {
+            title: format!("Formatter Editor #{}", instance_num),
+            profile,
+            rules: Vec::new(),
+            checked: Checked {
+                msg_globs: Vec::new(),
+                msg_errors: Vec::new(),
+                signal_globs: Vec::new(),
+                signal_errors: Vec::new(),
+                preview: None,
+            },
+            load_error: None,
+            status: None,
+            dirty: false,
+            selected: None,
+            latest_msgs: std::collections::HashMap::new(),
+        };
+        editor.load();
+        editor
+    }
+
+    fn load(&mut self) {
+        self.rules.clear();
+        self.load_error = None;
+        match formatter::Formatter::load_config(&self.profile) {
+            Ok(config) => {
+                self.rules = config
+                    .into_iter()
+                    .map(|(pattern, signals)| MsgRuleEdit {
+                        pattern,
+                        signals: signals
+                            .into_iter()
+                            .map(|(pattern, rule)| SignalRuleEdit {
+                                pattern,
+                                format: rule.into(),
+                            })
+                            .collect(),
+                    })
+                    .collect();
+            }
+            Err(e) => self.load_error = Some(e),
+        }
+        self.dirty = false;
+        self.selected = None;
+        self.check();
+    }
+
+    // Short form for rules that only set the display format, as people write them by hand
+    fn to_config(&self) -> formatter::FormatterConfig {
+        self.rules
+            .iter()
+            .map(|msg_rule| {
+                let signals = msg_rule
+                    .signals
+                    .iter()
+                    .map(|signal_rule| {
+                        let only_format = formatter::SignalFormat {
+                            format: None,
+                            ..signal_rule.format.clone()
+                        } == formatter::SignalFormat::default();
+                        let rule = match &signal_rule.format.format {
+                            Some(formatting) if only_format => {
+                                formatter::SignalRule::Format(formatting.clone())
+                            }
+                            _ => formatter::SignalRule::Full(signal_rule.format.clone()),
+                        };
+                        (signal_rule.pattern.clone(), rule)
+                    })
+                    .collect();
+                (msg_rule.pattern.clone(), signals)
+            })
+            .collect()
+    }
+
+    fn check(&mut self) {
+        let mut checked = Checked {
+            msg_globs: Vec::new(),
+            msg_errors: Vec::new(),
+            signal_globs: Vec::new(),
+            signal_errors: Vec::new(),
+            preview: None,
+        };
+        for (i, msg_rule) in self.rules.iter().enumerate() {
+            let glob = compile_glob(&msg_rule.pattern);
+            // The config is a map, a second rule with the same pattern would replace the first
+            let duplicate = self.rules[..i]
+                .iter()
+                .any(|earlier| earlier.pattern == msg_rule.pattern);
+            checked.msg_errors.push(match &glob {
+                Err(e) => Some(e.clone()),
+                Ok(_) if duplicate => Some(format!("\"{}\" is already used", msg_rule.pattern)),
+                Ok(_) => None,
+            });
+            checked.msg_globs.push(glob.ok());
+
+            let mut globs = Vec::new();
+            let mut errors = Vec::new();
+            for (j, signal_rule) in msg_rule.signals.iter().enumerate() {
+                let glob = compile_glob(&signal_rule.pattern);
+                let duplicate = msg_rule.signals[..j]
+                    .iter()
+                    .any(|earlier| earlier.pattern == signal_rule.pattern);
+                errors.push(match &glob {
+                    Err(e) => Some(e.clone()),
+                    Ok(_) if duplicate => {
+                        Some(format!("\"{}\" is already used", signal_rule.pattern))
+                    }
+                    Ok(_) => signal_rule.format.validate().err(),
+                });
+                globs.push(glob.ok());
+            }
+            checked.signal_globs.push(globs);
+            checked.signal_errors.push(errors);
+        }
+        if checked.error_count() == 0 {
+            checked.preview = formatter::Formatter::new(self.to_config()).ok();
+        }
+        self.checked = checked;
+    }
+
+    fn save(&mut self, action_queue: &mut Vec<action::AppAction>) {
+        match formatter::Formatter::save_config(&self.profile, &self.to_config()) {
+            Ok(()) => {
+                self.status = Some(Ok(format!(
+                    "Saved to profile {} and reloaded",
+                    self.profile.name
+                )));
+                self.dirty = false;
+                self.load_error = None;
+                action_queue.push(action::AppAction::ReloadFormatter);
+            }
+            Err(e) => {
+                self.status = Some(Err(format!(
+                    "Failed to write {}: {}",
+                    formatter::FORMATTER_CONFIG_FILE,
+                    e
+                )));
+            }
+        }
+    }
+
+    pub fn handle_can_message(&mut self, msg: &messages::MsgFromCan) {
+        if let messages::MsgFromCan::ParsedMessage(parsed_msg) = msg {
+            self.latest_msgs
+                .insert(parsed_msg.decoded.name.clone(), parsed_msg.clone());
+        }
+    }
+
+    pub fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        action_queue: &mut Vec<action::AppAction>,
+        parser: Option<&app::ParserInfo>,
+    ) -> egui_tiles::UiResponse {
+        ui.heading(format!("🖌 {}", self.title));
+        ui.label(
+            egui::RichText::new(format!(
+                "{} of profile {}. The first rule matching a message and then a signal is used, \
+                 glob patterns: * any text, ? one character, [ab] either.",
+                formatter::FORMATTER_CONFIG_FILE,
+                self.profile.name
+            ))
+            .weak(),
+        );
+        if let Some(e) = &self.load_error {
+            ui.colored_label(
+                ui.visuals().error_fg_color,
+                format!(
+                    "⚠ The file couldn't be read, so no formatting is applied: {}. \
+                     Saving replaces it.",
+                    e
+                ),
+            );
+        }
+
+        let error_count = self.checked.error_count();
+        ui.horizontal(|ui| {
+            if ui.button("➕ Message pattern").clicked() {
+                self.rules.push(MsgRuleEdit {
+                    pattern: "*".to_string(),
+                    signals: Vec::new(),
+                });
+                self.dirty = true;
+                self.check();
+            }
+            let save_label = if self.dirty {
+                "💾 Save and reload *"
+            } else {
+                "💾 Save and reload"
+            };
+            if ui
+                .add_enabled(error_count == 0, egui::Button::new(save_label))
+                .on_disabled_hover_text("Fix the rules marked in red first")
+                .clicked()
+            {
+                self.save(action_queue);
+            }
+            if ui.button("⟳ Revert").clicked() {
+                self.load();
+                self.status = None;
+            }
+            if error_count > 0 {
+                ui.colored_label(
+                    ui.visuals().error_fg_color,
+                    format!("{} rule(s) with errors", error_count),
+                );
+            }
+        });
+        match &self.status {
+            Some(Ok(msg)) => {
+                ui.label(msg);
+            }
+            Some(Err(msg)) => {
+                ui.colored_label(ui.visuals().error_fg_color, msg);
+            }
+            None => {}
+        }
+        ui.separator();
+
+        let msg_defs = parser.map(|p| p.parser.msg_defs()).unwrap_or_default();
+        let mut edit = None;
+        let mut changed = false;
+        egui::ScrollArea::vertical()
+            .id_salt(("formatter_editor_rules", &self.title))
+            .max_height(ui.available_height() * 0.6)
+            .show(ui, |ui| {
+                for (i, msg_rule) in self.rules.iter_mut().enumerate() {
+                    let msg_glob = self.checked.msg_globs.get(i).cloned().flatten();
+                    egui::Frame::group(ui.style()).show(ui, |ui| {
+                        ui.horizontal(|ui| {
+                            if ui.small_button("⬆").clicked() {
+                                edit = Some(Edit::MoveMsg(i, -1));
+                            }
+                            if ui.small_button("⬇").clicked() {
+                                edit = Some(Edit::MoveMsg(i, 1));
+                            }
+                            ui.label("Messages");
+                            changed |= ui
+                                .add(
+                                    egui::TextEdit::singleline(&mut msg_rule.pattern)
+                                        .desired_width(160.0)
+                                        .font(egui::TextStyle::Monospace),
+                                )
+                                .changed();
+                            if let Some(glob) = &msg_glob {
+                                let names: Vec<&str> = msg_defs
+                                    .iter()
+                                    .filter(|m| glob.is_match(&m.name))
+                                    .map(|m| m.name.as_str())
+                                    .collect();
+                                match_count_label(ui, names.len(), "message", &names);
+                            }
+                            if ui.small_button("🗑").clicked() {
+                                edit = Some(Edit::DeleteMsg(i));
+                            }
+                        });
+                        if let Some(Some(e)) = self.checked.msg_errors.get(i) {
+                            ui.colored_label(ui.visuals().error_fg_color, format!("⚠ {}", e));
+                        }
+
+                        for (j, signal_rule) in msg_rule.signals.iter_mut().enumerate() {
+                            ui.horizontal(|ui| {
+                                ui.add_space(24.0);
+                                if ui.small_button("⬆").clicked() {
+                                    edit = Some(Edit::MoveSignal(i, j, -1));
+                                }
+                                if ui.small_button("⬇").clicked() {
+                                    edit = Some(Edit::MoveSignal(i, j, 1));
+                                }
+                                ui.label("Signals");
+                                changed |= ui
+                                    .add(
+                                        egui::TextEdit::singleline(&mut signal_rule.pattern)
+                                            .desired_width(140.0)
+                                            .font(egui::TextStyle::Monospace),
+                                    )
+                                    .changed();
+                                let signal_glob = self
+                                    .checked
+                                    .signal_globs
+                                    .get(i)
+                                    .and_then(|globs| globs.get(j))
+                                    .cloned()
+                                    .flatten();
+                                if let (Some(msg_glob), Some(signal_glob)) =
+                                    (&msg_glob, &signal_glob)
+                                {
+                                    let names: Vec<String> = msg_defs
+                                        .iter()
+                                        .filter(|m| msg_glob.is_match(&m.name))
+                                        .flat_map(|m| {
+                                            m.signals
+                                                .iter()
+                                                .filter(|s| signal_glob.is_match(&s.name))
+                                                .map(|s| format!("{}.{}", m.name, s.name))
+                                        })
+                                        .collect();
+                                    let names: Vec<&str> =
+                                        names.iter().map(String::as_str).collect();
+                                    match_count_label(ui, names.len(), "signal", &names);
+                                }
+                                if ui
+                                    .selectable_label(self.selected == Some((i, j)), "👁 Preview")
+                                    .clicked()
+                                {
+                                    self.selected = Some((i, j));
+                                }
+                                if ui.small_button("🗑").clicked() {
+                                    edit = Some(Edit::DeleteSignal(i, j));
+                                }
+                            });
+                            ui.horizontal(|ui| {
+                                ui.add_space(48.0);
+                                changed |=
+                                    format_ui(ui, (&self.title, i, j), &mut signal_rule.format);
+                            });
+                            if let Some(Some(e)) = self
+                                .checked
+                                .signal_errors
+                                .get(i)
+                                .and_then(|errors| errors.get(j))
+                            {
+                                ui.horizontal(|ui| {
+                                    ui.add_space(48.0);
+                                    ui.colored_label(
+                                        ui.visuals().error_fg_color,
+                                        format!("⚠ {}", e),
+                                    );
+                                });
+                            }
+                        }
+                        ui.horizontal(|ui| {
+                            ui.add_space(24.0);
+                            if ui.small_button("➕ Signal pattern").clicked() {
+                                edit = Some(Edit::AddSignal(i));
+                            }
+                        });
+                    });
+                }
+            });
+
+        if let Some(edit) = edit {
+            self.apply_edit(edit);
+            changed = true;
+        }
+        if changed {
+            self.dirty = true;
+            self.status = None;
+            self.check();
+        }
+
+        ui.separator();
+        self.show_preview(ui, &msg_defs);
+
+        egui_tiles::UiResponse::None
+    }
+
+    fn apply_edit(&mut self, edit: Edit) {
+        let moved =
+            |len: usize, idx: usize, by: isize| idx.checked_add_signed(by).filter(|&to| to < len);
+        match edit {
+            Edit::MoveMsg(i, by) => {
+                if let Some(to) = moved(self.rules.len(), i, by) {
+                    self.rules.swap(i, to);
+                }
+            }
+            Edit::DeleteMsg(i) => {
+                self.rules.remove(i);
+            }
+            Edit::MoveSignal(i, j, by) => {
+                let signals = &mut self.rules[i].signals;
+                if let Some(to) = moved(signals.len(), j, by) {
+                    signals.swap(j, to);
+                }
+            }
+            Edit::DeleteSignal(i, j) => {
+                self.rules[i].signals.remove(j);
+            }
+            Edit::AddSignal(i) => {
+                self.rules[i].signals.push(SignalRuleEdit {
+                    pattern: "*".to_string(),
+                    format: formatter::SignalFormat {
+                        format: Some(formatter::Formatting::Decimal(2)),
+                        ..Default::default()
+                    },
+                });
+            }
+        }
+        // Indices moved, the preview would show another rule
+        self.selected = None;
+    }
+
+    // Every signal the selected rule matches, formatted with the unsaved rules
+    fn show_preview(&self, ui: &mut egui::Ui, msg_defs: &[can_dbc::Message]) {
+        let Some((i, j)) = self.selected else {
+            ui.label(egui::RichText::new("👁 Preview a signal pattern to see its output").weak());
+            return;
+        };
+        let (Some(Some(msg_glob)), Some(Some(signal_glob))) = (
+            self.checked.msg_globs.get(i),
+            self.checked
+                .signal_globs
+                .get(i)
+                .and_then(|globs| globs.get(j)),
+        ) else {
+            return;
+        };
+        if msg_defs.is_empty() {
+            ui.label(egui::RichText::new("Load a DBC to preview").weak());
+            return;
+        }
+        let Some(preview) = &self.checked.preview else {
+            ui.colored_label(
+                ui.visuals().warn_fg_color,
+                "The preview is shown once every rule is valid",
+            );
+            return;
+        };
+
+        let theme = ui::theme::get_theme(ui.ctx());
+        let matches: Vec<(&can_dbc::Message, &can_dbc::Signal)> = msg_defs
+            .iter()
+            .filter(|m| msg_glob.is_match(&m.name))
+            .flat_map(|m| {
+                m.signals
+                    .iter()
+                    .filter(|s| signal_glob.is_match(&s.name))
+                    .map(move |s| (m, s))
+            })
+            .collect();
+
+        egui::ScrollArea::vertical()
+            .id_salt(("formatter_editor_preview", &self.title))
+            .show(ui, |ui| {
+                egui::Grid::new(("formatter_editor_preview_grid", &self.title))
+                    .num_columns(4)
+                    .striped(true)
+                    .show(ui, |ui| {
+                        ui.label(egui::RichText::new("Signal").strong());
+                        ui.label(egui::RichText::new("Raw").strong());
+                        ui.label(egui::RichText::new("Formatted").strong());
+                        ui.label("");
+                        ui.end_row();
+
+                        for (msg, sig) in matches.iter().take(MAX_PREVIEW_ROWS) {
+                            ui.label(
+                                egui::RichText::new(format!("{}.{}", msg.name, sig.name))
+                                    .monospace(),
+                            );
+                            let live = self
+                                .latest_msgs
+                                .get(&msg.name)
+                                .and_then(|m| m.decoded.signals.get(&sig.name));
+                            match live {
+                                Some(signal) => {
+                                    ui.label(format!("{} {}", signal.value.physical, sig.unit));
+                                    let mut formatted = egui::RichText::new(preview.format(
+                                        &msg.name,
+                                        &sig.name,
+                                        Some(sig),
+                                        Some(&signal.unit),
+                                        &signal.value,
+                                    ))
+                                    .monospace();
+                                    if let Some(color) = preview.color(
+                                        &msg.name,
+                                        &sig.name,
+                                        &signal.unit,
+                                        signal.value.physical,
+                                        signal.value.enum_label.as_deref(),
+                                        &theme,
+                                    ) {
+                                        formatted = formatted.color(color).strong();
+                                    }
+                                    ui.label(formatted);
+                                }
+                                None => {
+                                    ui.label(egui::RichText::new("no data").weak());
+                                    ui.label("");
+                                }
+                            }
+                            match self.checked.first_match(&msg.name, &sig.name) {
+                                Some(first) if first != (i, j) => {
+                                    ui.label(
+                                        egui::RichText::new(format!(
+                                            "used by the earlier {} / {}",
+                                            self.rules[first.0].pattern,
+                                            self.rules[first.0].signals[first.1].pattern
+                                        ))
+                                        .color(ui.visuals().warn_fg_color),
+                                    );
+                                }
+                                _ => {
+                                    ui.label("");
+                                }
+                            }
+                            ui.end_row();
+                        }
+                    });
+                if matches.len() > MAX_PREVIEW_ROWS {
+                    ui.label(
+                        egui::RichText::new(format!(
+                            "and {} more",
+                            matches.len() - MAX_PREVIEW_ROWS
+                        ))
+                        .weak(),
+                    );
+                }
+            });
+    }
+}
+
+// "3 messages", with the names on hover
+fn match_count_label(ui: &mut egui::Ui, count: usize, what: &str, names: &[&str]) {
+    let text = format!("{} {}{}", count, what, if count == 1 { "" } else { "s" });
+    let label = if count == 0 {
+        ui.colored_label(ui.visuals().warn_fg_color, text)
+    } else {
+        ui.label(egui::RichText::new(text).weak())
+    };
+    if count > 0 {
+        label.on_hover_ui(|ui| {
+            for name in names.iter().take(MAX_PREVIEW_ROWS) {
+                ui.monospace(*name);
+            }
+            if names.len() > MAX_PREVIEW_ROWS {
+                ui.label(format!("and {} more", names.len() - MAX_PREVIEW_ROWS));
+            }
+        });
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum FormatKind {
+    Default,
+    Decimal,
+    Hex,
+    Binary,
+}
+
+fn format_ui(
+    ui: &mut egui::Ui,
+    id: (&String, usize, usize),
+    format: &mut formatter::SignalFormat,
+) -> bool {
+    let mut changed = false;
+
+    let mut kind = match format.format {
+        None => FormatKind::Default,
+        Some(formatter::Formatting::Decimal(_)) => FormatKind::Decimal,
+        Some(formatter::Formatting::Hex) => FormatKind::Hex,
+        Some(formatter::Formatting::Binary) => FormatKind::Binary,
+    };
+    let old_kind = kind;
+    egui::ComboBox::from_id_salt(("formatter_editor_kind", id))
+        .selected_text(match kind {
+            FormatKind::Default => "Default",
+            FormatKind::Decimal => "Decimals",
+            FormatKind::Hex => "Hex",
+            FormatKind::Binary => "Binary",
+        })
+        .show_ui(ui, |ui| {
+            ui.selectable_value(&mut kind, FormatKind::Default, "Default")
+                .on_hover_text("Enum label, or 2 decimals");
+            ui.selectable_value(&mut kind, FormatKind::Decimal, "Decimals");
+            ui.selectable_value(&mut kind, FormatKind::Hex, "Hex");
+            ui.selectable_value(&mut kind, FormatKind::Binary, "Binary");
+        });
+    if kind != old_kind {
+        format.format = match kind {
+            FormatKind::Default => None,
+            FormatKind::Decimal => Some(formatter::Formatting::Decimal(2)),
+            FormatKind::Hex => Some(formatter::Formatting::Hex),
+            FormatKind::Binary => Some(formatter::Formatting::Binary),
+        };
+        changed = true;
+    }
+    if let Some(formatter::Formatting::Decimal(places)) = &mut format.format {
+        changed |= ui.add(egui::DragValue::new(places).range(0..=9)).changed();
+    }
+
+    ui.label("Convert to");
+    changed |= optional_text(ui, &mut format.convert, "km/h", 60.0).changed();
+    ui.label("from");
+    changed |= optional_text(ui, &mut format.from, "DBC unit", 60.0)
+        .on_hover_text("Only needed when the DBC's unit is missing or spelled differently")
+        .changed();
+    ui.label("×");
+    changed |= optional_number(ui, &mut format.scale, 1.0);
+    ui.label("+");
+    changed |= optional_number(ui, &mut format.offset, 0.0);
+    ui.label("Unit");
+    changed |= optional_text(ui, &mut format.unit, "DBC unit", 50.0).changed();
+
+    let color_count = format.colors.len();
+    ui.menu_button(format!("🎨 Colors ({})", color_count), |ui| {
+        changed |= colors_ui(ui, &mut format.colors);
+    });
+
+    changed
+}
+
+// Empty text is unset
+fn optional_text(
+    ui: &mut egui::Ui,
+    value: &mut Option<String>,
+    hint: &str,
+    width: f32,
+) -> egui::Response {
+    let mut text = value.clone().unwrap_or_default();
+    let response = ui.add(
+        egui::TextEdit::singleline(&mut text)
+            .hint_text(hint)
+            .desired_width(width),
+    );
+    if response.changed() {
+        *value = Some(text.trim().to_string()).filter(|t| !t.is_empty());
+    }
+    response
+}
+
+// Unset shows the value that has no effect, and is unset again when set to it
+fn optional_number(ui: &mut egui::Ui, value: &mut Option<f64>, neutral: f64) -> bool {
+    let mut number = value.unwrap_or(neutral);
+    let changed = ui
+        .add(egui::DragValue::new(&mut number).speed(0.01))
+        .changed();
+    if changed {
+        *value = Some(number).filter(|n| *n != neutral);
+    }
+    changed
+}
+
+fn colors_ui(ui: &mut egui::Ui, colors: &mut Vec<formatter::ColorRule>) -> bool {
+    let mut changed = false;
+    let theme = ui::theme::get_theme(ui.ctx());
+    let mut to_delete = None;
+    ui.label(
+        egui::RichText::new(
+            "The first rule whose conditions all hold colors the value. Values are compared \
+             after conversion, labels are the DBC's value descriptions.",
+        )
+        .weak(),
+    );
+    egui::Grid::new("formatter_editor_colors")
+        .num_columns(6)
+        .show(ui, |ui| {
+            ui.label("Above");
+            ui.label("Below");
+            ui.label("Equals");
+            ui.label("Label");
+            ui.label("Color");
+            ui.label("");
+            ui.end_row();
+            for (k, rule) in colors.iter_mut().enumerate() {
+                changed |= optional_condition(ui, &mut rule.above);
+                changed |= optional_condition(ui, &mut rule.below);
+                changed |= optional_condition(ui, &mut rule.equals);
+                changed |= optional_text(ui, &mut rule.label, "any", 70.0).changed();
+                ui.horizontal(|ui| {
+                    egui::ComboBox::from_id_salt(("formatter_editor_color", k))
+                        .selected_text(&rule.color)
+                        .show_ui(ui, |ui| {
+                            for name in ["error", "warning", "success", "info"] {
+                                changed |= ui
+                                    .selectable_value(&mut rule.color, name.to_string(), name)
+                                    .changed();
+                            }
+                        });
+                    changed |= ui
+                        .add(egui::TextEdit::singleline(&mut rule.color).desired_width(70.0))
+                        .on_hover_text("Or #rrggbb")
+                        .changed();
+                    ui.colored_label(rule.resolve(&theme), "⬛");
+                });
+                if ui.small_button("🗑").clicked() {
+                    to_delete = Some(k);
+                }
+                ui.end_row();
+            }
+        });
+    if let Some(k) = to_delete {
+        colors.remove(k);
+        changed = true;
+    }
+    if ui.button("➕ Color rule").clicked() {
+        colors.push(formatter::ColorRule {
+            above: None,
+            below: None,
+            equals: None,
+            label: None,
+            color: "error".to_string(),
+        });
+        changed = true;
+    }
+    changed
+}
+
+fn optional_condition(ui: &mut egui::Ui, value: &mut Option<f64>) -> bool {
+    let mut enabled = value.is_some();
+    let mut changed = false;
+    ui.horizontal(|ui| {
+        if ui.checkbox(&mut enabled, "").changed() {
+            *value = enabled.then_some(0.0);
+            changed = true;
+        }
+        if let Some(number) = value {
+            changed |= ui.add(egui::DragValue::new(number).speed(0.1)).changed();
+        }
+    });
+    changed
+}
@@ -20,6 +20,11 @@ pub struct GgPlot {
     history_window_minutes: f64,
 }
 
+#[derive(serde::Serialize, serde::Deserialize, Clone, PartialEq)]
+pub struct GgPlotConfig {
+    pub history_window_minutes: f64,
+}
+
 impl GgPlot {
     pub fn new(instance_num: usize) -> Self {
         Self {
@@ -30,6 +35,20 @@ impl GgPlot {
         }
     }
 
+    pub fn clear(&mut self) {
+        self.points_g.clear();
+    }
+
+    pub fn config(&self) -> GgPlotConfig {
+        GgPlotConfig {
+            history_window_minutes: self.history_window_minutes,
+        }
+    }
+
+    pub fn apply_config(&mut self, config: GgPlotConfig) {
+        self.history_window_minutes = config.history_window_minutes;
+    }
+
     fn build_ring_points() -> Vec<(String, Vec<[f64; 2]>)> {
         [0.5, 1.0, 1.5]
             .iter()
@@ -147,7 +166,7 @@ impl GgPlot {
             ui.add(egui::Slider::new(&mut self.history_window_minutes, 0.5..=20.0).suffix(" min"));
             ui.separator();
             if ui.button("🗑 Clear").clicked() {
-                self.points_g.clear();
+                self.clear();
             }
         });
         ui.add_space(4.0);
@@ -1,4 +1,4 @@
-use crate::{app, messages, util};
+use crate::{app, dbc, messages, util};
 use eframe::egui;
 
 use super::dbc_msg_picker::{DbcMsgPickerState, no_dbc_placeholder};
@@ -17,6 +17,16 @@ pub struct Jitter {
     interval_count: u64,
     max_pct: f64,
     sum_pct: f64,
+
+    dbc_warning: Option<String>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, PartialEq)]
+pub struct JitterConfig {
+    // By name, like after a DBC reload
+    pub msg_name: Option<String>,
+    pub period_ms: usize,
+    pub active: bool,
 }
 
 impl Jitter {
@@ -35,22 +45,94 @@ impl Jitter {
             interval_count: 0,
             max_pct: 0.0,
             sum_pct: 0.0,
+
+            dbc_warning: None,
+        }
+    }
+
+    pub fn config(&self) -> JitterConfig {
+        JitterConfig {
+            msg_name: self.selected_msg.as_ref().map(|m| m.name.clone()),
+            period_ms: self.period_ms,
+            active: self.active,
+        }
+    }
+
+    pub fn apply_config(
+        &mut self,
+        config: JitterConfig,
+        parser: Option<&dbc::merged::MergedParser>,
+    ) -> Vec<String> {
+        self.period_ms = config.period_ms;
+        let Some(msg_name) = config.msg_name else {
+            return Vec::new();
+        };
+        match parser.and_then(|p| p.msg_defs().into_iter().find(|m| m.name == msg_name)) {
+            Some(msg) => {
+                self.selected_msg = Some(msg);
+                self.active = config.active;
+                Vec::new()
+            }
+            None => {
+                let warning = format!("{}: {} is not in the DBC", self.title, msg_name);
+                self.dbc_warning = Some(warning.clone());
+                vec![warning]
+            }
         }
     }
 
-    fn reset_stats(&mut self) {
+    pub fn reset_stats(&mut self) {
         self.last_timestamp = None;
         self.interval_count = 0;
         self.max_pct = 0.0;
         self.sum_pct = 0.0;
     }
 
+    pub fn select_msg(&mut self, msg: can_dbc::Message) {
+        if self.selected_msg.as_ref().is_none_or(|m| m.id != msg.id) {
+            self.reset_stats();
+        }
+        self.selected_msg = Some(msg);
+        self.dbc_warning = None;
+    }
+
     fn selected_msg_id(&self) -> Option<u32> {
         self.selected_msg
             .as_ref()
             .map(|m| util::can::can_dbc_to_u32_without_extid_flag(&m.id))
     }
 
+    pub fn on_dbc_reloaded(&mut self, parser: &dbc::merged::MergedParser) -> Vec<String> {
+        let Some(selected_msg) = &self.selected_msg else {
+            return Vec::new();
+        };
+
+        match parser
+            .msg_defs()
+            .into_iter()
+            .find(|m| m.name == selected_msg.name)
+        {
+            Some(new_msg) => {
+                if new_msg.id != selected_msg.id {
+                    // Intervals measured on the old ID don't mean anything for the new one
+                    self.reset_stats();
+                }
+                self.selected_msg = Some(new_msg);
+                Vec::new()
+            }
+            None => {
+                let warning = format!(
+                    "{}: {} no longer exists in the DBC",
+                    self.title, selected_msg.name
+                );
+                self.dbc_warning = Some(warning.clone());
+                self.selected_msg = None;
+                self.active = false;
+                vec![warning]
+            }
+        }
+    }
+
     pub fn handle_can_message(&mut self, msg: &messages::MsgFromCan) {
         if !self.active {
             return;
@@ -103,6 +185,11 @@ impl Jitter {
                             .show(ui, &parser.parser, self.selected_msg.is_none())
                     {
                         self.selected_msg = Some(msg);
+                        self.dbc_warning = None;
+                    }
+
+                    if let Some(warning) = &self.dbc_warning {
+                        ui.label(egui::RichText::new(warning).color(ui.visuals().warn_fg_color));
                     }
 
                     if let Some(selected_msg) = &self.selected_msg {
@@ -1,5 +1,8 @@
 use crate::app;
 use crate::daq_log_parse;
+use crate::dbc;
+use crate::derived;
+use crate::formatter;
 use eframe::egui;
 
 pub struct LogParser {
@@ -9,19 +12,32 @@ pub struct LogParser {
 
     output_prefix: String,
 
-    bus_0_dbc: Option<std::path::PathBuf>,
+    bus_0_dbcs: Vec<std::path::PathBuf>,
     bus_0_use_override: bool,
-    bus_1_dbc: Option<std::path::PathBuf>,
+    bus_1_dbcs: Vec<std::path::PathBuf>,
     bus_1_use_override: bool,
 
     parse_to_ui_rx: Option<std::sync::mpsc::Receiver<MsgFromParserThread>>,
     parse_text: String,
+    dbc_issues: Option<Vec<dbc::lint::LintIssue>>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, PartialEq)]
+pub struct LogParserConfig {
+    pub logs_dir: Option<std::path::PathBuf>,
+    pub output_dir: Option<std::path::PathBuf>,
+    pub output_prefix: String,
+    pub bus_0_dbcs: Vec<std::path::PathBuf>,
+    pub bus_0_use_override: bool,
+    pub bus_1_dbcs: Vec<std::path::PathBuf>,
+    pub bus_1_use_override: bool,
 }
 
 enum MsgFromParserThread {
     FatalExit(String),
     SuccessExit(String),
     Update(String),
+    DbcCheck(Vec<dbc::lint::LintIssue>),
 }
 
 impl LogParser {
@@ -31,15 +47,38 @@ impl LogParser {
             logs_dir: None,
             output_dir: None,
             output_prefix: "out".to_string(),
-            bus_0_dbc: None,
+            bus_0_dbcs: Vec::new(),
             bus_0_use_override: false,
-            bus_1_dbc: None,
+            bus_1_dbcs: Vec::new(),
             bus_1_use_override: false,
             parse_to_ui_rx: None,
             parse_text: String::new(),
+            dbc_issues: None,
+        }
+    }
+
+    pub fn config(&self) -> LogParserConfig {
+        LogParserConfig {
+            logs_dir: self.logs_dir.clone(),
+            output_dir: self.output_dir.clone(),
+            output_prefix: self.output_prefix.clone(),
+            bus_0_dbcs: self.bus_0_dbcs.clone(),
+            bus_0_use_override: self.bus_0_use_override,
+            bus_1_dbcs: self.bus_1_dbcs.clone(),
+            bus_1_use_override: self.bus_1_use_override,
         }
     }
 
+    pub fn apply_config(&mut self, config: LogParserConfig) {
+        self.logs_dir = config.logs_dir;
+        self.output_dir = config.output_dir;
+        self.output_prefix = config.output_prefix;
+        self.bus_0_dbcs = config.bus_0_dbcs;
+        self.bus_0_use_override = config.bus_0_use_override;
+        self.bus_1_dbcs = config.bus_1_dbcs;
+        self.bus_1_use_override = config.bus_1_use_override;
+    }
+
     fn select_logs_dir(&mut self) {
         if let Some(path) = rfd::FileDialog::new().pick_folder() {
             self.logs_dir = Some(path);
@@ -52,115 +91,145 @@ impl LogParser {
         }
     }
 
-    fn select_bus_dbc(current: &mut Option<std::path::PathBuf>) {
-        if let Some(path) = rfd::FileDialog::new()
+    fn select_bus_dbcs(current: &mut Vec<std::path::PathBuf>) {
+        if let Some(paths) = rfd::FileDialog::new()
             .add_filter("DBC Files", &["dbc"])
-            .pick_file()
+            .pick_files()
         {
-            *current = Some(path);
+            *current = paths;
         }
     }
 
-    fn parse_logs(&mut self, sidebar_parser: Option<&app::ParserInfo>) {
-        let logs_dir = match &self.logs_dir {
-            Some(p) => p,
-            None => {
-                // TODO: make persistent log directories
-                self.parse_text = "Error: Logs directory not selected".to_string();
-                log::error!("{}", self.parse_text);
-                return;
-            }
-        };
+    fn dbc_list_label(paths: &[std::path::PathBuf]) -> String {
+        paths
+            .iter()
+            .map(|p| dbc::merged::file_name(p))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
 
-        let output_dir = match &self.output_dir {
-            Some(p) => p,
-            None => {
-                self.parse_text = "Error: Output directory not selected".to_string();
+    // DBC files for each bus, either the override or the sidebar's. None after reporting an error.
+    fn bus_dbc_paths(
+        &mut self,
+        sidebar_parser: Option<&app::ParserInfo>,
+    ) -> Option<(Vec<std::path::PathBuf>, Vec<std::path::PathBuf>)> {
+        let dbc_paths_bus_0 = if self.bus_0_use_override {
+            if self.bus_0_dbcs.is_empty() {
+                self.parse_text =
+                    "Error: BUS 0 DBC override enabled but no file selected".to_string();
                 log::error!("{}", self.parse_text);
-                return;
-            }
-        };
-
-        let dbc_path_bus_0 = if self.bus_0_use_override {
-            match &self.bus_0_dbc {
-                Some(p) => p.clone(),
-                None => {
-                    self.parse_text =
-                        "Error: BUS 0 DBC override enabled but no file selected".to_string();
-                    log::error!("{}", self.parse_text);
-                    return;
-                }
+                return None;
             }
+            self.bus_0_dbcs.clone()
         } else {
             match sidebar_parser {
-                Some(p) => p.dbc_path.clone(),
+                Some(p) => p.dbc_paths.clone(),
                 None => {
                     self.parse_text = "Error: No DBC selected for BUS 0 (VCAN)".to_string();
                     log::error!("{}", self.parse_text);
-                    return;
+                    return None;
                 }
             }
         };
-        let dbc_path_bus_1 = if self.bus_1_use_override {
-            match &self.bus_1_dbc {
-                Some(p) => p.clone(),
-                None => {
-                    self.parse_text =
-                        "Error: BUS 1 DBC override enabled but no file selected".to_string();
-                    log::error!("{}", self.parse_text);
-                    return;
-                }
+        let dbc_paths_bus_1 = if self.bus_1_use_override {
+            if self.bus_1_dbcs.is_empty() {
+                self.parse_text =
+                    "Error: BUS 1 DBC override enabled but no file selected".to_string();
+                log::error!("{}", self.parse_text);
+                return None;
             }
+            self.bus_1_dbcs.clone()
         } else {
             match sidebar_parser {
-                Some(p) => p.dbc_path.clone(),
+                Some(p) => p.dbc_paths.clone(),
                 None => {
                     self.parse_text = "Error: No DBC selected for BUS 1 (MCAN)".to_string();
                     log::error!("{}", self.parse_text);
-                    return;
+                    return None;
                 }
             }
         };
 
+        Some((dbc_paths_bus_0, dbc_paths_bus_1))
+    }
+
+    // Runs the same checks as the parse thread, without needing a logs directory
+    fn check_dbcs(&mut self, sidebar_parser: Option<&app::ParserInfo>) {
+        let Some((dbc_paths_bus_0, dbc_paths_bus_1)) = self.bus_dbc_paths(sidebar_parser) else {
+            return;
+        };
+
+        match check_bus_dbcs(&dbc_paths_bus_0, &dbc_paths_bus_1) {
+            Ok((_, _, issues)) => {
+                self.parse_text = dbc_check_summary(&issues);
+                self.dbc_issues = Some(issues);
+            }
+            Err(e) => {
+                self.parse_text = format!("Error: {}", e);
+                log::error!("{}", self.parse_text);
+            }
+        }
+    }
+
+    fn parse_logs(
+        &mut self,
+        sidebar_parser: Option<&app::ParserInfo>,
+        formatter: &Option<formatter::Formatter>,
+    ) {
+        let logs_dir = match &self.logs_dir {
+            Some(p) => p.clone(),
+            None => {
+                // TODO: make persistent log directories
+                self.parse_text = "Error: Logs directory not selected".to_string();
+                log::error!("{}", self.parse_text);
+                return;
+            }
+        };
+
+        let output_dir = match &self.output_dir {
+            Some(p) => p.clone(),
+            None => {
+                self.parse_text = "Error: Output directory not selected".to_string();
+                log::error!("{}", self.parse_text);
+                return;
+            }
+        };
+
+        let Some((dbc_paths_bus_0, dbc_paths_bus_1)) = self.bus_dbc_paths(sidebar_parser) else {
+            return;
+        };
+
         let prefix = if self.output_prefix.trim().is_empty() {
             "out".to_string()
         } else {
             self.output_prefix.trim().to_string()
         };
 
-        let logs_dir = logs_dir.clone();
-        let output_dir = output_dir.clone();
-
         let (parse_to_ui_tx, parse_to_ui_rx) = std::sync::mpsc::channel::<MsgFromParserThread>();
         self.parse_to_ui_rx = Some(parse_to_ui_rx);
+        // Columns are in the units the formatter shows them in
+        let formatter = formatter.clone();
 
         std::thread::spawn(move || {
-            log::info!("Using DBC: {:?} for BUS 0 (VCAN)", dbc_path_bus_0);
-            log::info!("Using DBC: {:?} for BUS 1 (MCAN)", dbc_path_bus_1);
+            log::info!("Using DBC(s): {:?} for BUS 0 (VCAN)", dbc_paths_bus_0);
+            log::info!("Using DBC(s): {:?} for BUS 1 (MCAN)", dbc_paths_bus_1);
             log::info!("Parsing logs from: {}", logs_dir.display());
             log::info!("Output to: {} (prefix: {})", output_dir.display(), prefix);
 
-            let Ok(parser_bus_0) = can_decode::Parser::from_dbc_file(&dbc_path_bus_0) else {
-                log::error!(
-                    "Failed to create CAN parser from DBC file for BUS 0: {:?}",
-                    dbc_path_bus_0
-                );
-                let _ = parse_to_ui_tx.send(MsgFromParserThread::FatalExit(
-                    "Failed to create CAN parser from DBC file for BUS 0".to_string(),
-                ));
-                return;
-            };
-
-            let Ok(parser_bus_1) = can_decode::Parser::from_dbc_file(&dbc_path_bus_1) else {
-                log::error!(
-                    "Failed to create CAN parser from DBC file for BUS 1: {:?}",
-                    dbc_path_bus_1
-                );
-                let _ = parse_to_ui_tx.send(MsgFromParserThread::FatalExit(
-                    "Failed to create CAN parser from DBC file for BUS 1".to_string(),
-                ));
-                return;
-            };
+            let (parser_bus_0, parser_bus_1, issues) =
+                match check_bus_dbcs(&dbc_paths_bus_0, &dbc_paths_bus_1) {
+                    Ok(checked) => checked,
+                    Err(e) => {
+                        log::error!("{}", e);
+                        let _ = parse_to_ui_tx.send(MsgFromParserThread::FatalExit(e));
+                        return;
+                    }
+                };
+            // Bad layouts are reported up front instead of as decode errors for every frame
+            for issue in &issues {
+                log::warn!("DBC check: {}", issue);
+            }
+            let _ = parse_to_ui_tx.send(MsgFromParserThread::DbcCheck(issues));
 
             let _ = parse_to_ui_tx.send(MsgFromParserThread::Update("Parsing logs...".to_string()));
 
@@ -169,9 +238,10 @@ impl LogParser {
             let chunked_parsed = daq_log_parse::parse::chunk_parsed(parsed);
             let correlated_chunks = daq_log_parse::correlate::time_correlate_chunks(chunked_parsed);
 
-            let mut table_builder = daq_log_parse::table::TableBuilder::new();
+            let mut table_builder = daq_log_parse::table::TableBuilder::new(formatter);
             table_builder.create_header(&parser_bus_0, "VCAN");
             table_builder.create_header(&parser_bus_1, "MCAN");
+            table_builder.create_derived_header(&derived::DerivedConfig::load().signals);
             table_builder.create_and_write_tables(&output_dir, &prefix, correlated_chunks);
 
             log::info!("Parsing completed successfully");
@@ -186,6 +256,7 @@ impl LogParser {
         &mut self,
         ui: &mut egui::Ui,
         sidebar_parser: Option<&app::ParserInfo>,
+        formatter: &Option<formatter::Formatter>,
     ) -> egui_tiles::UiResponse {
         ui.heading(format!("🔧 {}", self.title));
         ui.separator();
@@ -229,36 +300,28 @@ impl LogParser {
         ui.horizontal(|ui| {
             ui.checkbox(&mut self.bus_0_use_override, "").on_hover_text(
                 "BUS 0 = VCAN (BUS ID bit cleared/0 in firmware).\n\
-                     ☑ Use the DBC selected here.\n\
+                     ☑ Use the DBC(s) selected here.\n\
                      ☐ Fall back to the DBC selected in the sidebar.",
             );
 
             let btn = egui::Button::new("📁 BUS 0 (VCAN)");
             if ui
                 .add_enabled(self.bus_0_use_override, btn)
-                .on_hover_text("Select a DBC file for BUS 0 (VCAN)")
+                .on_hover_text("Select one or more DBC files for BUS 0 (VCAN)")
                 .clicked()
             {
-                Self::select_bus_dbc(&mut self.bus_0_dbc);
+                Self::select_bus_dbcs(&mut self.bus_0_dbcs);
             }
 
             let label_text = if self.bus_0_use_override {
-                match &self.bus_0_dbc {
-                    Some(p) => p
-                        .file_name()
-                        .map(|n| n.to_string_lossy().into_owned())
-                        .unwrap_or_else(|| p.display().to_string()),
-                    None => "None selected".to_string(),
+                if self.bus_0_dbcs.is_empty() {
+                    "None selected".to_string()
+                } else {
+                    Self::dbc_list_label(&self.bus_0_dbcs)
                 }
             } else {
                 match sidebar_parser {
-                    Some(p) => format!(
-                        "{} (sidebar)",
-                        p.dbc_path
-                            .file_name()
-                            .map(|n| n.to_string_lossy().into_owned())
-                            .unwrap_or_else(|| p.dbc_path.display().to_string())
-                    ),
+                    Some(p) => format!("{} (sidebar)", Self::dbc_list_label(&p.dbc_paths)),
                     None => "None selected (sidebar)".to_string(),
                 }
             };
@@ -269,36 +332,28 @@ impl LogParser {
         ui.horizontal(|ui| {
             ui.checkbox(&mut self.bus_1_use_override, "").on_hover_text(
                 "BUS 1 = MCAN (BUS ID bit set/1 in firmware).\n\
-                     ☑ Use the DBC selected here.\n\
+                     ☑ Use the DBC(s) selected here.\n\
                      ☐ Fall back to the DBC selected in the sidebar.",
             );
 
             let btn = egui::Button::new("📁 BUS 1 (MCAN)");
             if ui
                 .add_enabled(self.bus_1_use_override, btn)
-                .on_hover_text("Select a DBC file for BUS 1 (MCAN)")
+                .on_hover_text("Select one or more DBC files for BUS 1 (MCAN)")
                 .clicked()
             {
-                Self::select_bus_dbc(&mut self.bus_1_dbc);
+                Self::select_bus_dbcs(&mut self.bus_1_dbcs);
             }
 
             let label_text = if self.bus_1_use_override {
-                match &self.bus_1_dbc {
-                    Some(p) => p
-                        .file_name()
-                        .map(|n| n.to_string_lossy().into_owned())
-                        .unwrap_or_else(|| p.display().to_string()),
-                    None => "None selected".to_string(),
+                if self.bus_1_dbcs.is_empty() {
+                    "None selected".to_string()
+                } else {
+                    Self::dbc_list_label(&self.bus_1_dbcs)
                 }
             } else {
                 match sidebar_parser {
-                    Some(p) => format!(
-                        "{} (sidebar)",
-                        p.dbc_path
-                            .file_name()
-                            .map(|n| n.to_string_lossy().into_owned())
-                            .unwrap_or_else(|| p.dbc_path.display().to_string())
-                    ),
+                    Some(p) => format!("{} (sidebar)", Self::dbc_list_label(&p.dbc_paths)),
                     None => "None selected (sidebar)".to_string(),
                 }
             };
@@ -309,12 +364,24 @@ impl LogParser {
 
         // Parse button
         let currently_parsing = self.parse_to_ui_rx.is_some();
-        if ui
-            .add_enabled(!currently_parsing, egui::Button::new("▶ Parse Logs"))
-            .clicked()
-        {
-            self.parse_logs(sidebar_parser);
-        }
+        ui.horizontal(|ui| {
+            if ui
+                .add_enabled(!currently_parsing, egui::Button::new("▶ Parse Logs"))
+                .clicked()
+            {
+                self.parse_logs(sidebar_parser, formatter);
+            }
+            if ui
+                .add_enabled(!currently_parsing, egui::Button::new("🔍 Check DBCs"))
+                .on_hover_text(
+                    "Check both buses' DBCs for layout mistakes and IDs defined differently \
+                     on BUS 0 and BUS 1",
+                )
+                .clicked()
+            {
+                self.check_dbcs(sidebar_parser);
+            }
+        });
 
         // Parser thread messages
         if let Some(rx) = &self.parse_to_ui_rx {
@@ -331,6 +398,9 @@ impl LogParser {
                     MsgFromParserThread::Update(text) => {
                         self.parse_text = text;
                     }
+                    MsgFromParserThread::DbcCheck(issues) => {
+                        self.dbc_issues = Some(issues);
+                    }
                 },
                 Err(std::sync::mpsc::TryRecvError::Empty) => {}
                 Err(std::sync::mpsc::TryRecvError::Disconnected) => {
@@ -342,6 +412,63 @@ impl LogParser {
         ui.separator();
         ui.label(&self.parse_text);
 
+        if let Some(issues) = &self.dbc_issues {
+            ui.separator();
+            egui::CollapsingHeader::new(dbc_check_summary(issues))
+                .id_salt(("log_parser_dbc_check", &self.title))
+                .default_open(dbc::lint::has_errors(issues))
+                .show(ui, |ui| {
+                    super::dbc_lint::show_issues(ui, issues);
+                });
+        }
+
         egui_tiles::UiResponse::None
     }
 }
+
+// Both buses' parsers and the issues found in them
+type CheckedDbcs = (
+    dbc::merged::MergedParser,
+    dbc::merged::MergedParser,
+    Vec<dbc::lint::LintIssue>,
+);
+
+// Loads both buses' DBCs and checks them on their own and against each other
+fn check_bus_dbcs(
+    dbc_paths_bus_0: &[std::path::PathBuf],
+    dbc_paths_bus_1: &[std::path::PathBuf],
+) -> Result<CheckedDbcs, String> {
+    let parser_bus_0 = dbc::merged::MergedParser::from_dbc_files(dbc_paths_bus_0).map_err(|e| {
+        format!(
+            "Failed to create CAN parser from DBC files for BUS 0: {}",
+            e
+        )
+    })?;
+    let parser_bus_1 = dbc::merged::MergedParser::from_dbc_files(dbc_paths_bus_1).map_err(|e| {
+        format!(
+            "Failed to create CAN parser from DBC files for BUS 1: {}",
+            e
+        )
+    })?;
+
+    let mut issues = dbc::lint::lint(&parser_bus_0);
+    // The sidebar DBC is commonly used for both buses, no need to report everything twice
+    if dbc_paths_bus_1 != dbc_paths_bus_0 {
+        issues.extend(dbc::lint::lint(&parser_bus_1));
+        issues.extend(dbc::lint::lint_cross_bus(&parser_bus_0, &parser_bus_1));
+    }
+    issues.sort_by_key(|i| i.severity);
+    Ok((parser_bus_0, parser_bus_1, issues))
+}
+
+fn dbc_check_summary(issues: &[dbc::lint::LintIssue]) -> String {
+    let error_count = issues
+        .iter()
+        .filter(|i| i.severity == dbc::lint::Severity::Error)
+        .count();
+    format!(
+        "DBC check: {} error(s), {} warning(s)",
+        error_count,
+        issues.len() - error_count
+    )
+}
@@ -1,15 +1,28 @@
+pub mod alarms;
 pub mod battery;
 pub mod bootloader;
 pub mod bus_load;
 pub mod command_palette;
+pub mod config_errors;
+pub mod dbc_browser;
+pub mod dbc_lint;
 pub mod dbc_msg_picker;
+pub mod dbc_reload;
+pub mod derived_signals;
 pub mod dynamics;
+pub mod fleet;
+pub mod formatter_editor;
 pub mod gg_plot;
 pub mod jitter;
 pub mod log_parser;
+pub mod rest_bus;
 pub mod scope;
 pub mod send;
+pub mod sequences;
+pub mod shortcut_help;
 pub mod sidebar;
 pub mod theme;
+pub mod theme_editor;
+pub mod uds;
 pub mod viewer_list;
 pub mod viewer_table;
@@ -0,0 +1,551 @@
+use crate::{app, can, dbc, formatter, messages, rest_bus, util};
+use eframe::egui;
+
+pub struct RestBus {
+    pub title: String,
+    ui_to_can_tx: std::sync::mpsc::Sender<messages::MsgFromUi>,
+    config: rest_bus::RestBusConfig,
+    rules_owner: rest_bus::RulesOwner,
+    // Messages of the simulated nodes, empty while stopped
+    sim_msgs: Vec<SimMessage>,
+    new_rule_when: String,
+    new_rule_reply: String,
+    warnings: Vec<String>,
+}
+
+struct SimSignal {
+    name: String,
+    value: f64,
+    min: f64,
+    max: f64,
+    unit: String,
+}
+
+struct SimMessage {
+    handle: can::scheduler::SendHandle,
+    name: String,
+    node: String,
+    msg_id: u32,
+    msg_id_with_ext_flag: u32,
+    is_msg_id_extended: bool,
+    // None when the DBC has no cycle time, the message is then only sent by reply rules
+    cycle_ms: Option<usize>,
+    signals: Vec<SimSignal>,
+    msg_bytes: Vec<u8>,
+    sent_count: u64,
+    stats: can::scheduler::PeriodStats,
+}
+
+impl Drop for RestBus {
+    fn drop(&mut self) {
+        // Same as the Send UI, closing the widget stops the simulated nodes
+        for msg in &self.sim_msgs {
+            if let Err(e) = self
+                .ui_to_can_tx
+                .send(messages::MsgFromUi::DeleteSendMessage { handle: msg.handle })
+            {
+                log::error!("Failed to send DeleteSendMessage for {}: {}", msg.name, e);
+            }
+        }
+        if let Err(e) = self.ui_to_can_tx.send(messages::MsgFromUi::SetReplyRules {
+            owner: self.rules_owner,
+            rules: Vec::new(),
+        }) {
+            log::error!("Failed to clear the reply rules of {}: {}", self.title, e);
+        }
+    }
+}
+
+impl RestBus {
+    pub fn new(
+        instance_num: usize,
+        ui_to_can_tx: std::sync::mpsc::Sender<messages::MsgFromUi>,
+    ) -> Self {
+        Self {
+            title: format!("Rest Bus #{}", instance_num),
+            ui_to_can_tx,
+            config: rest_bus::RestBusConfig::load(),
+            rules_owner: can::scheduler::new_handle(),
+            sim_msgs: Vec::new(),
+            new_rule_when: String::new(),
+            new_rule_reply: String::new(),
+            warnings: Vec::new(),
+        }
+    }
+
+    fn is_running(&self) -> bool {
+        !self.sim_msgs.is_empty()
+    }
+
+    fn start(&mut self, parser: &dbc::merged::MergedParser) {
+        self.stop();
+        self.warnings.clear();
+
+        for msg_def in parser.msg_defs() {
+            let node = util::can::transmitter_name(&msg_def.transmitter);
+            if !self.config.nodes.iter().any(|n| n == node) {
+                continue;
+            }
+            let msg_id_with_ext_flag = util::can::can_dbc_to_u32_with_extid_flag(&msg_def.id);
+            let Some(dbc) = parser.dbc_of(msg_id_with_ext_flag) else {
+                continue;
+            };
+
+            let cycle_ms = dbc::attributes::msg_attribute(
+                dbc,
+                &msg_def.id,
+                dbc::attributes::CYCLE_TIME_ATTRIBUTE,
+            )
+            .and_then(dbc::attributes::value_to_f64)
+            .filter(|ms| *ms >= 1.0)
+            .map(|ms| ms.round() as usize);
+
+            let signals = msg_def
+                .signals
+                .iter()
+                .map(|sig| initial_signal(dbc, &msg_def.id, sig))
+                .collect();
+
+            let mut sim_msg = SimMessage {
+                handle: can::scheduler::new_handle(),
+                name: msg_def.name.clone(),
+                node: node.to_string(),
+                msg_id: util::can::can_dbc_to_u32_without_extid_flag(&msg_def.id),
+                msg_id_with_ext_flag,
+                is_msg_id_extended: matches!(msg_def.id, can_dbc::MessageId::Extended(_)),
+                cycle_ms,
+                signals,
+                msg_bytes: Vec::new(),
+                sent_count: 0,
+                stats: can::scheduler::PeriodStats::default(),
+            };
+            let Some(msg_bytes) = encode(parser, &sim_msg) else {
+                self.warnings.push(format!(
+                    "{}: skipped {}, its initial values can't be encoded",
+                    self.title, sim_msg.name
+                ));
+                continue;
+            };
+            sim_msg.msg_bytes = msg_bytes;
+            self.send_cyclic(&sim_msg);
+            self.sim_msgs.push(sim_msg);
+        }
+
+        if self.sim_msgs.is_empty() {
+            self.warnings.push(format!(
+                "{}: the selected nodes don't transmit any message",
+                self.title
+            ));
+            return;
+        }
+        log::info!(
+            "{}: simulating {} message(s) from {:?}",
+            self.title,
+            self.sim_msgs.len(),
+            self.config.nodes
+        );
+        self.send_rules(parser);
+    }
+
+    fn stop(&mut self) {
+        for msg in self.sim_msgs.drain(..) {
+            self.ui_to_can_tx
+                .send(messages::MsgFromUi::DeleteSendMessage { handle: msg.handle })
+                .expect("Failed to send DeleteSendMessage");
+        }
+        self.ui_to_can_tx
+            .send(messages::MsgFromUi::SetReplyRules {
+                owner: self.rules_owner,
+                rules: Vec::new(),
+            })
+            .expect("Failed to send SetReplyRules");
+    }
+
+    fn send_cyclic(&self, sim_msg: &SimMessage) {
+        let Some(period) = sim_msg.cycle_ms else {
+            return;
+        };
+        self.ui_to_can_tx
+            .send(messages::MsgFromUi::AddSendMessage(
+                sim_msg.add_send_message(messages::SendAmount::Infinite { period }),
+            ))
+            .expect("Failed to send AddSendMessage");
+    }
+
+    // Resolves the rules against the running messages and hands them to the CAN thread
+    fn send_rules(&mut self, parser: &dbc::merged::MergedParser) {
+        let msg_defs = parser.msg_defs();
+        let mut rules = Vec::new();
+        for rule in &self.config.rules {
+            let trigger = msg_defs.iter().find(|m| m.name == rule.when);
+            let reply = self.sim_msgs.iter().find(|m| m.name == rule.reply);
+            match (trigger, reply) {
+                (Some(trigger), Some(reply)) => rules.push(rest_bus::ReplyRule {
+                    trigger_msg_id_with_ext_flag: util::can::can_dbc_to_u32_with_extid_flag(
+                        &trigger.id,
+                    ),
+                    reply: reply.add_send_message(messages::SendAmount::Once),
+                }),
+                (None, _) => self.warnings.push(format!(
+                    "{}: ignored rule on {}, it's not in the DBC",
+                    self.title, rule.when
+                )),
+                (_, None) => self.warnings.push(format!(
+                    "{}: ignored rule replying {}, it's not sent by the simulated nodes",
+                    self.title, rule.reply
+                )),
+            }
+        }
+        self.ui_to_can_tx
+            .send(messages::MsgFromUi::SetReplyRules {
+                owner: self.rules_owner,
+                rules,
+            })
+            .expect("Failed to send SetReplyRules");
+    }
+
+    pub fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        parser: Option<&app::ParserInfo>,
+        formatter: &Option<formatter::Formatter>,
+    ) -> egui_tiles::UiResponse {
+        ui.horizontal(|ui| {
+            ui.heading(format!("🖧 {}", self.title));
+            if let Some(parser) = parser {
+                if self.is_running() {
+                    if ui.button("⏹ Stop").clicked() {
+                        self.stop();
+                    }
+                } else if ui
+                    .add_enabled(!self.config.nodes.is_empty(), egui::Button::new("▶ Start"))
+                    .on_disabled_hover_text("Select at least one node to simulate")
+                    .clicked()
+                {
+                    self.start(&parser.parser);
+                }
+            }
+        });
+        ui.separator();
+
+        let Some(parser) = parser else {
+            ui.label(
+                egui::RichText::new("No DBC selected, load one to pick the nodes to simulate.")
+                    .italics()
+                    .weak(),
+            );
+            return egui_tiles::UiResponse::None;
+        };
+        let parser = &parser.parser;
+
+        for warning in &self.warnings {
+            ui.colored_label(ui.visuals().warn_fg_color, warning);
+        }
+
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            self.nodes_ui(ui, parser);
+            ui.separator();
+            self.rules_ui(ui, parser);
+            ui.separator();
+            self.messages_ui(ui, parser, formatter);
+        });
+
+        egui_tiles::UiResponse::None
+    }
+
+    fn nodes_ui(&mut self, ui: &mut egui::Ui, parser: &dbc::merged::MergedParser) {
+        // Node name -> number of messages it transmits
+        let mut nodes = std::collections::BTreeMap::<String, usize>::new();
+        for msg in parser.msg_defs() {
+            if let can_dbc::Transmitter::NodeName(node) = &msg.transmitter {
+                *nodes.entry(node.clone()).or_default() += 1;
+            }
+        }
+
+        ui.label(egui::RichText::new("Simulated nodes").strong());
+        if nodes.is_empty() {
+            ui.label(
+                egui::RichText::new("The DBC doesn't name any transmitter.")
+                    .italics()
+                    .weak(),
+            );
+            return;
+        }
+
+        let mut changed = false;
+        ui.add_enabled_ui(!self.is_running(), |ui| {
+            ui.horizontal_wrapped(|ui| {
+                for (node, msg_count) in &nodes {
+                    let mut selected = self.config.nodes.contains(node);
+                    if ui
+                        .checkbox(&mut selected, node)
+                        .on_hover_text(format!("{} message(s)", msg_count))
+                        .changed()
+                    {
+                        if selected {
+                            self.config.nodes.push(node.clone());
+                        } else {
+                            self.config.nodes.retain(|n| n != node);
+                        }
+                        changed = true;
+                    }
+                }
+            });
+        });
+        if changed {
+            self.config.save();
+        }
+    }
+
+    fn rules_ui(&mut self, ui: &mut egui::Ui, parser: &dbc::merged::MergedParser) {
+        let msg_defs = parser.msg_defs();
+        let mut changed = false;
+
+        ui.label(egui::RichText::new("Reply rules").strong())
+            .on_hover_text("Sent once each time the trigger message is received, with the reply's current values");
+        let mut to_delete = None;
+        for (idx, rule) in self.config.rules.iter().enumerate() {
+            ui.horizontal(|ui| {
+                ui.label(format!(
+                    "When {} arrives, reply with {}",
+                    rule.when, rule.reply
+                ));
+                if ui.button("🗑").on_hover_text("Delete rule").clicked() {
+                    to_delete = Some(idx);
+                }
+            });
+        }
+        if let Some(idx) = to_delete {
+            self.config.rules.remove(idx);
+            changed = true;
+        }
+
+        ui.horizontal(|ui| {
+            ui.label("When");
+            egui::ComboBox::from_id_salt((&self.title, "rule_when"))
+                .selected_text(&self.new_rule_when)
+                .show_ui(ui, |ui| {
+                    for msg in &msg_defs {
+                        ui.selectable_value(&mut self.new_rule_when, msg.name.clone(), &msg.name);
+                    }
+                });
+            ui.label("reply with");
+            egui::ComboBox::from_id_salt((&self.title, "rule_reply"))
+                .selected_text(&self.new_rule_reply)
+                .show_ui(ui, |ui| {
+                    for msg in &msg_defs {
+                        let node = util::can::transmitter_name(&msg.transmitter);
+                        if self.config.nodes.iter().any(|n| n == node) {
+                            ui.selectable_value(
+                                &mut self.new_rule_reply,
+                                msg.name.clone(),
+                                &msg.name,
+                            );
+                        }
+                    }
+                });
+            let can_add = !self.new_rule_when.is_empty() && !self.new_rule_reply.is_empty();
+            if ui
+                .add_enabled(can_add, egui::Button::new("➕ Add"))
+                .clicked()
+            {
+                let rule = rest_bus::RuleDef {
+                    when: std::mem::take(&mut self.new_rule_when),
+                    reply: std::mem::take(&mut self.new_rule_reply),
+                };
+                if !self.config.rules.contains(&rule) {
+                    self.config.rules.push(rule);
+                    changed = true;
+                }
+            }
+        });
+
+        if changed {
+            self.config.save();
+            if self.is_running() {
+                self.warnings.clear();
+                self.send_rules(parser);
+            }
+        }
+    }
+
+    fn messages_ui(
+        &mut self,
+        ui: &mut egui::Ui,
+        parser: &dbc::merged::MergedParser,
+        formatter: &Option<formatter::Formatter>,
+    ) {
+        if !self.is_running() {
+            ui.label(
+                egui::RichText::new(
+                    "Stopped. Start to send every message of the selected nodes at its DBC cycle time.",
+                )
+                .italics()
+                .weak(),
+            );
+            return;
+        }
+
+        let mut edited = Vec::new();
+        for (idx, sim_msg) in self.sim_msgs.iter_mut().enumerate() {
+            let header = format!(
+                "{} (0x{:03X}) from {}",
+                sim_msg.name, sim_msg.msg_id, sim_msg.node
+            );
+            egui::CollapsingHeader::new(header)
+                .id_salt((&self.title, sim_msg.handle))
+                .show(ui, |ui| {
+                    ui.horizontal(|ui| {
+                        match sim_msg.cycle_ms {
+                            Some(period) => {
+                                ui.label(format!("every {} ms", period));
+                                if sim_msg.stats.count > 0 {
+                                    ui.label(
+                                        egui::RichText::new(format!(
+                                            "measured {:.1} ms",
+                                            sim_msg.stats.mean_ms
+                                        ))
+                                        .color(ui.visuals().weak_text_color()),
+                                    );
+                                }
+                            }
+                            None => {
+                                ui.label(
+                                    egui::RichText::new(
+                                        "no cycle time in the DBC, only sent by reply rules",
+                                    )
+                                    .italics()
+                                    .weak(),
+                                );
+                            }
+                        }
+                        ui.label(format!("sent {}", sim_msg.sent_count));
+                        ui.label(
+                            egui::RichText::new(format!("{:02X?}", sim_msg.msg_bytes)).monospace(),
+                        );
+                    });
+
+                    for signal in &mut sim_msg.signals {
+                        ui.horizontal(|ui| {
+                            ui.label(egui::RichText::new(&signal.name).monospace());
+                            let expected_decimals = formatter
+                                .as_ref()
+                                .map(|f| f.expected_decimals(&sim_msg.name, &signal.name))
+                                .unwrap_or(2);
+                            let speed = 10f64.powi(-(expected_decimals as i32));
+                            if ui
+                                .add(
+                                    egui::DragValue::new(&mut signal.value)
+                                        .range(signal.min..=signal.max)
+                                        .speed(speed)
+                                        .suffix(format!(" {}", signal.unit)),
+                                )
+                                .changed()
+                            {
+                                edited.push(idx);
+                            }
+                        });
+                    }
+                });
+        }
+
+        edited.dedup();
+        let mut replies_changed = false;
+        for idx in edited {
+            let Some(msg_bytes) = encode(parser, &self.sim_msgs[idx]) else {
+                self.warnings.push(format!(
+                    "{}: failed to encode {}",
+                    self.title, self.sim_msgs[idx].name
+                ));
+                continue;
+            };
+            self.sim_msgs[idx].msg_bytes = msg_bytes;
+            self.send_cyclic(&self.sim_msgs[idx]);
+            replies_changed |= self
+                .config
+                .rules
+                .iter()
+                .any(|r| r.reply == self.sim_msgs[idx].name);
+        }
+        // Rules carry the reply bytes, so they go stale when a reply's values change
+        if replies_changed {
+            self.warnings.clear();
+            self.send_rules(parser);
+        }
+    }
+
+    pub fn on_dbc_reloaded(&mut self, _parser: &dbc::merged::MergedParser) -> Vec<String> {
+        if !self.is_running() {
+            return Vec::new();
+        }
+        // Message IDs, layouts and cycle times may all have changed, start over from the new file
+        // rather than patching the running messages
+        self.stop();
+        vec![format!(
+            "{}: stopped the simulation because the DBC changed, start it again to pick up the new definitions",
+            self.title
+        )]
+    }
+
+    pub fn handle_can_message(&mut self, msg: &messages::MsgFromCan) {
+        if let messages::MsgFromCan::MessageSent { handle, stats, .. } = msg
+            && let Some(sim_msg) = self.sim_msgs.iter_mut().find(|m| m.handle == *handle)
+        {
+            sim_msg.sent_count += 1;
+            sim_msg.stats = *stats;
+        }
+    }
+}
+
+impl SimMessage {
+    fn add_send_message(&self, amount: messages::SendAmount) -> messages::AddSendMessage {
+        messages::AddSendMessage {
+            // Replies get their own sender so they don't restart the cyclic one
+            handle: match amount {
+                messages::SendAmount::Once => can::scheduler::new_handle(),
+                _ => self.handle,
+            },
+            amount,
+            msg_id: self.msg_id,
+            is_msg_id_extended: self.is_msg_id_extended,
+            msg_bytes: self.msg_bytes.clone(),
+            auto_fields: Vec::new(),
+        }
+    }
+}
+
+fn encode(parser: &dbc::merged::MergedParser, sim_msg: &SimMessage) -> Option<Vec<u8>> {
+    let values = sim_msg
+        .signals
+        .iter()
+        .map(|signal| (signal.name.clone(), signal.value))
+        .collect();
+    parser.encode_msg(sim_msg.msg_id_with_ext_flag, &values)
+}
+
+// Starts from GenSigStartValue when the DBC has one, 0 otherwise, clamped to the signal's range
+fn initial_signal(
+    dbc: &can_dbc::Dbc,
+    msg_id: &can_dbc::MessageId,
+    sig: &can_dbc::Signal,
+) -> SimSignal {
+    let (min, max) = super::send::signal_range(sig);
+
+    let value = dbc::attributes::signal_attribute(
+        dbc,
+        msg_id,
+        &sig.name,
+        dbc::attributes::START_VALUE_ATTRIBUTE,
+    )
+    .and_then(dbc::attributes::value_to_f64)
+    .map(|raw| raw * sig.factor + sig.offset)
+    .unwrap_or(0.0);
+
+    SimSignal {
+        name: sig.name.clone(),
+        value: value.clamp(min, max),
+        min,
+        max,
+        unit: sig.unit.clone(),
+    }
+}
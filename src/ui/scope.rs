@@ -1,6 +1,6 @@
-use crate::messages;
+use crate::{dbc, derived, formatter, messages, util};
 use eframe::egui;
-use egui_plot::{Line, Plot, PlotPoints};
+use egui_plot::{Line, Plot, PlotPoints, VLine};
 use std::collections::VecDeque;
 
 pub struct Scope {
@@ -8,15 +8,46 @@ pub struct Scope {
     msg_id: u32,
     msg_name: String,
     signal_name: String,
+    // The DBC's, samples are kept in it and converted for display
+    unit: String,
     window: VecDeque<(f64, f64)>, // (time, value)
     window_duration_seconds: f64,
     decimation_factor: u64,
     decimation_counter: u64,
     reference_time: Option<chrono::DateTime<chrono::Local>>,
     is_paused: bool,
+    dbc_warning: Option<String>,
+    // (time, label), drawn as vertical lines
+    markers: Vec<(f64, String)>,
+}
+
+/// What a saved layout remembers of a scope.
+#[derive(serde::Serialize, serde::Deserialize, Clone, PartialEq)]
+pub struct ScopeConfig {
+    pub msg_id: u32,
+    pub msg_name: String,
+    pub signal_name: String,
+    pub window_duration_seconds: f64,
+    pub decimation_factor: u64,
 }
 
 impl Scope {
+    pub fn config(&self) -> ScopeConfig {
+        ScopeConfig {
+            msg_id: self.msg_id,
+            msg_name: self.msg_name.clone(),
+            signal_name: self.signal_name.clone(),
+            window_duration_seconds: self.window_duration_seconds,
+            decimation_factor: self.decimation_factor,
+        }
+    }
+
+    // The signal itself is set by `new`
+    pub fn apply_config(&mut self, config: ScopeConfig) {
+        self.window_duration_seconds = config.window_duration_seconds;
+        self.decimation_factor = config.decimation_factor;
+    }
+
     pub fn new(instance_num: usize, msg_id: u32, msg_name: String, signal_name: String) -> Self {
         let title = format!("Scope #{}", instance_num);
         Self {
@@ -24,12 +55,15 @@ impl Scope {
             msg_id,
             msg_name,
             signal_name,
+            unit: String::new(),
             window: VecDeque::new(),
             window_duration_seconds: 10.0, // Default 10 seconds
             decimation_factor: 0,
             decimation_counter: 0,
             reference_time: None,
             is_paused: false,
+            dbc_warning: None,
+            markers: Vec::new(),
         }
     }
 
@@ -62,13 +96,36 @@ impl Scope {
                 break;
             }
         }
+        self.markers.retain(|(time, _)| *time >= cutoff_time);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.is_paused
+    }
+
+    pub fn set_paused(&mut self, paused: bool) {
+        self.is_paused = paused;
     }
 
-    fn export_csv(&self) {
+    pub fn clear(&mut self) {
+        self.window.clear();
+        self.reference_time = None;
+        self.markers.clear();
+    }
+
+    // Nothing to mark before the first sample, the time axis starts there
+    pub fn add_marker(&mut self, timestamp: chrono::DateTime<chrono::Local>, label: &str) {
+        if let Some(reference) = self.reference_time {
+            let relative_time = (timestamp - reference).num_milliseconds() as f64 / 1000.0;
+            self.markers.push((relative_time, label.to_string()));
+        }
+    }
+
+    fn export_csv(&self, conversion: &formatter::Conversion) {
         // Create CSV content from the window data
-        let mut csv_content = String::from("Time_Seconds,Value\n");
+        let mut csv_content = format!("Time_Seconds,{}\n", conversion.label("Value"));
         for (relative_time, value) in &self.window {
-            csv_content.push_str(&format!("{},{}\n", relative_time, value));
+            csv_content.push_str(&format!("{},{}\n", relative_time, conversion.apply(*value)));
         }
 
         // Open file dialog to save CSV
@@ -85,12 +142,23 @@ impl Scope {
         }
     }
 
-    pub fn show(&mut self, ui: &mut egui::Ui) -> egui_tiles::UiResponse {
+    pub fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        formatter: &Option<formatter::Formatter>,
+    ) -> egui_tiles::UiResponse {
+        let conversion =
+            formatter::try_conversion(formatter, &self.msg_name, &self.signal_name, &self.unit);
+
         ui.heading(format!(
             "📊 {}: {} - {}",
             self.title, self.msg_name, self.signal_name
         ));
 
+        if let Some(warning) = &self.dbc_warning {
+            ui.label(egui::RichText::new(warning).color(ui.visuals().warn_fg_color));
+        }
+
         // Horizontal container
         ui.horizontal(|ui| {
             // Pause/Resume button
@@ -100,7 +168,7 @@ impl Scope {
                 "⏸ Pause"
             };
             if ui.button(pause_text).clicked() {
-                self.is_paused = !self.is_paused;
+                self.set_paused(!self.is_paused);
             }
 
             ui.separator();
@@ -122,15 +190,14 @@ impl Scope {
 
             // Export button
             if ui.button("📄 Export CSV").clicked() {
-                self.export_csv();
+                self.export_csv(&conversion);
             }
 
             ui.separator();
 
             // Clear button
             if ui.button("🗑 Clear").clicked() {
-                self.window.clear();
-                self.reference_time = None;
+                self.clear();
             }
 
             ui.separator();
@@ -142,7 +209,7 @@ impl Scope {
             .view_aspect(2.0)
             .auto_bounds(egui::Vec2b::TRUE)
             .x_axis_label("Time (seconds)")
-            .y_axis_label(&self.signal_name)
+            .y_axis_label(conversion.label(&self.signal_name))
             .show(ui, |plot_ui| {
                 if self.window.is_empty() {
                     return;
@@ -151,7 +218,7 @@ impl Scope {
                 let points: PlotPoints = self
                     .window
                     .iter()
-                    .map(|(time, value)| [*time, *value])
+                    .map(|(time, value)| [*time, conversion.apply(*value)])
                     .collect();
 
                 let line = Line::new(&self.signal_name, points)
@@ -162,22 +229,83 @@ impl Scope {
                     ));
 
                 plot_ui.line(line);
+
+                for (time, label) in &self.markers {
+                    plot_ui.vline(
+                        VLine::new(label, *time)
+                            .color(egui::Color32::from_rgb(230, 160, 60))
+                            .style(egui_plot::LineStyle::dashed_loose()),
+                    );
+                }
             });
 
         egui_tiles::UiResponse::None
     }
 
-    pub fn handle_can_message(&mut self, msg: &messages::MsgFromCan) {
-        if let messages::MsgFromCan::ParsedMessage(parsed_msg) = msg {
-            if parsed_msg.decoded.msg_id != self.msg_id {
-                return;
+    // Starts a new plot, the old samples were of another signal
+    pub fn set_signal(&mut self, msg_id: u32, msg_name: String, signal_name: String) {
+        self.msg_id = msg_id;
+        self.msg_name = msg_name;
+        self.signal_name = signal_name;
+        self.unit.clear();
+        self.clear();
+        self.decimation_counter = 0;
+        self.dbc_warning = None;
+    }
+
+    // Follows the message by name, so the plot keeps working if the ID was renumbered
+    pub fn on_dbc_reloaded(&mut self, parser: &dbc::merged::MergedParser) -> Vec<String> {
+        if self.msg_id == derived::DERIVED_MSG_ID {
+            return Vec::new();
+        }
+
+        let msg_defs = parser.msg_defs();
+        let warning = match msg_defs.iter().find(|m| m.name == self.msg_name) {
+            None => Some(format!("{} no longer exists in the DBC", self.msg_name)),
+            Some(msg) => {
+                self.msg_id = util::can::can_dbc_to_u32_without_extid_flag(&msg.id);
+                if msg.signals.iter().any(|s| s.name == self.signal_name) {
+                    None
+                } else {
+                    Some(format!(
+                        "{} no longer has a signal named {}",
+                        self.msg_name, self.signal_name
+                    ))
+                }
             }
+        };
 
-            let Some(signal) = parsed_msg.decoded.signals.get(&self.signal_name) else {
-                return;
-            };
+        self.dbc_warning = warning.clone();
+        warning
+            .map(|w| vec![format!("{}: {}", self.title, w)])
+            .unwrap_or_default()
+    }
 
-            self.add_point(parsed_msg.timestamp, signal.value.physical);
+    pub fn handle_can_message(&mut self, msg: &messages::MsgFromCan) {
+        match msg {
+            messages::MsgFromCan::ParsedMessage(parsed_msg) => {
+                if parsed_msg.decoded.msg_id != self.msg_id {
+                    return;
+                }
+
+                let Some(signal) = parsed_msg.decoded.signals.get(&self.signal_name) else {
+                    return;
+                };
+
+                if self.unit != signal.unit {
+                    self.unit = signal.unit.clone();
+                }
+                self.add_point(parsed_msg.timestamp, signal.value.physical);
+            }
+            messages::MsgFromCan::DerivedSignal(derived_signal) => {
+                if self.msg_id != derived::DERIVED_MSG_ID || derived_signal.name != self.signal_name
+                {
+                    return;
+                }
+
+                self.add_point(derived_signal.timestamp, derived_signal.value);
+            }
+            _ => {}
         }
     }
 }
@@ -1,7 +1,7 @@
-use crate::{app, formatter, messages, util};
+use crate::{app, can, dbc, formatter, messages, transmit_sets, ui, util};
 use eframe::egui;
 
-use super::dbc_msg_picker::{DbcMsgPickerState, no_dbc_placeholder};
+use super::dbc_msg_picker::DbcMsgPickerState;
 
 pub struct SendUi {
     pub title: String,
@@ -18,6 +18,17 @@ pub struct SendUi {
     finite_amount: usize,
     adjustable_values_enabled: bool,
 
+    // Raw mode sends a hand typed frame, for IDs that aren't in the DBC (or when none is loaded)
+    raw_mode: bool,
+    raw_id: String,
+    raw_is_extended: bool,
+    raw_dlc: usize,
+    raw_data: String,
+
+    transmit_sets: transmit_sets::TransmitSetsConfig,
+    new_set_name: String,
+    set_warnings: Vec<String>,
+
     error: Option<String>,
 
     // Required to be stored on the struct so Drop can send cancellation messages when the UI closes
@@ -34,25 +45,67 @@ enum SendMode {
 #[derive(Clone)]
 struct SignalValue {
     name: String,
+    // The DBC's, for the formatter's color rules
+    unit: String,
     value: f64,
     min: f64,
     max: f64,
+    auto: AutoMode,
+    // Bytes a checksum covers as typed, ex: "0-6". Empty means every byte it doesn't occupy.
+    checksum_bytes: String,
+}
+
+// Counters and checksums are filled in by the CAN thread at each send, ignoring `value`
+#[derive(Clone, Copy, PartialEq)]
+enum AutoMode {
+    Value,
+    Counter,
+    Checksum(can::auto_fields::ChecksumKind),
+}
+
+impl AutoMode {
+    fn display(&self) -> &'static str {
+        match self {
+            AutoMode::Value => "Value",
+            AutoMode::Counter => "Counter",
+            AutoMode::Checksum(kind) => kind.display(),
+        }
+    }
 }
 
 struct SendingMessage {
+    pub handle: can::scheduler::SendHandle,
     pub amount: messages::SendAmount,
+    // What was asked for, `amount` counts down as Finite messages are sent
+    pub initial_amount: messages::SendAmount,
     pub msg_name: String,
     pub msg_id: u32,
     pub msg_id_with_ext_flag: u32,
     pub is_msg_id_extended: bool,
     pub msg_bytes: Vec<u8>,
+    pub auto_fields: Vec<can::auto_fields::AutoField>,
     pub signal_values: Vec<SignalValue>,
     pub adjustable_values_enabled: bool,
+    pub is_raw: bool,
+    // Transmit set this message was started from, so the set can be stopped as a group
+    pub set_name: Option<String>,
     pub last_sent: chrono::DateTime<chrono::Local>,
+    // Measured by the CAN thread, to compare against the requested period
+    pub stats: can::scheduler::PeriodStats,
 }
 
 enum SendUiActions {
-    DeleteMessage { msg_id: u32 },
+    DeleteMessage { handle: can::scheduler::SendHandle },
+}
+
+enum SetAction {
+    Start(usize),
+    Stop(usize),
+    Delete(usize),
+    Export(usize),
+    SetLaunch(usize, bool),
+    SaveCurrent,
+    Import,
 }
 
 impl Drop for SendUi {
@@ -66,15 +119,15 @@ impl Drop for SendUi {
                 .collect::<Vec<_>>()
         );
         for msg in &self.sending_messages {
-            let msg_id = msg.msg_id;
+            let handle = msg.handle;
             if let Err(e) = self
                 .ui_to_can_tx
-                .send(messages::MsgFromUi::DeleteSendMessage { msg_id })
+                .send(messages::MsgFromUi::DeleteSendMessage { handle })
             {
                 // Don't panic in Drop, just log the error
                 log::error!(
                     "Failed to send DeleteSendMessage for msg_id {}: {}",
-                    msg_id,
+                    msg.msg_id,
                     e
                 );
             }
@@ -84,7 +137,7 @@ impl Drop for SendUi {
 
 impl SendUi {
     pub fn new(num: usize, ui_to_can_tx: std::sync::mpsc::Sender<messages::MsgFromUi>) -> Self {
-        Self {
+        let mut send_ui = Self {
             title: format!("Send UI {}", num),
 
             msg_picker: DbcMsgPickerState::default(),
@@ -99,10 +152,22 @@ impl SendUi {
             finite_amount: 10,
             adjustable_values_enabled: false,
 
+            raw_mode: false,
+            raw_id: String::new(),
+            raw_is_extended: false,
+            raw_dlc: 8,
+            raw_data: String::new(),
+
+            transmit_sets: transmit_sets::TransmitSetsConfig::default(),
+            new_set_name: String::new(),
+            set_warnings: Vec::new(),
+
             error: None,
 
             ui_to_can_tx,
-        }
+        };
+        send_ui.reload_sets();
+        send_ui
     }
 
     pub fn show(
@@ -111,188 +176,37 @@ impl SendUi {
         parser: Option<&app::ParserInfo>,
         formatter: &Option<formatter::Formatter>,
     ) -> egui_tiles::UiResponse {
-        let Some(parser) = parser else {
-            no_dbc_placeholder(ui);
-            return egui_tiles::UiResponse::None;
-        };
-
         egui::Frame::group(ui.style())
             .inner_margin(egui::Margin::symmetric(8, 6))
             .stroke(egui::Stroke::NONE)
             .show(ui, |ui| {
                 egui::ScrollArea::vertical().show(ui, |ui| {
-                    if let Some(msg) =
-                        self.msg_picker
-                            .show(ui, &parser.parser, self.selected_msg.is_none())
-                    {
-                        self.selected_msg = Some(msg.clone());
-                        self.signal_values = msg
-                            .signals
-                            .iter()
-                            .map(|sig| {
-                                let (min, max) = signal_range(sig);
-                                SignalValue {
-                                    name: sig.name.clone(),
-                                    value: 0.0,
-                                    min,
-                                    max,
-                                }
-                            })
-                            .collect();
-                        self.error = None;
-                    }
-
-                    if let Some(selected_msg) = &self.selected_msg {
-                        ui.separator();
-
-                        if let Some(error) = &self.error {
-                            ui.label(egui::RichText::new(error).color(ui.visuals().error_fg_color));
-                        }
-
-                        ui.label(
-                            egui::RichText::new(format!(
-                                "Selected Message: {} (0x{:03X})",
-                                selected_msg.name,
-                                util::can::can_dbc_to_u32_without_extid_flag(&selected_msg.id)
-                            ))
-                            .strong()
-                            .size(16.0),
-                        );
-
-                        // Send Amount selector
-                        ui.label(egui::RichText::new("Send Options").strong());
+                    self.transmit_sets_ui(ui, parser.map(|p| &p.parser));
+                    ui.separator();
 
-                        ui.horizontal(|ui| {
-                            ui.selectable_value(&mut self.send_mode, SendMode::Once, "Once");
-                            ui.selectable_value(
-                                &mut self.send_mode,
-                                SendMode::Infinite,
-                                "Infinite",
-                            );
-                            ui.selectable_value(&mut self.send_mode, SendMode::Finite, "Finite");
+                    ui.horizontal(|ui| {
+                        ui.add_enabled_ui(parser.is_some(), |ui| {
+                            ui.selectable_value(&mut self.raw_mode, false, "DBC Message");
                         });
+                        ui.selectable_value(&mut self.raw_mode, true, "Raw Frame");
+                    });
+                    ui.separator();
 
-                        match self.send_mode {
-                            SendMode::Once => {}
-                            SendMode::Infinite => {
-                                ui.horizontal(|ui| {
-                                    ui.label("Period (ms)");
-                                    ui.add(
-                                        egui::DragValue::new(&mut self.period_ms)
-                                            .speed(1)
-                                            .range(1..=10_000),
-                                    );
-                                });
-                            }
-                            SendMode::Finite => {
-                                ui.horizontal(|ui| {
-                                    ui.label("Amount");
-                                    ui.add(
-                                        egui::DragValue::new(&mut self.finite_amount)
-                                            .speed(1)
-                                            .range(1..=10_000),
-                                    );
-                                });
-                                ui.horizontal(|ui| {
-                                    ui.label("Period (ms)");
-                                    ui.add(
-                                        egui::DragValue::new(&mut self.period_ms)
-                                            .speed(1)
-                                            .range(1..=10_000),
-                                    );
-                                });
-                            }
+                    match parser {
+                        Some(parser) if !self.raw_mode => {
+                            self.dbc_msg_ui(ui, &parser.parser, formatter);
                         }
-                        ui.checkbox(&mut self.adjustable_values_enabled, "Adjustable values");
-                        for i in 0..self.signal_values.len() {
-                            ui.horizontal(|ui| {
-                                let signal = &mut self.signal_values[i];
-                                ui.label(signal.name.as_str());
-                                let expected_decimals = formatter
-                                    .as_ref()
-                                    .map(|f| f.expected_decimals(&selected_msg.name, &signal.name))
-                                    .unwrap_or(2);
-                                let speed = 10f64.powi(-(expected_decimals as i32));
-                                if ui
-                                    .add(
-                                        egui::DragValue::new(&mut signal.value)
-                                            .range(signal.min..=signal.max)
-                                            .speed(speed),
+                        _ => {
+                            if parser.is_none() {
+                                ui.label(
+                                    egui::RichText::new(
+                                        "No DBC selected, only raw frames can be sent.",
                                     )
-                                    .changed()
-                                {
-                                    self.signal_values[i].value = signal.value;
-                                }
-                            });
-                        }
-
-                        if ui.button("Send Message").clicked() {
-                            let msg_id_with_ext_flag =
-                                util::can::can_dbc_to_u32_with_extid_flag(&selected_msg.id);
-                            let encoded = encode_msg_from_signals(
-                                &parser.parser,
-                                msg_id_with_ext_flag,
-                                &self.signal_values,
-                            );
-
-                            let Some(msg_bytes) = encoded else {
-                                self.error = Some(
-                                    "Failed to encode message. Check signal values.".to_string(),
+                                    .italics()
+                                    .weak(),
                                 );
-
-                                return;
-                            };
-
-                            self.error = None;
-
-                            let send_amount = match self.send_mode {
-                                SendMode::Once => messages::SendAmount::Once,
-
-                                SendMode::Infinite => messages::SendAmount::Infinite {
-                                    period: self.period_ms,
-                                },
-
-                                SendMode::Finite => messages::SendAmount::Finite {
-                                    amount: self.finite_amount,
-                                    period: self.period_ms,
-                                },
-                            };
-
-                            let msg_id_u32 =
-                                util::can::can_dbc_to_u32_without_extid_flag(&selected_msg.id);
-
-                            self.sending_messages.push(SendingMessage {
-                                amount: send_amount,
-                                msg_name: selected_msg.name.clone(),
-                                msg_id: msg_id_u32,
-                                msg_id_with_ext_flag,
-                                is_msg_id_extended: matches!(
-                                    selected_msg.id,
-                                    can_dbc::MessageId::Extended(_)
-                                ),
-                                msg_bytes: msg_bytes.clone(),
-                                signal_values: self.signal_values.clone(),
-                                adjustable_values_enabled: self.adjustable_values_enabled,
-                                last_sent: chrono::Local::now(),
-                            });
-
-                            let add_send_msg = messages::AddSendMessage {
-                                amount: send_amount,
-                                msg_id: msg_id_u32,
-                                is_msg_id_extended: matches!(
-                                    selected_msg.id,
-                                    can_dbc::MessageId::Extended(_)
-                                ),
-                                msg_bytes,
-                            };
-
-                            self.selected_msg = None;
-                            self.signal_values.clear();
-                            self.adjustable_values_enabled = false;
-
-                            self.ui_to_can_tx
-                                .send(messages::MsgFromUi::AddSendMessage(add_send_msg))
-                                .expect("Failed to send AddSendMessage");
+                            }
+                            self.raw_frame_ui(ui);
                         }
                     }
 
@@ -320,6 +234,9 @@ impl SendUi {
                         if !msg.adjustable_values_enabled {
                             continue;
                         }
+                        let Some(parser) = parser else {
+                            continue;
+                        };
                         let encoded = encode_msg_from_signals(
                             &parser.parser,
                             msg.msg_id_with_ext_flag,
@@ -336,26 +253,20 @@ impl SendUi {
                         self.sending_messages[idx].msg_bytes = msg_bytes.clone();
                         self.error = None;
 
+                        // Only the values changed, the period keeps running
                         self.ui_to_can_tx
-                            .send(messages::MsgFromUi::AddSendMessage(
-                                messages::AddSendMessage {
-                                    amount: self.sending_messages[idx].amount,
-                                    msg_id: self.sending_messages[idx].msg_id,
-                                    is_msg_id_extended: self.sending_messages[idx]
-                                        .is_msg_id_extended,
-                                    msg_bytes,
-                                },
-                            ))
-                            .expect("Failed to send AddSendMessage");
+                            .send(messages::MsgFromUi::UpdateSendBytes {
+                                handle: self.sending_messages[idx].handle,
+                                msg_bytes,
+                                auto_fields: self.sending_messages[idx].auto_fields.clone(),
+                            })
+                            .expect("Failed to send UpdateSendBytes");
                     }
 
                     for action in all_actions {
                         match action {
-                            SendUiActions::DeleteMessage { msg_id } => {
-                                self.sending_messages.retain(|msg| msg.msg_id != msg_id);
-                                self.ui_to_can_tx
-                                    .send(messages::MsgFromUi::DeleteSendMessage { msg_id })
-                                    .expect("Failed to send DeleteSendMessage");
+                            SendUiActions::DeleteMessage { handle } => {
+                                self.stop_sending(handle);
                             }
                         }
                     }
@@ -365,25 +276,701 @@ impl SendUi {
         egui_tiles::UiResponse::None
     }
 
+    fn dbc_msg_ui(
+        &mut self,
+        ui: &mut egui::Ui,
+        parser: &dbc::merged::MergedParser,
+        formatter: &Option<formatter::Formatter>,
+    ) {
+        if let Some(msg) = self
+            .msg_picker
+            .show(ui, parser, self.selected_msg.is_none())
+        {
+            self.selected_msg = Some(msg.clone());
+            self.signal_values = msg
+                .signals
+                .iter()
+                .map(|sig| {
+                    let (min, max) = signal_range(sig);
+                    SignalValue {
+                        name: sig.name.clone(),
+                        unit: sig.unit.clone(),
+                        value: 0.0,
+                        min,
+                        max,
+                        auto: AutoMode::Value,
+                        checksum_bytes: String::new(),
+                    }
+                })
+                .collect();
+            self.error = None;
+        }
+
+        if let Some(selected_msg) = &self.selected_msg {
+            ui.separator();
+
+            if let Some(error) = &self.error {
+                ui.label(egui::RichText::new(error).color(ui.visuals().error_fg_color));
+            }
+
+            ui.label(
+                egui::RichText::new(format!(
+                    "Selected Message: {} (0x{:03X})",
+                    selected_msg.name,
+                    util::can::can_dbc_to_u32_without_extid_flag(&selected_msg.id)
+                ))
+                .strong()
+                .size(16.0),
+            );
+
+            ui.label(egui::RichText::new("Send Options").strong());
+            send_options_ui(
+                ui,
+                &mut self.send_mode,
+                &mut self.period_ms,
+                &mut self.finite_amount,
+            );
+
+            ui.checkbox(&mut self.adjustable_values_enabled, "Adjustable values");
+            for i in 0..self.signal_values.len() {
+                ui.horizontal(|ui| {
+                    let signal = &mut self.signal_values[i];
+                    ui.label(signal.name.as_str());
+                    egui::ComboBox::from_id_salt((&self.title, "auto_mode", i))
+                        .selected_text(signal.auto.display())
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(&mut signal.auto, AutoMode::Value, "Value");
+                            ui.selectable_value(&mut signal.auto, AutoMode::Counter, "Counter")
+                                .on_hover_text(
+                                    "Increments at every send and wraps at the signal's width",
+                                );
+                            for kind in can::auto_fields::ChecksumKind::ALL {
+                                ui.selectable_value(
+                                    &mut signal.auto,
+                                    AutoMode::Checksum(kind),
+                                    kind.display(),
+                                );
+                            }
+                        });
+                    match signal.auto {
+                        AutoMode::Value => {
+                            let expected_decimals = formatter
+                                .as_ref()
+                                .map(|f| f.expected_decimals(&selected_msg.name, &signal.name))
+                                .unwrap_or(2);
+                            let speed = 10f64.powi(-(expected_decimals as i32));
+                            ui.add(
+                                egui::DragValue::new(&mut signal.value)
+                                    .range(signal.min..=signal.max)
+                                    .speed(speed),
+                            );
+                        }
+                        AutoMode::Counter => {}
+                        AutoMode::Checksum(_) => {
+                            ui.label("over bytes");
+                            ui.add(
+                                egui::TextEdit::singleline(&mut signal.checksum_bytes)
+                                    .desired_width(80.0)
+                                    .hint_text("all others"),
+                            )
+                            .on_hover_text("Byte indices, ex: 0-6 or 1, 2, 5");
+                        }
+                    }
+                });
+            }
+
+            if ui.button("Send Message").clicked() {
+                let auto_fields = match auto_fields_for(selected_msg, &self.signal_values) {
+                    Ok(auto_fields) => auto_fields,
+                    Err(e) => {
+                        self.error = Some(e);
+                        return;
+                    }
+                };
+                let msg_id_with_ext_flag =
+                    util::can::can_dbc_to_u32_with_extid_flag(&selected_msg.id);
+                let encoded =
+                    encode_msg_from_signals(parser, msg_id_with_ext_flag, &self.signal_values);
+
+                let Some(msg_bytes) = encoded else {
+                    self.error = Some("Failed to encode message. Check signal values.".to_string());
+
+                    return;
+                };
+
+                self.error = None;
+
+                let msg_id_u32 = util::can::can_dbc_to_u32_without_extid_flag(&selected_msg.id);
+                let amount = self.send_amount();
+                let sending_msg = SendingMessage {
+                    handle: can::scheduler::new_handle(),
+                    amount,
+                    initial_amount: amount,
+                    msg_name: selected_msg.name.clone(),
+                    msg_id: msg_id_u32,
+                    msg_id_with_ext_flag,
+                    is_msg_id_extended: matches!(selected_msg.id, can_dbc::MessageId::Extended(_)),
+                    msg_bytes,
+                    auto_fields,
+                    signal_values: self.signal_values.clone(),
+                    adjustable_values_enabled: self.adjustable_values_enabled,
+                    is_raw: false,
+                    set_name: None,
+                    last_sent: chrono::Local::now(),
+                    stats: can::scheduler::PeriodStats::default(),
+                };
+
+                self.selected_msg = None;
+                self.signal_values.clear();
+                self.adjustable_values_enabled = false;
+
+                self.start_sending(sending_msg);
+            }
+        }
+    }
+
+    fn raw_frame_ui(&mut self, ui: &mut egui::Ui) {
+        if let Some(error) = &self.error {
+            ui.label(egui::RichText::new(error).color(ui.visuals().error_fg_color));
+        }
+
+        ui.label(egui::RichText::new("Raw Frame").strong().size(16.0));
+        ui.horizontal(|ui| {
+            ui.label("ID (hex)");
+            ui.add(
+                egui::TextEdit::singleline(&mut self.raw_id)
+                    .desired_width(90.0)
+                    .hint_text("1A4"),
+            );
+            ui.checkbox(&mut self.raw_is_extended, "Extended (29 bit)");
+        });
+        ui.horizontal(|ui| {
+            ui.label("DLC");
+            ui.add(egui::DragValue::new(&mut self.raw_dlc).range(0..=8));
+        });
+        ui.horizontal(|ui| {
+            ui.label("Data (hex)");
+            ui.add(egui::TextEdit::singleline(&mut self.raw_data).hint_text("01 02 0A FF"));
+        });
+
+        ui.label(egui::RichText::new("Send Options").strong());
+        send_options_ui(
+            ui,
+            &mut self.send_mode,
+            &mut self.period_ms,
+            &mut self.finite_amount,
+        );
+
+        if ui.button("Send Frame").clicked() {
+            let parsed = parse_raw_frame(
+                &self.raw_id,
+                self.raw_is_extended,
+                self.raw_dlc,
+                &self.raw_data,
+            );
+            let (msg_id, msg_bytes) = match parsed {
+                Ok(frame) => frame,
+                Err(e) => {
+                    self.error = Some(e);
+                    return;
+                }
+            };
+            self.error = None;
+
+            let msg_id_with_ext_flag = if self.raw_is_extended {
+                msg_id | util::can::EXTENDED_ID_FLAG
+            } else {
+                msg_id
+            };
+            let amount = self.send_amount();
+            self.start_sending(SendingMessage {
+                handle: can::scheduler::new_handle(),
+                amount,
+                initial_amount: amount,
+                msg_name: "Raw frame".to_string(),
+                msg_id,
+                msg_id_with_ext_flag,
+                is_msg_id_extended: self.raw_is_extended,
+                msg_bytes,
+                auto_fields: Vec::new(),
+                signal_values: Vec::new(),
+                adjustable_values_enabled: false,
+                is_raw: true,
+                set_name: None,
+                last_sent: chrono::Local::now(),
+                stats: can::scheduler::PeriodStats::default(),
+            });
+        }
+    }
+
+    fn send_amount(&self) -> messages::SendAmount {
+        match self.send_mode {
+            SendMode::Once => messages::SendAmount::Once,
+            SendMode::Infinite => messages::SendAmount::Infinite {
+                period: self.period_ms,
+            },
+            SendMode::Finite => messages::SendAmount::Finite {
+                amount: self.finite_amount,
+                period: self.period_ms,
+            },
+        }
+    }
+
+    // Other Send UIs can send the same ID independently, but within one Send UI sending an ID
+    // again takes over its sender
+    fn start_sending(&mut self, mut sending_msg: SendingMessage) {
+        if let Some(existing) = self
+            .sending_messages
+            .iter()
+            .find(|msg| msg.msg_id_with_ext_flag == sending_msg.msg_id_with_ext_flag)
+        {
+            sending_msg.handle = existing.handle;
+        }
+        let add_send_msg = messages::AddSendMessage {
+            handle: sending_msg.handle,
+            amount: sending_msg.amount,
+            msg_id: sending_msg.msg_id,
+            is_msg_id_extended: sending_msg.is_msg_id_extended,
+            msg_bytes: sending_msg.msg_bytes.clone(),
+            auto_fields: sending_msg.auto_fields.clone(),
+        };
+        self.sending_messages
+            .retain(|msg| msg.handle != sending_msg.handle);
+        self.sending_messages.push(sending_msg);
+
+        self.ui_to_can_tx
+            .send(messages::MsgFromUi::AddSendMessage(add_send_msg))
+            .expect("Failed to send AddSendMessage");
+    }
+
+    fn stop_sending(&mut self, handle: can::scheduler::SendHandle) {
+        self.sending_messages.retain(|msg| msg.handle != handle);
+        self.ui_to_can_tx
+            .send(messages::MsgFromUi::DeleteSendMessage { handle })
+            .expect("Failed to send DeleteSendMessage");
+    }
+
+    fn transmit_sets_ui(&mut self, ui: &mut egui::Ui, parser: Option<&dbc::merged::MergedParser>) {
+        let header = format!("Transmit Sets ({})", self.transmit_sets.sets.len());
+        let response = egui::CollapsingHeader::new(header)
+            .id_salt((&self.title, "transmit_sets"))
+            .show(ui, |ui| {
+                let mut set_action = None;
+                for (idx, set) in self.transmit_sets.sets.iter().enumerate() {
+                    let is_running = self
+                        .sending_messages
+                        .iter()
+                        .any(|m| m.set_name.as_ref() == Some(&set.name));
+                    ui.horizontal(|ui| {
+                        ui.label(egui::RichText::new(&set.name).strong());
+                        ui.label(
+                            egui::RichText::new(format!("{} msg(s)", set.messages.len())).weak(),
+                        );
+                        if is_running {
+                            if ui.button("⏹ Stop").clicked() {
+                                set_action = Some(SetAction::Stop(idx));
+                            }
+                        } else if ui.button("▶ Start").clicked() {
+                            set_action = Some(SetAction::Start(idx));
+                        }
+                        let mut start_on_launch = set.start_on_launch;
+                        if ui
+                            .checkbox(&mut start_on_launch, "On launch")
+                            .on_hover_text("Start this set in a Send UI when the app opens")
+                            .changed()
+                        {
+                            set_action = Some(SetAction::SetLaunch(idx, start_on_launch));
+                        }
+                        ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                            if ui.button("🗑").on_hover_text("Delete set").clicked() {
+                                set_action = Some(SetAction::Delete(idx));
+                            }
+                            if ui.button("📤").on_hover_text("Export to file").clicked() {
+                                set_action = Some(SetAction::Export(idx));
+                            }
+                        });
+                    });
+                }
+                ui.horizontal(|ui| {
+                    ui.add(
+                        egui::TextEdit::singleline(&mut self.new_set_name)
+                            .desired_width(140.0)
+                            .hint_text("Set name"),
+                    );
+                    let can_save =
+                        !self.new_set_name.trim().is_empty() && !self.sending_messages.is_empty();
+                    if ui
+                        .add_enabled(can_save, egui::Button::new("💾 Save current"))
+                        .on_hover_text("Save the messages being sent as a set, replacing any with the same name")
+                        .clicked()
+                    {
+                        set_action = Some(SetAction::SaveCurrent);
+                    }
+                    if ui.button("📂 Import").clicked() {
+                        set_action = Some(SetAction::Import);
+                    }
+                });
+
+                for warning in &self.set_warnings {
+                    ui.label(egui::RichText::new(warning).color(ui.visuals().warn_fg_color));
+                }
+
+                match set_action {
+                    Some(SetAction::Start(idx)) => {
+                        let set = self.transmit_sets.sets[idx].clone();
+                        self.set_warnings = self.start_set(&set, parser);
+                    }
+                    Some(SetAction::Stop(idx)) => {
+                        let name = self.transmit_sets.sets[idx].name.clone();
+                        self.stop_set(&name);
+                        self.set_warnings.clear();
+                    }
+                    // By name, the sets on disk may be in another order than the ones shown
+                    Some(SetAction::Delete(idx)) => {
+                        let name = self.transmit_sets.sets[idx].name.clone();
+                        self.update_sets(|config| config.sets.retain(|s| s.name != name));
+                    }
+                    Some(SetAction::SetLaunch(idx, start_on_launch)) => {
+                        let name = self.transmit_sets.sets[idx].name.clone();
+                        self.update_sets(|config| {
+                            if let Some(set) = config.sets.iter_mut().find(|s| s.name == name) {
+                                set.start_on_launch = start_on_launch;
+                            }
+                        });
+                    }
+                    Some(SetAction::Export(idx)) => {
+                        let set = &self.transmit_sets.sets[idx];
+                        if let Some(path) = rfd::FileDialog::new()
+                            .set_file_name(format!("{}.json", set.name.replace(' ', "_")))
+                            .add_filter("JSON Files", &["json"])
+                            .save_file()
+                        {
+                            self.set_warnings = transmit_sets::export_set(set, &path)
+                                .err()
+                                .into_iter()
+                                .collect();
+                        }
+                    }
+                    Some(SetAction::SaveCurrent) => {
+                        let name = self.new_set_name.trim().to_string();
+                        self.save_current_as_set(name);
+                        self.new_set_name.clear();
+                    }
+                    Some(SetAction::Import) => {
+                        if let Some(path) = rfd::FileDialog::new()
+                            .add_filter("JSON Files", &["json"])
+                            .pick_file()
+                        {
+                            match transmit_sets::import_set(&path) {
+                                Ok(set) => {
+                                    self.set_warnings.clear();
+                                    self.update_sets(|config| config.upsert(set));
+                                }
+                                Err(e) => self.set_warnings = vec![e],
+                            }
+                        }
+                    }
+                    None => {}
+                }
+            });
+        // Picks up sets saved by other Send UIs since
+        if response.header_response.clicked() {
+            self.reload_sets();
+        }
+    }
+
+    fn reload_sets(&mut self) {
+        match transmit_sets::TransmitSetsConfig::load() {
+            Ok(config) => self.transmit_sets = config,
+            Err(e) => self.set_warnings = vec![e],
+        }
+    }
+
+    // False when the sets on disk couldn't be read, the change isn't saved then
+    fn update_sets(&mut self, change: impl FnOnce(&mut transmit_sets::TransmitSetsConfig)) -> bool {
+        match transmit_sets::TransmitSetsConfig::update(change) {
+            Ok(config) => {
+                self.transmit_sets = config;
+                true
+            }
+            Err(e) => {
+                self.set_warnings = vec![e];
+                false
+            }
+        }
+    }
+
+    fn save_current_as_set(&mut self, name: String) {
+        let messages = self
+            .sending_messages
+            .iter()
+            .map(|msg| transmit_sets::SavedMessage {
+                msg_name: msg.msg_name.clone(),
+                msg_id: msg.msg_id,
+                is_msg_id_extended: msg.is_msg_id_extended,
+                is_raw: msg.is_raw,
+                msg_bytes: msg.msg_bytes.clone(),
+                signal_values: msg
+                    .signal_values
+                    .iter()
+                    .map(|v| (v.name.clone(), v.value))
+                    .collect(),
+                auto_signals: msg
+                    .signal_values
+                    .iter()
+                    .filter_map(|v| {
+                        v.auto_kind()
+                            .ok()
+                            .flatten()
+                            .map(|kind| (v.name.clone(), kind))
+                    })
+                    .collect(),
+                adjustable_values_enabled: msg.adjustable_values_enabled,
+                amount: msg.initial_amount,
+            })
+            .collect();
+
+        let saved = self.update_sets(|config| {
+            let start_on_launch = config
+                .sets
+                .iter()
+                .find(|s| s.name == name)
+                .is_some_and(|s| s.start_on_launch);
+            config.upsert(transmit_sets::TransmitSet {
+                name: name.clone(),
+                start_on_launch,
+                messages,
+            });
+        });
+
+        if !saved {
+            return;
+        }
+        // The messages now belong to the set, so Stop on it stops them
+        for msg in &mut self.sending_messages {
+            msg.set_name = Some(name.clone());
+        }
+    }
+
+    /// Starts every message of the set, replacing any already sending with the same ID. DBC
+    /// messages are re-encoded by name, the ones that can't be are skipped and returned as warnings.
+    pub fn start_set(
+        &mut self,
+        set: &transmit_sets::TransmitSet,
+        parser: Option<&dbc::merged::MergedParser>,
+    ) -> Vec<String> {
+        let mut warnings = Vec::new();
+        let msg_defs = parser.map(|p| p.msg_defs()).unwrap_or_default();
+
+        for saved in &set.messages {
+            if saved.is_raw {
+                let msg_id_with_ext_flag = if saved.is_msg_id_extended {
+                    saved.msg_id | util::can::EXTENDED_ID_FLAG
+                } else {
+                    saved.msg_id
+                };
+                self.start_sending(SendingMessage {
+                    handle: can::scheduler::new_handle(),
+                    amount: saved.amount,
+                    initial_amount: saved.amount,
+                    msg_name: saved.msg_name.clone(),
+                    msg_id: saved.msg_id,
+                    msg_id_with_ext_flag,
+                    is_msg_id_extended: saved.is_msg_id_extended,
+                    msg_bytes: saved.msg_bytes.clone(),
+                    auto_fields: Vec::new(),
+                    signal_values: Vec::new(),
+                    adjustable_values_enabled: false,
+                    is_raw: true,
+                    set_name: Some(set.name.clone()),
+                    last_sent: chrono::Local::now(),
+                    stats: can::scheduler::PeriodStats::default(),
+                });
+                continue;
+            }
+
+            let (Some(parser), Some(msg_def)) =
+                (parser, msg_defs.iter().find(|m| m.name == saved.msg_name))
+            else {
+                warnings.push(format!(
+                    "{}: skipped {}, it isn't in the loaded DBC",
+                    set.name, saved.msg_name
+                ));
+                continue;
+            };
+
+            let saved_values: Vec<SignalValue> = saved
+                .signal_values
+                .iter()
+                .map(|(name, value)| {
+                    let mut signal_value = SignalValue {
+                        name: name.clone(),
+                        unit: String::new(),
+                        value: *value,
+                        min: f64::MIN,
+                        max: f64::MAX,
+                        auto: AutoMode::Value,
+                        checksum_bytes: String::new(),
+                    };
+                    if let Some(auto) = saved.auto_signals.get(name) {
+                        signal_value.set_auto_kind(auto);
+                    }
+                    signal_value
+                })
+                .collect();
+            let signal_values = refresh_signal_values(&saved_values, msg_def);
+            let auto_fields = match auto_fields_for(msg_def, &signal_values) {
+                Ok(auto_fields) => auto_fields,
+                Err(e) => {
+                    warnings.push(format!("{}: skipped {}, {}", set.name, saved.msg_name, e));
+                    continue;
+                }
+            };
+            let msg_id_with_ext_flag = util::can::can_dbc_to_u32_with_extid_flag(&msg_def.id);
+            let Some(msg_bytes) =
+                encode_msg_from_signals(parser, msg_id_with_ext_flag, &signal_values)
+            else {
+                warnings.push(format!(
+                    "{}: skipped {}, it can't be encoded",
+                    set.name, saved.msg_name
+                ));
+                continue;
+            };
+
+            self.start_sending(SendingMessage {
+                handle: can::scheduler::new_handle(),
+                amount: saved.amount,
+                initial_amount: saved.amount,
+                msg_name: msg_def.name.clone(),
+                msg_id: util::can::can_dbc_to_u32_without_extid_flag(&msg_def.id),
+                msg_id_with_ext_flag,
+                is_msg_id_extended: matches!(msg_def.id, can_dbc::MessageId::Extended(_)),
+                msg_bytes,
+                auto_fields,
+                signal_values,
+                adjustable_values_enabled: saved.adjustable_values_enabled,
+                is_raw: false,
+                set_name: Some(set.name.clone()),
+                last_sent: chrono::Local::now(),
+                stats: can::scheduler::PeriodStats::default(),
+            });
+        }
+
+        warnings
+    }
+
+    fn stop_set(&mut self, set_name: &str) {
+        let handles: Vec<_> = self
+            .sending_messages
+            .iter()
+            .filter(|m| m.set_name.as_deref() == Some(set_name))
+            .map(|m| m.handle)
+            .collect();
+        for handle in handles {
+            self.stop_sending(handle);
+        }
+    }
+
+    // Re-resolves the selected and currently sending messages by name against a reloaded DBC.
+    // Sending messages are re-encoded with the new layout, or stopped if they can't be.
+    pub fn on_dbc_reloaded(&mut self, parser: &dbc::merged::MergedParser) -> Vec<String> {
+        let mut warnings = Vec::new();
+        let msg_defs = parser.msg_defs();
+
+        if let Some(selected_msg) = &self.selected_msg {
+            match msg_defs.iter().find(|m| m.name == selected_msg.name) {
+                Some(new_msg) => {
+                    self.signal_values = refresh_signal_values(&self.signal_values, new_msg);
+                    self.selected_msg = Some(new_msg.clone());
+                }
+                None => {
+                    let warning = format!(
+                        "{}: {} no longer exists in the DBC",
+                        self.title, selected_msg.name
+                    );
+                    self.error = Some(warning.clone());
+                    warnings.push(warning);
+                    self.selected_msg = None;
+                    self.signal_values.clear();
+                }
+            }
+        }
+
+        let mut to_stop = Vec::new();
+        for sending_msg in &mut self.sending_messages {
+            if sending_msg.is_raw {
+                continue;
+            }
+            let new_msg = msg_defs.iter().find(|m| m.name == sending_msg.msg_name);
+            let encoded = new_msg
+                .filter(|m| {
+                    util::can::can_dbc_to_u32_with_extid_flag(&m.id)
+                        == sending_msg.msg_id_with_ext_flag
+                })
+                .and_then(|m| {
+                    let signal_values = refresh_signal_values(&sending_msg.signal_values, m);
+                    let auto_fields = auto_fields_for(m, &signal_values).ok()?;
+                    encode_msg_from_signals(
+                        parser,
+                        sending_msg.msg_id_with_ext_flag,
+                        &signal_values,
+                    )
+                    .map(|bytes| (bytes, signal_values, auto_fields))
+                });
+
+            let Some((msg_bytes, signal_values, auto_fields)) = encoded else {
+                warnings.push(format!(
+                    "{}: stopped sending {} (0x{:03X}), it was removed, moved to a new ID or can't be encoded",
+                    self.title, sending_msg.msg_name, sending_msg.msg_id
+                ));
+                to_stop.push(sending_msg.handle);
+                continue;
+            };
+
+            sending_msg.signal_values = signal_values;
+            // Signals may have moved, which moves their counter and checksum bits too
+            sending_msg.auto_fields = auto_fields;
+            if sending_msg.msg_bytes != msg_bytes || !sending_msg.auto_fields.is_empty() {
+                sending_msg.msg_bytes = msg_bytes.clone();
+                self.ui_to_can_tx
+                    .send(messages::MsgFromUi::UpdateSendBytes {
+                        handle: sending_msg.handle,
+                        msg_bytes,
+                        auto_fields: sending_msg.auto_fields.clone(),
+                    })
+                    .expect("Failed to send UpdateSendBytes");
+            }
+        }
+        for handle in to_stop {
+            self.stop_sending(handle);
+        }
+
+        warnings
+    }
+
     pub fn handle_can_message(&mut self, msg: &messages::MsgFromCan) {
         if let messages::MsgFromCan::MessageSent {
-            msg_id,
+            handle,
             timestamp,
             amount_left,
+            stats,
+            ..
         } = msg
         {
             if let Some(rx_amount_left) = amount_left {
-                for sending_msg in &mut self.sending_messages {
-                    if sending_msg.msg_id == *msg_id {
-                        sending_msg.last_sent = *timestamp;
-                        sending_msg.amount = *rx_amount_left;
-                        break;
-                    }
+                if let Some(sending_msg) = self
+                    .sending_messages
+                    .iter_mut()
+                    .find(|m| m.handle == *handle)
+                {
+                    sending_msg.last_sent = *timestamp;
+                    sending_msg.amount = *rx_amount_left;
+                    sending_msg.stats = *stats;
                 }
             } else {
                 // If amount_left is None, it means the message is done sending,
                 // so we remove it from the list
-                self.sending_messages.retain(|msg| msg.msg_id != *msg_id);
+                self.sending_messages.retain(|msg| msg.handle != *handle);
             }
         }
     }
@@ -414,6 +1001,9 @@ impl SendingMessage {
                     .color(ui.visuals().text_color()),
             );
             ui.label(egui::RichText::new(self.amount.display()).color(ui.visuals().text_color()));
+            if let Some(set_name) = &self.set_name {
+                ui.label(egui::RichText::new(format!("[{}]", set_name)).weak());
+            }
             ui.label(
                 egui::RichText::new(format!(
                     "~{} ms ago",
@@ -422,10 +1012,32 @@ impl SendingMessage {
                 .italics()
                 .color(ui.visuals().weak_text_color()),
             );
+            if self.stats.count > 0 {
+                // Highlight periods that are off by more than 5% (or 1 ms for short ones)
+                let requested_ms = self.amount.period_ms() as f64;
+                let color =
+                    if (self.stats.mean_ms - requested_ms).abs() > (requested_ms * 0.05).max(1.0) {
+                        ui.visuals().warn_fg_color
+                    } else {
+                        ui.visuals().weak_text_color()
+                    };
+                ui.label(
+                    egui::RichText::new(format!("measured {:.1} ms", self.stats.mean_ms))
+                        .color(color),
+                )
+                .on_hover_text(format!(
+                    "Requested {} ms\nLast {:.1} ms, min {:.1} ms, max {:.1} ms over {} periods",
+                    requested_ms,
+                    self.stats.last_ms,
+                    self.stats.min_ms,
+                    self.stats.max_ms,
+                    self.stats.count
+                ));
+            }
             ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
                 if ui.button("🗑").on_hover_text("Delete message").clicked() {
                     delete_action = Some(SendUiActions::DeleteMessage {
-                        msg_id: self.msg_id,
+                        handle: self.handle,
                     });
                 }
                 ui.label(
@@ -440,6 +1052,11 @@ impl SendingMessage {
 
         ui.add_space(4.0);
 
+        // Raw frames have no signals, the header already shows their bytes
+        if self.signal_values.is_empty() {
+            return delete_action;
+        }
+
         // Card container
         egui::Frame::group(ui.style())
             .fill(ui.visuals().faint_bg_color)
@@ -447,13 +1064,28 @@ impl SendingMessage {
             .inner_margin(egui::Margin::symmetric(8, 6))
             .show(ui, |ui| {
                 ui.vertical(|ui| {
+                    let theme = ui::theme::get_theme(ui.ctx());
                     let total_signals = self.signal_values.len();
                     for (i, signal) in self.signal_values.iter_mut().enumerate() {
                         ui.horizontal(|ui| {
+                            // Counters and checksums have no meaningful value to color
+                            let color = (signal.auto == AutoMode::Value)
+                                .then(|| {
+                                    formatter::try_color(
+                                        formatter,
+                                        &self.msg_name,
+                                        &signal.name,
+                                        &signal.unit,
+                                        signal.value,
+                                        None,
+                                        &theme,
+                                    )
+                                })
+                                .flatten();
                             ui.label(
                                 egui::RichText::new(&signal.name)
                                     .monospace()
-                                    .color(ui.visuals().text_color()),
+                                    .color(color.unwrap_or_else(|| ui.visuals().text_color())),
                             );
                             ui.with_layout(
                                 egui::Layout::right_to_left(egui::Align::Center),
@@ -463,7 +1095,13 @@ impl SendingMessage {
                                         .map(|f| f.expected_decimals(&self.msg_name, &signal.name))
                                         .unwrap_or(2);
 
-                                    if self.adjustable_values_enabled {
+                                    if signal.auto != AutoMode::Value {
+                                        ui.label(
+                                            egui::RichText::new(signal.auto.display())
+                                                .italics()
+                                                .color(ui.visuals().weak_text_color()),
+                                        );
+                                    } else if self.adjustable_values_enabled {
                                         let speed = 10f64.powi(-(expected_decimals as i32));
                                         if ui
                                             .add(
@@ -498,8 +1136,159 @@ impl SendingMessage {
     }
 }
 
+fn send_options_ui(
+    ui: &mut egui::Ui,
+    send_mode: &mut SendMode,
+    period_ms: &mut usize,
+    finite_amount: &mut usize,
+) {
+    ui.horizontal(|ui| {
+        ui.selectable_value(send_mode, SendMode::Once, "Once");
+        ui.selectable_value(send_mode, SendMode::Infinite, "Infinite");
+        ui.selectable_value(send_mode, SendMode::Finite, "Finite");
+    });
+
+    match send_mode {
+        SendMode::Once => {}
+        SendMode::Infinite => {
+            ui.horizontal(|ui| {
+                ui.label("Period (ms)");
+                ui.add(egui::DragValue::new(period_ms).speed(1).range(1..=10_000));
+            });
+        }
+        SendMode::Finite => {
+            ui.horizontal(|ui| {
+                ui.label("Amount");
+                ui.add(
+                    egui::DragValue::new(finite_amount)
+                        .speed(1)
+                        .range(1..=10_000),
+                );
+            });
+            ui.horizontal(|ui| {
+                ui.label("Period (ms)");
+                ui.add(egui::DragValue::new(period_ms).speed(1).range(1..=10_000));
+            });
+        }
+    }
+}
+
+// Parses the hex ID and data typed into the raw frame editor, padding the data with zeros up to the DLC
+fn parse_raw_frame(
+    id_text: &str,
+    is_extended: bool,
+    dlc: usize,
+    data_text: &str,
+) -> Result<(u32, Vec<u8>), String> {
+    let id_text = id_text.trim();
+    let id_text = id_text
+        .strip_prefix("0x")
+        .or_else(|| id_text.strip_prefix("0X"))
+        .unwrap_or(id_text);
+    let msg_id =
+        u32::from_str_radix(id_text, 16).map_err(|_| format!("\"{}\" is not a hex ID", id_text))?;
+    let max_id = if is_extended {
+        util::can::EXTENDED_ID_MASK
+    } else {
+        util::can::STANDARD_ID_MASK
+    };
+    if msg_id > max_id {
+        return Err(format!(
+            "ID 0x{:X} doesn't fit in {} bits",
+            msg_id,
+            if is_extended { 29 } else { 11 }
+        ));
+    }
+
+    let hex: String = data_text
+        .chars()
+        .filter(|c| !c.is_whitespace() && *c != ',')
+        .collect();
+    if hex.len() % 2 != 0 {
+        return Err("Data must be whole bytes (two hex digits each)".to_string());
+    }
+    let mut msg_bytes = (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| format!("\"{}\" is not valid hex data", data_text.trim()))?;
+    if msg_bytes.len() > dlc {
+        return Err(format!(
+            "{} data bytes given but the DLC is {}",
+            msg_bytes.len(),
+            dlc
+        ));
+    }
+    msg_bytes.resize(dlc, 0);
+
+    Ok((msg_id, msg_bytes))
+}
+
+impl SignalValue {
+    fn auto_kind(&self) -> Result<Option<can::auto_fields::AutoKind>, String> {
+        Ok(match self.auto {
+            AutoMode::Value => None,
+            AutoMode::Counter => Some(can::auto_fields::AutoKind::Counter),
+            AutoMode::Checksum(kind) => Some(can::auto_fields::AutoKind::Checksum {
+                kind,
+                bytes: can::auto_fields::parse_byte_list(&self.checksum_bytes)?,
+            }),
+        })
+    }
+
+    fn set_auto_kind(&mut self, auto: &can::auto_fields::AutoKind) {
+        match auto {
+            can::auto_fields::AutoKind::Counter => self.auto = AutoMode::Counter,
+            can::auto_fields::AutoKind::Checksum { kind, bytes } => {
+                self.auto = AutoMode::Checksum(*kind);
+                self.checksum_bytes = can::auto_fields::format_byte_list(bytes);
+            }
+        }
+    }
+}
+
+// Resolves the counter and checksum signals to the bits the CAN thread writes them into
+fn auto_fields_for(
+    msg: &can_dbc::Message,
+    signals: &[SignalValue],
+) -> Result<Vec<can::auto_fields::AutoField>, String> {
+    let mut auto_fields = Vec::new();
+    for value in signals {
+        let Some(kind) = value
+            .auto_kind()
+            .map_err(|e| format!("{}: {}", value.name, e))?
+        else {
+            continue;
+        };
+        let sig = msg
+            .signals
+            .iter()
+            .find(|s| s.name == value.name)
+            .ok_or_else(|| format!("{} has no signal {}", msg.name, value.name))?;
+        if let can::auto_fields::AutoKind::Checksum { bytes, .. } = &kind {
+            if sig.size > 8 {
+                return Err(format!(
+                    "{} is {} bits, checksums are 8 bits at most",
+                    sig.name, sig.size
+                ));
+            }
+            if let Some(byte) = bytes.iter().find(|&&b| b as u64 >= msg.size) {
+                return Err(format!(
+                    "{} covers byte {} but {} is {} bytes long",
+                    sig.name, byte, msg.name, msg.size
+                ));
+            }
+        }
+        auto_fields.push(can::auto_fields::AutoField {
+            bits: dbc::layout::signal_bits(sig),
+            kind,
+        });
+    }
+    Ok(auto_fields)
+}
+
 fn encode_msg_from_signals(
-    parser: &can_decode::Parser,
+    parser: &dbc::merged::MergedParser,
     msg_id_with_ext_flag: u32,
     signals: &[SignalValue],
 ) -> Option<Vec<u8>> {
@@ -510,7 +1299,32 @@ fn encode_msg_from_signals(
     parser.encode_msg(msg_id_with_ext_flag, &values_hashmap)
 }
 
-fn signal_range(sig: &can_dbc::Signal) -> (f64, f64) {
+// Keeps the values and auto modes of signals that still exist (values clamped to their new
+// range), new signals start at 0
+fn refresh_signal_values(old: &[SignalValue], msg: &can_dbc::Message) -> Vec<SignalValue> {
+    msg.signals
+        .iter()
+        .map(|sig| {
+            let (min, max) = signal_range(sig);
+            let old_value = old.iter().find(|v| v.name == sig.name);
+            let value = old_value.map(|v| v.value).unwrap_or(0.0).clamp(min, max);
+            SignalValue {
+                name: sig.name.clone(),
+                unit: sig.unit.clone(),
+                value,
+                min,
+                max,
+                auto: old_value.map_or(AutoMode::Value, |v| v.auto),
+                checksum_bytes: old_value
+                    .map(|v| v.checksum_bytes.clone())
+                    .unwrap_or_default(),
+            }
+        })
+        .collect()
+}
+
+// The DBC's range, or a wide one for signals without a usable range. The rest bus uses it too.
+pub fn signal_range(sig: &can_dbc::Signal) -> (f64, f64) {
     let fallback = (-1000.0, 1000.0);
 
     let min = util::can::can_dbc_numeric_to_f64(&sig.min);
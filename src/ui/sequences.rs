@@ -0,0 +1,164 @@
+use crate::{config, messages, sequence};
+use eframe::egui;
+
+pub struct Sequences {
+    pub title: String,
+    ui_to_can_tx: std::sync::mpsc::Sender<messages::MsgFromUi>,
+    config: sequence::SequenceConfig,
+    // Sequence name -> last status reported by the CAN thread
+    statuses: std::collections::HashMap<String, sequence::SequenceStatus>,
+}
+
+impl Drop for Sequences {
+    fn drop(&mut self) {
+        // Same as the Send UI, closing the widget stops whatever it started
+        for name in self.statuses.keys() {
+            if let Err(e) = self
+                .ui_to_can_tx
+                .send(messages::MsgFromUi::StopSequence { name: name.clone() })
+            {
+                log::error!("Failed to send StopSequence for {}: {}", name, e);
+            }
+        }
+    }
+}
+
+impl Sequences {
+    pub fn new(
+        instance_num: usize,
+        ui_to_can_tx: std::sync::mpsc::Sender<messages::MsgFromUi>,
+    ) -> Self {
+        Self {
+            title: format!("Sequences #{}", instance_num),
+            ui_to_can_tx,
+            config: sequence::SequenceConfig::load(),
+            statuses: std::collections::HashMap::new(),
+        }
+    }
+
+    fn start(&mut self, seq: &sequence::Sequence) {
+        self.statuses.insert(
+            seq.name.clone(),
+            sequence::SequenceStatus::Running { step: 0 },
+        );
+        self.ui_to_can_tx
+            .send(messages::MsgFromUi::StartSequence(seq.clone()))
+            .expect("Failed to send StartSequence");
+    }
+
+    fn stop(&mut self, name: &str) {
+        self.statuses.remove(name);
+        self.ui_to_can_tx
+            .send(messages::MsgFromUi::StopSequence {
+                name: name.to_string(),
+            })
+            .expect("Failed to send StopSequence");
+    }
+
+    pub fn show(&mut self, ui: &mut egui::Ui) -> egui_tiles::UiResponse {
+        ui.horizontal(|ui| {
+            ui.heading(format!("⏯ {}", self.title));
+            if ui
+                .button("⟳ Reload")
+                .on_hover_text(format!(
+                    "Read {} again, running sequences keep their old steps",
+                    sequence::SEQUENCES_CONFIG_FILE
+                ))
+                .clicked()
+            {
+                self.config = sequence::SequenceConfig::load();
+            }
+        });
+        ui.separator();
+
+        if self.config.sequences.is_empty() {
+            ui.label(
+                egui::RichText::new(format!(
+                    "No sequences defined. Add them to {} in {}.",
+                    sequence::SEQUENCES_CONFIG_FILE,
+                    config::config_dir().display()
+                ))
+                .italics()
+                .weak(),
+            );
+            return egui_tiles::UiResponse::None;
+        }
+
+        let mut to_start = None;
+        let mut to_stop = None;
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            for seq in &self.config.sequences {
+                let status = self.statuses.get(&seq.name);
+                ui.horizontal(|ui| {
+                    ui.label(egui::RichText::new(&seq.name).strong().size(16.0));
+                    if status.is_some() {
+                        if ui.button("⏹ Stop").clicked() {
+                            to_stop = Some(seq.name.clone());
+                        }
+                        if ui.button("⟲ Restart").clicked() {
+                            to_start = Some(seq.clone());
+                        }
+                    } else if ui.button("▶ Run").clicked() {
+                        to_start = Some(seq.clone());
+                    }
+                    match status {
+                        Some(sequence::SequenceStatus::Running { step }) => {
+                            ui.label(format!("running step {}/{}", step + 1, seq.steps.len()));
+                        }
+                        Some(sequence::SequenceStatus::Finished) => {
+                            ui.label(
+                                egui::RichText::new("finished, periodic messages still sending")
+                                    .color(ui.visuals().weak_text_color()),
+                            );
+                        }
+                        Some(sequence::SequenceStatus::Failed(e)) => {
+                            ui.label(
+                                egui::RichText::new(format!("failed at {}", e))
+                                    .color(ui.visuals().error_fg_color),
+                            );
+                        }
+                        None => {}
+                    }
+                });
+
+                let current_step = match status {
+                    Some(sequence::SequenceStatus::Running { step }) => Some(*step),
+                    _ => None,
+                };
+                egui::CollapsingHeader::new(format!("{} step(s)", seq.steps.len()))
+                    .id_salt((&self.title, &seq.name))
+                    .default_open(current_step.is_some())
+                    .show(ui, |ui| {
+                        for (idx, step) in seq.steps.iter().enumerate() {
+                            let text =
+                                egui::RichText::new(format!("{:>2}. {}", idx + 1, step.describe()))
+                                    .monospace();
+                            if current_step == Some(idx) {
+                                ui.label(text.strong().color(ui.visuals().selection.stroke.color));
+                            } else {
+                                ui.label(text);
+                            }
+                        }
+                    });
+                ui.separator();
+            }
+        });
+
+        if let Some(name) = to_stop {
+            self.stop(&name);
+        }
+        if let Some(seq) = to_start {
+            self.start(&seq);
+        }
+
+        egui_tiles::UiResponse::None
+    }
+
+    pub fn handle_can_message(&mut self, msg: &messages::MsgFromCan) {
+        if let messages::MsgFromCan::SequenceStatus { name, status } = msg
+            && self.statuses.contains_key(name)
+        {
+            self.statuses.insert(name.clone(), status.clone());
+        }
+    }
+}
@@ -0,0 +1,74 @@
+use crate::{app, shortcuts};
+use eframe::egui;
+
+// Cheat sheet of the keymap, with the bindings that couldn't be used
+pub fn show(app: &mut app::DAQApp, ctx: &egui::Context) {
+    if !app.show_shortcut_help {
+        return;
+    }
+    if ctx.input(|i| i.key_pressed(egui::Key::Escape)) {
+        app.show_shortcut_help = false;
+        return;
+    }
+
+    let mut open = true;
+    let mut reload = false;
+    egui::Window::new("⌨ Keyboard Shortcuts")
+        .open(&mut open)
+        .collapsible(false)
+        .resizable(false)
+        .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+        .show(ctx, |ui| {
+            for issue in &app.keymap.issues {
+                ui.label(
+                    egui::RichText::new(format!("⚠ {}", issue)).color(ui.visuals().warn_fg_color),
+                );
+            }
+            if !app.keymap.issues.is_empty() {
+                ui.separator();
+            }
+
+            egui::ScrollArea::vertical()
+                .max_height(400.0)
+                .show(ui, |ui| {
+                    egui::Grid::new("shortcut_help_grid")
+                        .num_columns(2)
+                        .spacing([24.0, 4.0])
+                        .striped(true)
+                        .show(ui, |ui| {
+                            let mut bindings: Vec<_> = app.keymap.bindings.iter().collect();
+                            bindings.sort_by_key(|b| b.command.describe());
+                            for binding in bindings {
+                                ui.label(
+                                    egui::RichText::new(ctx.format_shortcut(&binding.shortcut))
+                                        .monospace()
+                                        .strong(),
+                                );
+                                ui.label(binding.command.describe());
+                                ui.end_row();
+                            }
+                        });
+                });
+
+            ui.separator();
+            ui.horizontal(|ui| {
+                ui.label(
+                    egui::RichText::new(format!(
+                        "Edit {} to change them",
+                        shortcuts::SHORTCUTS_FILE
+                    ))
+                    .weak(),
+                );
+                if ui.button("⟳ Reload").clicked() {
+                    reload = true;
+                }
+            });
+        });
+
+    if reload {
+        app.keymap = shortcuts::Keymap::load();
+    }
+    if !open {
+        app.show_shortcut_help = false;
+    }
+}
@@ -1,24 +1,44 @@
-use crate::{action, app, assets, connection, formatter, messages, util};
+use crate::{action, app, assets, config, connection, dbc, messages, theme, util};
 use eframe::egui;
 
+// Replaces the selected DBCs when `append` is false, otherwise adds to them
 pub fn select_dbc(
     app: &mut app::DAQApp,
     ui_to_can_tx: &std::sync::mpsc::Sender<messages::MsgFromUi>,
+    append: bool,
 ) {
-    if let Some(path) = rfd::FileDialog::new()
+    if let Some(picked) = rfd::FileDialog::new()
         .add_filter("DBC Files", &["dbc"])
-        .pick_file()
+        .pick_files()
     {
-        app.parser = app::ParserInfo::new(path.clone());
-        if app.parser.is_some() {
-            ui_to_can_tx
-                .send(messages::MsgFromUi::DbcSelected(path))
-                .expect("Failed to send DBC selected message");
-            app.save_settings();
+        let mut paths = match (&app.parser, append) {
+            (Some(p), true) => p.dbc_paths.clone(),
+            _ => Vec::new(),
+        };
+        for path in picked {
+            if !paths.contains(&path) {
+                paths.push(path);
+            }
         }
+        set_dbc_paths(app, ui_to_can_tx, paths);
     }
 }
 
+fn set_dbc_paths(
+    app: &mut app::DAQApp,
+    ui_to_can_tx: &std::sync::mpsc::Sender<messages::MsgFromUi>,
+    paths: Vec<std::path::PathBuf>,
+) {
+    let Some(parser) = app::ParserInfo::new_maybe(paths.clone()) else {
+        return;
+    };
+    app.parser = Some(parser);
+    ui_to_can_tx
+        .send(messages::MsgFromUi::DbcSelected(paths))
+        .expect("Failed to send DBC selected message");
+    app.save_settings();
+}
+
 pub fn show(app: &mut app::DAQApp, ctx: &egui::Context) {
     egui::SidePanel::left("left_sidebar")
         .resizable(true)
@@ -33,12 +53,66 @@ pub fn show(app: &mut app::DAQApp, ctx: &egui::Context) {
             });
             ui.separator();
 
-            let theme_label = format!("🎨 Theme: {}", app.theme_selection.get_name());
+            ui.horizontal(|ui| {
+                ui.label("🎨 Theme");
+                egui::ComboBox::from_id_salt("theme_combo")
+                    .selected_text(&app.theme_name)
+                    .show_ui(ui, |ui| {
+                        for theme in &app.themes.themes {
+                            if ui
+                                .selectable_label(theme.name == app.theme_name, &theme.name)
+                                .clicked()
+                            {
+                                app.action_queue
+                                    .push(action::AppAction::SelectTheme(theme.name.clone()));
+                            }
+                        }
+                    });
+            })
+            .response
+            .on_hover_text(format!(
+                "Themes are the *.toml files in {}",
+                config::config_dir().join(theme::THEMES_DIR).display()
+            ));
+
+            ui.separator();
+            ui.heading("Profile")
+                .on_hover_text(format!("Stored in {}", config::config_dir().display()));
 
-            if ui.button(theme_label).clicked() {
-                app.toggle_theme();
-                app.save_settings();
-            }
+            egui::ComboBox::from_id_salt("profile_combo")
+                .selected_text(&app.profile.name)
+                .show_ui(ui, |ui| {
+                    for name in &app.profiles {
+                        if ui
+                            .selectable_label(*name == app.profile.name, name)
+                            .clicked()
+                        {
+                            app.action_queue
+                                .push(action::AppAction::SwitchProfile(name.clone()));
+                        }
+                    }
+                });
+            ui.horizontal(|ui| {
+                ui.add(
+                    egui::TextEdit::singleline(&mut app.new_profile_name)
+                        .hint_text("Profile name")
+                        .desired_width(120.0),
+                );
+                let name = app.new_profile_name.trim().to_string();
+                let can_create = config::is_valid_file_name(&name) && !app.profiles.contains(&name);
+                if ui
+                    .add_enabled(can_create, egui::Button::new("➕ New"))
+                    .on_hover_text(
+                        "Copy the DBCs, source, theme, formatter and layouts of this profile \
+                         under a new name, and switch to it",
+                    )
+                    .clicked()
+                {
+                    app.action_queue
+                        .push(action::AppAction::CreateProfile(name));
+                    app.new_profile_name.clear();
+                }
+            });
 
             ui.separator();
 
@@ -100,6 +174,82 @@ pub fn show(app: &mut app::DAQApp, ctx: &egui::Context) {
                 app.action_queue
                     .push(action::AppAction::SpawnWidget(action::WidgetType::Jitter));
             }
+            if ui.button("Add Derived Signals").clicked() {
+                app.action_queue.push(action::AppAction::SpawnWidget(
+                    action::WidgetType::DerivedSignals,
+                ));
+            }
+            if ui.button("Add DBC Browser").clicked() {
+                app.action_queue.push(action::AppAction::SpawnWidget(
+                    action::WidgetType::DbcBrowser,
+                ));
+            }
+            if ui.button("Add Sequences").clicked() {
+                app.action_queue.push(action::AppAction::SpawnWidget(
+                    action::WidgetType::Sequences,
+                ));
+            }
+            if ui.button("Add Rest Bus").clicked() {
+                app.action_queue
+                    .push(action::AppAction::SpawnWidget(action::WidgetType::RestBus));
+            }
+            if ui.button("Add Fleet").clicked() {
+                app.action_queue
+                    .push(action::AppAction::SpawnWidget(action::WidgetType::Fleet));
+            }
+            if ui.button("Add UDS Client").clicked() {
+                app.action_queue.push(action::AppAction::SpawnWidget(
+                    action::WidgetType::UdsClient,
+                ));
+            }
+            if ui.button("Add Theme Editor").clicked() {
+                app.action_queue.push(action::AppAction::SpawnWidget(
+                    action::WidgetType::ThemeEditor,
+                ));
+            }
+            if ui.button("Add Formatter Editor").clicked() {
+                app.action_queue.push(action::AppAction::SpawnWidget(
+                    action::WidgetType::FormatterEditor,
+                ));
+            }
+
+            ui.separator();
+            ui.heading("Layouts");
+
+            for named in &app.layouts.named {
+                ui.horizontal(|ui| {
+                    if ui
+                        .button(&named.name)
+                        .on_hover_text("Replace the workspace with this layout")
+                        .clicked()
+                    {
+                        app.action_queue
+                            .push(action::AppAction::LoadLayout(named.name.clone()));
+                    }
+                    if ui.small_button("🗑").on_hover_text("Delete").clicked() {
+                        app.action_queue
+                            .push(action::AppAction::DeleteLayout(named.name.clone()));
+                    }
+                });
+            }
+            ui.horizontal(|ui| {
+                ui.add(
+                    egui::TextEdit::singleline(&mut app.new_layout_name)
+                        .hint_text("Layout name")
+                        .desired_width(120.0),
+                );
+                let name = app.new_layout_name.trim().to_string();
+                if ui
+                    .add_enabled(!name.is_empty(), egui::Button::new("💾 Save"))
+                    .on_hover_text(
+                        "Save the current workspace, replacing a layout with the same name",
+                    )
+                    .clicked()
+                {
+                    app.action_queue.push(action::AppAction::SaveLayout(name));
+                    app.new_layout_name.clear();
+                }
+            });
 
             ui.separator();
             ui.heading("Connection Settings");
@@ -185,10 +335,14 @@ pub fn show(app: &mut app::DAQApp, ctx: &egui::Context) {
                         }
                         ui.separator();
                         ui.label("Simulated");
-                        let dbc_path = app.parser.as_ref().map(|p| p.dbc_path.clone());
+                        let dbc_paths = app
+                            .parser
+                            .as_ref()
+                            .map(|p| p.dbc_paths.clone())
+                            .unwrap_or_default();
                         let sim_sources = [
-                            connection::ConnectionSource::Simulated(true, dbc_path.clone()),
-                            connection::ConnectionSource::Simulated(false, dbc_path.clone()),
+                            connection::ConnectionSource::Simulated(true, dbc_paths.clone()),
+                            connection::ConnectionSource::Simulated(false, dbc_paths),
                         ];
                         for sim_source in sim_sources {
                             let label = match sim_source {
@@ -249,29 +403,89 @@ pub fn show(app: &mut app::DAQApp, ctx: &egui::Context) {
                 ui.label(egui::RichText::new(status_icon).color(status_color));
             });
 
-            ui.horizontal(|ui| {
-                // Clone the sender so we don’t borrow app immutably yet
-                let ui_to_can_tx = app.ui_to_can_tx.clone();
+            // Clone the sender so we don’t borrow app immutably yet
+            let ui_to_can_tx = app.ui_to_can_tx.clone();
 
-                if ui.button("📁 Select DBC").clicked() {
-                    select_dbc(app, &ui_to_can_tx); // mutable borrow is fine
+            ui.horizontal(|ui| {
+                if ui
+                    .button("📁 Select DBC")
+                    .on_hover_text("Select one or more DBC files, they are decoded as one")
+                    .clicked()
+                {
+                    select_dbc(app, &ui_to_can_tx, false); // mutable borrow is fine
                 }
 
-                if let Some(path) = app.parser.as_ref().map(|p| &p.dbc_path) {
-                    let dbc_name = path
-                        .file_name()
-                        .map(|n| n.to_string_lossy())
-                        .unwrap_or_else(|| path.display().to_string().into());
-                    ui.label(format!("{}", dbc_name));
+                if app.parser.is_some() {
+                    if ui
+                        .small_button("➕")
+                        .on_hover_text("Add more DBC files")
+                        .clicked()
+                    {
+                        select_dbc(app, &ui_to_can_tx, true);
+                    }
+                    if ui
+                        .small_button("⟳")
+                        .on_hover_text(
+                            "Reload DBC (also happens automatically when a file changes)",
+                        )
+                        .clicked()
+                    {
+                        let dbc_paths = app
+                            .parser
+                            .as_ref()
+                            .map(|p| p.dbc_paths.clone())
+                            .unwrap_or_default();
+                        app.reload_dbc(dbc_paths);
+                    }
                 } else {
                     ui.label("DBC: None selected");
                 }
             });
 
+            if let Some(parser) = &app.parser {
+                let mut remove_idx = None;
+                for (i, path) in parser.dbc_paths.iter().enumerate() {
+                    ui.horizontal(|ui| {
+                        let can_remove = parser.dbc_paths.len() > 1;
+                        if ui
+                            .add_enabled(can_remove, egui::Button::new("✖").small())
+                            .on_hover_text("Remove this DBC")
+                            .clicked()
+                        {
+                            remove_idx = Some(i);
+                        }
+                        ui.label(dbc::merged::file_name(path))
+                            .on_hover_text(path.display().to_string());
+                    });
+                }
+
+                if !parser.lint.is_empty() {
+                    let color = if dbc::lint::has_errors(&parser.lint) {
+                        ui.visuals().error_fg_color
+                    } else {
+                        ui.visuals().warn_fg_color
+                    };
+                    egui::CollapsingHeader::new(
+                        egui::RichText::new(format!("⚠ {} DBC issue(s)", parser.lint.len()))
+                            .color(color),
+                    )
+                    .id_salt("dbc_lint")
+                    .show(ui, |ui| {
+                        super::dbc_lint::show_issues(ui, &parser.lint);
+                    });
+                }
+
+                if let Some(i) = remove_idx {
+                    let mut paths = parser.dbc_paths.clone();
+                    paths.remove(i);
+                    set_dbc_paths(app, &ui_to_can_tx, paths);
+                }
+            }
+
             ui.separator();
 
             if ui.button("Reload formatter").clicked() {
-                app.value_formatter = formatter::Formatter::try_load();
+                app.action_queue.push(action::AppAction::ReloadFormatter);
             }
         });
 }
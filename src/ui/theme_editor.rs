@@ -0,0 +1,268 @@
+use crate::{action, config, theme};
+use eframe::egui;
+
+// WCAG's minimum for body text, dark themes in direct sun need well above it
+const MIN_CONTRAST: f32 = 4.5;
+
+pub struct ThemeEditor {
+    pub title: String,
+    // Themes to start from
+    themes: theme::Themes,
+    base: String,
+    name: String,
+    colors: theme::ThemeColors,
+    // Shows the edited colors on the whole app, not just the preview
+    live: bool,
+    preview_text: String,
+    status: Option<Result<String, String>>,
+}
+
+impl ThemeEditor {
+    pub fn new(instance_num: usize, current_theme: &str) -> Self {
+        let themes = theme::Themes::load();
+        let base = themes.get(current_theme);
+        Self {
+            title: format!("Theme Editor #{}", instance_num),
+            base: base.name.clone(),
+            name: Self::name_for(&base.name),
+            colors: base.colors(),
+            themes,
+            live: false,
+            preview_text: "Editable text".to_string(),
+            status: None,
+        }
+    }
+
+    // Built-in themes can't be overwritten, their copy gets a name of its own
+    fn name_for(base: &str) -> String {
+        if Self::is_builtin(base) {
+            format!("{}_custom", base.to_lowercase())
+        } else {
+            base.to_string()
+        }
+    }
+
+    fn is_builtin(name: &str) -> bool {
+        name.eq_ignore_ascii_case(theme::DEFAULT_THEME)
+            || name.eq_ignore_ascii_case(theme::SUNLIGHT_THEME)
+    }
+
+    fn save(&mut self, action_queue: &mut Vec<action::AppAction>) {
+        match self.colors.save(&self.name) {
+            Ok(()) => {
+                self.status = Some(Ok(format!("Saved {}", self.name)));
+                self.themes = theme::Themes::load();
+                self.base = self.name.clone();
+                self.live = false;
+                action_queue.push(action::AppAction::ThemeSaved(self.name.clone()));
+            }
+            Err(e) => {
+                self.status = Some(Err(format!("Failed to save {}: {}", self.name, e)));
+            }
+        }
+    }
+
+    pub fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        action_queue: &mut Vec<action::AppAction>,
+    ) -> egui_tiles::UiResponse {
+        ui.heading(format!("🎨 {}", self.title));
+
+        let mut changed = false;
+        ui.horizontal(|ui| {
+            ui.label("Start from");
+            egui::ComboBox::from_id_salt(("theme_editor_base", &self.title))
+                .selected_text(&self.base)
+                .show_ui(ui, |ui| {
+                    for theme in &self.themes.themes {
+                        if ui
+                            .selectable_label(theme.name == self.base, &theme.name)
+                            .clicked()
+                        {
+                            self.base = theme.name.clone();
+                            self.name = Self::name_for(&theme.name);
+                            self.colors = theme.colors();
+                            changed = true;
+                        }
+                    }
+                });
+            if ui.button("↺ Revert").clicked() {
+                self.colors = self.themes.get(&self.base).colors();
+                changed = true;
+            }
+            if ui
+                .checkbox(&mut self.live, "Apply to the app")
+                .on_hover_text("Until another theme is picked, nothing is kept without saving")
+                .changed()
+            {
+                if self.live {
+                    changed = true;
+                } else {
+                    action_queue.push(action::AppAction::EndThemePreview);
+                }
+            }
+        });
+        ui.separator();
+
+        ui.columns(2, |columns| {
+            egui::Grid::new(("theme_editor_grid", &self.title))
+                .num_columns(3)
+                .striped(true)
+                .show(&mut columns[0], |ui| {
+                    let colors = &mut self.colors;
+                    changed |= color_row(ui, "Background", &mut colors.background);
+                    changed |= color_row(ui, "Panel", &mut colors.panel_bg);
+                    changed |= color_row(ui, "Text", &mut colors.text);
+                    changed |= color_row(ui, "Accent", &mut colors.accent);
+                    changed |= color_row(ui, "Button", &mut colors.button);
+                    changed |= color_row(ui, "Button hover", &mut colors.button_hover);
+                    changed |= color_row(ui, "Button text", &mut colors.button_text);
+                    let (error, warning) = (colors.error_color(), colors.warning_color());
+                    let (success, info) = (colors.success_color(), colors.info_color());
+                    changed |= optional_color_row(ui, "Error", &mut colors.error, error);
+                    changed |= optional_color_row(ui, "Warning", &mut colors.warning, warning);
+                    changed |= optional_color_row(ui, "Success", &mut colors.success, success);
+                    changed |= optional_color_row(ui, "Info", &mut colors.info, info);
+
+                    ui.label("Light base");
+                    changed |= ui
+                        .checkbox(&mut colors.light, "")
+                        .on_hover_text("Start from egui's light style, for light backgrounds")
+                        .changed();
+                    ui.end_row();
+                });
+
+            self.show_preview(&mut columns[1]);
+        });
+
+        ui.separator();
+        ui.horizontal(|ui| {
+            ui.label("Name");
+            ui.add(egui::TextEdit::singleline(&mut self.name).desired_width(160.0));
+            let can_save = config::is_valid_file_name(&self.name) && !Self::is_builtin(&self.name);
+            if ui
+                .add_enabled(can_save, egui::Button::new("💾 Save"))
+                .on_hover_text(format!(
+                    "To {}",
+                    config::config_dir()
+                        .join(theme::THEMES_DIR)
+                        .join(format!("{}.toml", self.name))
+                        .display()
+                ))
+                .clicked()
+            {
+                self.save(action_queue);
+            }
+        });
+        match &self.status {
+            Some(Ok(msg)) => {
+                ui.label(msg);
+            }
+            Some(Err(msg)) => {
+                ui.colored_label(ui.visuals().error_fg_color, msg);
+            }
+            None => {}
+        }
+
+        if changed && self.live {
+            action_queue.push(action::AppAction::PreviewTheme(self.colors.clone()));
+        }
+
+        egui_tiles::UiResponse::None
+    }
+
+    fn show_preview(&mut self, ui: &mut egui::Ui) {
+        ui.label(egui::RichText::new("Preview").strong());
+
+        let colors = &self.colors;
+        let text = colors.text_color();
+        let contrast = contrast_ratio(text, theme::ThemeColors::parse_hex(&colors.panel_bg));
+        let contrast_label = format!("Text contrast {:.1}:1", contrast);
+        if contrast < MIN_CONTRAST {
+            ui.colored_label(ui.visuals().warn_fg_color, format!("⚠ {}", contrast_label))
+                .on_hover_text("Hard to read in bright light");
+        } else {
+            ui.label(egui::RichText::new(contrast_label).weak());
+        }
+
+        let style = colors.to_egui_style();
+        ui.scope(|ui| {
+            ui.set_style(style.clone());
+            egui::Frame::new()
+                .fill(colors.panel_color())
+                .inner_margin(8.0)
+                .corner_radius(4.0)
+                .show(ui, |ui| {
+                    ui.label("Text on a panel");
+                    ui.horizontal(|ui| {
+                        let _ = ui.button("Button");
+                        let _ = ui.selectable_label(true, "Selected");
+                        let _ = ui.selectable_label(false, "Not selected");
+                    });
+                    ui.text_edit_singleline(&mut self.preview_text);
+                    egui::Frame::window(&style).show(ui, |ui| {
+                        ui.label("Text in a window");
+                        ui.colored_label(colors.accent_color(), "Accent");
+                    });
+                    ui.horizontal(|ui| {
+                        ui.colored_label(colors.error_color(), "● Error");
+                        ui.colored_label(colors.warning_color(), "● Warning");
+                        ui.colored_label(colors.success_color(), "● Success");
+                        ui.colored_label(colors.info_color(), "● Info");
+                    });
+                });
+        });
+    }
+}
+
+fn color_row(ui: &mut egui::Ui, label: &str, hex: &mut String) -> bool {
+    ui.label(label);
+    let mut color = theme::ThemeColors::parse_hex(hex);
+    let changed = egui::color_picker::color_edit_button_srgba(
+        ui,
+        &mut color,
+        egui::color_picker::Alpha::OnlyBlend,
+    )
+    .changed();
+    if changed {
+        *hex = theme::ThemeColors::to_hex(color);
+    }
+    ui.label(egui::RichText::new(hex.as_str()).monospace().weak());
+    ui.end_row();
+    changed
+}
+
+// Shows the fallback until it is changed, then it is written to the file
+fn optional_color_row(
+    ui: &mut egui::Ui,
+    label: &str,
+    hex: &mut Option<String>,
+    fallback: egui::Color32,
+) -> bool {
+    let mut value = hex
+        .clone()
+        .unwrap_or_else(|| theme::ThemeColors::to_hex(fallback));
+    let changed = color_row(ui, label, &mut value);
+    if changed {
+        *hex = Some(value);
+    }
+    changed
+}
+
+// WCAG contrast ratio, from 1 (same color) to 21 (black on white)
+fn contrast_ratio(a: egui::Color32, b: egui::Color32) -> f32 {
+    let luminance = |c: egui::Color32| {
+        let channel = |v: u8| {
+            let v = v as f32 / 255.0;
+            if v <= 0.03928 {
+                v / 12.92
+            } else {
+                ((v + 0.055) / 1.055).powf(2.4)
+            }
+        };
+        0.2126 * channel(c.r()) + 0.7152 * channel(c.g()) + 0.0722 * channel(c.b())
+    };
+    let (la, lb) = (luminance(a), luminance(b));
+    (la.max(lb) + 0.05) / (la.min(lb) + 0.05)
+}
@@ -0,0 +1,400 @@
+use std::collections::VecDeque;
+
+use crate::{can, config, messages, uds};
+use eframe::egui;
+
+const MAX_HISTORY: usize = 50;
+
+#[derive(Clone, Copy, PartialEq)]
+enum ServiceKind {
+    SessionControl,
+    EcuReset,
+    ReadDataByIdentifier,
+    ReadDtc,
+    ClearDtc,
+}
+
+impl ServiceKind {
+    const ALL: [ServiceKind; 5] = [
+        ServiceKind::SessionControl,
+        ServiceKind::EcuReset,
+        ServiceKind::ReadDataByIdentifier,
+        ServiceKind::ReadDtc,
+        ServiceKind::ClearDtc,
+    ];
+
+    fn name(&self) -> &'static str {
+        match self {
+            ServiceKind::SessionControl => "DiagnosticSessionControl (0x10)",
+            ServiceKind::EcuReset => "ECUReset (0x11)",
+            ServiceKind::ReadDataByIdentifier => "ReadDataByIdentifier (0x22)",
+            ServiceKind::ReadDtc => "ReadDTCInformation (0x19)",
+            ServiceKind::ClearDtc => "ClearDiagnosticInformation (0x14)",
+        }
+    }
+}
+
+struct HistoryEntry {
+    timestamp: chrono::DateTime<chrono::Local>,
+    ecu: String,
+    request: Vec<u8>,
+    // Raw response and its description, or why there is none
+    response: Result<(Vec<u8>, Vec<String>), String>,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, PartialEq)]
+pub struct UdsClientConfig {
+    pub ecu: Option<String>,
+}
+
+pub struct UdsClient {
+    pub title: String,
+    ui_to_can_tx: std::sync::mpsc::Sender<messages::MsgFromUi>,
+    // Responses to other clients' requests are ignored
+    client: uds::ClientId,
+    config: uds::DiagnosticsConfig,
+    selected_ecu: Option<usize>,
+    kind: ServiceKind,
+    session: u8,
+    reset_type: u8,
+    did: u16,
+    dtc_mask: u8,
+    dtc_group: u32,
+    // Request waiting for its response
+    pending: Option<(uds::Service, String, Vec<u8>)>,
+    history: VecDeque<HistoryEntry>,
+    tester_present: Option<can::scheduler::SendHandle>,
+}
+
+impl Drop for UdsClient {
+    fn drop(&mut self) {
+        self.stop_tester_present();
+    }
+}
+
+impl UdsClient {
+    pub fn new(
+        instance_num: usize,
+        ui_to_can_tx: std::sync::mpsc::Sender<messages::MsgFromUi>,
+    ) -> Self {
+        let config = uds::DiagnosticsConfig::load();
+        Self {
+            title: format!("UDS Client #{}", instance_num),
+            ui_to_can_tx,
+            client: can::scheduler::new_handle(),
+            selected_ecu: (!config.ecus.is_empty()).then_some(0),
+            config,
+            kind: ServiceKind::ReadDataByIdentifier,
+            session: 0x03,
+            reset_type: 0x01,
+            // VIN
+            did: 0xF190,
+            dtc_mask: 0xFF,
+            dtc_group: 0xFFFFFF,
+            pending: None,
+            history: VecDeque::new(),
+            tester_present: None,
+        }
+    }
+
+    pub fn config(&self) -> UdsClientConfig {
+        UdsClientConfig {
+            ecu: self.ecu().map(|ecu| ecu.name.clone()),
+        }
+    }
+
+    pub fn apply_config(&mut self, config: UdsClientConfig) {
+        if let Some(idx) = config
+            .ecu
+            .and_then(|name| self.config.ecus.iter().position(|ecu| ecu.name == name))
+        {
+            self.selected_ecu = Some(idx);
+        }
+    }
+
+    fn ecu(&self) -> Option<&uds::Ecu> {
+        self.selected_ecu.and_then(|idx| self.config.ecus.get(idx))
+    }
+
+    fn service(&self) -> uds::Service {
+        match self.kind {
+            ServiceKind::SessionControl => uds::Service::SessionControl {
+                session: self.session,
+            },
+            ServiceKind::EcuReset => uds::Service::EcuReset {
+                reset_type: self.reset_type,
+            },
+            ServiceKind::ReadDataByIdentifier => {
+                uds::Service::ReadDataByIdentifier { did: self.did }
+            }
+            ServiceKind::ReadDtc => uds::Service::ReadDtcByStatusMask {
+                mask: self.dtc_mask,
+            },
+            ServiceKind::ClearDtc => uds::Service::ClearDtc {
+                group: self.dtc_group,
+            },
+        }
+    }
+
+    fn send_request(&mut self) {
+        let Some(ecu) = self.ecu().cloned() else {
+            return;
+        };
+        let service = self.service();
+        let payload = service.request();
+        self.pending = Some((service, ecu.name.clone(), payload.clone()));
+        self.ui_to_can_tx
+            .send(messages::MsgFromUi::UdsRequest {
+                client: self.client,
+                ecu,
+                payload,
+            })
+            .expect("Failed to send UdsRequest");
+    }
+
+    fn start_tester_present(&mut self) {
+        let Some(ecu) = self.ecu() else {
+            return;
+        };
+        let handle = can::scheduler::new_handle();
+        let add_msg = uds::tester_present(ecu, handle);
+        self.ui_to_can_tx
+            .send(messages::MsgFromUi::AddSendMessage(add_msg))
+            .expect("Failed to send AddSendMessage");
+        self.tester_present = Some(handle);
+    }
+
+    fn stop_tester_present(&mut self) {
+        if let Some(handle) = self.tester_present.take()
+            && let Err(e) = self
+                .ui_to_can_tx
+                .send(messages::MsgFromUi::DeleteSendMessage { handle })
+        {
+            log::error!("Failed to stop TesterPresent: {}", e);
+        }
+    }
+
+    pub fn show(&mut self, ui: &mut egui::Ui) -> egui_tiles::UiResponse {
+        ui.horizontal(|ui| {
+            ui.heading(format!("🩺 {}", self.title));
+            if ui
+                .button("⟳ Reload")
+                .on_hover_text(format!("Read {} again", uds::DIAGNOSTICS_CONFIG_FILE))
+                .clicked()
+            {
+                self.stop_tester_present();
+                self.config = uds::DiagnosticsConfig::load();
+                self.selected_ecu = (!self.config.ecus.is_empty()).then_some(0);
+            }
+        });
+        ui.separator();
+
+        if self.config.ecus.is_empty() {
+            ui.label(
+                egui::RichText::new(format!(
+                    "No ECUs defined. Add them to {} in {}.",
+                    uds::DIAGNOSTICS_CONFIG_FILE,
+                    config::config_dir().display()
+                ))
+                .italics()
+                .weak(),
+            );
+            return egui_tiles::UiResponse::None;
+        }
+
+        egui::Grid::new((&self.title, "uds_request"))
+            .num_columns(2)
+            .spacing([12.0, 6.0])
+            .show(ui, |ui| {
+                ui.label("ECU:");
+                ui.add_enabled_ui(self.tester_present.is_none(), |ui| {
+                    egui::ComboBox::from_id_salt((&self.title, "uds_ecu"))
+                        .selected_text(self.ecu().map_or("", |ecu| ecu.name.as_str()))
+                        .show_ui(ui, |ui| {
+                            for (idx, ecu) in self.config.ecus.iter().enumerate() {
+                                ui.selectable_value(
+                                    &mut self.selected_ecu,
+                                    Some(idx),
+                                    format!("{} (0x{:X} → 0x{:X})", ecu.name, ecu.tx_id, ecu.rx_id),
+                                );
+                            }
+                        });
+                });
+                ui.end_row();
+
+                ui.label("Service:");
+                egui::ComboBox::from_id_salt((&self.title, "uds_service"))
+                    .selected_text(self.kind.name())
+                    .show_ui(ui, |ui| {
+                        for kind in ServiceKind::ALL {
+                            ui.selectable_value(&mut self.kind, kind, kind.name());
+                        }
+                    });
+                ui.end_row();
+
+                match self.kind {
+                    ServiceKind::SessionControl => {
+                        ui.label("Session:");
+                        egui::ComboBox::from_id_salt((&self.title, "uds_session"))
+                            .selected_text(format!(
+                                "0x{:02X} ({})",
+                                self.session,
+                                uds::session_name(self.session)
+                            ))
+                            .show_ui(ui, |ui| {
+                                for session in [0x01, 0x02, 0x03] {
+                                    ui.selectable_value(
+                                        &mut self.session,
+                                        session,
+                                        format!(
+                                            "0x{:02X} ({})",
+                                            session,
+                                            uds::session_name(session)
+                                        ),
+                                    );
+                                }
+                            });
+                    }
+                    ServiceKind::EcuReset => {
+                        ui.label("Reset type:");
+                        egui::ComboBox::from_id_salt((&self.title, "uds_reset"))
+                            .selected_text(format!(
+                                "0x{:02X} ({})",
+                                self.reset_type,
+                                uds::reset_name(self.reset_type)
+                            ))
+                            .show_ui(ui, |ui| {
+                                for reset_type in [0x01, 0x02, 0x03] {
+                                    ui.selectable_value(
+                                        &mut self.reset_type,
+                                        reset_type,
+                                        format!(
+                                            "0x{:02X} ({})",
+                                            reset_type,
+                                            uds::reset_name(reset_type)
+                                        ),
+                                    );
+                                }
+                            });
+                    }
+                    ServiceKind::ReadDataByIdentifier => {
+                        ui.label("DID:");
+                        ui.add(egui::DragValue::new(&mut self.did).hexadecimal(4, false, true));
+                    }
+                    ServiceKind::ReadDtc => {
+                        ui.label("Status mask:");
+                        ui.add(
+                            egui::DragValue::new(&mut self.dtc_mask).hexadecimal(2, false, true),
+                        )
+                        .on_hover_text("DTCs with any of these status bits set are reported");
+                    }
+                    ServiceKind::ClearDtc => {
+                        ui.label("Group:");
+                        ui.add(
+                            egui::DragValue::new(&mut self.dtc_group)
+                                .range(0..=0xFFFFFF)
+                                .hexadecimal(6, false, true),
+                        )
+                        .on_hover_text("0xFFFFFF clears every DTC");
+                    }
+                }
+                ui.end_row();
+            });
+
+        ui.horizontal(|ui| {
+            if ui
+                .add_enabled(self.pending.is_none(), egui::Button::new("▶ Send"))
+                .clicked()
+            {
+                self.send_request();
+            }
+            if self.pending.is_some() {
+                ui.add(egui::Spinner::new());
+            }
+
+            let mut tester_present = self.tester_present.is_some();
+            if ui
+                .checkbox(&mut tester_present, "Tester present")
+                .on_hover_text("Send TesterPresent every 2 s so a non-default session stays open")
+                .changed()
+            {
+                if tester_present {
+                    self.start_tester_present();
+                } else {
+                    self.stop_tester_present();
+                }
+            }
+
+            if ui.button("Clear").clicked() {
+                self.history.clear();
+            }
+        });
+        ui.separator();
+
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            for entry in &self.history {
+                ui.horizontal(|ui| {
+                    ui.label(
+                        egui::RichText::new(entry.timestamp.format("%H:%M:%S%.3f").to_string())
+                            .weak(),
+                    );
+                    ui.label(egui::RichText::new(&entry.ecu).strong());
+                    ui.label(
+                        egui::RichText::new(format!("→ {}", uds::hex(&entry.request))).monospace(),
+                    );
+                });
+                match &entry.response {
+                    Ok((raw, lines)) => {
+                        ui.label(egui::RichText::new(format!("← {}", uds::hex(raw))).monospace());
+                        for line in lines {
+                            ui.label(line);
+                        }
+                    }
+                    Err(e) => {
+                        ui.colored_label(ui.visuals().error_fg_color, e);
+                    }
+                }
+                ui.separator();
+            }
+        });
+
+        egui_tiles::UiResponse::None
+    }
+
+    pub fn handle_can_message(&mut self, msg: &messages::MsgFromCan) {
+        let messages::MsgFromCan::UdsResponse { client, result } = msg else {
+            return;
+        };
+        if *client != self.client {
+            return;
+        }
+        let Some((service, ecu, request)) = self.pending.take() else {
+            return;
+        };
+
+        let response = match result {
+            Ok(raw) => match service.describe_response(raw) {
+                Ok(lines) => Ok((raw.clone(), lines)),
+                Err(e) => Err(e),
+            },
+            Err(e) => Err(e.clone()),
+        };
+        if let Err(e) = &response {
+            log::warn!(
+                "UDS request {} to {} failed: {}",
+                uds::hex(&request),
+                ecu,
+                e
+            );
+        }
+
+        // Newest first
+        self.history.push_front(HistoryEntry {
+            timestamp: chrono::Local::now(),
+            ecu,
+            request,
+            response,
+        });
+        self.history.truncate(MAX_HISTORY);
+    }
+}
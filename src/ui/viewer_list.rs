@@ -1,4 +1,4 @@
-use crate::{app, formatter, frozen, messages};
+use crate::{app, formatter, frozen, messages, uds, ui};
 use eframe::egui;
 use std::collections::VecDeque;
 
@@ -8,6 +8,7 @@ const MAX_MESSAGES: usize = 200;
 enum Msg {
     Decoded(messages::ParsedMessage),
     Undecoded(messages::UnparsedMessage),
+    IsoTp(messages::IsoTpMessage),
 }
 type MsgList = VecDeque<Msg>;
 
@@ -26,6 +27,23 @@ impl ViewerList {
         }
     }
 
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+        if paused {
+            self.msgs.freeze();
+        } else {
+            self.msgs.unfreeze();
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.msgs.apply_both(|ms| ms.clear());
+    }
+
     pub fn show(
         &mut self,
         ui: &mut egui::Ui,
@@ -33,22 +51,18 @@ impl ViewerList {
         parser: Option<&app::ParserInfo>,
     ) -> egui_tiles::UiResponse {
         ui.heading(format!("🚗 {}", self.title));
+        let theme = ui::theme::get_theme(ui.ctx());
 
         ui.horizontal(|ui| {
             if ui
                 .button(if self.paused { "Resume" } else { "Pause" })
                 .clicked()
             {
-                self.paused = !self.paused;
-                if self.paused {
-                    self.msgs.freeze();
-                } else {
-                    self.msgs.unfreeze();
-                }
+                self.set_paused(!self.paused);
             }
 
             if ui.button("Clear").clicked() {
-                self.msgs.apply_both(|ms| ms.clear());
+                self.clear();
             }
         });
 
@@ -106,16 +120,26 @@ impl ViewerList {
                                         let sig_def = msg_def.and_then(|md| {
                                             md.signals.iter().find(|s| s.name == *sig_name)
                                         });
-                                        {
-                                            ui.label(formatter::try_format(
-                                                formatter,
-                                                &decoded_msg.decoded.name,
-                                                sig_name,
-                                                sig_def,
-                                                Some(&signal.unit),
-                                                &signal.value,
-                                            ));
+                                        let mut value = egui::RichText::new(formatter::try_format(
+                                            formatter,
+                                            &decoded_msg.decoded.name,
+                                            sig_name,
+                                            sig_def,
+                                            Some(&signal.unit),
+                                            &signal.value,
+                                        ));
+                                        if let Some(color) = formatter::try_color(
+                                            formatter,
+                                            &decoded_msg.decoded.name,
+                                            sig_name,
+                                            &signal.unit,
+                                            signal.value.physical,
+                                            signal.value.enum_label.as_deref(),
+                                            &theme,
+                                        ) {
+                                            value = value.color(color).strong();
                                         }
+                                        ui.label(value);
                                     });
                                 });
                             }
@@ -144,6 +168,24 @@ impl ViewerList {
                                 });
                             });
                         }
+                        Msg::IsoTp(isotp_msg) => {
+                            body.row(18.0, |mut row| {
+                                row.col(|ui| {
+                                    ui.label(
+                                        isotp_msg.timestamp.format("%H:%M:%S:%3f").to_string(),
+                                    );
+                                });
+                                row.col(|ui| {
+                                    ui.label(format!("ISO-TP (0x{:X})", isotp_msg.msg_id));
+                                });
+                                row.col(|ui| {
+                                    ui.label(uds::describe_payload(&isotp_msg.payload));
+                                });
+                                row.col(|ui| {
+                                    ui.label(uds::hex(&isotp_msg.payload));
+                                });
+                            });
+                        }
                     }
                 }
             });
@@ -174,6 +216,10 @@ impl ViewerList {
                     .get_mut()
                     .push_back(Msg::Undecoded(unparsed_msg.clone()));
             }
+            messages::MsgFromCan::IsoTpMessage(isotp_msg) => {
+                self.make_space_for_new_message();
+                self.msgs.get_mut().push_back(Msg::IsoTp(isotp_msg.clone()));
+            }
             _ => {}
         }
     }
@@ -1,11 +1,136 @@
-use crate::{action, app, formatter, frozen, messages};
+use crate::{action, app, dbc, derived, formatter, frozen, messages, uds, ui, util};
 use eframe::egui;
 
-type DecodedMsgMap = hashbrown::HashMap<u32, messages::ParsedMessage>;
+type DecodedMsgMap = hashbrown::HashMap<u32, DecodedEntry>;
 type UndecodedMsgMap = hashbrown::HashMap<u32, messages::UnparsedMessage>;
+type DerivedSignalMap = std::collections::BTreeMap<String, messages::DerivedSignal>;
+// Latest reassembled payload per ID
+type IsoTpMsgMap = hashbrown::HashMap<u32, messages::IsoTpMessage>;
 
-#[derive(Clone, PartialEq, Eq)]
-enum TxNodeSearch {
+// Longer payloads are cut in the card header, the full payload is in the CAN list
+const MAX_ISOTP_BYTES_SHOWN: usize = 24;
+// Weight of the newest interval in the measured period
+const RATE_SMOOTHING: f64 = 0.2;
+// Longer intervals are counted as this many periods, a gap (pause, node off) isn't a slower rate
+// and would hide a stale message for several cycles
+const MAX_INTERVAL_PERIODS: f64 = 2.0;
+// How long a signal that took a new value stays highlighted
+const CHANGE_HIGHLIGHT_SECONDS: f64 = 1.0;
+const STALE_OPACITY: f32 = 0.4;
+
+// Latest message of an ID and what was seen of it so far
+#[derive(Clone)]
+struct DecodedEntry {
+    msg: messages::ParsedMessage,
+    count: u64,
+    // Smoothed time between two receptions, None until the second one
+    period_seconds: Option<f64>,
+    // When each signal last took a new value
+    changed_at: hashbrown::HashMap<String, chrono::DateTime<chrono::Local>>,
+}
+
+impl DecodedEntry {
+    fn new(msg: messages::ParsedMessage) -> Self {
+        Self {
+            msg,
+            count: 1,
+            period_seconds: None,
+            changed_at: hashbrown::HashMap::new(),
+        }
+    }
+
+    fn update(&mut self, msg: messages::ParsedMessage) {
+        let interval = (msg.timestamp - self.msg.timestamp)
+            .num_microseconds()
+            .map(|us| us as f64 / 1e6)
+            .filter(|s| *s > 0.0);
+        if let Some(interval) = interval {
+            self.period_seconds = Some(smooth_period(self.period_seconds, interval));
+        }
+        for (name, signal) in &msg.decoded.signals {
+            let changed = self
+                .msg
+                .decoded
+                .signals
+                .get(name)
+                .is_some_and(|old| old.value.physical != signal.value.physical);
+            if changed {
+                self.changed_at.insert(name.clone(), msg.timestamp);
+            }
+        }
+        self.count += 1;
+        self.msg = msg;
+    }
+
+    fn rate_hz(&self) -> Option<f64> {
+        self.period_seconds.map(|period| 1.0 / period)
+    }
+
+    // 1 right after the change, fading to 0
+    fn highlight(&self, signal_name: &str, now: chrono::DateTime<chrono::Local>) -> f32 {
+        self.changed_at
+            .get(signal_name)
+            .map_or(0.0, |changed_at| highlight_fade(*changed_at, now))
+    }
+}
+
+fn smooth_period(period: Option<f64>, interval: f64) -> f64 {
+    match period {
+        Some(period) => {
+            let interval = interval.min(period * MAX_INTERVAL_PERIODS);
+            period + RATE_SMOOTHING * (interval - period)
+        }
+        None => interval,
+    }
+}
+
+fn highlight_fade(
+    changed_at: chrono::DateTime<chrono::Local>,
+    now: chrono::DateTime<chrono::Local>,
+) -> f32 {
+    let elapsed = (now - changed_at).num_milliseconds() as f64 / 1000.0;
+    (1.0 - elapsed / CHANGE_HIGHLIGHT_SECONDS).clamp(0.0, 1.0) as f32
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SortColumn {
+    #[default]
+    Id,
+    Name,
+    Node,
+    Count,
+    Rate,
+    Age,
+}
+
+impl SortColumn {
+    const ALL: [SortColumn; 6] = [
+        SortColumn::Id,
+        SortColumn::Name,
+        SortColumn::Node,
+        SortColumn::Count,
+        SortColumn::Rate,
+        SortColumn::Age,
+    ];
+
+    fn label(&self) -> &'static str {
+        match self {
+            SortColumn::Id => "ID",
+            SortColumn::Name => "Name",
+            SortColumn::Node => "Node",
+            SortColumn::Count => "Count",
+            SortColumn::Rate => "Rate",
+            SortColumn::Age => "Age",
+        }
+    }
+}
+
+fn default_stale_cycles() -> f64 {
+    3.0
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, PartialEq, Eq)]
+pub enum TxNodeSearch {
     Any,
     Unparsed,
     Node(String),
@@ -15,9 +140,29 @@ pub struct ViewerTable {
     pub title: String,
     decoded_msgs: frozen::Frozen<DecodedMsgMap>,
     undecoded_msgs: frozen::Frozen<UndecodedMsgMap>,
+    derived_signals: frozen::Frozen<DerivedSignalMap>,
+    isotp_msgs: frozen::Frozen<IsoTpMsgMap>,
     paused: bool,
+    // Ages are counted up to here while paused
+    paused_at: Option<chrono::DateTime<chrono::Local>>,
     search: String,
     tx_node: TxNodeSearch,
+    sort: SortColumn,
+    sort_descending: bool,
+    // Messages not seen for this many cycle times are greyed out
+    stale_cycles: f64,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Clone, PartialEq)]
+pub struct ViewerTableConfig {
+    pub search: String,
+    pub tx_node: TxNodeSearch,
+    #[serde(default)]
+    pub sort: SortColumn,
+    #[serde(default)]
+    pub sort_descending: bool,
+    #[serde(default = "default_stale_cycles")]
+    pub stale_cycles: f64,
 }
 
 impl TxNodeSearch {
@@ -36,12 +181,63 @@ impl ViewerTable {
             title: format!("CAN Viewer Table #{}", instance_num),
             decoded_msgs: frozen::Frozen::new(DecodedMsgMap::new()),
             undecoded_msgs: frozen::Frozen::new(UndecodedMsgMap::new()),
+            derived_signals: frozen::Frozen::new(DerivedSignalMap::new()),
+            isotp_msgs: frozen::Frozen::new(IsoTpMsgMap::new()),
             paused: false,
+            paused_at: None,
             search: String::new(),
             tx_node: TxNodeSearch::Any,
+            sort: SortColumn::Id,
+            sort_descending: false,
+            stale_cycles: default_stale_cycles(),
+        }
+    }
+
+    pub fn config(&self) -> ViewerTableConfig {
+        ViewerTableConfig {
+            search: self.search.clone(),
+            tx_node: self.tx_node.clone(),
+            sort: self.sort,
+            sort_descending: self.sort_descending,
+            stale_cycles: self.stale_cycles,
         }
     }
 
+    pub fn apply_config(&mut self, config: ViewerTableConfig) {
+        self.search = config.search;
+        self.tx_node = config.tx_node;
+        self.sort = config.sort;
+        self.sort_descending = config.sort_descending;
+        self.stale_cycles = config.stale_cycles;
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+        self.paused_at = paused.then(chrono::Local::now);
+        if paused {
+            self.decoded_msgs.freeze();
+            self.undecoded_msgs.freeze();
+            self.derived_signals.freeze();
+            self.isotp_msgs.freeze();
+        } else {
+            self.decoded_msgs.unfreeze();
+            self.undecoded_msgs.unfreeze();
+            self.derived_signals.unfreeze();
+            self.isotp_msgs.unfreeze();
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.decoded_msgs.apply_both(|ms| ms.clear());
+        self.undecoded_msgs.apply_both(|ms| ms.clear());
+        self.derived_signals.apply_both(|ms| ms.clear());
+        self.isotp_msgs.apply_both(|ms| ms.clear());
+    }
+
     pub fn show(
         &mut self,
         ui: &mut egui::Ui,
@@ -50,25 +246,18 @@ impl ViewerTable {
         parser: Option<&app::ParserInfo>,
     ) -> egui_tiles::UiResponse {
         ui.heading(format!("🚗 {}", self.title));
+        let theme = ui::theme::get_theme(ui.ctx());
 
         ui.horizontal(|ui| {
             if ui
                 .button(if self.paused { "Resume" } else { "Pause" })
                 .clicked()
             {
-                self.paused = !self.paused;
-                if self.paused {
-                    self.decoded_msgs.freeze();
-                    self.undecoded_msgs.freeze();
-                } else {
-                    self.decoded_msgs.unfreeze();
-                    self.undecoded_msgs.unfreeze();
-                }
+                self.set_paused(!self.paused);
             }
 
             if ui.button("Clear").clicked() {
-                self.decoded_msgs.apply_both(|ms| ms.clear());
-                self.undecoded_msgs.apply_both(|ms| ms.clear());
+                self.clear();
             }
         });
 
@@ -92,7 +281,7 @@ impl ViewerTable {
                         .decoded_msgs
                         .get()
                         .values()
-                        .map(|msg| msg.decoded.tx_node.clone())
+                        .map(|entry| entry.msg.decoded.tx_node.clone())
                         .collect::<Vec<_>>();
                     all_tx_nodes.sort_unstable();
                     all_tx_nodes.dedup();
@@ -121,12 +310,55 @@ impl ViewerTable {
                             }
                         });
                 });
+                ui.horizontal(|ui| {
+                    ui.label("Sort:");
+                    for column in SortColumn::ALL {
+                        if ui
+                            .selectable_label(self.sort == column, column.label())
+                            .clicked()
+                        {
+                            // Picking the current column again flips the order
+                            if self.sort == column {
+                                self.sort_descending = !self.sort_descending;
+                            } else {
+                                self.sort = column;
+                                self.sort_descending = false;
+                            }
+                        }
+                    }
+                    if ui
+                        .small_button(if self.sort_descending { "⬇" } else { "⬆" })
+                        .on_hover_text("Ascending or descending")
+                        .clicked()
+                    {
+                        self.sort_descending = !self.sort_descending;
+                    }
+
+                    ui.add_space(8.0);
+
+                    ui.label("Grey out after");
+                    ui.add(
+                        egui::DragValue::new(&mut self.stale_cycles)
+                            .range(1.0..=100.0)
+                            .speed(0.1)
+                            .suffix("× cycle"),
+                    )
+                    .on_hover_text(
+                        "Cycle time from the DBC's GenMsgCycleTime, or as measured when it has none",
+                    );
+                });
                 ui.add_space(8.0);
 
                 let decoded = self.decoded_msgs.get();
                 let undecoded = self.undecoded_msgs.get();
+                let derived_signals = self.derived_signals.get();
+                let isotp_msgs = self.isotp_msgs.get();
 
-                if decoded.is_empty() && undecoded.is_empty() {
+                if decoded.is_empty()
+                    && undecoded.is_empty()
+                    && derived_signals.is_empty()
+                    && isotp_msgs.is_empty()
+                {
                     ui.centered_and_justified(|ui| {
                         ui.label(
                             egui::RichText::new("No CAN messages to display.")
@@ -137,6 +369,13 @@ impl ViewerTable {
                     return;
                 }
 
+                let now = self.paused_at.unwrap_or_else(chrono::Local::now);
+                // Ages, rates and highlights move without new messages
+                if !self.paused && !decoded.is_empty() {
+                    ui.ctx()
+                        .request_repaint_after(std::time::Duration::from_millis(100));
+                }
+
                 egui::ScrollArea::vertical().show(ui, |ui| {
                     let low_search = self.search.to_lowercase();
 
@@ -178,9 +417,106 @@ impl ViewerTable {
                                 msg_name: "Error: Unknown",
                                 msg_id: msg.msg_id,
                                 tx_node: "Unparsed",
+                                source_file: None,
+                                raw_bytes: &raw_bytes_str,
+                                timestamp: &msg.timestamp.format("%-I:%M:%S%.3f").to_string(),
+                                signals: Vec::new(),
+                                stats: None,
+                                search: &self.search,
+                            }
+                            .ui(ui)
+                            .into_iter()
+                            .for_each(|spawn| action_queue.push(spawn));
+                        }
+                        ui.add_space(8.0);
+                    }
+
+                    if !derived_signals.is_empty() && self.tx_node == TxNodeSearch::Any {
+                        let signals: Vec<SignalCell> = derived_signals
+                            .values()
+                            .filter(|sig| {
+                                self.search.is_empty()
+                                    || derived::DERIVED_MSG_NAME.contains(&low_search)
+                                    || sig.name.to_lowercase().contains(&low_search)
+                            })
+                            .map(|sig| {
+                                let value = if sig.unit.is_empty() {
+                                    format!("{:.2}", sig.value)
+                                } else {
+                                    format!("{:.2} {}", sig.value, sig.unit)
+                                };
+                                SignalCell {
+                                    name: sig.name.as_str(),
+                                    highlight: 0.0,
+                                    color: formatter::try_color(
+                                        formatter,
+                                        derived::DERIVED_MSG_NAME,
+                                        &sig.name,
+                                        &sig.unit,
+                                        sig.value,
+                                        None,
+                                        &theme,
+                                    ),
+                                    value,
+                                }
+                            })
+                            .collect();
+                        if !signals.is_empty() {
+                            let latest = derived_signals.values().map(|sig| sig.timestamp).max();
+                            MessageCard {
+                                msg_name: derived::DERIVED_MSG_NAME,
+                                msg_id: derived::DERIVED_MSG_ID,
+                                tx_node: "Derived",
+                                source_file: None,
+                                raw_bytes: "",
+                                timestamp: &latest
+                                    .map(|t| t.format("%-I:%M:%S%.3f").to_string())
+                                    .unwrap_or_default(),
+                                signals,
+                                stats: None,
+                                search: &self.search,
+                            }
+                            .ui(ui)
+                            .into_iter()
+                            .for_each(|spawn| action_queue.push(spawn));
+                            ui.add_space(8.0);
+                        }
+                    }
+
+                    if !isotp_msgs.is_empty() && self.tx_node == TxNodeSearch::Any {
+                        let mut isotp_msg_keys = isotp_msgs
+                            .iter()
+                            .filter(|(_, msg)| {
+                                self.search.is_empty()
+                                    || format!("{:03X}", msg.msg_id)
+                                        .to_lowercase()
+                                        .contains(&low_search)
+                                    || "iso-tp".contains(&low_search)
+                                    || uds::describe_payload(&msg.payload)
+                                        .to_lowercase()
+                                        .contains(&low_search)
+                            })
+                            .map(|(&msg_id, _)| msg_id)
+                            .collect::<Vec<_>>();
+                        isotp_msg_keys.sort();
+                        for msg_id in isotp_msg_keys {
+                            let msg = &isotp_msgs[&msg_id];
+                            let shown =
+                                &msg.payload[..msg.payload.len().min(MAX_ISOTP_BYTES_SHOWN)];
+                            let raw_bytes_str = if shown.len() < msg.payload.len() {
+                                format!("{} … ({} bytes)", uds::hex(shown), msg.payload.len())
+                            } else {
+                                uds::hex(shown)
+                            };
+                            MessageCard {
+                                msg_name: &uds::describe_payload(&msg.payload),
+                                msg_id: msg.msg_id,
+                                tx_node: "ISO-TP",
+                                source_file: None,
                                 raw_bytes: &raw_bytes_str,
                                 timestamp: &msg.timestamp.format("%-I:%M:%S%.3f").to_string(),
                                 signals: Vec::new(),
+                                stats: None,
                                 search: &self.search,
                             }
                             .ui(ui)
@@ -192,7 +528,8 @@ impl ViewerTable {
 
                     let mut decoded_msg_keys = decoded
                         .iter()
-                        .filter_map(|(&msg_id, msg)| {
+                        .filter_map(|(&msg_id, entry)| {
+                            let msg = &entry.msg;
                             let tx_filter = self.tx_node.matches(&msg.decoded.tx_node);
                             if !tx_filter {
                                 return None;
@@ -217,22 +554,48 @@ impl ViewerTable {
                         })
                         .collect::<Vec<_>>();
                     decoded_msg_keys.sort();
+                    // Stable, ties stay in ID order
+                    match self.sort {
+                        SortColumn::Id => {}
+                        SortColumn::Name => decoded_msg_keys.sort_by(|a, b| {
+                            decoded[a].msg.decoded.name.cmp(&decoded[b].msg.decoded.name)
+                        }),
+                        SortColumn::Node => decoded_msg_keys.sort_by(|a, b| {
+                            let (a, b) = (&decoded[a].msg.decoded, &decoded[b].msg.decoded);
+                            (&a.tx_node, &a.name).cmp(&(&b.tx_node, &b.name))
+                        }),
+                        SortColumn::Count => decoded_msg_keys.sort_by_key(|id| decoded[id].count),
+                        SortColumn::Rate => decoded_msg_keys.sort_by(|a, b| {
+                            let rate = |id: &u32| decoded[id].rate_hz().unwrap_or(0.0);
+                            rate(a).total_cmp(&rate(b))
+                        }),
+                        // Most recently seen first
+                        SortColumn::Age => decoded_msg_keys
+                            .sort_by_key(|id| std::cmp::Reverse(decoded[id].msg.timestamp)),
+                    }
+                    if self.sort_descending {
+                        decoded_msg_keys.reverse();
+                    }
+                    // Provenance is only interesting once several DBCs are merged
+                    let show_source = parser.is_some_and(|p| p.dbc_paths.len() > 1);
                     for msg_id in decoded_msg_keys {
-                        let msg = &decoded[&msg_id];
+                        let entry = &decoded[&msg_id];
+                        let msg = &entry.msg;
                         let msg_def = parser
                             .as_ref()
                             .map(|p| &p.parser)
                             .and_then(|p| p.msg_def(msg_id));
-                        let signals: Vec<(&str, String)> = msg
+                        let signals: Vec<SignalCell> = msg
                             .decoded
                             .signals
                             .iter()
                             .map(|(sig_name, signal)| {
                                 let sig_def = msg_def
                                     .and_then(|md| md.signals.iter().find(|s| s.name == *sig_name));
-                                (
-                                    sig_name.as_str(),
-                                    formatter::try_format(
+                                SignalCell {
+                                    name: sig_name.as_str(),
+                                    highlight: entry.highlight(sig_name, now),
+                                    value: formatter::try_format(
                                         formatter,
                                         &msg.decoded.name,
                                         sig_name,
@@ -240,7 +603,16 @@ impl ViewerTable {
                                         Some(&signal.unit),
                                         &signal.value,
                                     ),
-                                )
+                                    color: formatter::try_color(
+                                        formatter,
+                                        &msg.decoded.name,
+                                        sig_name,
+                                        &signal.unit,
+                                        signal.value.physical,
+                                        signal.value.enum_label.as_deref(),
+                                        &theme,
+                                    ),
+                                }
                             })
                             .collect();
                         let raw_bytes_str = msg
@@ -249,16 +621,43 @@ impl ViewerTable {
                             .map(|b| format!("{:02X}", b))
                             .collect::<Vec<_>>()
                             .join(" ");
-                        MessageCard {
+                        let source_file = parser
+                            .filter(|_| show_source)
+                            .and_then(|p| p.parser.source_of(msg_id))
+                            .map(dbc::merged::file_name);
+                        let dbc_cycle_ms = parser.zip(msg_def).and_then(|(p, md)| {
+                            let dbc = p.parser.dbc_of(
+                                util::can::can_dbc_to_u32_with_extid_flag(&md.id),
+                            )?;
+                            dbc::attributes::cycle_time_ms(dbc, md)
+                        });
+                        let stats = MsgStats {
+                            count: entry.count,
+                            rate_hz: entry.rate_hz(),
+                            age_ms: (now - msg.timestamp).num_milliseconds(),
+                            dbc_cycle_ms,
+                            measured_cycle_ms: entry.period_seconds.map(|s| s * 1000.0),
+                            stale_cycles: self.stale_cycles,
+                        };
+                        let stale = stats.is_stale();
+                        let card = MessageCard {
                             msg_name: &msg.decoded.name,
                             msg_id: msg.decoded.msg_id,
                             tx_node: &msg.decoded.tx_node,
+                            source_file: source_file.as_deref(),
                             raw_bytes: &raw_bytes_str,
                             timestamp: &msg.timestamp.format("%-I:%M:%S%.3f").to_string(),
                             signals,
+                            stats: Some(stats),
                             search: &self.search,
-                        }
-                        .ui(ui)
+                        };
+                        ui.scope(|ui| {
+                            if stale {
+                                ui.multiply_opacity(STALE_OPACITY);
+                            }
+                            card.ui(ui)
+                        })
+                        .inner
                         .into_iter()
                         .for_each(|spawn| action_queue.push(spawn));
                         ui.add_space(8.0);
@@ -272,15 +671,32 @@ impl ViewerTable {
     pub fn handle_can_message(&mut self, msg: &messages::MsgFromCan) {
         match msg {
             messages::MsgFromCan::ParsedMessage(parsed_msg) => {
-                self.decoded_msgs
-                    .get_mut()
-                    .insert(parsed_msg.decoded.msg_id, parsed_msg.clone());
+                let decoded = self.decoded_msgs.get_mut();
+                match decoded.get_mut(&parsed_msg.decoded.msg_id) {
+                    Some(entry) => entry.update(parsed_msg.clone()),
+                    None => {
+                        decoded.insert(
+                            parsed_msg.decoded.msg_id,
+                            DecodedEntry::new(parsed_msg.clone()),
+                        );
+                    }
+                }
             }
             messages::MsgFromCan::UnparsedMessage(unparsed_msg) => {
                 self.undecoded_msgs
                     .get_mut()
                     .insert(unparsed_msg.msg_id, unparsed_msg.clone());
             }
+            messages::MsgFromCan::DerivedSignal(derived_signal) => {
+                self.derived_signals
+                    .get_mut()
+                    .insert(derived_signal.name.clone(), derived_signal.clone());
+            }
+            messages::MsgFromCan::IsoTpMessage(isotp_msg) => {
+                self.isotp_msgs
+                    .get_mut()
+                    .insert(isotp_msg.msg_id, isotp_msg.clone());
+            }
             _ => {}
         }
     }
@@ -290,8 +706,8 @@ impl ViewerTable {
         let decoded = &self.decoded_msgs.rt_data;
         let undecoded = self.undecoded_msgs.get_mut();
         undecoded.retain(|&msg_id, unparsed_msg| {
-            if let Some(parsed_msg) = decoded.get(&msg_id) {
-                parsed_msg.timestamp <= unparsed_msg.timestamp
+            if let Some(entry) = decoded.get(&msg_id) {
+                entry.msg.timestamp <= unparsed_msg.timestamp
             } else {
                 true
             }
@@ -299,37 +715,82 @@ impl ViewerTable {
     }
 }
 
+struct SignalCell<'a> {
+    name: &'a str,
+    value: String,
+    // 1 when the value just changed, fading to 0
+    highlight: f32,
+    // From the formatter's color rules
+    color: Option<egui::Color32>,
+}
+
 struct MessageCard<'a> {
     msg_name: &'a str,
     msg_id: u32,
     tx_node: &'a str,
+    // DBC file the message definition came from
+    source_file: Option<&'a str>,
     raw_bytes: &'a str,
     timestamp: &'a str,
-    signals: Vec<(&'a str, String)>,
+    signals: Vec<SignalCell<'a>>,
+    // Only kept for decoded messages
+    stats: Option<MsgStats>,
     search: &'a str,
 }
 
+struct MsgStats {
+    count: u64,
+    rate_hz: Option<f64>,
+    age_ms: i64,
+    dbc_cycle_ms: Option<f64>,
+    measured_cycle_ms: Option<f64>,
+    stale_cycles: f64,
+}
+
+impl MsgStats {
+    fn cycle_ms(&self) -> Option<f64> {
+        self.dbc_cycle_ms.or(self.measured_cycle_ms)
+    }
+
+    fn is_stale(&self) -> bool {
+        self.cycle_ms()
+            .is_some_and(|cycle| self.age_ms as f64 > cycle * self.stale_cycles)
+    }
+
+    fn age_text(&self) -> String {
+        if self.age_ms < 1000 {
+            format!("{} ms ago", self.age_ms.max(0))
+        } else {
+            format!("{:.1} s ago", self.age_ms as f64 / 1000.0)
+        }
+    }
+}
+
 impl MessageCard<'_> {
     fn ui(&self, ui: &mut egui::Ui) -> Vec<action::AppAction> {
         let mut action_queue = Vec::new();
+        let theme = ui::theme::get_theme(ui.ctx());
         // Header (outside card)
         ui.horizontal(|ui| {
+            // Derived signals have no real ID to show
+            let header = if self.msg_id == derived::DERIVED_MSG_ID {
+                self.msg_name.to_string()
+            } else {
+                format!("{}  (0x{:03X})", self.msg_name, self.msg_id)
+            };
             ui.label(
-                egui::RichText::new(format!("{}  (0x{:03X})", self.msg_name, self.msg_id))
-                    .strong()
-                    .size(16.0)
-                    .color(
-                        if self.search.is_empty()
-                            || self
-                                .msg_name
-                                .to_lowercase()
-                                .contains(&self.search.to_lowercase())
-                        {
-                            ui.visuals().text_color()
-                        } else {
-                            ui.visuals().weak_text_color()
-                        },
-                    ),
+                egui::RichText::new(header).strong().size(16.0).color(
+                    if self.search.is_empty()
+                        || self
+                            .msg_name
+                            .to_lowercase()
+                            .contains(&self.search.to_lowercase())
+                    {
+                        ui.visuals().text_color()
+                    } else {
+                        ui.visuals().weak_text_color()
+                    },
+                ),
             );
             ui.label(
                 egui::RichText::new(format!("from {}", self.tx_node)).color(
@@ -345,11 +806,45 @@ impl MessageCard<'_> {
                     },
                 ),
             );
+            if let Some(source_file) = self.source_file {
+                ui.label(
+                    egui::RichText::new(format!("📄 {}", source_file))
+                        .color(ui.visuals().weak_text_color()),
+                )
+                .on_hover_text("DBC file this message is defined in");
+            }
             ui.label(
                 egui::RichText::new(self.timestamp)
                     .italics()
                     .color(ui.visuals().weak_text_color()),
             );
+            if let Some(stats) = &self.stats {
+                let rate = stats
+                    .rate_hz
+                    .map(|hz| format!("{:.1} Hz", hz))
+                    .unwrap_or_else(|| "– Hz".to_string());
+                let cycle = match (stats.dbc_cycle_ms, stats.measured_cycle_ms) {
+                    (Some(dbc), _) => format!("DBC cycle time {} ms", dbc),
+                    (None, Some(measured)) => {
+                        format!("No cycle time in the DBC, measured {:.1} ms", measured)
+                    }
+                    (None, None) => "No cycle time in the DBC".to_string(),
+                };
+                ui.label(
+                    egui::RichText::new(format!(
+                        "×{}  {}  {}",
+                        stats.count,
+                        rate,
+                        stats.age_text()
+                    ))
+                    .monospace()
+                    .color(ui.visuals().weak_text_color()),
+                )
+                .on_hover_text(format!(
+                    "Received {} times\n{}\nGreyed out when not seen for {}× the cycle time",
+                    stats.count, cycle, stats.stale_cycles
+                ));
+            }
             ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
                 ui.label(
                     egui::RichText::new(self.raw_bytes)
@@ -373,7 +868,8 @@ impl MessageCard<'_> {
             .inner_margin(egui::Margin::symmetric(8, 6))
             .show(ui, |ui| {
                 ui.vertical(|ui| {
-                    for (i, (sig_name, value)) in self.signals.iter().enumerate() {
+                    for (i, signal) in self.signals.iter().enumerate() {
+                        let sig_name = &signal.name;
                         ui.horizontal(|ui| {
                             ui.label(
                                 egui::RichText::new(*sig_name).monospace().color(
@@ -401,7 +897,18 @@ impl MessageCard<'_> {
                                         ));
                                     }
                                     ui.add_space(8.0);
-                                    ui.label(egui::RichText::new(value).monospace());
+                                    let mut value = egui::RichText::new(&signal.value).monospace();
+                                    if let Some(color) = signal.color {
+                                        value = value.color(color).strong();
+                                    }
+                                    if signal.highlight > 0.0 {
+                                        value = value.background_color(
+                                            theme
+                                                .accent_color()
+                                                .gamma_multiply(0.5 * signal.highlight),
+                                        );
+                                    }
+                                    ui.label(value);
                                 },
                             );
                         });
@@ -415,3 +922,56 @@ impl MessageCard<'_> {
         action_queue
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats(age_ms: i64, dbc_cycle_ms: Option<f64>, measured_cycle_ms: Option<f64>) -> MsgStats {
+        MsgStats {
+            count: 10,
+            rate_hz: None,
+            age_ms,
+            dbc_cycle_ms,
+            measured_cycle_ms,
+            stale_cycles: default_stale_cycles(),
+        }
+    }
+
+    #[test]
+    fn period_converges_to_the_interval() {
+        let mut period = smooth_period(None, 0.1);
+        assert_eq!(period, 0.1);
+        for _ in 0..50 {
+            period = smooth_period(Some(period), 0.05);
+        }
+        assert!((period - 0.05).abs() < 1e-4);
+    }
+
+    #[test]
+    fn long_gap_barely_moves_the_period() {
+        let period = smooth_period(Some(0.1), 30.0);
+        assert!((period - 0.12).abs() < 1e-9);
+        // Stale again three cycles after the gap, not 18 s later
+        assert!(stats(500, None, Some(period * 1000.0)).is_stale());
+    }
+
+    #[test]
+    fn staleness_prefers_the_dbc_cycle_time() {
+        assert!(!stats(250, Some(100.0), None).is_stale());
+        assert!(stats(350, Some(100.0), None).is_stale());
+        assert!(stats(350, Some(100.0), Some(1000.0)).is_stale());
+        assert!(!stats(350, None, Some(1000.0)).is_stale());
+        // Nothing to compare against without a cycle time
+        assert!(!stats(60_000, None, None).is_stale());
+    }
+
+    #[test]
+    fn highlight_fades_over_a_second() {
+        let changed_at = chrono::Local::now();
+        let after = |ms| changed_at + chrono::Duration::milliseconds(ms);
+        assert_eq!(highlight_fade(changed_at, changed_at), 1.0);
+        assert!((highlight_fade(changed_at, after(250)) - 0.75).abs() < 1e-6);
+        assert_eq!(highlight_fade(changed_at, after(1500)), 0.0);
+    }
+}
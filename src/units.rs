@@ -0,0 +1,154 @@
+// Physical units the formatter can convert between, ex: m/s to km/h
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Dimension {
+    Temperature,
+    AngularVelocity,
+    Speed,
+    Pressure,
+    Angle,
+}
+
+struct Unit {
+    // Spellings found in DBCs, matched ignoring case
+    names: &'static [&'static str],
+    dimension: Dimension,
+    // In the dimension's base unit: value * scale + offset
+    scale: f64,
+    offset: f64,
+}
+
+const UNITS: &[Unit] = &[
+    // Base °C
+    Unit {
+        names: &["°C", "degC", "C", "celsius"],
+        dimension: Dimension::Temperature,
+        scale: 1.0,
+        offset: 0.0,
+    },
+    Unit {
+        names: &["°F", "degF", "F", "fahrenheit"],
+        dimension: Dimension::Temperature,
+        scale: 5.0 / 9.0,
+        offset: -160.0 / 9.0,
+    },
+    Unit {
+        names: &["K", "kelvin"],
+        dimension: Dimension::Temperature,
+        scale: 1.0,
+        offset: -273.15,
+    },
+    // Base rad/s
+    Unit {
+        names: &["rad/s"],
+        dimension: Dimension::AngularVelocity,
+        scale: 1.0,
+        offset: 0.0,
+    },
+    Unit {
+        names: &["deg/s", "°/s", "dps"],
+        dimension: Dimension::AngularVelocity,
+        scale: std::f64::consts::PI / 180.0,
+        offset: 0.0,
+    },
+    Unit {
+        names: &["rpm"],
+        dimension: Dimension::AngularVelocity,
+        scale: std::f64::consts::PI / 30.0,
+        offset: 0.0,
+    },
+    // Base m/s
+    Unit {
+        names: &["m/s"],
+        dimension: Dimension::Speed,
+        scale: 1.0,
+        offset: 0.0,
+    },
+    Unit {
+        names: &["km/h", "kph", "kmh"],
+        dimension: Dimension::Speed,
+        scale: 1.0 / 3.6,
+        offset: 0.0,
+    },
+    Unit {
+        names: &["mph"],
+        dimension: Dimension::Speed,
+        scale: 0.44704,
+        offset: 0.0,
+    },
+    // Base Pa
+    Unit {
+        names: &["Pa"],
+        dimension: Dimension::Pressure,
+        scale: 1.0,
+        offset: 0.0,
+    },
+    Unit {
+        names: &["kPa"],
+        dimension: Dimension::Pressure,
+        scale: 1000.0,
+        offset: 0.0,
+    },
+    Unit {
+        names: &["mbar"],
+        dimension: Dimension::Pressure,
+        scale: 100.0,
+        offset: 0.0,
+    },
+    Unit {
+        names: &["bar"],
+        dimension: Dimension::Pressure,
+        scale: 100_000.0,
+        offset: 0.0,
+    },
+    Unit {
+        names: &["psi"],
+        dimension: Dimension::Pressure,
+        scale: 6894.757_293_168,
+        offset: 0.0,
+    },
+    // Base rad
+    Unit {
+        names: &["rad"],
+        dimension: Dimension::Angle,
+        scale: 1.0,
+        offset: 0.0,
+    },
+    Unit {
+        names: &["deg", "°"],
+        dimension: Dimension::Angle,
+        scale: std::f64::consts::PI / 180.0,
+        offset: 0.0,
+    },
+];
+
+fn find(name: &str) -> Option<&'static Unit> {
+    let name = name.trim();
+    UNITS
+        .iter()
+        .find(|unit| unit.names.iter().any(|n| n.eq_ignore_ascii_case(name)))
+}
+
+pub fn is_known(name: &str) -> bool {
+    find(name).is_some()
+}
+
+// Unit names grouped by what they measure, for error messages
+pub fn known_units() -> String {
+    UNITS
+        .iter()
+        .map(|unit| unit.names[0])
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// `(scale, offset)` so that `to = from * scale + offset`, None when either unit is unknown or
+/// they measure different things.
+pub fn linear(from: &str, to: &str) -> Option<(f64, f64)> {
+    let from = find(from)?;
+    let to = find(to)?;
+    if from.dimension != to.dimension {
+        return None;
+    }
+    Some((from.scale / to.scale, (from.offset - to.offset) / to.scale))
+}
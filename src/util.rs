@@ -46,7 +46,7 @@ pub fn hsv_to_color32(h: f64, s: f64, v: f64) -> eframe::egui::Color32 {
 }
 
 pub mod can {
-    const EXTENDED_ID_FLAG: u32 = 0x80000000;
+    pub const EXTENDED_ID_FLAG: u32 = 0x80000000;
     pub const STANDARD_ID_MASK: u32 = 0x7FF;
     pub const EXTENDED_ID_MASK: u32 = 0x1FFFFFFF;
 
@@ -100,4 +100,12 @@ pub mod can {
             can_dbc::NumericValue::Double(v) => *v,
         }
     }
+
+    // Name of the node sending a message, "N/A" for messages without one (Vector__XXX).
+    pub fn transmitter_name(transmitter: &can_dbc::Transmitter) -> &str {
+        match transmitter {
+            can_dbc::Transmitter::NodeName(n) => n,
+            can_dbc::Transmitter::VectorXXX => "N/A",
+        }
+    }
 }
@@ -1,4 +1,4 @@
-use crate::{action, app, formatter, messages, ui};
+use crate::{action, app, dbc, formatter, messages, ui, util};
 use eframe::egui;
 
 pub enum Widget {
@@ -14,9 +14,121 @@ pub enum Widget {
     GgPlot(ui::gg_plot::GgPlot),
     Dynamics(ui::dynamics::Dynamics),
     Jitter(ui::jitter::Jitter),
+    DerivedSignals(ui::derived_signals::DerivedSignals),
+    DbcBrowser(ui::dbc_browser::DbcBrowser),
+    Sequences(ui::sequences::Sequences),
+    RestBus(ui::rest_bus::RestBus),
+    Fleet(ui::fleet::Fleet),
+    UdsClient(ui::uds::UdsClient),
+    ThemeEditor(ui::theme_editor::ThemeEditor),
+    FormatterEditor(ui::formatter_editor::FormatterEditor),
+}
+
+/// A widget's kind and settings, as stored in a saved layout. Live data isn't kept.
+#[derive(serde::Serialize, serde::Deserialize, Clone, PartialEq)]
+#[serde(tag = "widget")]
+pub enum WidgetConfig {
+    ViewerTable(ui::viewer_table::ViewerTableConfig),
+    ViewerList,
+    Bootloader,
+    Scope(ui::scope::ScopeConfig),
+    LogParser(ui::log_parser::LogParserConfig),
+    SendUi,
+    BusLoad,
+    BatteryVoltage,
+    BatteryTemps,
+    GgPlot(ui::gg_plot::GgPlotConfig),
+    Dynamics,
+    Jitter(ui::jitter::JitterConfig),
+    DerivedSignals,
+    DbcBrowser(ui::dbc_browser::DbcBrowserConfig),
+    Sequences,
+    RestBus,
+    Fleet,
+    UdsClient(ui::uds::UdsClientConfig),
+    ThemeEditor,
+    FormatterEditor,
+}
+
+impl WidgetConfig {
+    pub fn widget_type(&self) -> action::WidgetType {
+        match self {
+            WidgetConfig::ViewerTable(_) => action::WidgetType::ViewerTable,
+            WidgetConfig::ViewerList => action::WidgetType::ViewerList,
+            WidgetConfig::Bootloader => action::WidgetType::Bootloader,
+            WidgetConfig::Scope(config) => action::WidgetType::Scope {
+                msg_id: config.msg_id,
+                msg_name: config.msg_name.clone(),
+                signal_name: config.signal_name.clone(),
+            },
+            WidgetConfig::LogParser(_) => action::WidgetType::LogParser,
+            WidgetConfig::SendUi => action::WidgetType::SendUi,
+            WidgetConfig::BusLoad => action::WidgetType::BusLoad,
+            WidgetConfig::BatteryVoltage => action::WidgetType::BatteryVoltage,
+            WidgetConfig::BatteryTemps => action::WidgetType::BatteryTemps,
+            WidgetConfig::GgPlot(_) => action::WidgetType::GgPlot,
+            WidgetConfig::Dynamics => action::WidgetType::Dynamics,
+            WidgetConfig::Jitter(_) => action::WidgetType::Jitter,
+            WidgetConfig::DerivedSignals => action::WidgetType::DerivedSignals,
+            WidgetConfig::DbcBrowser(_) => action::WidgetType::DbcBrowser,
+            WidgetConfig::Sequences => action::WidgetType::Sequences,
+            WidgetConfig::RestBus => action::WidgetType::RestBus,
+            WidgetConfig::Fleet => action::WidgetType::Fleet,
+            WidgetConfig::UdsClient(_) => action::WidgetType::UdsClient,
+            WidgetConfig::ThemeEditor => action::WidgetType::ThemeEditor,
+            WidgetConfig::FormatterEditor => action::WidgetType::FormatterEditor,
+        }
+    }
 }
 
 impl Widget {
+    pub fn config(&self) -> WidgetConfig {
+        match self {
+            Widget::ViewerTable(w) => WidgetConfig::ViewerTable(w.config()),
+            Widget::ViewerList(_) => WidgetConfig::ViewerList,
+            Widget::Bootloader(_) => WidgetConfig::Bootloader,
+            Widget::Scope(w) => WidgetConfig::Scope(w.config()),
+            Widget::LogParser(w) => WidgetConfig::LogParser(w.config()),
+            Widget::SendUi(_) => WidgetConfig::SendUi,
+            Widget::BusLoad(_) => WidgetConfig::BusLoad,
+            Widget::BatteryVoltage(_) => WidgetConfig::BatteryVoltage,
+            Widget::BatteryTemps(_) => WidgetConfig::BatteryTemps,
+            Widget::GgPlot(w) => WidgetConfig::GgPlot(w.config()),
+            Widget::Dynamics(_) => WidgetConfig::Dynamics,
+            Widget::Jitter(w) => WidgetConfig::Jitter(w.config()),
+            Widget::DerivedSignals(_) => WidgetConfig::DerivedSignals,
+            Widget::DbcBrowser(w) => WidgetConfig::DbcBrowser(w.config()),
+            Widget::Sequences(_) => WidgetConfig::Sequences,
+            Widget::RestBus(_) => WidgetConfig::RestBus,
+            Widget::Fleet(_) => WidgetConfig::Fleet,
+            Widget::UdsClient(w) => WidgetConfig::UdsClient(w.config()),
+            Widget::ThemeEditor(_) => WidgetConfig::ThemeEditor,
+            Widget::FormatterEditor(_) => WidgetConfig::FormatterEditor,
+        }
+    }
+
+    // Called on a widget freshly spawned from `config.widget_type()`. Returns a warning for
+    // every setting that couldn't be restored.
+    pub fn apply_config(
+        &mut self,
+        config: WidgetConfig,
+        parser: Option<&dbc::merged::MergedParser>,
+    ) -> Vec<String> {
+        match (self, config) {
+            (Widget::ViewerTable(w), WidgetConfig::ViewerTable(config)) => w.apply_config(config),
+            (Widget::Scope(w), WidgetConfig::Scope(config)) => w.apply_config(config),
+            (Widget::LogParser(w), WidgetConfig::LogParser(config)) => w.apply_config(config),
+            (Widget::GgPlot(w), WidgetConfig::GgPlot(config)) => w.apply_config(config),
+            (Widget::Jitter(w), WidgetConfig::Jitter(config)) => {
+                return w.apply_config(config, parser);
+            }
+            (Widget::DbcBrowser(w), WidgetConfig::DbcBrowser(config)) => w.apply_config(config),
+            (Widget::UdsClient(w), WidgetConfig::UdsClient(config)) => w.apply_config(config),
+            _ => {}
+        }
+        Vec::new()
+    }
+
     pub fn title(&self) -> &str {
         match self {
             Widget::ViewerTable(w) => &w.title,
@@ -31,6 +143,14 @@ impl Widget {
             Widget::GgPlot(w) => &w.title,
             Widget::Dynamics(w) => &w.title,
             Widget::Jitter(w) => &w.title,
+            Widget::DerivedSignals(w) => &w.title,
+            Widget::DbcBrowser(w) => &w.title,
+            Widget::Sequences(w) => &w.title,
+            Widget::RestBus(w) => &w.title,
+            Widget::Fleet(w) => &w.title,
+            Widget::UdsClient(w) => &w.title,
+            Widget::ThemeEditor(w) => &w.title,
+            Widget::FormatterEditor(w) => &w.title,
         }
     }
 
@@ -59,8 +179,8 @@ impl Widget {
             Widget::ViewerTable(w) => w.show(ui, action_queue, formatter, parser),
             Widget::ViewerList(w) => w.show(ui, formatter, parser),
             Widget::Bootloader(w) => w.show(ui),
-            Widget::Scope(w) => w.show(ui),
-            Widget::LogParser(w) => w.show(ui, parser),
+            Widget::Scope(w) => w.show(ui, formatter),
+            Widget::LogParser(w) => w.show(ui, parser, formatter),
             Widget::SendUi(w) => w.show(ui, parser, formatter),
             Widget::BusLoad(w) => w.show(ui),
             Widget::BatteryVoltage(w) => w.show(ui),
@@ -68,6 +188,91 @@ impl Widget {
             Widget::GgPlot(w) => w.show(ui),
             Widget::Dynamics(w) => w.show(ui),
             Widget::Jitter(w) => w.show(ui, parser),
+            Widget::DerivedSignals(w) => w.show(ui, action_queue),
+            Widget::DbcBrowser(w) => w.show(ui, parser),
+            Widget::Sequences(w) => w.show(ui),
+            Widget::RestBus(w) => w.show(ui, parser, formatter),
+            Widget::Fleet(w) => w.show(ui),
+            Widget::UdsClient(w) => w.show(ui),
+            Widget::ThemeEditor(w) => w.show(ui, action_queue),
+            Widget::FormatterEditor(w) => w.show(ui, action_queue, parser),
+        }
+    }
+
+    // Called after the DBC was reloaded from disk. Returns a warning for everything the widget
+    // could no longer find in the new definitions.
+    pub fn on_dbc_reloaded(&mut self, parser: &dbc::merged::MergedParser) -> Vec<String> {
+        match self {
+            Widget::Scope(w) => w.on_dbc_reloaded(parser),
+            Widget::SendUi(w) => w.on_dbc_reloaded(parser),
+            Widget::Jitter(w) => w.on_dbc_reloaded(parser),
+            Widget::DbcBrowser(w) => w.on_dbc_reloaded(parser),
+            Widget::RestBus(w) => w.on_dbc_reloaded(parser),
+            _ => Vec::new(),
+        }
+    }
+
+    // Picked in the command palette while this widget had focus. Returns false when the widget
+    // has no use for a message, so a new one is opened instead.
+    pub fn take_message(&mut self, msg: &can_dbc::Message) -> bool {
+        match self {
+            Widget::Jitter(w) => w.select_msg(msg.clone()),
+            Widget::DbcBrowser(w) => w.select_msg(&msg.name),
+            _ => return false,
+        }
+        true
+    }
+
+    // Same as `take_message`, widgets that only work on whole messages take the signal's message
+    pub fn take_signal(&mut self, msg: &can_dbc::Message, signal_name: &str) -> bool {
+        match self {
+            Widget::Scope(w) => {
+                w.set_signal(
+                    util::can::can_dbc_to_u32_without_extid_flag(&msg.id),
+                    msg.name.clone(),
+                    signal_name.to_string(),
+                );
+                true
+            }
+            _ => self.take_message(msg),
+        }
+    }
+
+    // None for widgets that can't be paused
+    pub fn is_paused(&self) -> Option<bool> {
+        match self {
+            Widget::ViewerTable(w) => Some(w.is_paused()),
+            Widget::ViewerList(w) => Some(w.is_paused()),
+            Widget::Scope(w) => Some(w.is_paused()),
+            _ => None,
+        }
+    }
+
+    pub fn set_paused(&mut self, paused: bool) {
+        match self {
+            Widget::ViewerTable(w) => w.set_paused(paused),
+            Widget::ViewerList(w) => w.set_paused(paused),
+            Widget::Scope(w) => w.set_paused(paused),
+            _ => {}
+        }
+    }
+
+    // Returns false when the widget has nothing to clear
+    pub fn clear(&mut self) -> bool {
+        match self {
+            Widget::ViewerTable(w) => w.clear(),
+            Widget::ViewerList(w) => w.clear(),
+            Widget::Scope(w) => w.clear(),
+            Widget::GgPlot(w) => w.clear(),
+            Widget::Jitter(w) => w.reset_stats(),
+            _ => return false,
+        }
+        true
+    }
+
+    pub fn add_marker(&mut self, timestamp: chrono::DateTime<chrono::Local>, label: &str) {
+        if let Widget::Scope(w) = self {
+            w.add_marker(timestamp, label);
         }
     }
 
@@ -83,6 +288,13 @@ impl Widget {
             Widget::GgPlot(w) => w.handle_can_message(msg),
             Widget::Dynamics(w) => w.handle_can_message(msg),
             Widget::Jitter(w) => w.handle_can_message(msg),
+            Widget::DerivedSignals(w) => w.handle_can_message(msg),
+            Widget::Sequences(w) => w.handle_can_message(msg),
+            Widget::Bootloader(w) => w.handle_can_message(msg),
+            Widget::RestBus(w) => w.handle_can_message(msg),
+            Widget::Fleet(w) => w.handle_can_message(msg),
+            Widget::UdsClient(w) => w.handle_can_message(msg),
+            Widget::FormatterEditor(w) => w.handle_can_message(msg),
             _ => {}
         }
     }
@@ -1,13 +1,60 @@
-use crate::{action, app, formatter, messages, widgets};
+use crate::{action, app, formatter, layout, messages, shortcuts, widgets};
 use eframe::egui;
 
+const DEFAULT_DETACHED_SIZE: [f32; 2] = [800.0, 600.0];
+
+/// A widget popped out of the tile tree into its own native window.
+pub struct DetachedWidget {
+    pub viewport_id: egui::ViewportId,
+    pub widget: widgets::Widget,
+    // Where the window was opened, kept as is so the builder doesn't move it back every frame
+    initial_pos: Option<egui::Pos2>,
+    initial_size: egui::Vec2,
+    // Where it is now, for the saved layout
+    pos: Option<egui::Pos2>,
+    size: Option<egui::Vec2>,
+}
+
+impl DetachedWidget {
+    pub fn new(
+        viewport_id: egui::ViewportId,
+        widget: widgets::Widget,
+        pos: Option<[f32; 2]>,
+        size: Option<[f32; 2]>,
+    ) -> Self {
+        Self {
+            viewport_id,
+            widget,
+            initial_pos: pos.map(egui::Pos2::from),
+            initial_size: size.unwrap_or(DEFAULT_DETACHED_SIZE).into(),
+            pos: pos.map(egui::Pos2::from),
+            size: size.map(egui::Vec2::from),
+        }
+    }
+
+    pub fn config(&self) -> layout::DetachedConfig {
+        layout::DetachedConfig {
+            widget: self.widget.config(),
+            pos: self.pos.map(|p| [p.x, p.y]),
+            size: self.size.map(|s| [s.x, s.y]),
+        }
+    }
+}
+
 pub fn show(app: &mut app::DAQApp, ctx: &egui::Context) {
     egui::CentralPanel::default().show(ctx, |ui| {
         if app.tile_tree.is_empty() {
             ui.vertical_centered(|ui| {
                 ui.label("No widgets in workspace yet.");
-                ui.label("CMD+S to toggle the sidebar.");
-                ui.label("CMD+P to toggle the command palette.");
+                for command in [
+                    shortcuts::Command::ToggleSidebar,
+                    shortcuts::Command::ToggleCommandPalette,
+                    shortcuts::Command::ToggleShortcutHelp,
+                ] {
+                    if let Some(chord) = app.keymap.chord_for(ctx, &command) {
+                        ui.label(format!("{}: {}", chord, command.describe()));
+                    }
+                }
             });
         } else {
             let mut behavior = WorkspaceTileBehavior {
@@ -16,27 +63,109 @@ pub fn show(app: &mut app::DAQApp, ctx: &egui::Context) {
                 parser: app.parser.as_ref(),
                 ui_to_can_tx: app.ui_to_can_tx.clone(),
                 formatter: &app.value_formatter,
+                focused_tile: &mut app.focused_tile,
             };
             app.tile_tree.ui(&mut behavior, ui);
         }
     });
 }
 
+// Each detached widget gets an immediate viewport, so it is drawn with the same messages as the
+// tiles in the main window
+pub fn show_detached(app: &mut app::DAQApp, ctx: &egui::Context) {
+    for detached in &mut app.detached {
+        let mut builder = egui::ViewportBuilder::default()
+            .with_title(detached.widget.title())
+            .with_inner_size(detached.initial_size);
+        if let Some(pos) = detached.initial_pos {
+            builder = builder.with_position(pos);
+        }
+
+        let viewport_id = detached.viewport_id;
+        let title = detached.widget.title().to_string();
+        ctx.show_viewport_immediate(viewport_id, builder, |ctx, class| {
+            let mut show_widget = |ui: &mut egui::Ui| {
+                ui.horizontal(|ui| {
+                    if ui
+                        .button("⮌ Dock")
+                        .on_hover_text("Move back into the main window")
+                        .clicked()
+                    {
+                        app.action_queue
+                            .push(action::AppAction::DockWidget(viewport_id));
+                    }
+                });
+                ui.separator();
+                detached.widget.show(
+                    ui,
+                    &app.can_messages,
+                    &mut app.action_queue,
+                    app.parser.as_ref(),
+                    app.ui_to_can_tx.clone(),
+                    &app.value_formatter,
+                );
+            };
+
+            // Backends without multiple windows embed the viewport in the main one
+            if class == egui::ViewportClass::Embedded {
+                let mut open = true;
+                egui::Window::new(title.as_str())
+                    .id(egui::Id::new(viewport_id))
+                    .open(&mut open)
+                    .show(ctx, |ui| show_widget(ui));
+                if !open {
+                    app.action_queue
+                        .push(action::AppAction::DockWidget(viewport_id));
+                }
+                return;
+            }
+
+            egui::CentralPanel::default().show(ctx, |ui| show_widget(ui));
+
+            let (close_requested, outer_rect, inner_rect) = ctx.input(|i| {
+                let viewport = i.viewport();
+                (
+                    viewport.close_requested(),
+                    viewport.outer_rect,
+                    viewport.inner_rect,
+                )
+            });
+            if let Some(rect) = outer_rect {
+                detached.pos = Some(rect.min);
+            }
+            if let Some(rect) = inner_rect {
+                detached.size = Some(rect.size());
+            }
+            // Closing the window docks the widget instead of losing it
+            if close_requested {
+                ctx.send_viewport_cmd(egui::ViewportCommand::CancelClose);
+                app.action_queue
+                    .push(action::AppAction::DockWidget(viewport_id));
+            }
+        });
+    }
+}
+
 struct WorkspaceTileBehavior<'a> {
     can_messages: &'a [messages::MsgFromCan],
     action_queue: &'a mut Vec<action::AppAction>,
     parser: Option<&'a app::ParserInfo>,
     ui_to_can_tx: std::sync::mpsc::Sender<messages::MsgFromUi>,
     formatter: &'a Option<formatter::Formatter>,
+    focused_tile: &'a mut Option<egui_tiles::TileId>,
 }
 
 impl egui_tiles::Behavior<widgets::Widget> for WorkspaceTileBehavior<'_> {
     fn pane_ui(
         &mut self,
         ui: &mut egui::Ui,
-        _tile_id: egui_tiles::TileId,
+        tile_id: egui_tiles::TileId,
         widget: &mut widgets::Widget,
     ) -> egui_tiles::UiResponse {
+        // Clicking anywhere in a pane focuses it
+        if ui.ui_contains_pointer() && ui.input(|i| i.pointer.any_pressed()) {
+            *self.focused_tile = Some(tile_id);
+        }
         widget.show(
             ui,
             self.can_messages,
@@ -51,6 +180,28 @@ impl egui_tiles::Behavior<widgets::Widget> for WorkspaceTileBehavior<'_> {
         widget.title().into()
     }
 
+    fn top_bar_right_ui(
+        &mut self,
+        tiles: &egui_tiles::Tiles<widgets::Widget>,
+        ui: &mut egui::Ui,
+        _tile_id: egui_tiles::TileId,
+        tabs: &egui_tiles::Tabs,
+        _scroll_offset: &mut f32,
+    ) {
+        let Some(active) = tabs.active else {
+            return;
+        };
+        if matches!(tiles.get(active), Some(egui_tiles::Tile::Pane(_)))
+            && ui
+                .small_button("⧉")
+                .on_hover_text("Pop out into its own window")
+                .clicked()
+        {
+            self.action_queue
+                .push(action::AppAction::DetachWidget(active));
+        }
+    }
+
     fn tab_bar_color(&self, visuals: &egui::Visuals) -> egui::Color32 {
         visuals.window_fill
     }